@@ -19,6 +19,40 @@ function getRandom() {
   return Math.random()
 }
 
+function openFile(accept, callback) {
+  const input = document.createElement('input');
+  input.type = 'file';
+  input.accept = accept;
+  input.addEventListener('change', () => {
+    const file = input.files[0];
+    if (file) {
+      file.text().then((contents) => callback(file.name, contents));
+    }
+  });
+  input.click();
+}
+
+function downloadFile(name, contents) {
+  const url = URL.createObjectURL(new Blob([contents], { type: 'application/json' }));
+  const a = document.createElement('a');
+  a.href = url;
+  a.download = name;
+  a.click();
+  URL.revokeObjectURL(url);
+}
+
+function readJournal() {
+  return window.localStorage.getItem('journal');
+}
+
+function writeJournal(contents) {
+  window.localStorage.setItem('journal', contents);
+}
+
+function clearJournal() {
+  window.localStorage.removeItem('journal');
+}
+
 window.addEventListener('TrunkApplicationStarted', (_) => {
   version = window.wasmBindings.version;
 });
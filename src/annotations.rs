@@ -1,16 +1,94 @@
-use std::collections::HashMap;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
 
-use egui::{Button, Color32, Grid, TextEdit};
+use egui::{
+    ahash::HashSet, Button, Color32, ComboBox, Grid, Key, Rect, RichText, Sense, TextEdit, Vec2,
+};
 use serde::{Deserialize, Serialize};
 
-use crate::{bitcoin::Txid, export};
+use crate::{
+    bip329::{self, Label},
+    bitcoin::Txid,
+    bookmark::Bookmark,
+    csv, export,
+    prices::Fiat,
+    sticky::Sticky,
+};
 
-#[derive(PartialEq, Eq, Debug, Default, Serialize, Deserialize, Clone)]
+#[derive(PartialEq, Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Annotations {
     tx_color: HashMap<Txid, [u8; 3]>,
     tx_label: HashMap<Txid, String>,
     coin_color: HashMap<(Txid, usize), [u8; 3]>,
     coin_label: HashMap<(Txid, usize), String>,
+    /// Applied to coins with that address as they're added to the graph.
+    #[serde(default)]
+    address_label: HashMap<String, String>,
+    /// Overrides the change detection for outputs.
+    #[serde(default)]
+    change: HashMap<(Txid, usize), bool>,
+    /// E.g. from an exchange export.
+    #[serde(default)]
+    cost_basis: HashMap<Txid, Fiat>,
+    /// A transaction or address is in at most one group.
+    #[serde(default)]
+    groups: Vec<Group>,
+    /// Notes, frames and arrows on the canvas.
+    #[serde(default)]
+    stickies: Vec<Sticky>,
+    #[serde(default)]
+    categories: Vec<Category>,
+    /// By category name.
+    #[serde(default)]
+    tx_category: HashMap<Txid, String>,
+    #[serde(default)]
+    coin_category: HashMap<(Txid, usize), String>,
+    /// Applies to coins with that address that don't have a category of their own.
+    #[serde(default)]
+    address_category: HashMap<String, String>,
+    /// Addresses of the coins in the graph, see [Self::apply_address_labels].
+    #[serde(skip)]
+    coin_address: HashMap<(Txid, usize), String>,
+    /// Saved views, in the order they're presented.
+    #[serde(default)]
+    bookmarks: Vec<Bookmark>,
+}
+
+/// A class of transactions and coins, e.g. victim funds or an exchange. Tints them where they
+/// don't have a color of their own.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct Category {
+    pub name: String,
+    pub color: [u8; 3],
+    /// Hides its transactions and coins in the graph.
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+impl Category {
+    fn color(&self) -> Color32 {
+        let [r, g, b] = self.color;
+        Color32::from_rgb(r, g, b)
+    }
+}
+
+/// Transactions that can be collapsed into a single node, e.g. the churn of an exchange wallet.
+/// Transactions spending from one of its addresses are members too.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct Group {
+    pub name: String,
+    pub txids: Vec<Txid>,
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    pub collapsed: bool,
+}
+
+impl Group {
+    fn is_empty(&self) -> bool {
+        self.txids.is_empty() && self.addresses.is_empty()
+    }
 }
 
 impl Annotations {
@@ -57,6 +135,50 @@ impl Annotations {
             tx_label: txids_from_strings(&annotations.tx_label)?,
             coin_color: txos_from_strings(&annotations.coin_color)?,
             coin_label: txos_from_strings(&annotations.coin_label)?,
+            address_label: annotations.address_label.clone(),
+            change: txos_from_strings(&annotations.change)?,
+            cost_basis: txids_from_strings(&annotations.cost_basis)?,
+            groups: annotations
+                .groups
+                .iter()
+                .map(|group| {
+                    Ok(Group {
+                        name: group.name.clone(),
+                        txids: group
+                            .txids
+                            .iter()
+                            .map(|s| Txid::new(s))
+                            .collect::<Result<_, String>>()?,
+                        addresses: group.addresses.clone(),
+                        collapsed: group.collapsed,
+                    })
+                })
+                .collect::<Result<_, String>>()?,
+            stickies: annotations
+                .stickies
+                .iter()
+                .cloned()
+                .map(Sticky::import)
+                .collect(),
+            categories: annotations
+                .categories
+                .iter()
+                .map(|category| Category {
+                    name: category.name.clone(),
+                    color: category.color,
+                    hidden: category.hidden,
+                })
+                .collect(),
+            tx_category: txids_from_strings(&annotations.tx_category)?,
+            coin_category: txos_from_strings(&annotations.coin_category)?,
+            address_category: annotations.address_category.clone(),
+            coin_address: HashMap::new(),
+            bookmarks: annotations
+                .bookmarks
+                .iter()
+                .cloned()
+                .map(Bookmark::import)
+                .collect(),
         };
 
         Ok(result)
@@ -80,6 +202,166 @@ impl Annotations {
             tx_label: txids_to_strings(&self.tx_label),
             coin_color: txos_to_strings(&self.coin_color),
             coin_label: txos_to_strings(&self.coin_label),
+            address_label: self.address_label.clone(),
+            change: txos_to_strings(&self.change),
+            cost_basis: txids_to_strings(&self.cost_basis),
+            groups: self
+                .groups
+                .iter()
+                .map(|group| export::Group0 {
+                    name: group.name.clone(),
+                    txids: group.txids.iter().map(Txid::to_string).collect(),
+                    addresses: group.addresses.clone(),
+                    collapsed: group.collapsed,
+                })
+                .collect(),
+            stickies: self.stickies.iter().map(Sticky::export).collect(),
+            categories: self
+                .categories
+                .iter()
+                .map(|category| export::Category0 {
+                    name: category.name.clone(),
+                    color: category.color,
+                    hidden: category.hidden,
+                })
+                .collect(),
+            tx_category: txids_to_strings(&self.tx_category),
+            coin_category: txos_to_strings(&self.coin_category),
+            address_category: self.address_category.clone(),
+            bookmarks: self.bookmarks.iter().map(Bookmark::export).collect(),
+        }
+    }
+
+    /// Add annotations from [other] where there are none yet.
+    pub fn merge(&mut self, other: Annotations) {
+        fn merge_map<K: std::hash::Hash + Eq, V>(into: &mut HashMap<K, V>, from: HashMap<K, V>) {
+            for (k, v) in from {
+                into.entry(k).or_insert(v);
+            }
+        }
+        merge_map(&mut self.tx_color, other.tx_color);
+        merge_map(&mut self.tx_label, other.tx_label);
+        merge_map(&mut self.coin_color, other.coin_color);
+        merge_map(&mut self.coin_label, other.coin_label);
+        merge_map(&mut self.address_label, other.address_label);
+        merge_map(&mut self.change, other.change);
+        merge_map(&mut self.cost_basis, other.cost_basis);
+        for group in other.groups {
+            let txids: Vec<Txid> = group
+                .txids
+                .into_iter()
+                .filter(|txid| self.group_of(*txid).is_none())
+                .collect();
+            let addresses: Vec<String> = group
+                .addresses
+                .into_iter()
+                .filter(|address| self.address_group(address).is_none())
+                .collect();
+            match self.groups.iter_mut().find(|g| g.name == group.name) {
+                Some(existing) => {
+                    existing.txids.extend(txids);
+                    existing.addresses.extend(addresses);
+                }
+                None if !txids.is_empty() || !addresses.is_empty() => self.groups.push(Group {
+                    txids,
+                    addresses,
+                    ..group
+                }),
+                None => {}
+            }
+        }
+        for category in other.categories {
+            if !self.categories.iter().any(|c| c.name == category.name) {
+                self.categories.push(category);
+            }
+        }
+        merge_map(&mut self.tx_category, other.tx_category);
+        merge_map(&mut self.coin_category, other.coin_category);
+        merge_map(&mut self.address_category, other.address_category);
+        for sticky in other.stickies {
+            if !self.stickies.contains(&sticky) {
+                self.stickies.push(sticky);
+            }
+        }
+        for bookmark in other.bookmarks {
+            if !self.bookmarks.iter().any(|b| b.name == bookmark.name) {
+                self.bookmarks.push(bookmark);
+            }
+        }
+    }
+
+    /// Only what's attached to [txids] or to [addresses], their addresses, plus stickies within
+    /// [area] (graph coordinates). Category definitions are kept, bookmarks aren't.
+    pub fn extract(
+        &self,
+        txids: &HashSet<Txid>,
+        addresses: &HashSet<&str>,
+        area: Rect,
+    ) -> Annotations {
+        fn by_tx<V: Clone>(map: &HashMap<Txid, V>, txids: &HashSet<Txid>) -> HashMap<Txid, V> {
+            map.iter()
+                .filter(|(txid, _)| txids.contains(*txid))
+                .map(|(k, v)| (*k, v.clone()))
+                .collect()
+        }
+        fn by_coin<V: Clone>(
+            map: &HashMap<(Txid, usize), V>,
+            txids: &HashSet<Txid>,
+        ) -> HashMap<(Txid, usize), V> {
+            map.iter()
+                .filter(|((txid, _), _)| txids.contains(txid))
+                .map(|(k, v)| (*k, v.clone()))
+                .collect()
+        }
+        fn by_address<V: Clone>(
+            map: &HashMap<String, V>,
+            addresses: &HashSet<&str>,
+        ) -> HashMap<String, V> {
+            map.iter()
+                .filter(|(address, _)| addresses.contains(address.as_str()))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        }
+        Annotations {
+            tx_color: by_tx(&self.tx_color, txids),
+            tx_label: by_tx(&self.tx_label, txids),
+            coin_color: by_coin(&self.coin_color, txids),
+            coin_label: by_coin(&self.coin_label, txids),
+            address_label: by_address(&self.address_label, addresses),
+            change: by_coin(&self.change, txids),
+            cost_basis: by_tx(&self.cost_basis, txids),
+            groups: self
+                .groups
+                .iter()
+                .map(|group| Group {
+                    txids: group
+                        .txids
+                        .iter()
+                        .filter(|txid| txids.contains(*txid))
+                        .copied()
+                        .collect(),
+                    addresses: group
+                        .addresses
+                        .iter()
+                        .filter(|address| addresses.contains(address.as_str()))
+                        .cloned()
+                        .collect(),
+                    ..group.clone()
+                })
+                .filter(|group| !group.is_empty())
+                .collect(),
+            stickies: self
+                .stickies
+                .iter()
+                .filter(|sticky| area.contains(sticky.start) && area.contains(sticky.end))
+                .cloned()
+                .collect(),
+            categories: self.categories.clone(),
+            tx_category: by_tx(&self.tx_category, txids),
+            coin_category: by_coin(&self.coin_category, txids),
+            address_category: by_address(&self.address_category, addresses),
+            coin_address: by_coin(&self.coin_address, txids),
+            bookmarks: vec![],
         }
     }
 
@@ -93,19 +375,58 @@ impl Annotations {
             .insert(coin, [color.r(), color.g(), color.b()]);
     }
 
+    /// Its own color, or else the color of its category.
     pub fn tx_color(&self, txid: Txid) -> Option<Color32> {
         self.tx_color
             .get(&txid)
             .map(|c| Color32::from_rgb(c[0], c[1], c[2]))
+            .or_else(|| Some(self.category(self.tx_category.get(&txid)?)?.color()))
     }
 
+    /// Its own color, or else the color of its category, that of its address or that of its
+    /// transaction.
     pub fn coin_color(&self, coin: (Txid, usize)) -> Option<Color32> {
         self.coin_color
             .get(&coin)
             .map(|c| Color32::from_rgb(c[0], c[1], c[2]))
+            .or_else(|| {
+                let name = self
+                    .coin_category(coin)
+                    .or_else(|| self.tx_category.get(&coin.0))?;
+                Some(self.category(name)?.color())
+            })
+    }
+
+    /// Its own category, or else that of its address.
+    fn coin_category(&self, coin: (Txid, usize)) -> Option<&String> {
+        self.coin_category
+            .get(&coin)
+            .or_else(|| self.address_category.get(self.coin_address.get(&coin)?))
+    }
+
+    fn category(&self, name: &str) -> Option<&Category> {
+        self.categories.iter().find(|c| c.name == name)
+    }
+
+    pub fn has_categories(&self) -> bool {
+        !self.categories.is_empty()
+    }
+
+    /// Whether the category of [txid] is hidden.
+    pub fn hidden_tx(&self, txid: Txid) -> bool {
+        self.tx_category
+            .get(&txid)
+            .and_then(|name| self.category(name))
+            .is_some_and(|c| c.hidden)
+    }
+
+    /// Whether the category of [coin], or of its address, is hidden.
+    pub fn hidden_coin(&self, coin: (Txid, usize)) -> bool {
+        self.coin_category(coin)
+            .and_then(|name| self.category(name))
+            .is_some_and(|c| c.hidden)
     }
 
-    #[allow(dead_code)]
     pub fn set_tx_label(&mut self, txid: Txid, label: String) {
         self.tx_label.insert(txid, label);
     }
@@ -123,6 +444,163 @@ impl Annotations {
         self.coin_label.get(&coin).map(|l| l.to_owned())
     }
 
+    /// Changes when coin labels do, for caching what's computed from the entities.
+    pub fn coin_labels_fingerprint(&self) -> u64 {
+        fingerprint(&self.coin_label)
+    }
+
+    /// Changes when address labels do, which decide the tracked addresses.
+    pub fn address_labels_fingerprint(&self) -> u64 {
+        fingerprint(&self.address_label)
+    }
+
+    /// Changes when a recorded cost basis does.
+    pub fn cost_basis_fingerprint(&self) -> u64 {
+        fingerprint(&self.cost_basis)
+    }
+
+    pub fn address_label(&self, address: &str) -> Option<String> {
+        self.address_label.get(address).map(|l| l.to_owned())
+    }
+
+    pub fn set_cost_basis(&mut self, txid: Txid, cost_basis: Fiat) {
+        self.cost_basis.insert(txid, cost_basis);
+    }
+
+    pub fn cost_basis(&self, txid: Txid) -> Option<&Fiat> {
+        self.cost_basis.get(&txid)
+    }
+
+    /// `None` if the change detection decides.
+    pub fn change(&self, coin: (Txid, usize)) -> Option<bool> {
+        self.change.get(&coin).copied()
+    }
+
+    pub fn change_menu(&mut self, coin: (Txid, usize), ui: &mut egui::Ui) {
+        let mut change = self.change(coin);
+        ui.horizontal(|ui| {
+            ui.label("Change:");
+            ui.selectable_value(&mut change, None, "Guess");
+            ui.selectable_value(&mut change, Some(true), "Yes");
+            ui.selectable_value(&mut change, Some(false), "No");
+        });
+        match change {
+            Some(change) => self.change.insert(coin, change),
+            None => self.change.remove(&coin),
+        };
+    }
+
+    /// Label the given coins whose address has a label, unless they're labelled already. Their
+    /// addresses are kept for address categories.
+    pub fn apply_address_labels<'a>(
+        &mut self,
+        coins: impl Iterator<Item = ((Txid, usize), &'a str)>,
+    ) {
+        for (coin, address) in coins {
+            self.coin_address.insert(coin, address.to_string());
+            if let Some(label) = self.address_label.get(address) {
+                self.coin_label.entry(coin).or_insert_with(|| label.clone());
+            }
+        }
+    }
+
+    /// Label all given coins with an address in [labels]. With [remember], the labels are also
+    /// applied to coins added later. Returns the number of labelled coins and the number of
+    /// addresses that didn't match any coin.
+    pub fn import_address_labels<'a>(
+        &mut self,
+        labels: Vec<(String, String)>,
+        coins: impl Iterator<Item = ((Txid, usize), &'a str)>,
+        remember: bool,
+    ) -> (usize, usize) {
+        let mut by_address: HashMap<&str, Vec<(Txid, usize)>> = HashMap::new();
+        for (coin, address) in coins {
+            by_address.entry(address).or_default().push(coin);
+        }
+
+        let mut applied = 0;
+        let mut unmatched = 0;
+        for (address, label) in labels {
+            match by_address.get(address.as_str()) {
+                Some(coins) => {
+                    for coin in coins {
+                        self.coin_label.insert(*coin, label.clone());
+                        applied += 1;
+                    }
+                }
+                None => unmatched += 1,
+            }
+            if remember {
+                self.address_label.insert(address, label);
+            }
+        }
+        (applied, unmatched)
+    }
+
+    /// Transaction, output and address labels, sorted so that exports are stable.
+    pub fn bip329_labels(&self) -> Vec<Label> {
+        let mut txs: Vec<_> = self.tx_label.iter().collect();
+        txs.sort();
+        let mut coins: Vec<_> = self.coin_label.iter().collect();
+        coins.sort();
+        let mut addresses: Vec<_> = self.address_label.iter().collect();
+        addresses.sort();
+
+        let txs = txs
+            .into_iter()
+            .map(|(txid, label)| Label::new(bip329::Type::Tx, txid.hex_string(), label.clone()));
+        let coins = coins.into_iter().map(|((txid, vout), label)| {
+            Label::new(
+                bip329::Type::Output,
+                format!("{}:{}", txid, vout),
+                label.clone(),
+            )
+        });
+        let addresses = addresses
+            .into_iter()
+            .map(|(address, label)| Label::new(bip329::Type::Addr, address.clone(), label.clone()));
+        txs.chain(coins).chain(addresses).collect()
+    }
+
+    /// Apply transaction, output and address labels, replacing existing ones. Address labels
+    /// also apply to coins added later. Returns the number of imported labels and the number of
+    /// labels of other types (inputs, keys), which are skipped.
+    pub fn import_bip329<'a>(
+        &mut self,
+        labels: Vec<Label>,
+        coins: impl Iterator<Item = ((Txid, usize), &'a str)>,
+    ) -> Result<(usize, usize), String> {
+        // Check everything before changing anything.
+        let mut txs = vec![];
+        let mut outputs = vec![];
+        let mut addresses = vec![];
+        let mut skipped = 0;
+        for Label { type_, ref_, label } in labels {
+            let Some(label) = label else {
+                continue;
+            };
+            match type_ {
+                bip329::Type::Tx => txs.push((Txid::new(&ref_)?, label)),
+                bip329::Type::Output => {
+                    let Some((txid, vout)) = ref_.split_once(':') else {
+                        return Err(format!("Expected `txid:vout`, got `{}`", ref_));
+                    };
+                    let vout = vout.parse::<usize>().map_err(|e| e.to_string())?;
+                    outputs.push(((Txid::new(txid)?, vout), label));
+                }
+                bip329::Type::Addr => addresses.push((ref_, label)),
+                _ => skipped += 1,
+            }
+        }
+
+        let imported = txs.len() + outputs.len() + addresses.len();
+        self.tx_label.extend(txs);
+        // Output labels are more specific, so they win over address labels.
+        self.import_address_labels(addresses, coins, true);
+        self.coin_label.extend(outputs);
+        Ok((imported, skipped))
+    }
+
     pub fn coin_menu(&mut self, coin: (Txid, usize), ui: &mut egui::Ui) {
         let mut label = self
             .coin_label
@@ -159,6 +637,34 @@ impl Annotations {
                 }
             });
             ui.end_row();
+
+            if !self.categories.is_empty() {
+                ui.label("Category:");
+                let current = self.coin_category.get(&coin).cloned();
+                if let Some(category) =
+                    category_combo(ui, "CoinCategory", &self.categories, current)
+                {
+                    match category {
+                        Some(name) => self.coin_category.insert(coin, name),
+                        None => self.coin_category.remove(&coin),
+                    };
+                }
+                ui.end_row();
+
+                if let Some(address) = self.coin_address.get(&coin).cloned() {
+                    ui.label("Address category:");
+                    let current = self.address_category.get(&address).cloned();
+                    if let Some(category) =
+                        category_combo(ui, "AddressCategory", &self.categories, current)
+                    {
+                        match category {
+                            Some(name) => self.address_category.insert(address, name),
+                            None => self.address_category.remove(&address),
+                        };
+                    }
+                    ui.end_row();
+                }
+            }
         });
 
         if label.is_empty() {
@@ -168,6 +674,335 @@ impl Annotations {
         }
     }
 
+    pub fn bookmarks(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    pub fn categories(&self) -> &[Category] {
+        &self.categories
+    }
+
+    pub fn categories_mut(&mut self) -> &mut Vec<Category> {
+        &mut self.categories
+    }
+
+    pub fn bookmarks_mut(&mut self) -> &mut Vec<Bookmark> {
+        &mut self.bookmarks
+    }
+
+    /// Names of the categories that are currently hidden, e.g. to save in a [Bookmark].
+    pub fn hidden_categories(&self) -> Vec<String> {
+        self.categories
+            .iter()
+            .filter(|c| c.hidden)
+            .map(|c| c.name.clone())
+            .collect()
+    }
+
+    pub fn set_hidden_categories(&mut self, hidden: &[String]) {
+        for category in &mut self.categories {
+            category.hidden = hidden.contains(&category.name);
+        }
+    }
+
+    pub fn stickies(&self) -> &[Sticky] {
+        &self.stickies
+    }
+
+    pub fn stickies_mut(&mut self) -> &mut Vec<Sticky> {
+        &mut self.stickies
+    }
+
+    /// Adding, renaming, recoloring and removing categories, for the Categories menu.
+    pub fn categories_ui(&mut self, ui: &mut egui::Ui) {
+        let mut rename = None;
+        let mut remove = None;
+        Grid::new("Categories").num_columns(4).show(ui, |ui| {
+            for (i, category) in self.categories.iter_mut().enumerate() {
+                ui.color_edit_button_srgb(&mut category.color);
+                let mut name = category.name.clone();
+                if ui
+                    .add(TextEdit::singleline(&mut name).desired_width(150.0))
+                    .changed()
+                {
+                    rename = Some((i, name));
+                }
+                let mut visible = !category.hidden;
+                ui.checkbox(&mut visible, "Visible");
+                category.hidden = !visible;
+                if ui.button("✖").on_hover_text("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some((i, name)) = rename {
+            if !self.categories.iter().any(|c| c.name == name) {
+                let old = std::mem::replace(&mut self.categories[i].name, name.clone());
+                for category in self
+                    .tx_category
+                    .values_mut()
+                    .chain(self.coin_category.values_mut())
+                    .chain(self.address_category.values_mut())
+                {
+                    if *category == old {
+                        *category = name.clone();
+                    }
+                }
+            }
+        }
+        if let Some(i) = remove {
+            let name = self.categories.remove(i).name;
+            self.tx_category.retain(|_, c| *c != name);
+            self.coin_category.retain(|_, c| *c != name);
+            self.address_category.retain(|_, c| *c != name);
+        }
+
+        if ui.button("Add Category").clicked() {
+            let n = (1..)
+                .find(|n| {
+                    !self
+                        .categories
+                        .iter()
+                        .any(|c| c.name == format!("Category {}", n))
+                })
+                .unwrap();
+            let color = Self::COLORS[self.categories.len() % Self::COLORS.len()];
+            self.categories.push(Category {
+                name: format!("Category {}", n),
+                color: [color.r(), color.g(), color.b()],
+                hidden: false,
+            });
+        }
+        ui.weak("Assign categories to transactions, coins and addresses in their context menus.");
+    }
+
+    /// Compact list of the categories on the canvas, toggling their visibility.
+    pub fn legend_ui(&mut self, ui: &mut egui::Ui) {
+        for category in &mut self.categories {
+            ui.horizontal(|ui| {
+                let (rect, _) = ui.allocate_exact_size(Vec2::splat(10.0), Sense::hover());
+                ui.painter().rect_filled(rect, 2.0, category.color());
+                let mut visible = !category.hidden;
+                ui.checkbox(&mut visible, &category.name);
+                category.hidden = !visible;
+            });
+        }
+    }
+
+    pub fn groups(&self) -> &[Group] {
+        &self.groups
+    }
+
+    /// The group listing [txid].
+    pub fn group_of(&self, txid: Txid) -> Option<usize> {
+        self.groups.iter().position(|g| g.txids.contains(&txid))
+    }
+
+    pub fn address_group(&self, address: &str) -> Option<usize> {
+        self.groups
+            .iter()
+            .position(|g| g.addresses.iter().any(|a| a == address))
+    }
+
+    /// The group listing [txid], or else the group of one of its [input_addresses].
+    pub fn member_of(&self, txid: Txid, input_addresses: &[&str]) -> Option<usize> {
+        self.group_of(txid).or_else(|| {
+            input_addresses
+                .iter()
+                .find_map(|address| self.address_group(address))
+        })
+    }
+
+    /// Members of collapsed groups among [txs], given with their input addresses, with the index
+    /// of their group.
+    pub fn collapsed<'a>(
+        &self,
+        txs: impl IntoIterator<Item = (Txid, Vec<&'a str>)>,
+    ) -> HashMap<Txid, usize> {
+        txs.into_iter()
+            .filter_map(|(txid, input_addresses)| {
+                let group = self.member_of(txid, &input_addresses)?;
+                self.groups[group].collapsed.then_some((txid, group))
+            })
+            .collect()
+    }
+
+    pub fn set_collapsed(&mut self, group: usize, collapsed: bool) {
+        if let Some(group) = self.groups.get_mut(group) {
+            group.collapsed = collapsed;
+        }
+    }
+
+    /// The existing group at [group], or a new one if `None`.
+    fn group_mut(&mut self, group: Option<usize>) -> &mut Group {
+        match group {
+            Some(i) => &mut self.groups[i],
+            None => {
+                let n = (1..)
+                    .find(|n| !self.groups.iter().any(|g| g.name == format!("Group {}", n)))
+                    .unwrap();
+                self.groups.push(Group {
+                    name: format!("Group {}", n),
+                    txids: vec![],
+                    addresses: vec![],
+                    collapsed: false,
+                });
+                self.groups.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Moves [txid] to [group], or into a new group if `None`.
+    pub fn add_to_group(&mut self, txid: Txid, group: Option<usize>) {
+        let name = group.map(|i| self.groups[i].name.clone());
+        self.remove_from_group(txid);
+        let group = name.and_then(|name| self.groups.iter().position(|g| g.name == name));
+        self.group_mut(group).txids.push(txid);
+    }
+
+    /// Empty groups are removed.
+    pub fn remove_from_group(&mut self, txid: Txid) {
+        for group in &mut self.groups {
+            group.txids.retain(|t| *t != txid);
+        }
+        self.groups.retain(|g| !g.is_empty());
+    }
+
+    /// Moves [address] to [group], or into a new group if `None`.
+    pub fn add_address_to_group(&mut self, address: &str, group: Option<usize>) {
+        let name = group.map(|i| self.groups[i].name.clone());
+        self.remove_address_from_group(address);
+        let group = name.and_then(|name| self.groups.iter().position(|g| g.name == name));
+        self.group_mut(group).addresses.push(address.to_string());
+    }
+
+    /// Empty groups are removed.
+    pub fn remove_address_from_group(&mut self, address: &str) {
+        for group in &mut self.groups {
+            group.addresses.retain(|a| a != address);
+        }
+        self.groups.retain(|g| !g.is_empty());
+    }
+
+    /// Names must be unique, the name of another group is rejected.
+    pub fn rename_group(&mut self, group: usize, name: String) -> bool {
+        let taken = self
+            .groups
+            .iter()
+            .enumerate()
+            .any(|(i, g)| i != group && g.name == name);
+        if !taken {
+            self.groups[group].name = name;
+        }
+        !taken
+    }
+
+    /// Entries to move something between groups. [add] moves it to a group, or a new one if
+    /// `None`.
+    fn membership_menu(
+        &mut self,
+        ui: &mut egui::Ui,
+        current: Option<usize>,
+        add: impl Fn(&mut Self, Option<usize>),
+        remove: impl Fn(&mut Self),
+    ) {
+        if ui.button("New Group").clicked() {
+            add(self, None);
+            ui.close_menu();
+        }
+        for i in 0..self.groups.len() {
+            if Some(i) != current
+                && ui
+                    .button(format!("Add to {}", self.groups[i].name))
+                    .clicked()
+            {
+                add(self, Some(i));
+                ui.close_menu();
+            }
+        }
+        if let Some(i) = current {
+            ui.separator();
+            if ui
+                .button(format!("Collapse {}", self.groups[i].name))
+                .clicked()
+            {
+                self.set_collapsed(i, true);
+                ui.close_menu();
+            }
+            if ui
+                .button(format!("Remove from {}", self.groups[i].name))
+                .clicked()
+            {
+                remove(self);
+                ui.close_menu();
+            }
+        }
+    }
+
+    pub fn group_menu(&mut self, txid: Txid, ui: &mut egui::Ui) {
+        let current = self.group_of(txid);
+        self.membership_menu(
+            ui,
+            current,
+            |slf, group| slf.add_to_group(txid, group),
+            |slf| slf.remove_from_group(txid),
+        );
+    }
+
+    /// Transactions spending from [address] join its group.
+    pub fn address_group_menu(&mut self, address: &str, ui: &mut egui::Ui) {
+        let current = self.address_group(address);
+        self.membership_menu(
+            ui,
+            current,
+            |slf, group| slf.add_address_to_group(address, group),
+            |slf| slf.remove_address_from_group(address),
+        );
+    }
+
+    /// Names and collapsed state of all groups, for the Groups menu.
+    pub fn groups_ui(&mut self, ui: &mut egui::Ui) {
+        if self.groups.is_empty() {
+            ui.label("Add transactions or addresses to a group from their context menus.");
+            return;
+        }
+        let mut rename = None;
+        let mut ungroup = None;
+        Grid::new("Groups").num_columns(4).show(ui, |ui| {
+            for (i, group) in self.groups.iter_mut().enumerate() {
+                let mut name = group.name.clone();
+                if ui
+                    .add(TextEdit::singleline(&mut name).desired_width(150.0))
+                    .changed()
+                {
+                    rename = Some((i, name));
+                }
+                if group.addresses.is_empty() {
+                    ui.label(format!("{} tx", group.txids.len()));
+                } else {
+                    ui.label(format!(
+                        "{} tx, {} addresses",
+                        group.txids.len(),
+                        group.addresses.len()
+                    ));
+                }
+                ui.checkbox(&mut group.collapsed, "Collapsed");
+                if ui.button("✖").on_hover_text("Ungroup").clicked() {
+                    ungroup = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some((i, name)) = rename {
+            self.rename_group(i, name);
+        }
+        if let Some(i) = ungroup {
+            self.groups.remove(i);
+        }
+    }
+
     pub fn tx_menu(&mut self, txid: Txid, ui: &mut egui::Ui) {
         let mut label = self
             .tx_label
@@ -208,6 +1043,19 @@ impl Annotations {
                 }
             });
             ui.end_row();
+
+            if !self.categories.is_empty() {
+                ui.label("Category:");
+                let current = self.tx_category.get(&txid).cloned();
+                if let Some(category) = category_combo(ui, "TxCategory", &self.categories, current)
+                {
+                    match category {
+                        Some(name) => self.tx_category.insert(txid, name),
+                        None => self.tx_category.remove(&txid),
+                    };
+                }
+                ui.end_row();
+            }
         });
 
         if label.is_empty() {
@@ -216,4 +1064,311 @@ impl Annotations {
             self.tx_label.insert(txid, label);
         }
     }
+
+    /// Label and category for several transactions at once.
+    pub fn txs_menu(&mut self, txids: &[Txid], ui: &mut egui::Ui) {
+        let id = ui.id().with("BulkLabel");
+        let mut label = ui.data_mut(|d| d.get_temp::<String>(id).unwrap_or_default());
+
+        Grid::new("BulkAnnotations").num_columns(2).show(ui, |ui| {
+            ui.label("Label:");
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    TextEdit::singleline(&mut label)
+                        .hint_text("Same label for all")
+                        .desired_width(200.0),
+                );
+                let enter = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                if ui.button("Apply").clicked() || enter {
+                    for txid in txids {
+                        if label.is_empty() {
+                            self.tx_label.remove(txid);
+                        } else {
+                            self.tx_label.insert(*txid, label.clone());
+                        }
+                    }
+                    ui.close_menu();
+                }
+            });
+            ui.end_row();
+
+            if !self.categories.is_empty() {
+                ui.label("Category:");
+                let mut current: Vec<_> = txids.iter().map(|t| self.tx_category.get(t)).collect();
+                current.dedup();
+                let current = match current[..] {
+                    [category] => category.cloned(),
+                    _ => None,
+                };
+                if let Some(category) = category_combo(ui, "TxCategory", &self.categories, current)
+                {
+                    for txid in txids {
+                        match &category {
+                            Some(name) => self.tx_category.insert(*txid, name.clone()),
+                            None => self.tx_category.remove(txid),
+                        };
+                    }
+                }
+                ui.end_row();
+            }
+        });
+
+        ui.data_mut(|d| d.insert_temp(id, label));
+    }
+}
+
+/// Returns the new category if it was changed, `Some(None)` if it was removed.
+fn category_combo(
+    ui: &mut egui::Ui,
+    id_source: &str,
+    categories: &[Category],
+    current: Option<String>,
+) -> Option<Option<String>> {
+    let mut selected = current.clone();
+    ComboBox::from_id_source(id_source)
+        .selected_text(selected.as_deref().unwrap_or("None"))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut selected, None, "None");
+            for category in categories {
+                ui.selectable_value(
+                    &mut selected,
+                    Some(category.name.clone()),
+                    RichText::new(&category.name).color(category.color()),
+                );
+            }
+        });
+    (selected != current).then_some(selected)
+}
+
+/// Read `address,label` rows, e.g. exported from a spreadsheet. A header row is skipped.
+pub fn parse_address_labels(contents: &str) -> Result<Vec<(String, String)>, String> {
+    let mut labels = vec![];
+    for (i, record) in csv::parse(contents)?.into_iter().enumerate() {
+        if i == 0 && record[0].trim().eq_ignore_ascii_case("address") {
+            continue;
+        }
+        match record.as_slice() {
+            [address, label, ..] if !address.trim().is_empty() => {
+                labels.push((address.trim().to_string(), label.trim().to_string()))
+            }
+            _ => return Err(format!("Expected `address,label` in row {}", i + 1)),
+        }
+    }
+    Ok(labels)
+}
+
+/// A reusable label and color that can be applied to a transaction in one click.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Stamp {
+    label: String,
+    color: [u8; 3],
+}
+
+/// Stamps are shared across all workspaces so markup stays consistent.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Stamps {
+    stamps: Vec<Stamp>,
+    #[serde(skip)]
+    input_label: String,
+    #[serde(skip)]
+    input_color: usize,
+}
+
+impl Default for Stamps {
+    fn default() -> Self {
+        let stamp = |label: &str, color: Color32| Stamp {
+            label: label.to_string(),
+            color: [color.r(), color.g(), color.b()],
+        };
+        Self {
+            stamps: vec![
+                stamp("Mixer", Color32::from_rgb(255, 128, 0)),
+                stamp("Exchange", Color32::from_rgb(0, 255, 255)),
+                stamp("Verified ownership", Color32::GREEN),
+            ],
+            input_label: String::new(),
+            input_color: 0,
+        }
+    }
+}
+
+impl Stamps {
+    /// Stamp buttons for the context menu of a transaction.
+    pub fn tx_menu(&self, annotations: &mut Annotations, txid: Txid, ui: &mut egui::Ui) {
+        if self.stamps.is_empty() {
+            ui.label("No stamps defined.");
+        }
+        for stamp in &self.stamps {
+            let [r, g, b] = stamp.color;
+            let color = Color32::from_rgb(r, g, b);
+            ui.horizontal(|ui| {
+                ui.add(Button::new("  ").fill(color).sense(egui::Sense::hover()));
+                if ui.button(&stamp.label).clicked() {
+                    annotations.set_tx_label(txid, stamp.label.clone());
+                    annotations.set_tx_color(txid, color);
+                    ui.close_menu();
+                }
+            });
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let mut remove = None;
+        Grid::new("Stamps").num_columns(3).show(ui, |ui| {
+            for (i, stamp) in self.stamps.iter().enumerate() {
+                let [r, g, b] = stamp.color;
+                ui.add(
+                    Button::new("  ")
+                        .fill(Color32::from_rgb(r, g, b))
+                        .sense(egui::Sense::hover()),
+                );
+                ui.label(&stamp.label);
+                if ui.button("✖").on_hover_text("Remove stamp").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.stamps.remove(i);
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.input_label)
+                    .hint_text("New stamp...")
+                    .desired_width(150.0),
+            );
+            for (i, color) in Annotations::COLORS.iter().enumerate() {
+                let button =
+                    Button::new(if i == self.input_color { "✔" } else { "  " }).fill(*color);
+                if ui.add(button).clicked() {
+                    self.input_color = i;
+                }
+            }
+            let label = self.input_label.trim();
+            if ui
+                .add_enabled(!label.is_empty(), Button::new("Add"))
+                .clicked()
+            {
+                let color = Annotations::COLORS[self.input_color];
+                self.stamps.push(Stamp {
+                    label: label.to_string(),
+                    color: [color.r(), color.g(), color.b()],
+                });
+                self.input_label.clear();
+            }
+        });
+    }
+}
+
+/// Independent of the iteration order.
+fn fingerprint<K: Hash, V: Hash>(map: &HashMap<K, V>) -> u64 {
+    map.iter()
+        .map(|entry| {
+            let mut hasher = DefaultHasher::new();
+            entry.hash(&mut hasher);
+            hasher.finish()
+        })
+        .fold(map.len() as u64, u64::wrapping_add)
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::Color32;
+
+    use super::{Annotations, Category};
+    use crate::bitcoin::Txid;
+
+    fn txid(i: u8) -> Txid {
+        Txid::new(&format!("{:064x}", i)).unwrap()
+    }
+
+    #[test]
+    fn groups_of_addresses() {
+        let mut annotations = Annotations::default();
+        annotations.add_to_group(txid(1), None);
+        annotations.add_address_to_group("bc1qhot", Some(0));
+        annotations.add_address_to_group("bc1qcold", None);
+        assert_eq!(annotations.groups().len(), 2);
+
+        // Listed transactions stay in their group.
+        assert_eq!(annotations.member_of(txid(1), &["bc1qcold"]), Some(0));
+        assert_eq!(annotations.member_of(txid(2), &["bc1qcold"]), Some(1));
+        assert_eq!(annotations.member_of(txid(3), &["bc1qother"]), None);
+
+        annotations.set_collapsed(0, true);
+        let txs = [
+            (txid(1), vec![]),
+            (txid(2), vec!["bc1qother", "bc1qhot"]),
+            (txid(3), vec!["bc1qcold"]),
+        ];
+        let collapsed = annotations.collapsed(txs);
+        assert_eq!(collapsed.len(), 2);
+        assert_eq!(collapsed[&txid(2)], 0);
+
+        annotations.remove_address_from_group("bc1qcold");
+        assert_eq!(annotations.groups().len(), 1);
+        annotations.remove_from_group(txid(1));
+        assert_eq!(annotations.groups()[0].addresses, vec!["bc1qhot"]);
+    }
+
+    #[test]
+    fn unique_group_names() {
+        let mut annotations = Annotations::default();
+        annotations.add_to_group(txid(1), None);
+        annotations.add_to_group(txid(2), None);
+        assert_eq!(annotations.groups()[1].name, "Group 2");
+
+        assert!(!annotations.rename_group(1, "Group 1".to_string()));
+        assert_eq!(annotations.groups()[1].name, "Group 2");
+        assert!(annotations.rename_group(1, "Exchange".to_string()));
+        assert!(annotations.rename_group(1, "Exchange".to_string()));
+        assert_eq!(annotations.groups()[1].name, "Exchange");
+
+        // A new group doesn't take a name in use either.
+        annotations.rename_group(0, "Group 3".to_string());
+        annotations.add_to_group(txid(3), None);
+        assert_eq!(annotations.groups()[2].name, "Group 1");
+    }
+
+    #[test]
+    fn address_categories() {
+        let mut annotations = Annotations::default();
+        for (name, color) in [("Exchange", [0, 0, 255]), ("Victim", [255, 0, 0])] {
+            annotations.categories.push(Category {
+                name: name.to_string(),
+                color,
+                hidden: false,
+            });
+        }
+        annotations
+            .address_category
+            .insert("bc1qexchange".to_string(), "Exchange".to_string());
+        let (first, second) = ((txid(1), 0), (txid(1), 1));
+        annotations
+            .apply_address_labels([(first, "bc1qexchange"), (second, "bc1qother")].into_iter());
+        assert_eq!(
+            annotations.coin_color(first),
+            Some(Color32::from_rgb(0, 0, 255))
+        );
+        assert_eq!(annotations.coin_color(second), None);
+
+        // The coin's own category comes first.
+        annotations
+            .coin_category
+            .insert(first, "Victim".to_string());
+        assert_eq!(
+            annotations.coin_color(first),
+            Some(Color32::from_rgb(255, 0, 0))
+        );
+        annotations.coin_category.clear();
+        annotations.categories[0].hidden = true;
+        assert!(annotations.hidden_coin(first));
+
+        let imported = Annotations::import(&annotations.export()).unwrap();
+        assert_eq!(imported.address_category, annotations.address_category);
+        assert_eq!(imported.categories, annotations.categories);
+    }
 }
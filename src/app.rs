@@ -1,23 +1,54 @@
 use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
 
-use egui::{Context, CursorIcon, Frame, Key, Pos2, Rect, RichText, Sense, Vec2};
+use egui::{Context, CursorIcon, Frame, Key, Modifiers, Pos2, Rect, RichText, Sense, Vec2};
 
 use crate::{
-    annotations::Annotations,
+    annotations::{self, Annotations, Stamps},
+    arrange::Alignment,
+    balance::BalanceChart,
+    bip329,
     bitcoin::{Transaction, Txid},
-    components::{about::About, custom_tx::CustomTx},
+    bookmark::{self, Bookmark, Filters},
+    chain::{self, Network},
+    client::{Client, RequestHandle},
+    cluster::{ClusterView, Clusters},
+    coin_selection::CoinSelection,
+    compare::Compare,
+    components::{
+        about::About, address_history::AddressHistory, changelog::Changelog, custom_tx::CustomTx,
+        wallet_import::WalletImport,
+    },
+    entity::{EntityReport, FlowMatrix, FlowMatrixSettings, ReportCard},
+    ethereum::EthereumView,
+    expand::{Direction, ExpandSettings, Expansion},
     export::{self, Workspace},
+    features::Feature,
     flight::Flight,
     framerate::FrameRate,
     graph::Graph,
+    import::{self, ImportedTx},
+    import_csv::CsvImport,
     layout::Layout,
     loading::Loading,
-    notifications::Notifications,
+    mempool::MempoolWatch,
+    minimap,
+    notifications::{Kind, Notifications, NotifyExt},
+    palette::Palette,
+    path::{self, PathFinder},
     platform::inner as platform,
-    workspaces::{Workspaces, WorkspacesHandle},
-    style::{Theme, ThemeSwitch},
+    repair::RepairReport,
+    sankey::SankeyView,
+    settings::{Preferences, Settings},
+    snapshot::Snapshot,
+    stats::GraphStats,
+    sticky,
+    style::{self, Theme, ThemeSwitch},
+    tax::{self, TaxReportSettings},
     transform::Transform,
-    tx_cache::TxCache,
+    tx_cache::{Refresh, TxCache},
+    utxos::UtxoDashboard,
+    widgets::UiExt,
+    workspaces::{Workspaces, WorkspacesHandle},
 };
 
 #[derive(Default, serde::Deserialize, serde::Serialize)]
@@ -27,6 +58,22 @@ pub struct AppStore {
     transform: Transform,
     theme: Theme,
     about: About,
+    changelog: Changelog,
+    coin_selection: CoinSelection,
+    utxo_dashboard: UtxoDashboard,
+    clusters: ClusterView,
+    expand: ExpandSettings,
+    stamps: Stamps,
+    compare: Compare,
+    entity_report: EntityReport,
+    flow_matrix: FlowMatrixSettings,
+    balance_chart: BalanceChart,
+    sankey: SankeyView,
+    ethereum: EthereumView,
+    tax_report: TaxReportSettings,
+    settings: Settings,
+    path_finder: PathFinder,
+    remember_address_labels: bool,
 }
 
 pub enum Update {
@@ -42,11 +89,95 @@ pub enum Update {
         tx: Transaction,
         pos: Pos2,
     },
+    /// The transactions of a workspace being loaded. Unlike [Update::AddTx], they keep the
+    /// recorded source of the workspace.
+    RestoreTxs {
+        txs: Vec<(Txid, Transaction, Pos2)>,
+    },
     RemoveTx {
         txid: Txid,
     },
+    CopyTxCsv {
+        txid: Txid,
+    },
+    /// Ask how far to expand the graph from the transaction.
+    Expand {
+        txid: Txid,
+    },
+    /// One hop of a running [Expansion] has been fetched.
+    ExpandHop {
+        id: u64,
+        txs: Vec<(Txid, Transaction, Pos2)>,
+    },
+    ImportAddressLabels {
+        labels: Vec<(String, String)>,
+        /// Also apply to transactions added later.
+        remember: bool,
+    },
+    /// An exchange export to map the columns of.
+    OpenCsvImport {
+        import: CsvImport,
+    },
+    /// Wallet labels, e.g. from Sparrow.
+    ImportBip329 {
+        labels: Vec<bip329::Label>,
+    },
+    /// Label all addresses of a cluster, including coins added later.
+    NameCluster {
+        addresses: Vec<String>,
+        name: String,
+    },
+    /// Transactions from another tool's export, or an address history.
+    ImportTxs {
+        txs: Vec<ImportedTx>,
+        /// Don't announce the import, the caller reports progress itself.
+        quiet: bool,
+    },
+    ImportPreferences {
+        preferences: Preferences,
+    },
     LoadWorkspace {
-        data: Workspace,
+        data: Box<Workspace>,
+        /// Whether to fetch from the backend recorded in `data`.
+        pinned: bool,
+        /// Someone else's workspace, where edits aren't saved.
+        view_only: bool,
+    },
+    /// Switch the open workspace to another network.
+    SetNetwork {
+        network: Network,
+    },
+    /// Fiat currency of the open workspace, `None` to hide fiat values.
+    SetCurrency {
+        currency: Option<String>,
+    },
+    /// From the defaults of a new workspace.
+    SetExpandSettings {
+        settings: ExpandSettings,
+    },
+    /// Check whether unconfirmed transactions confirmed or disappeared.
+    PollMempool {
+        txids: Vec<Txid>,
+    },
+    MempoolPolled {
+        refreshes: Vec<Refresh>,
+    },
+    TipHeight {
+        height: u32,
+    },
+    /// An unconfirmed transaction is no longer known to the chain source.
+    TxGone {
+        txid: Txid,
+        /// Spends the same coin, if it was replaced.
+        replaced_by: Option<Txid>,
+    },
+    RefreshWorkspace {
+        /// Show a summary of what changed.
+        report: bool,
+    },
+    ReplaceTxs {
+        txs: Vec<Transaction>,
+        report: Option<RepairReport>,
     },
 }
 
@@ -61,11 +192,44 @@ pub struct App {
 
     flight: Flight,
     ui_size: Vec2,
+    /// Where the graph is drawn.
+    canvas_rect: Rect,
+    /// Where the context menu of the canvas was opened, in graph coordinates.
+    canvas_menu_pos: Pos2,
+    /// Start of a Shift-drag selecting everything in its rectangle, in screen coordinates.
+    rubber_band: Option<Pos2>,
+    snapshot: Snapshot,
     custom_tx: CustomTx,
+    quick_add: CustomTx,
+    address_history: AddressHistory,
+    wallet_import: WalletImport,
+    /// Requests for the transactions of the workspace being loaded.
+    workspace_requests: Vec<RequestHandle>,
+    /// Transaction the expand dialog is open for.
+    expand_target: Option<Txid>,
+    expansion: Option<Expansion>,
+    expansion_count: u64,
+    csv_import: Option<CsvImport>,
+    /// Of the open workspace.
+    currency: Option<String>,
+    /// Where the transactions of the open workspace were fetched from, see
+    /// [export::Workspace::source].
+    source: Option<export::DataSource0>,
+    /// The open workspace isn't the user's, so edits are only kept by forking it.
+    view_only: bool,
+    cost_basis_method: tax::Method,
+    mempool: MempoolWatch,
     framerate: FrameRate,
     about_rect: Option<egui::Rect>,
+    developer_console: bool,
     notifications: Notifications,
     workspaces: Workspaces,
+    /// Ctrl+F, finds transactions in the graph.
+    search: Palette,
+    /// By graph revision and coin labels.
+    stats: Option<((u64, u64), GraphStats)>,
+    /// By graph revision.
+    clusters: Option<(u64, Clusters)>,
 }
 
 impl App {
@@ -78,7 +242,9 @@ impl App {
         );
         fonts.font_data.insert(
             "iosevka".to_owned(),
-            egui::FontData::from_static(include_bytes!("./fonts/iosevka-custom-regular.subset.ttf")),
+            egui::FontData::from_static(include_bytes!(
+                "./fonts/iosevka-custom-regular.subset.ttf"
+            )),
         );
         fonts.font_data.insert(
             "iosevka-bold".to_owned(),
@@ -97,16 +263,17 @@ impl App {
             .entry(egui::FontFamily::Proportional)
             .or_default()
             .insert(0, "iosevka".to_owned());
-        fonts
-            .families
-            .insert(egui::FontFamily::Name("bold".into()), vec!["iosevka-bold".to_owned()]);
+        fonts.families.insert(
+            egui::FontFamily::Name("bold".into()),
+            vec!["iosevka-bold".to_owned()],
+        );
         cc.egui_ctx.set_fonts(fonts);
 
         let (update_sender, update_receiver) = channel();
 
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
-        let (store, workspaces) = if let Some(storage) = cc.storage {
+        let (mut store, workspaces) = if let Some(storage) = cc.storage {
             let store = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
             let workspaces = Workspaces::load(&cc.egui_ctx, storage, update_sender.clone());
             (store, workspaces)
@@ -117,11 +284,9 @@ impl App {
             )
         };
 
-        update_sender
-            .send(Update::LoadWorkspace {
-                data: workspaces.current_data(),
-            })
-            .unwrap();
+        store.settings.apply(&cc.egui_ctx);
+        store.changelog.on_start();
+        update_sender.send(workspaces.load_current()).unwrap();
 
         platform::add_route_listener(update_sender.clone(), cc.egui_ctx.clone());
 
@@ -135,11 +300,32 @@ impl App {
 
             flight: Flight::new(),
             ui_size: platform::get_viewport_dimensions().unwrap_or_default(),
+            canvas_rect: Rect::NOTHING,
+            canvas_menu_pos: Pos2::ZERO,
+            rubber_band: None,
+            snapshot: Snapshot::default(),
             custom_tx: Default::default(),
+            quick_add: Default::default(),
+            address_history: Default::default(),
+            wallet_import: Default::default(),
+            workspace_requests: vec![],
+            expand_target: None,
+            expansion: None,
+            expansion_count: 0,
+            csv_import: None,
+            currency: None,
+            source: None,
+            view_only: false,
+            cost_basis_method: Default::default(),
+            mempool: Default::default(),
             framerate: FrameRate::default(),
             about_rect: None,
+            developer_console: false,
             notifications: Notifications::new(&cc.egui_ctx),
             workspaces,
+            search: Palette::new(Key::F).substring(),
+            stats: None,
+            clusters: None,
         }
     }
 
@@ -185,34 +371,629 @@ impl App {
                 }
             }
             Update::AddTx { txid, tx, pos } => {
+                if self.graph.get_tx_pos(txid).is_none() {
+                    self.source = Some(Client::data_source(ctx));
+                }
                 self.graph.add_tx(txid, tx, pos);
+                self.annotations
+                    .apply_address_labels(self.graph.coin_addresses(Some(txid)).into_iter());
+            }
+            Update::RestoreTxs { txs } => {
+                for (txid, tx, pos) in txs {
+                    self.graph.add_tx(txid, tx, pos);
+                    self.annotations
+                        .apply_address_labels(self.graph.coin_addresses(Some(txid)).into_iter());
+                }
             }
             Update::RemoveTx { txid } => {
                 self.graph.remove_tx(txid);
             }
-            Update::LoadWorkspace { data } => {
+            Update::CopyTxCsv { txid } => {
+                let label = self.annotations.tx_label(txid);
+                let unit = self.store.settings.export_unit;
+                let currency = self.currency.as_deref();
+                if let Some((csv, priced)) = self.graph.export_csv(ctx, txid, label, unit, currency)
+                {
+                    ctx.output_mut(|o| o.copied_text = csv);
+                    if !priced {
+                        ctx.notify_warn(
+                            "Copied CSV without fiat values",
+                            Some(
+                                "The price of the day isn't loaded yet. Try again in a moment."
+                                    .to_string(),
+                            ),
+                        );
+                    }
+                }
+            }
+            Update::Expand { txid } => {
+                self.expand_target = Some(txid);
+            }
+            Update::ExpandHop { id, txs } => {
+                let Some(expansion) = self.expansion.as_mut().filter(|e| e.id == id) else {
+                    return;
+                };
+                expansion.requests.clear();
+                expansion.depth += 1;
+                let mut frontier = vec![];
+                for (txid, tx, pos) in txs {
+                    if self.graph.get_tx_pos(txid).is_none() {
+                        self.graph.add_tx(txid, tx, pos);
+                        self.annotations.apply_address_labels(
+                            self.graph.coin_addresses(Some(txid)).into_iter(),
+                        );
+                        expansion.added += 1;
+                        frontier.push(txid);
+                    }
+                }
+                if !frontier.is_empty() {
+                    self.source = Some(Client::data_source(ctx));
+                }
+                self.expand_hop(ctx, frontier);
+            }
+            Update::ImportAddressLabels { labels, remember } => {
+                let network = Client::network(ctx);
+                let foreign = labels
+                    .iter()
+                    .filter(|(address, _)| !network.matches_address(address))
+                    .count();
+                if foreign > 0 {
+                    ctx.notify_warn(
+                        format!(
+                            "{} address(es) are not {} addresses.",
+                            foreign,
+                            network.name()
+                        ),
+                        Some("Check the network of the workspace."),
+                    );
+                }
+                let (applied, unmatched) = self.annotations.import_address_labels(
+                    labels,
+                    self.graph.coin_addresses(None).into_iter(),
+                    remember,
+                );
+                ctx.notify_success(format!(
+                    "Labelled {} coin(s), {} address(es) didn't match.",
+                    applied, unmatched
+                ));
+            }
+            Update::OpenCsvImport { import } => {
+                self.csv_import = Some(import);
+            }
+            Update::ImportBip329 { labels } => {
+                match self
+                    .annotations
+                    .import_bip329(labels, self.graph.coin_addresses(None).into_iter())
+                {
+                    Ok((imported, 0)) => {
+                        ctx.notify_success(format!("Imported {} label(s).", imported))
+                    }
+                    Ok((imported, skipped)) => ctx.notify_warn(
+                        format!("Imported {} label(s).", imported),
+                        Some(format!(
+                            "Skipped {} input, key or xpub label(s), which can't be shown.",
+                            skipped
+                        )),
+                    ),
+                    Err(e) => ctx.notify_error("Could not import labels", Some(e)),
+                }
+            }
+            Update::NameCluster { addresses, name } => {
+                let labels = addresses
+                    .into_iter()
+                    .map(|address| (address, name.clone()))
+                    .collect();
+                let (applied, _) = self.annotations.import_address_labels(
+                    labels,
+                    self.graph.coin_addresses(None).into_iter(),
+                    true,
+                );
+                ctx.notify_success(format!("Named cluster `{}` ({} coin(s)).", name, applied));
+            }
+            Update::ImportTxs { txs, quiet } => {
+                let center = self
+                    .store
+                    .transform
+                    .pos_from_screen((self.ui_size / 2.0).to_pos2());
+                let spread = 100.0 * (txs.len() as f32).sqrt();
+                let mut added = 0;
+                for ImportedTx { txid, label } in txs {
+                    if let Some(label) = label {
+                        if self.annotations.tx_label(txid).is_none() {
+                            self.annotations.set_tx_label(txid, label);
+                        }
+                    }
+                    if self.graph.get_tx_pos(txid).is_some() {
+                        continue;
+                    }
+                    added += 1;
+                    let pos = center + platform::get_random_vec2(spread);
+                    let sender = self.update_sender.clone();
+                    TxCache::get(ctx, txid, move |tx| {
+                        sender.send(Update::AddTx { txid, tx, pos }).unwrap();
+                    });
+                }
+                if !quiet {
+                    ctx.notify_info(format!("Importing {} transaction(s).", added));
+                }
+            }
+            Update::ImportPreferences { preferences } => {
+                self.store.settings.import_preferences(ctx, &preferences);
+                self.store.theme = preferences.theme;
+                self.store.layout = preferences.layout;
+                self.store.stamps = preferences.stamps;
+                ctx.notify_success("Imported settings.");
+            }
+            Update::LoadWorkspace {
+                data,
+                pinned,
+                view_only,
+            } => {
+                let data = *data;
+                self.view_only = view_only;
+                Client::pin(ctx, None);
+                let network = data
+                    .source
+                    .as_ref()
+                    .and_then(|source| Network::from_name(&source.network))
+                    .unwrap_or_default();
+                Client::set_network(ctx, network);
+                if let Some(source) = &data.source {
+                    let current = Client::data_source(ctx);
+                    if pinned && source.network == current.network {
+                        Client::pin(ctx, Some(source.api_base.clone()));
+                    } else if *source != current {
+                        ctx.notify_warn(
+                            "Workspace data came from a different source",
+                            Some(format!(
+                                "Recorded: {} ({}), configured: {} ({})",
+                                source.api_base, source.network, current.api_base, current.network
+                            )),
+                        );
+                    }
+                }
+                self.source = data.source;
                 self.annotations = data.annotations;
+                self.currency = data.currency;
+                self.cost_basis_method = data.cost_basis_method;
+                self.store.ethereum.import(ctx, data.ethereum.as_ref());
                 self.store.layout.import(&data.layout);
                 self.store.transform = Transform::import(data.transform);
                 self.graph = Graph::default();
 
+                // Don't let a previous workspace that's still loading, or anything else still
+                // running in the background, add its transactions.
+                for request in self.workspace_requests.drain(..) {
+                    request.cancel(ctx);
+                }
+                self.cancel_expansion(ctx);
+                self.address_history.cancel(ctx);
+                self.wallet_import.cancel(ctx);
+
                 let txids: Vec<_> = data.transactions.iter().map(|tx| tx.txid).collect();
                 let sender = self.update_sender.clone();
-                TxCache::get_batch(ctx, &txids, move |txs| {
-                    for ptx in data.transactions {
-                        let tx = txs.get(&ptx.txid).unwrap();
-                        sender
-                            .send(Update::AddTx {
-                                txid: ptx.txid,
-                                tx: tx.clone(),
-                                pos: ptx.position,
-                            })
-                            .unwrap();
+                self.workspace_requests = TxCache::get_batch(ctx, &txids, move |txs| {
+                    let txs = data
+                        .transactions
+                        .into_iter()
+                        .map(|ptx| (ptx.txid, txs[&ptx.txid].clone(), ptx.position))
+                        .collect();
+                    sender.send(Update::RestoreTxs { txs }).unwrap();
+                });
+            }
+            Update::SetNetwork { network } => {
+                Client::set_network(ctx, network);
+                // Only empty workspaces change their network, so nothing came from elsewhere.
+                self.source = Some(Client::data_source(ctx));
+                ctx.notify_info(format!("Switched workspace to {}.", network.name()));
+            }
+            Update::SetCurrency { currency } => {
+                self.currency = currency;
+            }
+            Update::SetExpandSettings { settings } => {
+                self.store.expand = settings;
+            }
+            Update::PollMempool { txids } => {
+                // The backend doesn't serve the tip, and nothing goes to Esplora unless chosen.
+                if chain::uses_esplora(ctx) {
+                    let sender = self.update_sender.clone();
+                    chain::fetch_tip_height(ctx, move |result| {
+                        if let Ok(height) = result {
+                            sender.send(Update::TipHeight { height }).unwrap();
+                        }
+                    });
+                }
+                let sender = self.update_sender.clone();
+                TxCache::refresh_batch(ctx, &txids, move |refreshes| {
+                    sender.send(Update::MempoolPolled { refreshes }).unwrap();
+                });
+            }
+            Update::MempoolPolled { refreshes } => {
+                for refresh in refreshes {
+                    let txid = refresh.txid;
+                    if let Some(tx) = refresh.changed() {
+                        if tx.block_height > 0 {
+                            ctx.notify_success(format!(
+                                "{} confirmed in block {}.",
+                                self.tx_name(txid),
+                                tx.block_height
+                            ));
+                        }
+                        self.graph.replace_tx(txid, tx.clone());
+                    } else if refresh.not_found {
+                        // Other errors are tried again next time.
+                        let sender = self.update_sender.clone();
+                        let first_input = self.graph.first_input(txid);
+                        match first_input.filter(|_| chain::uses_esplora(ctx)) {
+                            // Find out whether another transaction spends the same coin.
+                            Some((funding_txid, vout)) => {
+                                chain::fetch_outspend(ctx, funding_txid, vout, move |result| {
+                                    let replaced_by = result.ok().flatten();
+                                    if replaced_by != Some(txid) {
+                                        sender.send(Update::TxGone { txid, replaced_by }).unwrap();
+                                    }
+                                });
+                            }
+                            None => sender
+                                .send(Update::TxGone {
+                                    txid,
+                                    replaced_by: None,
+                                })
+                                .unwrap(),
+                        }
                     }
+                }
+            }
+            Update::TipHeight { height } => {
+                self.graph.set_tip_height(height);
+            }
+            Update::TxGone { txid, replaced_by } => {
+                self.mempool.mark_gone(txid);
+                match replaced_by {
+                    Some(by) => ctx.notify_warn(
+                        format!("{} was replaced.", self.tx_name(txid)),
+                        Some(format!("Its first input is now spent by {}.", by)),
+                    ),
+                    None => ctx.notify_warn(
+                        format!("{} was dropped from the mempool.", self.tx_name(txid)),
+                        None::<&str>,
+                    ),
+                }
+            }
+            Update::RefreshWorkspace { report } => {
+                let sender = self.update_sender.clone();
+                TxCache::refresh_batch(ctx, &self.graph.txids(), move |refreshes| {
+                    sender
+                        .send(Update::ReplaceTxs {
+                            txs: refreshes
+                                .iter()
+                                .filter_map(|r| r.changed().cloned())
+                                .collect(),
+                            report: report.then(|| RepairReport::new(&refreshes)),
+                        })
+                        .unwrap();
                 });
             }
+            Update::ReplaceTxs { txs, report } => {
+                if let Some(report) = report {
+                    let kind = if report.is_suspicious() {
+                        Kind::Warn
+                    } else {
+                        Kind::Success
+                    };
+                    ctx.notify(kind, "Re-validated workspace", Some(report), 12.0);
+                } else if !txs.is_empty() {
+                    ctx.notify_info(format!(
+                        "Refreshed workspace: {} transaction(s) changed.",
+                        txs.len()
+                    ));
+                }
+                for tx in txs {
+                    self.graph.replace_tx(tx.txid, tx);
+                }
+            }
+        }
+    }
+
+    /// Label, or shortened txid.
+    fn tx_name(&self, txid: Txid) -> String {
+        self.annotations
+            .tx_label(txid)
+            .unwrap_or_else(|| format!("Transaction {}…", &txid.hex_string()[..12]))
+    }
+
+    /// Shows the view saved in the [i]th bookmark, and its filters if they were saved.
+    fn jump_to_bookmark(&mut self, ctx: &Context, i: usize) {
+        let Some(bookmark) = self.annotations.bookmarks().get(i).cloned() else {
+            return;
+        };
+        self.store.transform =
+            Transform::centered(bookmark.center, bookmark.zoom, self.canvas_rect.center());
+        self.flight.interrupt();
+        if let Some(filters) = bookmark.filters {
+            self.annotations
+                .set_hidden_categories(&filters.hidden_categories);
+            self.store.layout.edge_filter = filters.edge_filter;
+        }
+        ctx.notify_info(bookmark.name);
+    }
+
+    /// Escape ends the multi-selection, Delete removes it.
+    fn multi_selection_keys(&mut self, ctx: &Context) {
+        let (escape, delete) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::Escape),
+                i.consume_key(Modifiers::NONE, Key::Delete)
+                    || i.consume_key(Modifiers::NONE, Key::Backspace),
+            )
+        });
+        if escape {
+            self.graph.deselect();
+        } else if delete {
+            self.remove_multi_selection();
+        }
+    }
+
+    fn remove_multi_selection(&mut self) {
+        let txids: Vec<Txid> = self.graph.multi_selection().iter().copied().collect();
+        self.graph.deselect();
+        for txid in txids {
+            self.graph.remove_tx(txid);
+        }
+    }
+
+    /// Bulk actions on the multi-selection.
+    fn selection_menu(&mut self, ui: &mut egui::Ui) {
+        if ui.button("Select All").clicked() {
+            self.graph.select_all();
+            ui.close_menu();
+        }
+        let txids: Vec<Txid> = self.graph.multi_selection().iter().copied().collect();
+        if txids.is_empty() {
+            ui.label("Shift-click transactions or Shift-drag on the canvas to select several.");
+            return;
+        }
+        if ui.button("Clear Selection").clicked() {
+            self.graph.clear_multi_selection();
+            ui.close_menu();
+        }
+        ui.separator();
+        ui.bold(format!("{} transaction(s) selected", txids.len()));
+        ui.menu_button("Annotate", |ui| self.annotations.txs_menu(&txids, ui));
+        ui.menu_button("Align", |ui| {
+            for alignment in Alignment::ALL {
+                if ui.button(alignment.name()).clicked() {
+                    self.graph.align(alignment, &style::get(ui));
+                    ui.close_menu();
+                }
+            }
+        });
+        if ui.button("Collapse into Group").clicked() {
+            for (i, txid) in txids.iter().enumerate() {
+                let group = (i > 0).then(|| self.annotations.groups().len() - 1);
+                self.annotations.add_to_group(*txid, group);
+            }
+            let group = self.annotations.groups().len() - 1;
+            self.annotations.set_collapsed(group, true);
+            self.graph.clear_multi_selection();
+            ui.close_menu();
+        }
+        if ui.button("Extract to New Workspace").clicked() {
+            let data = self.export_selection();
+            let name = format!("{} (extract)", self.workspaces.current_name());
+            WorkspacesHandle::new_workspace(ui.ctx(), name, data);
+            ui.close_menu();
+        }
+        ui.menu_button("Export", |ui| {
+            if ui.button("Copy as JSON").clicked() {
+                let data = self.export_selection();
+                ui.output_mut(|o| o.copied_text = serde_json::to_string(&data).unwrap());
+                ui.ctx().notify_success(format!(
+                    "Exported {} transaction(s) to clipboard.",
+                    data.transactions.len()
+                ));
+                ui.close_menu();
+            }
+            if ui.button("Copy Compact").clicked() {
+                let data = self.export_selection();
+                ui.output_mut(|o| o.copied_text = data.to_compact());
+                ui.ctx().notify_success(format!(
+                    "Exported {} transaction(s) to clipboard.",
+                    data.transactions.len()
+                ));
+                ui.close_menu();
+            }
+            if ui.button("Save as JSON…").clicked() {
+                let data = self.export_selection();
+                let contents = serde_json::to_string_pretty(&data).unwrap();
+                let file_name = self.workspaces.selection_file_name();
+                if platform::save_file(ui.ctx(), &file_name, &contents) {
+                    ui.ctx().notify_success(format!(
+                        "Exported {} transaction(s).",
+                        data.transactions.len()
+                    ));
+                }
+                ui.close_menu();
+            }
+        });
+        ui.separator();
+        if ui.button("Remove").clicked() {
+            self.remove_multi_selection();
+            ui.close_menu();
+        }
+    }
+
+    /// Computes [Self::clusters] again if the graph changed.
+    fn update_clusters(&mut self) {
+        let revision = self.graph.revision();
+        if self.clusters.as_ref().map(|(r, _)| *r) != Some(revision) {
+            self.clusters = Some((revision, Clusters::new(self.graph.input_addresses())));
+        }
+    }
+
+    /// Only the selected transactions and their annotations.
+    fn export_selection(&self) -> export::Workspace {
+        let selection = self.graph.multi_selection();
+        let addresses = selection
+            .iter()
+            .flat_map(|txid| self.graph.coin_addresses(Some(*txid)))
+            .map(|(_, address)| address)
+            .collect();
+        export::Workspace::new(
+            &self.graph,
+            &self.annotations,
+            &self.store.layout,
+            &self.store.transform,
+            self.source.clone(),
+            self.currency.clone(),
+            self.cost_basis_method,
+            None,
+        )
+        .extract(selection, &addresses)
+    }
+
+    /// Arrow keys follow the edges of the selected transaction, Tab cycles through its outputs,
+    /// Enter expands and Delete removes it. Escape clears the selection.
+    fn navigate(&mut self, ctx: &Context, txid: Txid) {
+        let (escape, enter, delete, tab, shift_tab) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::Escape),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+                i.consume_key(Modifiers::NONE, Key::Delete)
+                    || i.consume_key(Modifiers::NONE, Key::Backspace),
+                i.consume_key(Modifiers::NONE, Key::Tab),
+                i.consume_key(Modifiers::SHIFT, Key::Tab),
+            )
+        });
+        let direction = [
+            (Key::ArrowUp, Vec2::UP),
+            (Key::ArrowDown, Vec2::DOWN),
+            (Key::ArrowLeft, Vec2::LEFT),
+            (Key::ArrowRight, Vec2::RIGHT),
+        ]
+        .into_iter()
+        .find(|(key, _)| ctx.input_mut(|i| i.consume_key(Modifiers::NONE, *key)))
+        .map(|(_, direction)| direction);
+
+        if escape {
+            self.graph.deselect();
+        } else if enter {
+            self.apply_update(ctx, Update::Expand { txid });
+        } else if delete {
+            self.graph.deselect();
+            self.apply_update(ctx, Update::RemoveTx { txid });
+        } else if tab || shift_tab {
+            self.graph.cycle_output(shift_tab);
+        } else if let Some(direction) = direction {
+            if let Some(next) = self.graph.connected_in_direction(txid, direction) {
+                self.apply_update(
+                    ctx,
+                    Update::LoadOrSelectTx {
+                        txid: next,
+                        pos: None,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Expands from all of [origins] at once, the first one is shown as the origin.
+    fn start_expansion(&mut self, ctx: &Context, origins: Vec<Txid>, settings: ExpandSettings) {
+        let Some(origin) = origins.first() else {
+            return;
+        };
+        self.cancel_expansion(ctx);
+        self.expansion_count += 1;
+        self.expansion = Some(Expansion::new(self.expansion_count, *origin, settings));
+        self.expand_hop(ctx, origins);
+    }
+
+    fn cancel_expansion(&mut self, ctx: &Context) {
+        if let Some(mut expansion) = self.expansion.take() {
+            expansion.cancel(ctx);
+            self.store.path_finder.finish_fetching();
         }
     }
+
+    /// Searches the loaded graph for the path finder's endpoints. Without a path and with
+    /// [fetch], first expands that many hops from the source and then searches again.
+    fn find_path(&mut self, ctx: &Context, fetch: Option<usize>) {
+        let (from, to) = self.store.path_finder.endpoints();
+        if from.is_empty() || to.is_empty() {
+            self.store
+                .path_finder
+                .set_result(Err("Enter a source and a destination.".to_string()));
+            return;
+        }
+
+        // Coins leave an address in the transactions spending from it, if those are loaded.
+        let mut sources = self.graph.resolve(&from, true);
+        if sources.is_empty() {
+            sources = self.graph.resolve(&from, false);
+        }
+        let targets = self.graph.resolve(&to, false);
+        let path = path::shortest(&sources, &targets, |txid| self.graph.spends(txid));
+
+        if let (None, Some(depth), false) = (&path, fetch, sources.is_empty()) {
+            self.cancel_expansion(ctx);
+            self.store.path_finder.start_fetching();
+            let settings = ExpandSettings {
+                depth,
+                direction: Direction::Forward,
+                ..self.store.expand.clone()
+            };
+            self.start_expansion(ctx, sources, settings);
+            return;
+        }
+
+        let result = if sources.is_empty() {
+            Err(format!("The source {} isn't in the graph.", from))
+        } else if targets.is_empty() {
+            Err(format!("The destination {} isn't in the graph.", to))
+        } else {
+            path.ok_or_else(|| {
+                "Coins from the source don't reach the destination in the loaded graph.".to_string()
+            })
+        };
+        self.graph
+            .set_path(result.as_ref().map_or(vec![], |path| path.txids.clone()));
+        self.store.path_finder.set_result(result);
+    }
+
+    /// Fetch the neighbors of [frontier], or finish the running expansion.
+    fn expand_hop(&mut self, ctx: &Context, frontier: Vec<Txid>) {
+        let Some(expansion) = self.expansion.as_mut() else {
+            return;
+        };
+
+        let next = expansion.next_hop(&self.graph, frontier);
+        if next.is_empty() {
+            let mut message = format!(
+                "Expanded {} hop(s), added {} transaction(s).",
+                expansion.depth, expansion.added
+            );
+            if expansion.remaining() == 0 {
+                message += " Stopped at the transaction limit.";
+            }
+            ctx.notify_success(message);
+            self.expansion = None;
+            if self.store.path_finder.finish_fetching() {
+                self.find_path(ctx, None);
+            }
+            return;
+        }
+
+        let id = expansion.id;
+        let txids: Vec<Txid> = next.iter().map(|(txid, _)| *txid).collect();
+        let sender = self.update_sender.clone();
+        expansion.requests = TxCache::get_available(ctx, &txids, move |mut txs| {
+            let txs = next
+                .into_iter()
+                .filter_map(|(txid, pos)| Some((txid, txs.remove(&txid)?, pos)))
+                .collect();
+            sender.send(Update::ExpandHop { id, txs }).unwrap();
+        });
+    }
 }
 
 impl eframe::App for App {
@@ -251,6 +1032,14 @@ impl eframe::App for App {
                             self.custom_tx.ui(ui, load_tx);
                         });
 
+                        ui.menu_button("Add Address History", |ui| {
+                            self.address_history.ui(ui, self.update_sender.clone());
+                        });
+
+                        ui.menu_button("Add Wallet (xpub)", |ui| {
+                            self.wallet_import.ui(ui, self.update_sender.clone());
+                        });
+
                         ui.menu_button("Hallo of Fame", |ui| {
                             ui.allocate_space(Vec2::new(200., 0.));
 
@@ -264,8 +1053,16 @@ impl eframe::App for App {
                             ui.separator();
                             ui.label(RichText::new("(from kycp.org)").strong());
                         });
+
+                        ui.separator();
+                        if ui.button("Find Path…").clicked() {
+                            self.store.path_finder.open();
+                            ui.close_menu();
+                        }
                     });
 
+                    self.quick_add.quick_add_ui(ui, load_tx);
+
                     ui.menu_button("Reset", |ui| {
                         if ui.button("Zoom").clicked() {
                             self.store
@@ -283,16 +1080,419 @@ impl eframe::App for App {
                         }
                         if ui.button("All").clicked() {
                             self.store = AppStore::default();
+                            self.store.settings.apply(ui.ctx());
                             ui.close_menu();
                         }
                     });
 
                     ui.menu_button("Layout", |ui| {
-                        self.store.layout.ui(ui);
+                        let currency = self.currency.as_deref();
+                        let Some(algorithm) = self.store.layout.ui(ui, currency) else {
+                            return;
+                        };
+                        let only = if !self.store.layout.arrange_selected_only {
+                            None
+                        } else if !self.graph.multi_selection().is_empty() {
+                            Some(self.graph.multi_selection().clone())
+                        } else {
+                            let Some(txid) = self.graph.selected() else {
+                                ui.ctx()
+                                    .notify_warn("Select a transaction first.", None::<&str>);
+                                return;
+                            };
+                            Some(self.graph.component(txid))
+                        };
+                        self.graph
+                            .arrange(algorithm, &style::get(ui), only.as_ref());
+                        if self.store.layout.force_params.active {
+                            self.store.layout.force_params.active = false;
+                            ui.ctx().notify_info(
+                                "Paused the layout algorithm so the arrangement stays in place.",
+                            );
+                        }
+                        ui.close_menu();
+                    });
+
+                    ui.menu_button("Compare", |ui| {
+                        self.store.compare.ui(ui);
+                    });
+
+                    ui.menu_button("Stamps", |ui| {
+                        self.store.stamps.ui(ui);
+                    });
+
+                    ui.menu_button("Groups", |ui| {
+                        self.annotations.groups_ui(ui);
+                    });
+
+                    ui.menu_button("Categories", |ui| {
+                        self.annotations.categories_ui(ui);
+                    });
+
+                    ui.menu_button("Selection", |ui| self.selection_menu(ui));
+
+                    ui.menu_button("Views", |ui| {
+                        match bookmark::menu(ui, self.annotations.bookmarks_mut()) {
+                            Some(bookmark::Action::Jump(i)) => self.jump_to_bookmark(ctx, i),
+                            Some(bookmark::Action::Save { name, filters }) => {
+                                let filters = filters.then(|| Filters {
+                                    hidden_categories: self.annotations.hidden_categories(),
+                                    edge_filter: self.store.layout.edge_filter.clone(),
+                                });
+                                let transform = &self.store.transform;
+                                self.annotations.bookmarks_mut().push(Bookmark {
+                                    name,
+                                    center: transform.pos_from_screen(self.canvas_rect.center()),
+                                    zoom: transform.zoom_level(),
+                                    filters,
+                                });
+                            }
+                            None => {}
+                        }
+                    });
+
+                    ui.menu_button("Labels", |ui| {
+                        ui.checkbox(
+                            &mut self.store.remember_address_labels,
+                            "Also label transactions added later",
+                        );
+                        if ui
+                            .button("Import Address Labels…")
+                            .on_hover_text("CSV file with `address,label` rows.")
+                            .clicked()
+                        {
+                            let sender = self.update_sender.clone();
+                            let ctx = ui.ctx().clone();
+                            let remember = self.store.remember_address_labels;
+                            platform::open_file(ui.ctx(), "csv", move |file_name, contents| {
+                                match annotations::parse_address_labels(&contents) {
+                                    Ok(labels) => {
+                                        sender
+                                            .send(Update::ImportAddressLabels { labels, remember })
+                                            .unwrap();
+                                        ctx.request_repaint();
+                                    }
+                                    Err(e) => ctx.notify_error(
+                                        format!("Could not import `{}`", file_name),
+                                        Some(e),
+                                    ),
+                                }
+                            });
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        if ui
+                            .button("Import BIP-329…")
+                            .on_hover_text("Wallet labels, e.g. exported from Sparrow.")
+                            .clicked()
+                        {
+                            let sender = self.update_sender.clone();
+                            let ctx = ui.ctx().clone();
+                            platform::open_file(ui.ctx(), "jsonl", move |file_name, contents| {
+                                match bip329::parse(&contents) {
+                                    Ok(labels) => {
+                                        sender.send(Update::ImportBip329 { labels }).unwrap();
+                                        ctx.request_repaint();
+                                    }
+                                    Err(e) => ctx.notify_error(
+                                        format!("Could not import `{}`", file_name),
+                                        Some(e),
+                                    ),
+                                }
+                            });
+                            ui.close_menu();
+                        }
+                        if ui.button("Export BIP-329…").clicked() {
+                            let labels = self.annotations.bip329_labels();
+                            let contents = bip329::write(&labels);
+                            if platform::save_file(ui.ctx(), "txgraph-labels.jsonl", &contents) {
+                                ui.ctx()
+                                    .notify_success(format!("Exported {} label(s).", labels.len()));
+                            }
+                            ui.close_menu();
+                        }
+                    });
+
+                    if self
+                        .store
+                        .settings
+                        .features
+                        .is_enabled(Feature::ForeignImport)
+                    {
+                        ui.menu_button("Import", |ui| {
+                            ui.label("Transactions and labels from other tools:");
+                            for format in import::Format::ALL {
+                                if ui.button(format!("{}…", format.name())).clicked() {
+                                    let sender = self.update_sender.clone();
+                                    let ctx = ui.ctx().clone();
+                                    platform::open_file(
+                                        ui.ctx(),
+                                        format.extension(),
+                                        move |file_name, contents| match format.parse(&contents) {
+                                            Ok(txs) => {
+                                                sender
+                                                    .send(Update::ImportTxs { txs, quiet: false })
+                                                    .unwrap();
+                                                ctx.request_repaint();
+                                            }
+                                            Err(e) => ctx.notify_error(
+                                                format!("Could not import `{}`", file_name),
+                                                Some(e),
+                                            ),
+                                        },
+                                    );
+                                    ui.close_menu();
+                                }
+                            }
+                            ui.separator();
+                            if ui
+                                .button("Exchange CSV…")
+                                .on_hover_text("Kraken, Coinbase or other exports with txids.")
+                                .clicked()
+                            {
+                                let sender = self.update_sender.clone();
+                                let ctx = ui.ctx().clone();
+                                platform::open_file(ui.ctx(), "csv", move |file_name, contents| {
+                                    match CsvImport::new(file_name.clone(), &contents) {
+                                        Ok(import) => {
+                                            sender.send(Update::OpenCsvImport { import }).unwrap();
+                                            ctx.request_repaint();
+                                        }
+                                        Err(e) => ctx.notify_error(
+                                            format!("Could not import `{}`", file_name),
+                                            Some(e),
+                                        ),
+                                    }
+                                });
+                                ui.close_menu();
+                            }
+                        });
+                    }
+
+                    ui.menu_button("Export", |ui| {
+                        let style = style::get(ui);
+                        let background = ui.visuals().panel_fill;
+                        if ui.button("SVG of View…").clicked() {
+                            let view = Rect::from_min_max(
+                                self.store.transform.pos_from_screen(self.canvas_rect.min),
+                                self.store.transform.pos_from_screen(self.canvas_rect.max),
+                            );
+                            let contents = self.graph.to_svg(
+                                &self.annotations,
+                                &style,
+                                background,
+                                Some(view),
+                            );
+                            if platform::save_file(ui.ctx(), "txgraph.svg", &contents) {
+                                ui.ctx().notify_success("Exported SVG.");
+                            }
+                            ui.close_menu();
+                        }
+                        if ui.button("SVG of Whole Graph…").clicked() {
+                            let contents =
+                                self.graph
+                                    .to_svg(&self.annotations, &style, background, None);
+                            if platform::save_file(ui.ctx(), "txgraph.svg", &contents) {
+                                ui.ctx().notify_success("Exported SVG.");
+                            }
+                            ui.close_menu();
+                        }
+                        if ui
+                            .add_enabled(
+                                platform::CAN_SCREENSHOT,
+                                egui::Button::new("PNG of View…"),
+                            )
+                            .on_disabled_hover_text("Only in the desktop app.")
+                            .clicked()
+                        {
+                            self.snapshot.request();
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        if ui
+                            .button("DOT…")
+                            .on_hover_text("The graph for GraphViz and other tools.")
+                            .clicked()
+                        {
+                            let contents = self.graph.to_dot(&self.annotations);
+                            if platform::save_file(ui.ctx(), "txgraph.dot", &contents) {
+                                ui.ctx().notify_success("Exported DOT.");
+                            }
+                            ui.close_menu();
+                        }
+                    });
+
+                    ui.menu_button("Stats", |ui| {
+                        let key = (
+                            self.graph.revision(),
+                            self.annotations.coin_labels_fingerprint(),
+                        );
+                        if self.stats.as_ref().map(|(k, _)| *k) != Some(key) {
+                            self.stats = Some((key, self.graph.stats(&self.annotations)));
+                        }
+                        if let Some((_, stats)) = &self.stats {
+                            stats.ui(ui);
+                        }
+                        ui.separator();
+                        self.graph.fee_summary().ui(ui, &self.annotations, load_tx);
+                        ui.separator();
+                        if ui
+                            .button("Balance Chart…")
+                            .on_hover_text("Balance of the labelled addresses over time.")
+                            .clicked()
+                        {
+                            self.store.balance_chart.open();
+                            ui.close_menu();
+                        }
+                    });
+
+                    if self
+                        .store
+                        .settings
+                        .features
+                        .is_enabled(Feature::EntityAnalysis)
+                    {
+                        ui.menu_button("Entities", |ui| {
+                            ui.menu_button("Report Card", |ui| {
+                                let entities = self.graph.entities(&self.annotations);
+                                self.store.entity_report.ui(ui, &entities, |entity| {
+                                    ReportCard::new(
+                                        entity.to_string(),
+                                        self.graph.entity_flows(&self.annotations, entity),
+                                    )
+                                });
+                            });
+                            ui.menu_button("Flow Matrix", |ui| {
+                                let matrix =
+                                    FlowMatrix::new(self.graph.entity_transfers(&self.annotations));
+                                self.store.flow_matrix.ui(ui, matrix);
+                            });
+                            if ui
+                                .button("Sankey Diagram…")
+                                .on_hover_text("Summed flows from entity to entity.")
+                                .clicked()
+                            {
+                                self.store.sankey.open();
+                                ui.close_menu();
+                            }
+                        });
+                    }
+
+                    ui.menu_button("Tax Report", |ui| {
+                        let entities = self.graph.entities(&self.annotations);
+                        self.store.tax_report.ui(
+                            ui,
+                            self.graph.revision(),
+                            &entities,
+                            self.currency.as_deref(),
+                            &mut self.cost_basis_method,
+                            &self.annotations,
+                            |entity| self.graph.entity_flows(&self.annotations, entity),
+                        );
+                    });
+
+                    ui.menu_button("Clusters", |ui| {
+                        self.update_clusters();
+                        let clusters = &self.clusters.as_ref().unwrap().1;
+                        let annotations = &self.annotations;
+                        if let Some((addresses, name)) =
+                            self.store
+                                .clusters
+                                .ui(ui, clusters, |address| annotations.address_label(address))
+                        {
+                            sender2
+                                .send(Update::NameCluster { addresses, name })
+                                .unwrap();
+                        }
+                    });
+
+                    ui.menu_button("UTXOs", |ui| {
+                        let utxos = self.graph.utxo_details(&self.annotations);
+                        self.store.utxo_dashboard.ui(ui, utxos, load_tx);
+                    });
+
+                    ui.menu_button("Coin Selection", |ui| {
+                        self.store
+                            .coin_selection
+                            .ui(ui, &self.graph.utxos(), load_tx);
+                    });
+
+                    if self.store.settings.features.is_enabled(Feature::Ethereum) {
+                        ui.menu_button("Ethereum", |ui| {
+                            if ui.button("Trace Address…").clicked() {
+                                self.store.ethereum.open();
+                                ui.close_menu();
+                            }
+                        });
+                    }
+
+                    ui.menu_button("Settings", |ui| {
+                        self.store.settings.ui(ui);
+
+                        ui.horizontal(|ui| {
+                            if ui.button("What's New…").clicked() {
+                                self.store.changelog.open();
+                                ui.close_menu();
+                            }
+                            if ui.button("Developer Console…").clicked() {
+                                self.developer_console = true;
+                                ui.close_menu();
+                            }
+                        });
+
+                        ui.separator();
+                        ui.bold("Transfer:");
+                        ui.checkbox(
+                            &mut self.store.settings.export_server,
+                            "Include server URL in export",
+                        );
+                        ui.horizontal(|ui| {
+                            if ui.button("Export…").clicked() {
+                                let preferences = self.store.settings.preferences(
+                                    self.store.theme,
+                                    self.store.layout.clone(),
+                                    self.store.stamps.clone(),
+                                );
+                                let contents = serde_json::to_string_pretty(&preferences).unwrap();
+                                if platform::save_file(ui.ctx(), "txgraph-settings.json", &contents)
+                                {
+                                    ui.ctx().notify_success("Exported settings.");
+                                }
+                                ui.close_menu();
+                            }
+                            if ui.button("Import…").clicked() {
+                                let sender = self.update_sender.clone();
+                                let ctx = ui.ctx().clone();
+                                platform::open_file(
+                                    ui.ctx(),
+                                    "json",
+                                    move |file_name, contents| match serde_json::from_str(&contents)
+                                    {
+                                        Ok(preferences) => {
+                                            sender
+                                                .send(Update::ImportPreferences { preferences })
+                                                .unwrap();
+                                            ctx.request_repaint();
+                                        }
+                                        Err(e) => ctx.notify_error(
+                                            format!("Could not import `{}`", file_name),
+                                            Some(e),
+                                        ),
+                                    },
+                                );
+                                ui.close_menu();
+                            }
+                        });
                     });
 
                     ui.add(ThemeSwitch::new(&mut self.store.theme));
 
+                    if let Some(expansion) = &self.expansion {
+                        if expansion.status_ui(ui) {
+                            self.cancel_expansion(ui.ctx());
+                        }
+                    }
+                    Client::health_ui(ui);
                     Loading::spinner(ui);
                 });
             });
@@ -316,16 +1516,48 @@ impl eframe::App for App {
                 None,
             ));
 
-            #[cfg(testnet)]
-            ui.child_ui(
-                Rect::from_min_max(
-                    response.rect.left_bottom() + Vec2::new(5., -20.),
-                    response.rect.left_bottom() + Vec2::new(30., -25.),
-                ),
-                egui::Layout::left_to_right(egui::Align::Max),
-                None,
-            ).colored_label(egui::Color32::LIGHT_RED, "TESTNET");
+            let network = Client::network(ctx);
+            if network != Network::Mainnet {
+                ui.child_ui(
+                    Rect::from_min_max(
+                        response.rect.left_bottom() + Vec2::new(5., -20.),
+                        response.rect.left_bottom() + Vec2::new(30., -25.),
+                    ),
+                    egui::Layout::left_to_right(egui::Align::Max),
+                    None,
+                )
+                .colored_label(egui::Color32::LIGHT_RED, network.name().to_uppercase());
+            }
+
+            if let Some(status) = self.store.compare.status() {
+                ui.child_ui(
+                    Rect::from_min_size(response.rect.left_top() + Vec2::new(5., 5.), Vec2::ZERO),
+                    egui::Layout::left_to_right(egui::Align::Min),
+                    None,
+                )
+                .label(RichText::new(status).strong());
+            }
+
+            if self.annotations.has_categories() {
+                self.annotations.legend_ui(&mut ui.child_ui(
+                    Rect::from_min_max(
+                        response.rect.left_bottom() + Vec2::new(5., -35.),
+                        response.rect.left_bottom() + Vec2::new(200., -35.),
+                    ),
+                    egui::Layout::bottom_up(egui::Align::Min),
+                    None,
+                ));
+            }
 
+            self.canvas_rect = response.rect;
+            if response.secondary_clicked() {
+                if let Some(pos) = response.interact_pointer_pos() {
+                    self.canvas_menu_pos = self.store.transform.pos_from_screen(pos);
+                }
+            }
+            response.context_menu(|ui| {
+                sticky::canvas_menu(ui, self.canvas_menu_pos, self.annotations.stickies_mut())
+            });
             ui.set_clip_rect(response.rect);
 
             if self.flight.is_active() {
@@ -351,30 +1583,66 @@ impl eframe::App for App {
                 }
             }
 
-            // Drag
-            if response.dragged_by(egui::PointerButton::Primary) {
+            // Drag, or select with Shift
+            if response.drag_started_by(egui::PointerButton::Primary)
+                && ui.input(|i| i.modifiers.shift)
+            {
+                self.rubber_band = response.interact_pointer_pos();
+            }
+            if let Some(start) = self.rubber_band {
+                if response.drag_stopped() {
+                    if let Some(end) = ctx.pointer_latest_pos() {
+                        let transform = &self.store.transform;
+                        let rect = Rect::from_two_pos(
+                            transform.pos_from_screen(start),
+                            transform.pos_from_screen(end),
+                        );
+                        self.graph.select_in(rect, &style::get(ui));
+                    }
+                    self.rubber_band = None;
+                }
+            } else if response.dragged_by(egui::PointerButton::Primary) {
                 response = response.on_hover_cursor(CursorIcon::Grabbing);
                 self.store.transform.translate(response.drag_delta());
                 self.flight.interrupt();
             }
 
-            let mut pan = Vec2::ZERO;
-            if ui.input(|i| i.key_down(Key::ArrowDown)) {
-                pan += Vec2::DOWN;
-            }
-            if ui.input(|i| i.key_down(Key::ArrowUp)) {
-                pan += Vec2::UP;
+            if response.clicked() && !ui.input(|i| i.modifiers.shift) {
+                self.graph.clear_multi_selection();
             }
-            if ui.input(|i| i.key_down(Key::ArrowLeft)) {
-                pan += Vec2::LEFT;
+            if !self.graph.multi_selection().is_empty() && !ctx.wants_keyboard_input() {
+                self.multi_selection_keys(ctx);
             }
-            if ui.input(|i| i.key_down(Key::ArrowRight)) {
-                pan += Vec2::RIGHT;
+            if let Some(i) = bookmark::shortcut(ctx) {
+                self.jump_to_bookmark(ctx, i);
             }
-            if pan != Vec2::ZERO {
-                self.store.transform.translate(pan * 2.);
-                self.flight.interrupt();
-                ctx.request_repaint();
+
+            // With a selection, the keyboard moves it around the graph, otherwise it pans.
+            let selected = self
+                .graph
+                .selected()
+                .filter(|_| !ctx.wants_keyboard_input());
+            if let Some(txid) = selected {
+                self.navigate(ctx, txid);
+            } else {
+                let mut pan = Vec2::ZERO;
+                if ui.input(|i| i.key_down(Key::ArrowDown)) {
+                    pan += Vec2::DOWN;
+                }
+                if ui.input(|i| i.key_down(Key::ArrowUp)) {
+                    pan += Vec2::UP;
+                }
+                if ui.input(|i| i.key_down(Key::ArrowLeft)) {
+                    pan += Vec2::LEFT;
+                }
+                if ui.input(|i| i.key_down(Key::ArrowRight)) {
+                    pan += Vec2::RIGHT;
+                }
+                if pan != Vec2::ZERO {
+                    self.store.transform.translate(pan * 2.);
+                    self.flight.interrupt();
+                    ctx.request_repaint();
+                }
             }
 
             loop {
@@ -385,23 +1653,196 @@ impl eframe::App for App {
                 }
             }
 
+            if self.store.clusters.show_colors {
+                self.update_clusters();
+            }
+            let clusters = self
+                .clusters
+                .as_ref()
+                .filter(|_| self.store.clusters.show_colors)
+                .map(|(_, clusters)| clusters);
+            sticky::ui(ui, &self.store.transform, self.annotations.stickies_mut());
             self.graph.draw(
                 ui,
                 &self.store.transform,
                 sender2,
                 &self.store.layout,
                 &mut self.annotations,
+                &self.store.stamps,
+                self.store.compare.window(),
+                clusters,
+                self.currency.as_deref(),
             );
+
+            if let (Some(start), Some(end)) = (self.rubber_band, ctx.pointer_latest_pos()) {
+                let selection = ui.visuals().selection;
+                ui.painter().rect(
+                    Rect::from_two_pos(start, end),
+                    0.0,
+                    selection.bg_fill.gamma_multiply(0.2),
+                    selection.stroke,
+                );
+            }
+
+            if self.store.layout.show_minimap {
+                let nodes = self.graph.node_rects(&style::get(ui));
+                if minimap::ui(ui, response.rect, &nodes, &mut self.store.transform) {
+                    self.flight.interrupt();
+                }
+            }
+
+            if self.view_only {
+                view_only_banner(ui, response.rect);
+            }
         });
 
         self.about_rect = self.store.about.show_window(ctx, load_tx);
+        self.store.changelog.show_window(ctx);
+        match self.store.path_finder.show_window(ctx) {
+            Some(path::Action::Find) => self.find_path(ctx, None),
+            Some(path::Action::Fetch(depth)) => self.find_path(ctx, Some(depth)),
+            Some(path::Action::Clear) => self.graph.set_path(vec![]),
+            None => {}
+        }
+        let hint = "Search txids, addresses, amounts and labels...";
+        let items = self.graph.search_items(&self.annotations);
+        if let Some(txid) = self.search.show(ctx, hint, items) {
+            let inputs = self.graph.input_addresses_of(txid);
+            if let Some(group) = self.annotations.member_of(txid, &inputs) {
+                self.annotations.set_collapsed(group, false);
+            }
+            self.graph.flash(txid, ctx.input(|i| i.time));
+            self.apply_update(ctx, Update::LoadOrSelectTx { txid, pos: None });
+        }
+        let annotations = &self.annotations;
+        self.store.balance_chart.show_window(
+            ctx,
+            self.graph.revision(),
+            self.currency.as_deref(),
+            annotations,
+            || self.graph.wallets(annotations),
+            |wallet| {
+                self.graph.balance_changes(|address| match wallet {
+                    Some(wallet) => annotations.address_label(address).as_deref() == Some(wallet),
+                    None => annotations.address_label(address).is_some(),
+                })
+            },
+            load_tx,
+        );
+        self.store.sankey.show_window(ctx, || {
+            FlowMatrix::new(self.graph.entity_transfers(&self.annotations))
+        });
+        if let Some((address, label)) = self.store.ethereum.show_window(ctx, &self.annotations) {
+            self.annotations.import_address_labels(
+                vec![(address, label)],
+                std::iter::empty(),
+                true,
+            );
+        }
+        if let Some(txid) = self.expand_target {
+            let mut open = true;
+            let mut start = false;
+            egui::Window::new("Expand")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(format!("Add transactions around {}.", txid));
+                    start = self.store.expand.ui(ui);
+                });
+            if start {
+                self.start_expansion(ctx, vec![txid], self.store.expand.clone());
+            }
+            if start || !open {
+                self.expand_target = None;
+            }
+        }
+
+        if let Some(import) = &mut self.csv_import {
+            let mut open = true;
+            let mut rows = None;
+            egui::Window::new("Import CSV")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| rows = import.ui(ui));
+            if let Some(rows) = rows {
+                let mut txs = vec![];
+                for row in rows {
+                    if let Some(cost_basis) = row.cost_basis {
+                        self.annotations.set_cost_basis(row.txid, cost_basis);
+                    }
+                    txs.push(ImportedTx {
+                        txid: row.txid,
+                        label: row.label,
+                    });
+                }
+                self.update_sender
+                    .send(Update::ImportTxs { txs, quiet: false })
+                    .unwrap();
+                open = false;
+            }
+            if !open {
+                self.csv_import = None;
+            }
+        }
+
+        egui::Window::new("Developer Console")
+            .open(&mut self.developer_console)
+            .default_width(600.0)
+            .show(ctx, Client::request_log_ui);
 
         WorkspacesHandle::update_workspace(
             ctx,
-            export::Workspace::new(&self.graph, &self.annotations, &self.store.layout, &self.store.transform),
+            export::Workspace::new(
+                &self.graph,
+                &self.annotations,
+                &self.store.layout,
+                &self.store.transform,
+                self.source.clone(),
+                self.currency.clone(),
+                self.cost_basis_method,
+                self.store.ethereum.export(),
+            ),
         );
         self.workspaces.show_window(ctx);
+        self.workspaces.tick(ctx);
+        Client::tick(ctx);
+        self.mempool
+            .tick(ctx, self.graph.unconfirmed(), &self.update_sender);
+
+        match self.snapshot.tick(ctx, self.canvas_rect) {
+            Some(Ok(png)) if platform::save_binary_file(ctx, "txgraph.png", &png) => {
+                ctx.notify_success("Exported PNG.")
+            }
+            Some(Err(e)) => ctx.notify_error("Could not export PNG", Some(e)),
+            _ => {}
+        }
 
         self.notifications.show(ctx);
     }
 }
+
+/// Says at the top of the canvas that edits won't be saved unless the workspace is forked.
+fn view_only_banner(ui: &mut egui::Ui, canvas: Rect) {
+    egui::Area::new(egui::Id::new("View only banner"))
+        .fixed_pos(canvas.center_top() + Vec2::new(0.0, 10.0))
+        .pivot(egui::Align2::CENTER_TOP)
+        .show(ui.ctx(), |ui| {
+            Frame::popup(ui.style()).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new("View only")
+                            .strong()
+                            .color(ui.visuals().warn_fg_color),
+                    );
+                    ui.label("This workspace isn't yours, so changes aren't saved.");
+                    if ui
+                        .button("Fork to keep changes")
+                        .on_hover_text("Create an editable copy with the changes made so far.")
+                        .clicked()
+                    {
+                        WorkspacesHandle::fork(ui.ctx());
+                    }
+                });
+            });
+        });
+}
@@ -0,0 +1,400 @@
+//! One-off automatic layouts of the graph, as opposed to the live force simulation in
+//! [crate::graph::Graph::draw].
+
+use egui::{Pos2, Rect, Vec2};
+
+use crate::{bezier::Cubic, bitcoin::Txid};
+
+const LAYER_GAP: f32 = 60.0;
+const NODE_GAP: f32 = 30.0;
+/// Preferred distance between connected transactions in the force-directed layout.
+const EDGE_LENGTH: f32 = 120.0;
+const ITERATIONS: usize = 300;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Layered,
+    ForceDirected,
+}
+
+impl Algorithm {
+    pub const ALL: [Self; 2] = [Self::Layered, Self::ForceDirected];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Layered => "Layered",
+            Self::ForceDirected => "Force-directed",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Layered => {
+                "Rows in the order coins flow, each transaction below the ones it spends from."
+            }
+            Self::ForceDirected => {
+                "Connected transactions close together, everything else spread out."
+            }
+        }
+    }
+
+    /// New centers for the nodes with the given [sizes], keeping the center of mass in place.
+    /// [edges] are indices into the nodes, from funding to spending transaction.
+    pub fn apply(&self, positions: &[Pos2], sizes: &[Vec2], edges: &[(usize, usize)]) -> Vec<Pos2> {
+        let mut edges: Vec<_> = edges.iter().copied().filter(|(a, b)| a != b).collect();
+        edges.sort();
+        edges.dedup();
+
+        let mut result = match self {
+            Self::Layered => layered(positions, sizes, &edges),
+            Self::ForceDirected => force_directed(positions, &edges),
+        };
+
+        let shift = centroid(positions) - centroid(&result);
+        for pos in &mut result {
+            *pos += shift;
+        }
+        result
+    }
+}
+
+fn centroid(positions: &[Pos2]) -> Pos2 {
+    let sum = positions
+        .iter()
+        .fold(Vec2::ZERO, |sum, pos| sum + pos.to_vec2());
+    (sum / positions.len().max(1) as f32).to_pos2()
+}
+
+/// Sugiyama-style: layers by longest path from the sources, then a few sweeps ordering each
+/// layer by the barycenter of its neighbours to reduce crossings.
+fn layered(positions: &[Pos2], sizes: &[Vec2], edges: &[(usize, usize)]) -> Vec<Pos2> {
+    let n = positions.len();
+    let mut predecessors = vec![vec![]; n];
+    let mut successors = vec![vec![]; n];
+    for &(from, to) in edges {
+        successors[from].push(to);
+        predecessors[to].push(from);
+    }
+
+    // Transactions can only spend earlier ones, so there are no cycles. Anything left over
+    // in case of bad data stays in the layer it got so far.
+    let mut layer = vec![0; n];
+    let mut missing: Vec<usize> = predecessors.iter().map(Vec::len).collect();
+    let mut queue: Vec<usize> = (0..n).filter(|&i| missing[i] == 0).collect();
+    while let Some(node) = queue.pop() {
+        for &next in &successors[node] {
+            layer[next] = layer[next].max(layer[node] + 1);
+            missing[next] -= 1;
+            if missing[next] == 0 {
+                queue.push(next);
+            }
+        }
+    }
+
+    // Start from the current left-to-right order so the result feels familiar.
+    let layers = layer.iter().max().map_or(0, |max| max + 1);
+    let mut rows: Vec<Vec<usize>> = vec![vec![]; layers];
+    let mut by_x: Vec<usize> = (0..n).collect();
+    by_x.sort_by(|&a, &b| positions[a].x.total_cmp(&positions[b].x));
+    for i in by_x {
+        rows[layer[i]].push(i);
+    }
+
+    // Relative position within the layer, so layers of different lengths line up.
+    let mut rank = vec![0.0; n];
+    for row in &rows {
+        for (i, &node) in row.iter().enumerate() {
+            rank[node] = (i as f32 + 0.5) / row.len() as f32;
+        }
+    }
+    for sweep in 0..8 {
+        let neighbours = if sweep % 2 == 0 {
+            &predecessors
+        } else {
+            &successors
+        };
+        let order: Vec<usize> = if sweep % 2 == 0 {
+            (0..layers).collect()
+        } else {
+            (0..layers).rev().collect()
+        };
+        for l in order {
+            let barycenter = |node: usize| {
+                let ns = &neighbours[node];
+                if ns.is_empty() {
+                    rank[node]
+                } else {
+                    ns.iter().map(|&other| rank[other]).sum::<f32>() / ns.len() as f32
+                }
+            };
+            let mut keyed: Vec<(f32, usize)> = rows[l]
+                .iter()
+                .map(|&node| (barycenter(node), node))
+                .collect();
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+            rows[l] = keyed.into_iter().map(|(_, node)| node).collect();
+            for (i, &node) in rows[l].iter().enumerate() {
+                rank[node] = (i as f32 + 0.5) / rows[l].len() as f32;
+            }
+        }
+    }
+
+    // Each layer is a row, top to bottom, since nodes take their inputs at the top edge and give
+    // their outputs at the bottom.
+    let mut result = vec![Pos2::ZERO; n];
+    let mut y = 0.0;
+    for row in &rows {
+        let height = row.iter().map(|&i| sizes[i].y).fold(0.0, f32::max);
+        let width: f32 = row.iter().map(|&i| sizes[i].x).sum::<f32>()
+            + row.len().saturating_sub(1) as f32 * NODE_GAP;
+        let mut x = -width / 2.0;
+        for &i in row {
+            result[i] = Pos2::new(x + sizes[i].x / 2.0, y + height / 2.0);
+            x += sizes[i].x + NODE_GAP;
+        }
+        y += height + LAYER_GAP;
+    }
+    result
+}
+
+/// Fruchterman-Reingold with a cooling schedule, plus a pull that keeps spending transactions
+/// below the ones they spend from, like in the live layout.
+fn force_directed(positions: &[Pos2], edges: &[(usize, usize)]) -> Vec<Pos2> {
+    let n = positions.len();
+    let k = EDGE_LENGTH;
+    let mut pos = positions.to_vec();
+
+    for iteration in 0..ITERATIONS {
+        let temperature = k * (1.0 - iteration as f32 / ITERATIONS as f32);
+        let mut displacement = vec![Vec2::ZERO; n];
+
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let mut diff = pos[i] - pos[j];
+                if diff.length_sq() < 0.01 {
+                    // Nodes on top of each other, push them apart in some direction.
+                    diff = Vec2::angled(i as f32 + j as f32);
+                }
+                let force = diff.normalized() * k * k / diff.length();
+                displacement[i] += force;
+                displacement[j] -= force;
+            }
+        }
+
+        for &(from, to) in edges {
+            let diff = pos[to] - pos[from];
+            let mut force = diff.normalized() * diff.length_sq() / k;
+            if diff.y < k {
+                force.y -= k - diff.y;
+            }
+            displacement[from] += force;
+            displacement[to] -= force;
+        }
+
+        for (p, d) in pos.iter_mut().zip(displacement) {
+            *p += d.normalized() * d.length().min(temperature);
+        }
+    }
+    pos
+}
+
+/// Lining up selected transactions by their bounding boxes.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+    Top,
+    Middle,
+    Bottom,
+    /// Equal gaps between neighbours, keeping the outermost in place.
+    DistributeHorizontally,
+    DistributeVertically,
+}
+
+impl Alignment {
+    pub const ALL: [Self; 8] = [
+        Self::Left,
+        Self::Center,
+        Self::Right,
+        Self::Top,
+        Self::Middle,
+        Self::Bottom,
+        Self::DistributeHorizontally,
+        Self::DistributeVertically,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Left => "Align Left",
+            Self::Center => "Align Centers Horizontally",
+            Self::Right => "Align Right",
+            Self::Top => "Align Top",
+            Self::Middle => "Align Centers Vertically",
+            Self::Bottom => "Align Bottom",
+            Self::DistributeHorizontally => "Distribute Horizontally",
+            Self::DistributeVertically => "Distribute Vertically",
+        }
+    }
+
+    /// How far to move each of [rects].
+    pub fn apply(&self, rects: &[Rect]) -> Vec<Vec2> {
+        let Some(bounds) = rects.iter().copied().reduce(Rect::union) else {
+            return vec![];
+        };
+        let x = |x: f32| Vec2::new(x, 0.0);
+        let y = |y: f32| Vec2::new(0.0, y);
+        match self {
+            Self::Left => rects.iter().map(|r| x(bounds.left() - r.left())).collect(),
+            Self::Center => rects
+                .iter()
+                .map(|r| x(bounds.center().x - r.center().x))
+                .collect(),
+            Self::Right => rects
+                .iter()
+                .map(|r| x(bounds.right() - r.right()))
+                .collect(),
+            Self::Top => rects.iter().map(|r| y(bounds.top() - r.top())).collect(),
+            Self::Middle => rects
+                .iter()
+                .map(|r| y(bounds.center().y - r.center().y))
+                .collect(),
+            Self::Bottom => rects
+                .iter()
+                .map(|r| y(bounds.bottom() - r.bottom()))
+                .collect(),
+            Self::DistributeHorizontally => {
+                distribute(rects, |r| (r.left(), r.width()), bounds.width())
+                    .into_iter()
+                    .map(x)
+                    .collect()
+            }
+            Self::DistributeVertically => {
+                distribute(rects, |r| (r.top(), r.height()), bounds.height())
+                    .into_iter()
+                    .map(y)
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Offsets along one axis, given the start and length of each rect on it and the total span.
+fn distribute(rects: &[Rect], axis: impl Fn(&Rect) -> (f32, f32), span: f32) -> Vec<f32> {
+    let mut order: Vec<usize> = (0..rects.len()).collect();
+    order.sort_by(|&a, &b| {
+        let center = |i: usize| axis(&rects[i]).0 + axis(&rects[i]).1 / 2.0;
+        center(a).total_cmp(&center(b))
+    });
+
+    let total: f32 = rects.iter().map(|r| axis(r).1).sum();
+    let gap = (span - total) / rects.len().saturating_sub(1).max(1) as f32;
+    let mut offsets = vec![0.0; rects.len()];
+    let mut next = rects
+        .iter()
+        .map(|r| axis(r).0)
+        .fold(f32::INFINITY, f32::min);
+    for i in order {
+        let (start, length) = axis(&rects[i]);
+        offsets[i] = next - start;
+        next += length + gap;
+    }
+    offsets
+}
+
+/// Moves nodes from their old to their new positions over a few frames.
+pub struct Animation {
+    moves: Vec<(Txid, Pos2, Pos2)>,
+    time: f32,
+}
+
+impl Animation {
+    pub fn new(moves: Vec<(Txid, Pos2, Pos2)>) -> Self {
+        Self { moves, time: 0.0 }
+    }
+
+    /// Positions for the next frame, and whether the animation is done.
+    pub fn step(&mut self) -> (impl Iterator<Item = (Txid, Pos2)> + '_, bool) {
+        self.time = (self.time + 0.04).min(1.0);
+        let t = Cubic::move_to().eval(self.time).y;
+        let positions = self
+            .moves
+            .iter()
+            .map(move |(txid, from, to)| (*txid, from.lerp(*to, t)));
+        (positions, self.time >= 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{Pos2, Rect, Vec2};
+
+    use super::{Algorithm, Alignment};
+
+    #[test]
+    fn layered() {
+        // 0 funds 1 and 2, 1 funds 2. 3 is on its own.
+        let positions = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(50.0, 0.0),
+            Pos2::new(-50.0, 0.0),
+            Pos2::new(100.0, 0.0),
+        ];
+        let sizes = [Vec2::new(20.0, 40.0); 4];
+        let edges = [(0, 1), (0, 2), (1, 2), (1, 2)];
+        let result = Algorithm::Layered.apply(&positions, &sizes, &edges);
+
+        // Layers by longest path: {0, 3}, {1}, {2}.
+        assert_eq!(result[0].y, result[3].y);
+        assert_eq!(result[1].y - result[0].y, 100.0);
+        assert_eq!(result[2].y - result[1].y, 100.0);
+        assert!(result[0].x < result[3].x);
+        assert_eq!(result[1].x, result[2].x);
+
+        let center = |ps: &[Pos2]| ps.iter().fold(Vec2::ZERO, |s, p| s + p.to_vec2()) / 4.0;
+        assert!((center(&result) - center(&positions)).length() < 0.01);
+    }
+
+    #[test]
+    fn force_directed() {
+        let positions = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(10.0, 0.0),
+            Pos2::new(5.0, 5.0),
+        ];
+        let sizes = [Vec2::new(20.0, 40.0); 3];
+        let result = Algorithm::ForceDirected.apply(&positions, &sizes, &[(0, 1)]);
+
+        // The spending transaction ends up below, and nothing overlaps.
+        assert!(result[1].y > result[0].y);
+        for (i, a) in result.iter().enumerate() {
+            for b in &result[i + 1..] {
+                assert!(a.distance(*b) > 40.0);
+            }
+        }
+    }
+
+    #[test]
+    fn alignment() {
+        let rects = [
+            Rect::from_min_size(Pos2::new(0.0, 0.0), Vec2::new(10.0, 10.0)),
+            Rect::from_min_size(Pos2::new(100.0, 5.0), Vec2::new(20.0, 10.0)),
+            Rect::from_min_size(Pos2::new(30.0, 50.0), Vec2::new(10.0, 30.0)),
+        ];
+
+        let left = Alignment::Left.apply(&rects);
+        assert_eq!(
+            left,
+            [Vec2::ZERO, Vec2::new(-100.0, 0.0), Vec2::new(-30.0, 0.0)]
+        );
+        let bottom = Alignment::Bottom.apply(&rects);
+        assert_eq!(
+            bottom,
+            [Vec2::new(0.0, 70.0), Vec2::new(0.0, 65.0), Vec2::ZERO]
+        );
+
+        // 120 wide in total, 40 of which are rects, leaves gaps of 40. The order stays.
+        let distributed = Alignment::DistributeHorizontally.apply(&rects);
+        assert_eq!(distributed, [Vec2::ZERO, Vec2::ZERO, Vec2::new(20.0, 0.0)]);
+    }
+}
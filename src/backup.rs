@@ -0,0 +1,244 @@
+//! Scheduled backups of all workspaces to a local directory, independent of the app storage.
+
+use chrono::{DateTime, Duration, Local, Utc};
+use egui::{ComboBox, Grid, ScrollArea, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::{notifications::NotifyExt, platform::inner as platform, widgets::UiExt};
+
+const PREFIX: &str = "workspaces-backup-";
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Interval {
+    #[default]
+    Off,
+    Daily,
+    Weekly,
+}
+
+impl Interval {
+    fn duration(&self) -> Option<Duration> {
+        match self {
+            Interval::Off => None,
+            Interval::Daily => Some(Duration::days(1)),
+            Interval::Weekly => Some(Duration::weeks(1)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Interval::Off => "Off",
+            Interval::Daily => "Daily",
+            Interval::Weekly => "Weekly",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Backups {
+    interval: Interval,
+    /// Empty means [platform::default_backup_dir].
+    directory: String,
+    /// Older backups are deleted.
+    keep: usize,
+    last_backup: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    listing: Option<Vec<String>>,
+}
+
+impl Default for Backups {
+    fn default() -> Self {
+        Self {
+            interval: Interval::Off,
+            directory: String::new(),
+            keep: 7,
+            last_backup: None,
+            listing: None,
+        }
+    }
+}
+
+/// What the user asked for in [Backups::ui].
+pub enum Action {
+    BackupNow,
+    /// Contents of the chosen backup.
+    Restore(String),
+}
+
+impl Backups {
+    fn directory(&self) -> Option<String> {
+        if self.directory.trim().is_empty() {
+            platform::default_backup_dir()
+        } else {
+            Some(self.directory.trim().to_string())
+        }
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        is_due(self.interval, self.last_backup, now)
+    }
+
+    /// Write [contents] to a new backup file and delete the ones that are too old.
+    pub fn write(&mut self, contents: &str, now: DateTime<Utc>) -> Result<(), String> {
+        // Also when failing, so we don't try again every frame.
+        self.last_backup = Some(now);
+        self.listing = None;
+
+        let dir = self.directory().ok_or("No backup directory")?;
+        let name = format!(
+            "{}{}.json",
+            PREFIX,
+            now.with_timezone(&Local).format("%Y%m%d-%H%M%S")
+        );
+        platform::write_file(&dir, &name, contents)?;
+
+        let backups = platform::list_files(&dir, PREFIX)?;
+        for name in to_delete(backups, self.keep) {
+            platform::remove_file(&dir, &name)?;
+        }
+        Ok(())
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<Action> {
+        let Some(default_dir) = platform::default_backup_dir() else {
+            ui.label("Automatic backups are only available in the desktop app.");
+            return None;
+        };
+        let mut action = None;
+
+        Grid::new("Backups").num_columns(2).show(ui, |ui| {
+            ui.label("Back up all workspaces:");
+            ComboBox::from_id_source("BackupInterval")
+                .selected_text(self.interval.name())
+                .show_ui(ui, |ui| {
+                    for interval in [Interval::Off, Interval::Daily, Interval::Weekly] {
+                        ui.selectable_value(&mut self.interval, interval, interval.name());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Directory:");
+            if ui
+                .add(TextEdit::singleline(&mut self.directory).hint_text(&default_dir))
+                .changed()
+            {
+                self.listing = None;
+            }
+            ui.end_row();
+
+            ui.label("Keep:");
+            ui.add(
+                egui::DragValue::new(&mut self.keep)
+                    .range(1..=100)
+                    .suffix(" backups"),
+            );
+            ui.end_row();
+
+            ui.label("Last backup:");
+            ui.label(self.last_backup.map_or("Never".to_string(), |t| {
+                t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+            }));
+            ui.end_row();
+        });
+
+        if ui.button("Back up now").clicked() {
+            action = Some(Action::BackupNow);
+        }
+
+        ui.add_space(3.0);
+        ui.bold("Restore:");
+        let Some(dir) = self.directory() else {
+            return action;
+        };
+        let listing = self.listing.get_or_insert_with(|| {
+            let mut files = platform::list_files(&dir, PREFIX).unwrap_or_default();
+            files.reverse();
+            files
+        });
+        if listing.is_empty() {
+            ui.weak("No backups yet.");
+        }
+        ScrollArea::vertical()
+            .id_source("BackupListing")
+            .max_height(120.0)
+            .show(ui, |ui| {
+                for name in listing.iter() {
+                    ui.horizontal(|ui| {
+                        ui.monospace(name);
+                        if ui
+                            .small_button("Restore")
+                            .on_hover_text("Workspaces that changed since are kept as copies.")
+                            .clicked()
+                        {
+                            match platform::read_file(&dir, name) {
+                                Ok(contents) => action = Some(Action::Restore(contents)),
+                                Err(e) => ui.ctx().notify_error("Could not read backup", Some(e)),
+                            }
+                        }
+                    });
+                }
+            });
+
+        action
+    }
+}
+
+fn is_due(interval: Interval, last_backup: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    match (interval.duration(), last_backup) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some(duration), Some(last)) => now - last >= duration,
+    }
+}
+
+/// The oldest backups beyond the [keep] newest. Names sort by date.
+fn to_delete(mut names: Vec<String>, keep: usize) -> Vec<String> {
+    names.sort();
+    let excess = names.len().saturating_sub(keep.max(1));
+    names.truncate(excess);
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{is_due, to_delete, Interval};
+
+    #[test]
+    fn schedule() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        assert!(!is_due(Interval::Off, None, now));
+        assert!(is_due(Interval::Daily, None, now));
+        assert!(!is_due(
+            Interval::Daily,
+            Some(now - Duration::hours(23)),
+            now
+        ));
+        assert!(is_due(
+            Interval::Daily,
+            Some(now - Duration::hours(24)),
+            now
+        ));
+        assert!(!is_due(
+            Interval::Weekly,
+            Some(now - Duration::days(6)),
+            now
+        ));
+    }
+
+    #[test]
+    fn rotation() {
+        let names = vec![
+            "workspaces-backup-20240103-000000.json".to_string(),
+            "workspaces-backup-20240101-000000.json".to_string(),
+            "workspaces-backup-20240102-000000.json".to_string(),
+        ];
+        assert_eq!(
+            to_delete(names.clone(), 2),
+            vec!["workspaces-backup-20240101-000000.json"]
+        );
+        assert!(to_delete(names, 5).is_empty());
+    }
+}
@@ -0,0 +1,362 @@
+//! Balance of the tracked addresses over time.
+
+use chrono::Utc;
+use egui::{ComboBox, Pos2};
+use egui_plot::{Line, Plot, PlotPoints, Points};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotations::Annotations,
+    bitcoin::{format_btc, Txid},
+    prices::{format_date, format_fiat, Prices},
+};
+
+const DAY: i64 = 86_400;
+
+/// How a transaction changed the balance.
+pub struct Change {
+    pub txid: Txid,
+    pub timestamp: i64,
+    pub delta: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Step {
+    pub txid: Txid,
+    pub timestamp: i64,
+    pub delta: i64,
+    /// After the transaction.
+    pub balance: i64,
+}
+
+/// Running balance, oldest first.
+pub fn steps(mut changes: Vec<Change>) -> Vec<Step> {
+    changes.sort_by_key(|change| (change.timestamp, change.txid));
+    let mut balance = 0;
+    changes
+        .into_iter()
+        .map(|change| {
+            balance += change.delta;
+            Step {
+                txid: change.txid,
+                timestamp: change.timestamp,
+                delta: change.delta,
+                balance,
+            }
+        })
+        .collect()
+}
+
+/// The balance in BTC over time, changing with each step.
+fn btc_points(steps: &[Step], until: i64) -> Vec<[f64; 2]> {
+    let btc = |sats: i64| sats as f64 / 100_000_000.0;
+    let mut points = vec![];
+    let mut previous = 0;
+    for step in steps {
+        points.push([step.timestamp as f64, btc(previous)]);
+        points.push([step.timestamp as f64, btc(step.balance)]);
+        previous = step.balance;
+    }
+    if let Some(last) = steps.last() {
+        points.push([until.max(last.timestamp) as f64, btc(last.balance)]);
+    }
+    points
+}
+
+/// Value of the balance at the end of each day from the first step until [until], at the rate
+/// of that day. [rates] are by day, oldest first. Days without a rate take the one before.
+pub fn daily_values(steps: &[Step], rates: &[(i64, f64)], until: i64) -> Vec<[f64; 2]> {
+    let Some(first) = steps.first() else {
+        return vec![];
+    };
+    let mut points = vec![];
+    let mut day = first.timestamp - first.timestamp.rem_euclid(DAY);
+    let (mut next_step, mut balance) = (0, 0);
+    let (mut next_rate, mut rate) = (0, rates.first().map_or(0.0, |(_, rate)| *rate));
+    while day <= until {
+        while next_step < steps.len() && steps[next_step].timestamp < day + DAY {
+            balance = steps[next_step].balance;
+            next_step += 1;
+        }
+        while next_rate < rates.len() && rates[next_rate].0 <= day {
+            rate = rates[next_rate].1;
+            next_rate += 1;
+        }
+        points.push([day as f64, balance as f64 / 100_000_000.0 * rate]);
+        day += DAY;
+    }
+    points
+}
+
+/// The last of [points] at or before [x].
+fn value_at(points: &[[f64; 2]], x: f64) -> f64 {
+    let i = points.partition_point(|point| point[0] <= x);
+    points[i.saturating_sub(1)][1]
+}
+
+/// Graph revision and fingerprint of the address labels.
+type WalletsKey = (u64, u64);
+
+/// Also the wallet, and the currency of fiat values.
+type SeriesKey = (u64, u64, Option<String>, Option<String>);
+
+struct Series {
+    key: SeriesKey,
+    steps: Vec<Step>,
+    /// The balance in BTC or its daily fiat value, `None` while prices are loading.
+    points: Option<Vec<[f64; 2]>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BalanceChart {
+    /// Address label to show, all tracked addresses if `None`.
+    wallet: Option<String>,
+    /// Whether to plot the fiat value instead of BTC.
+    fiat: bool,
+    #[serde(skip)]
+    open: bool,
+    #[serde(skip)]
+    wallets: Option<(WalletsKey, Vec<String>)>,
+    #[serde(skip)]
+    series: Option<Series>,
+}
+
+impl BalanceChart {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn show_window(
+        &mut self,
+        ctx: &egui::Context,
+        revision: u64,
+        currency: Option<&str>,
+        annotations: &Annotations,
+        wallets: impl FnOnce() -> Vec<String>,
+        changes: impl FnOnce(Option<&str>) -> Vec<Change>,
+        load_tx: impl Fn(Txid, Option<Pos2>),
+    ) {
+        if !self.open {
+            return;
+        }
+        let key = (revision, annotations.address_labels_fingerprint());
+        if self.wallets.as_ref().map(|(k, _)| *k) != Some(key) {
+            self.wallets = Some((key, wallets()));
+        }
+        let mut open = self.open;
+        egui::Window::new("Balance")
+            .open(&mut open)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                self.ui(ui, key, currency, annotations, changes, load_tx)
+            });
+        self.open = open;
+    }
+
+    fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        key: WalletsKey,
+        currency: Option<&str>,
+        annotations: &Annotations,
+        changes: impl FnOnce(Option<&str>) -> Vec<Change>,
+        load_tx: impl Fn(Txid, Option<Pos2>),
+    ) {
+        let wallets = self
+            .wallets
+            .as_ref()
+            .map(|(_, wallets)| wallets.clone())
+            .unwrap_or_default();
+        if wallets.is_empty() {
+            ui.label("Label some addresses first. Labelled addresses are tracked.");
+            return;
+        }
+        if self.wallet.as_ref().is_some_and(|w| !wallets.contains(w)) {
+            self.wallet = None;
+        }
+
+        ui.horizontal(|ui| {
+            ComboBox::from_label("Addresses")
+                .selected_text(self.wallet.as_deref().unwrap_or("All tracked"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.wallet, None, "All tracked");
+                    for wallet in &wallets {
+                        ui.selectable_value(&mut self.wallet, Some(wallet.clone()), wallet);
+                    }
+                });
+            ui.add_enabled_ui(currency.is_some(), |ui| {
+                ui.checkbox(&mut self.fiat, "Fiat value")
+                    .on_disabled_hover_text("Choose a fiat currency for the workspace first.");
+            });
+        });
+        ui.weak("Confirmed transactions in the graph only. Click a step to go to its transaction.");
+
+        let currency = currency.filter(|_| self.fiat);
+        let key = (
+            key.0,
+            key.1,
+            self.wallet.clone(),
+            currency.map(str::to_string),
+        );
+        if self.series.as_ref().map(|series| &series.key) != Some(&key) {
+            self.series = Some(Series {
+                key,
+                steps: steps(changes(self.wallet.as_deref())),
+                points: None,
+            });
+        }
+        let Some(series) = self.series.as_mut() else {
+            return;
+        };
+        if series.steps.is_empty() {
+            ui.label("No confirmed transactions yet.");
+            return;
+        }
+
+        let now = Utc::now().timestamp();
+        if series.points.is_none() {
+            series.points = match currency {
+                Some(currency) => {
+                    let first = series.steps[0].timestamp;
+                    Prices::daily(ui.ctx(), currency, first, now).map(|rates| {
+                        if rates.is_empty() {
+                            vec![]
+                        } else {
+                            daily_values(&series.steps, &rates, now)
+                        }
+                    })
+                }
+                None => Some(btc_points(&series.steps, now)),
+            };
+        }
+        let Some(points) = &series.points else {
+            let currency = currency.unwrap_or_default().to_uppercase();
+            ui.weak(format!("Waiting for {} prices…", currency));
+            return;
+        };
+        if points.is_empty() {
+            let currency = currency.unwrap_or_default().to_uppercase();
+            ui.weak(format!("No {} prices.", currency));
+            return;
+        }
+        let unit = |value: f64| match currency {
+            Some(currency) => format_fiat(value, currency),
+            None => format!("{:.8} BTC", value),
+        };
+
+        if let Some(txid) = chart_ui(ui, &series.steps, points, unit, annotations) {
+            load_tx(txid, None);
+        }
+    }
+}
+
+/// [points] over time, with a marker at each step. Returns the transaction of the step that was
+/// clicked.
+fn chart_ui(
+    ui: &mut egui::Ui,
+    steps: &[Step],
+    points: &[[f64; 2]],
+    unit: impl Fn(f64) -> String,
+    annotations: &Annotations,
+) -> Option<Txid> {
+    let color = ui.visuals().hyperlink_color;
+    let markers: Vec<[f64; 2]> = steps
+        .iter()
+        .map(|step| {
+            let x = step.timestamp as f64;
+            [x, value_at(points, x)]
+        })
+        .collect();
+
+    let plot = Plot::new("BalanceChart")
+        .height(220.0)
+        .allow_scroll(false)
+        .include_y(0.0)
+        .show_x(false)
+        .show_y(false)
+        .x_axis_formatter(|mark, _| format_date(mark.value as i64))
+        .y_axis_formatter(|mark, _| unit(mark.value))
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(PlotPoints::from(points.to_vec())).color(color));
+            plot_ui.points(Points::new(markers).radius(3.0).color(color));
+        });
+
+    let pointer = plot.response.hover_pos()?;
+    let x = plot.transform.value_from_position(pointer).x;
+    let distance = |step: &Step| (step.timestamp as f64 - x).abs();
+    let step = steps
+        .iter()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))?;
+
+    let clicked = plot.response.clicked();
+    plot.response.on_hover_ui_at_pointer(|ui| {
+        ui.label(format_date(step.timestamp));
+        ui.label(
+            annotations
+                .tx_label(step.txid)
+                .unwrap_or_else(|| step.txid.hex_string()[..16].to_string()),
+        );
+        let sign = if step.delta > 0 { "+" } else { "" };
+        ui.label(format!("{}{} BTC", sign, format_btc(step.delta)));
+        ui.label(format!(
+            "Balance: {}",
+            unit(value_at(points, step.timestamp as f64))
+        ));
+    });
+    clicked.then_some(step.txid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{daily_values, steps, Change};
+    use crate::bitcoin::Txid;
+
+    #[test]
+    fn running_balance() {
+        let txid = |n: u8| Txid::new(&format!("{:02x}", n).repeat(32)).unwrap();
+        let change = |n, timestamp, delta| Change {
+            txid: txid(n),
+            timestamp,
+            delta,
+        };
+        let steps = steps(vec![
+            change(2, 20, -300),
+            change(1, 10, 1000),
+            change(3, 20, 50),
+        ]);
+        let balances: Vec<_> = steps.iter().map(|s| (s.txid, s.balance)).collect();
+        assert_eq!(
+            balances,
+            vec![(txid(1), 1000), (txid(2), 700), (txid(3), 750)]
+        );
+    }
+
+    #[test]
+    fn revalued_daily() {
+        let day = 86_400;
+        let txid = Txid::new(&"11".repeat(32)).unwrap();
+        let change = |timestamp, delta| Change {
+            txid,
+            timestamp,
+            delta,
+        };
+        // One BTC on the first day, half of it spent on the third.
+        let steps = steps(vec![
+            change(day + 100, 100_000_000),
+            change(3 * day + 100, -50_000_000),
+        ]);
+        // No rate for the third day, it keeps the one of the second.
+        let rates = [(day, 10.0), (2 * day, 20.0), (4 * day, 40.0)];
+        assert_eq!(
+            daily_values(&steps, &rates, 4 * day + 5),
+            vec![
+                [day as f64, 10.0],
+                [2.0 * day as f64, 20.0],
+                [3.0 * day as f64, 10.0],
+                [4.0 * day as f64, 20.0],
+            ]
+        );
+    }
+}
@@ -30,6 +30,10 @@ impl Cubic {
         }
     }
 
+    pub fn points(&self) -> [Pos2; 4] {
+        [self.p0, self.p1, self.p2, self.p3]
+    }
+
     pub fn eval(&self, t: f32) -> Pos2 {
         let c = 1.0 - t;
         let c2 = c * c;
@@ -0,0 +1,86 @@
+//! Wallet labels in the BIP-329 format: one JSON object per line, e.g.
+//! `{"type":"tx","ref":"<txid>","label":"Rent"}`.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Type {
+    Tx,
+    Addr,
+    Pubkey,
+    Input,
+    Output,
+    Xpub,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Label {
+    #[serde(rename = "type")]
+    pub type_: Type,
+    #[serde(rename = "ref")]
+    pub ref_: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl Label {
+    pub fn new(type_: Type, ref_: String, label: String) -> Self {
+        Self {
+            type_,
+            ref_,
+            label: Some(label),
+        }
+    }
+}
+
+/// Empty lines are skipped. Records without a label, e.g. only with `spendable`, are dropped.
+pub fn parse(contents: &str) -> Result<Vec<Label>, String> {
+    let mut labels = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let label: Label =
+            serde_json::from_str(line).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        if label.label.as_ref().is_some_and(|l| !l.is_empty()) {
+            labels.push(label);
+        }
+    }
+    Ok(labels)
+}
+
+pub fn write(labels: &[Label]) -> String {
+    labels
+        .iter()
+        .map(|label| serde_json::to_string(label).unwrap() + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, write, Type};
+
+    #[test]
+    fn roundtrip() {
+        let contents = r#"{"type":"tx","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd","label":"Transaction","origin":"wpkh([d34db33f/84'/0'/0'])"}
+
+{"type":"output","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:1","label":"Output","spendable":false}
+{"type":"output","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:0","spendable":true}
+{"type":"addr","ref":"bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c","label":"Address"}
+"#;
+        let labels = parse(contents).unwrap();
+        assert_eq!(labels.len(), 3);
+        assert_eq!(labels[1].type_, Type::Output);
+        assert_eq!(
+            write(&labels[2..]),
+            "{\"type\":\"addr\",\"ref\":\"bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c\",\"label\":\"Address\"}\n"
+        );
+        assert_eq!(parse(&write(&labels)).unwrap(), labels);
+
+        assert_eq!(
+            parse("{\"type\":\"tx\"}").unwrap_err(),
+            "Line 1: missing field `ref` at line 1 column 13"
+        );
+    }
+}
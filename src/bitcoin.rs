@@ -7,7 +7,7 @@ use egui::{text::LayoutJob, Widget};
 use hex::{FromHex, ToHex};
 use serde::{Deserialize, Serialize};
 
-use crate::{graph::sats_layout, platform::inner::get_random_int, style::Style};
+use crate::{graph::sats_layout, platform::inner::get_random_int, script, style::Style};
 
 #[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone)]
 pub struct Txid([u8; 32]);
@@ -140,7 +140,8 @@ impl Debug for Txid {
 impl<'de> Deserialize<'de> for Txid {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         let string = String::deserialize(deserializer)?;
-        Ok(Self::new(&string).unwrap()) // TODO better error handling
+        Self::new(&string)
+            .map_err(|e| serde::de::Error::custom(format!("invalid txid {:?}: {}", string, e)))
     }
 }
 
@@ -156,7 +157,7 @@ impl AsRef<[u8]> for Txid {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Transaction {
     pub timestamp: i64,
     pub txid: Txid,
@@ -165,16 +166,36 @@ pub struct Transaction {
     pub outputs: Vec<Output>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Input {
     pub txid: Txid,
     pub vout: u32,
     pub value: u64,
     pub address: String,
     pub address_type: AddressType,
+    /// Hex encoded. Older backends don't send this.
+    #[serde(default)]
+    pub script_sig: Option<String>,
+    /// Hex encoded items. Older backends don't send this.
+    #[serde(default)]
+    pub witness: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+impl Input {
+    pub fn script_sig_bytes(&self) -> Option<Vec<u8>> {
+        Vec::from_hex(self.script_sig.as_ref()?).ok()
+    }
+
+    pub fn witness_bytes(&self) -> Vec<Vec<u8>> {
+        self.witness
+            .iter()
+            .flatten()
+            .filter_map(|item| Vec::from_hex(item).ok())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum AddressType {
     P2PKH,
@@ -185,12 +206,26 @@ pub enum AddressType {
     Unknown,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Output {
     pub spending_txid: Option<Txid>,
     pub value: u64,
     pub address: String,
     pub address_type: AddressType,
+    /// Hex encoded. Older backends don't send this.
+    #[serde(default)]
+    pub script_pubkey: Option<String>,
+}
+
+impl Output {
+    pub fn script_pubkey_bytes(&self) -> Option<Vec<u8>> {
+        Vec::from_hex(self.script_pubkey.as_ref()?).ok()
+    }
+
+    /// The data pushed after `OP_RETURN`, if this is a data carrier output.
+    pub fn op_return_data(&self) -> Option<Vec<u8>> {
+        script::op_return_data(&self.script_pubkey_bytes()?)
+    }
 }
 
 impl Transaction {
@@ -213,6 +248,55 @@ impl Transaction {
         assert!(fees >= 0, "fees negative");
         fees as u64
     }
+
+    /// Weight units (BIP 141), from the size of the serialized transaction. `None` for coinbase
+    /// transactions and if scripts are missing, which older backends don't send.
+    pub fn weight(&self) -> Option<u64> {
+        fn var_int(n: usize) -> u64 {
+            match n {
+                0..=0xfc => 1,
+                0xfd..=0xffff => 3,
+                0x1_0000..=0xffff_ffff => 5,
+                _ => 9,
+            }
+        }
+        fn with_len(hex: &str) -> u64 {
+            let len = hex.len() / 2;
+            var_int(len) + len as u64
+        }
+
+        if self.is_coinbase() {
+            return None;
+        }
+        // Version and lock time
+        let mut base = 8 + var_int(self.inputs.len()) + var_int(self.outputs.len());
+        let mut witness = 0;
+        for input in &self.inputs {
+            // Outpoint and sequence
+            base += 36 + with_len(input.script_sig.as_ref()?) + 4;
+            let items = input.witness.as_deref().unwrap_or_default();
+            witness += var_int(items.len()) + items.iter().map(|i| with_len(i)).sum::<u64>();
+        }
+        for output in &self.outputs {
+            base += 8 + with_len(output.script_pubkey.as_ref()?);
+        }
+
+        let segwit = self
+            .inputs
+            .iter()
+            .any(|input| input.witness.as_ref().is_some_and(|w| !w.is_empty()));
+        Some(if segwit {
+            // Marker and flag
+            base * 4 + 2 + witness
+        } else {
+            base * 4
+        })
+    }
+
+    /// Virtual size in vbytes.
+    pub fn vsize(&self) -> Option<u64> {
+        Some(self.weight()?.div_ceil(4))
+    }
 }
 
 pub struct Sats(pub u64);
@@ -277,6 +361,13 @@ impl Widget for SatsDisplay<'_> {
     }
 }
 
+/// Exactly, without going through floats, e.g. `-0.00012345`.
+pub fn format_btc(sats: i64) -> String {
+    let sign = if sats < 0 { "-" } else { "" };
+    let abs = sats.unsigned_abs();
+    format!("{}{}.{:08}", sign, abs / 100_000_000, abs % 100_000_000)
+}
+
 impl Display for Sats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let AmountComponents {
@@ -342,6 +433,8 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
                     vout: 0,
                     address: "fslkfjeslk".to_string(),
                     address_type: AddressType::P2PKH,
+                    script_sig: None,
+                    witness: None,
                 }],
                 outputs: vec![
                     Output {
@@ -349,12 +442,14 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
                         value: 100_230_000,
                         address: "fsklefj".to_string(),
                         address_type: AddressType::P2PKH,
+                        script_pubkey: None,
                     },
                     Output {
                         spending_txid: Some(c),
                         value: 12_300_000,
                         address: "fsklefj".to_string(),
                         address_type: AddressType::P2PKH,
+                        script_pubkey: None,
                     },
                 ],
             },
@@ -371,12 +466,15 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
                     vout: 0,
                     address: "fslkfjeslk".to_string(),
                     address_type: AddressType::P2PKH,
+                    script_sig: None,
+                    witness: None,
                 }],
                 outputs: vec![Output {
                     spending_txid: Some(c),
                     value: 12_300_000,
                     address: "fsklefj".to_string(),
                     address_type: AddressType::P2PKH,
+                    script_pubkey: None,
                 }],
             },
         ),
@@ -393,6 +491,8 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
                         vout: 1,
                         address: "fslkfjeslk".to_string(),
                         address_type: AddressType::P2PKH,
+                        script_sig: None,
+                        witness: None,
                     },
                     Input {
                         value: 12_300_000,
@@ -400,6 +500,8 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
                         vout: 0,
                         address: "fslkfjeslk".to_string(),
                         address_type: AddressType::P2PKH,
+                        script_sig: None,
+                        witness: None,
                     },
                 ],
                 outputs: vec![],
@@ -410,7 +512,7 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
 
 #[cfg(test)]
 mod tests {
-    use crate::bitcoin::{Sats, Txid};
+    use crate::bitcoin::{AddressType, Input, Output, Sats, Transaction, Txid};
 
     #[test]
     #[allow(clippy::inconsistent_digit_grouping)]
@@ -438,4 +540,38 @@ mod tests {
             "afe8d3199cd68f973a7cba01cb6b59f733864b782e9be49f61bb7f3d928a8382"
         );
     }
+
+    #[test]
+    fn vsize() {
+        let txid =
+            Txid::new("afe8d3199cd68f973a7cba01cb6b59f733864b782e9be49f61bb7f3d928a8382").unwrap();
+        let output = Output {
+            spending_txid: None,
+            value: 1000,
+            address: String::new(),
+            address_type: AddressType::P2WPKH,
+            script_pubkey: Some(format!("0014{}", "00".repeat(20))),
+        };
+        let mut tx = Transaction {
+            timestamp: 0,
+            txid,
+            block_height: 0,
+            inputs: vec![Input {
+                txid,
+                vout: 0,
+                value: 3000,
+                address: String::new(),
+                address_type: AddressType::P2WPKH,
+                script_sig: Some(String::new()),
+                witness: Some(vec!["00".repeat(72), "00".repeat(33)]),
+            }],
+            outputs: vec![output.clone(), output],
+        };
+        // One P2WPKH input, two P2WPKH outputs
+        assert_eq!(tx.weight(), Some(562));
+        assert_eq!(tx.vsize(), Some(141));
+
+        tx.outputs[0].script_pubkey = None;
+        assert_eq!(tx.vsize(), None);
+    }
 }
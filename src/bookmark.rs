@@ -0,0 +1,158 @@
+//! Named views of the graph, for walking someone through a case one step at a time.
+
+use egui::{Context, Grid, Key, Modifiers, Pos2, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    export::{Bookmark0, Filters0, Point0},
+    layout::EdgeFilter,
+};
+
+/// The first bookmarks can be jumped to with these.
+const KEYS: [Key; 9] = [
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    /// Graph coordinates shown in the middle of the canvas, so that the view fits any window.
+    pub center: Pos2,
+    pub zoom: f32,
+    /// Restored together with the view, if they were saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filters: Option<Filters>,
+}
+
+impl Bookmark {
+    pub fn export(&self) -> Bookmark0 {
+        Bookmark0 {
+            name: self.name.clone(),
+            center: Point0 {
+                x: self.center.x,
+                y: self.center.y,
+            },
+            zoom: self.zoom,
+            filters: self.filters.as_ref().map(|filters| Filters0 {
+                hidden_categories: filters.hidden_categories.clone(),
+                edge_filter: filters.edge_filter.export(),
+            }),
+        }
+    }
+
+    pub fn import(bookmark0: Bookmark0) -> Self {
+        Self {
+            name: bookmark0.name,
+            center: Pos2::new(bookmark0.center.x, bookmark0.center.y),
+            zoom: bookmark0.zoom,
+            filters: bookmark0.filters.map(|filters| Filters {
+                hidden_categories: filters.hidden_categories,
+                edge_filter: EdgeFilter::import(filters.edge_filter),
+            }),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Filters {
+    pub hidden_categories: Vec<String>,
+    pub edge_filter: EdgeFilter,
+}
+
+pub enum Action {
+    Jump(usize),
+    /// Save the current view under [name], with the filters if [filters] is set.
+    Save {
+        name: String,
+        filters: bool,
+    },
+}
+
+/// The bookmark whose number key was pressed, unless a text field has the keyboard.
+pub fn shortcut(ctx: &Context) -> Option<usize> {
+    if ctx.wants_keyboard_input() {
+        return None;
+    }
+    KEYS.iter()
+        .position(|key| ctx.input_mut(|i| i.consume_key(Modifiers::NONE, *key)))
+}
+
+/// Lists the bookmarks to jump to, rename, reorder and delete, and saves new ones.
+pub fn menu(ui: &mut egui::Ui, bookmarks: &mut Vec<Bookmark>) -> Option<Action> {
+    let mut action = None;
+
+    if bookmarks.is_empty() {
+        ui.label("No saved views yet.");
+    } else {
+        let mut up = None;
+        let mut delete = None;
+        Grid::new("Bookmarks").num_columns(5).show(ui, |ui| {
+            for (i, bookmark) in bookmarks.iter_mut().enumerate() {
+                if i < KEYS.len() {
+                    ui.weak(format!("{}", i + 1));
+                } else {
+                    ui.label("");
+                }
+                ui.add(TextEdit::singleline(&mut bookmark.name).desired_width(150.0));
+                if ui.button("Go").clicked() {
+                    action = Some(Action::Jump(i));
+                    ui.close_menu();
+                }
+                if ui
+                    .add_enabled(i > 0, egui::Button::new("⏶"))
+                    .on_hover_text("Move up")
+                    .clicked()
+                {
+                    up = Some(i);
+                }
+                if ui.button("✖").on_hover_text("Delete").clicked() {
+                    delete = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = up {
+            bookmarks.swap(i - 1, i);
+        }
+        if let Some(i) = delete {
+            bookmarks.remove(i);
+        }
+        ui.weak("Press 1-9 on the canvas to jump to a view.");
+    }
+
+    ui.separator();
+    let id = ui.id().with("NewBookmark");
+    let (mut name, mut filters) =
+        ui.data_mut(|d| d.get_temp::<(String, bool)>(id).unwrap_or_default());
+    ui.horizontal(|ui| {
+        let hint = format!("View {}", bookmarks.len() + 1);
+        ui.add(
+            TextEdit::singleline(&mut name)
+                .hint_text(&hint)
+                .desired_width(150.0),
+        );
+        if ui.button("Save Current View").clicked() {
+            let name = match name.trim() {
+                "" => hint,
+                name => name.to_string(),
+            };
+            action = Some(Action::Save { name, filters });
+            ui.close_menu();
+        }
+    });
+    ui.checkbox(&mut filters, "Include category visibility and flow filter");
+    if matches!(action, Some(Action::Save { .. })) {
+        name.clear();
+    }
+    ui.data_mut(|d| d.insert_temp(id, (name, filters)));
+
+    action
+}
@@ -0,0 +1,567 @@
+//! Where transactions come from: the txgraph backend, or any Esplora-compatible REST API such
+//! as mempool.space or blockstream.info.
+
+use std::sync::Arc;
+
+use egui::{mutex::Mutex, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitcoin::{AddressType, Input, Output, Transaction, Txid},
+    client::{ApiError, Client, RequestHandle},
+};
+
+/// Bitcoin or a chain derived from it, with the same transaction format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chain {
+    Bitcoin,
+    Litecoin,
+}
+
+impl Chain {
+    /// Coin id for the price API, see [crate::prices].
+    pub fn coingecko_id(&self) -> &'static str {
+        match self {
+            Chain::Bitcoin => "bitcoin",
+            Chain::Litecoin => "litecoin",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+    Litecoin,
+    LitecoinTestnet,
+}
+
+impl Default for Network {
+    /// The network of the txgraph backend.
+    fn default() -> Self {
+        if cfg!(testnet) {
+            Network::Testnet
+        } else {
+            Network::Mainnet
+        }
+    }
+}
+
+impl Network {
+    pub const ALL: [Network; 6] = [
+        Network::Mainnet,
+        Network::Testnet,
+        Network::Signet,
+        Network::Regtest,
+        Network::Litecoin,
+        Network::LitecoinTestnet,
+    ];
+
+    /// As recorded in [crate::export::DataSource0].
+    pub fn name(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Signet => "signet",
+            Network::Regtest => "regtest",
+            Network::Litecoin => "litecoin",
+            Network::LitecoinTestnet => "litecoin-testnet",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|n| n.name() == name)
+    }
+
+    pub fn chain(&self) -> Chain {
+        match self {
+            Network::Mainnet | Network::Testnet | Network::Signet | Network::Regtest => {
+                Chain::Bitcoin
+            }
+            Network::Litecoin | Network::LitecoinTestnet => Chain::Litecoin,
+        }
+    }
+
+    /// Used when no Esplora URL is configured, or the backend doesn't serve this network.
+    /// Regtest assumes a local electrs with its default port.
+    pub fn default_esplora_url(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "https://mempool.space/api",
+            Network::Testnet => "https://mempool.space/testnet/api",
+            Network::Signet => "https://mempool.space/signet/api",
+            Network::Regtest => "http://localhost:3002",
+            Network::Litecoin => "https://litecoinspace.org/api",
+            Network::LitecoinTestnet => "https://litecoinspace.org/testnet/api",
+        }
+    }
+
+    /// Human-readable part of segwit addresses.
+    pub fn bech32_hrp(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Testnet | Network::Signet => "tb",
+            Network::Regtest => "bcrt",
+            Network::Litecoin => "ltc",
+            Network::LitecoinTestnet => "tltc",
+        }
+    }
+
+    /// First characters of legacy (base58) addresses.
+    fn base58_prefixes(&self) -> &'static [&'static str] {
+        match self {
+            Network::Mainnet => &["1", "3"],
+            Network::Testnet | Network::Signet | Network::Regtest => &["m", "n", "2"],
+            Network::Litecoin => &["l", "m", "3"],
+            Network::LitecoinTestnet => &["m", "n", "2", "q"],
+        }
+    }
+
+    /// Version bytes of legacy P2PKH and P2SH addresses.
+    pub fn base58_versions(&self) -> (u8, u8) {
+        match self {
+            Network::Mainnet => (0x00, 0x05),
+            Network::Testnet | Network::Signet | Network::Regtest => (0x6f, 0xc4),
+            Network::Litecoin => (0x30, 0x32),
+            Network::LitecoinTestnet => (0x6f, 0x3a),
+        }
+    }
+
+    /// Whether [address] could belong to this network, judging by its prefix.
+    pub fn matches_address(&self, address: &str) -> bool {
+        let lower = address.to_ascii_lowercase();
+        if let Some((hrp, _)) = lower.split_once('1') {
+            if hrp == self.bech32_hrp() {
+                return true;
+            }
+        }
+        self.base58_prefixes().iter().any(|p| lower.starts_with(p))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Source {
+    /// The txgraph backend, see [Client::DEFAULT_BASE_URL].
+    #[default]
+    Backend,
+    /// Empty means [Network::default_esplora_url].
+    Esplora {
+        base_url: String,
+        /// The network [base_url] serves. `None` in settings from older versions, which meant
+        /// [Network::default].
+        #[serde(default)]
+        network: Option<String>,
+    },
+}
+
+impl Source {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Source::Backend => "txgraph backend",
+            Source::Esplora { .. } => "Esplora API",
+        }
+    }
+
+    /// The configured Esplora API if it serves [network], otherwise the default one.
+    pub fn esplora_url(&self, network: Network) -> String {
+        match self {
+            Source::Esplora {
+                base_url,
+                network: configured,
+            } if !base_url.trim().is_empty()
+                && configured.as_deref().unwrap_or(Network::default().name()) == network.name() =>
+            {
+                base_url.trim().trim_end_matches('/').to_string()
+            }
+            _ => network.default_esplora_url().to_string(),
+        }
+    }
+}
+
+/// The configured Esplora API, or the default one for the current network.
+fn esplora_url(ctx: &Context) -> String {
+    Client::source(ctx).esplora_url(Client::network(ctx))
+}
+
+/// Whether [fetch_tx] goes to Esplora rather than the backend.
+pub fn uses_esplora(ctx: &Context) -> bool {
+    Client::source(ctx) != Source::Backend || Client::network(ctx) != Network::default()
+}
+
+/// Fetch [txid] from the configured source. The backend only serves [Network::default], so
+/// other networks always go to Esplora.
+pub fn fetch_tx(
+    ctx: &Context,
+    txid: Txid,
+    on_done: impl 'static + Send + FnOnce(Result<Transaction, ApiError>),
+) -> Vec<RequestHandle> {
+    match Client::source(ctx) {
+        Source::Backend if Client::network(ctx) == Network::default() => {
+            vec![Client::fetch_json(
+                move |base_url| ehttp::Request::get(format!("{}/tx/{}", base_url, txid)),
+                ctx,
+                on_done,
+            )]
+        }
+        _ => esplora_fetch_tx(ctx, &esplora_url(ctx), txid, on_done),
+    }
+}
+
+/// Confirmed transactions per page of an address history.
+const HISTORY_PAGE_SIZE: usize = 25;
+
+/// One page of transactions touching [address], newest first. Pass the last confirmed txid of
+/// the previous page as [after] to get the next one. Unconfirmed transactions come with the
+/// first page. The backend has no address index, so this always uses Esplora.
+pub fn fetch_address_history(
+    ctx: &Context,
+    address: &str,
+    after: Option<Txid>,
+    on_done: impl 'static + Send + FnOnce(Result<HistoryPage, ApiError>),
+) -> RequestHandle {
+    let url = match after {
+        None => format!("{}/address/{}/txs", esplora_url(ctx), address),
+        Some(txid) => format!(
+            "{}/address/{}/txs/chain/{}",
+            esplora_url(ctx),
+            address,
+            txid
+        ),
+    };
+    Client::fetch_external_json(url, ctx, move |result: Result<Vec<HistoryTx>, ApiError>| {
+        on_done(result.map(HistoryPage::new))
+    })
+}
+
+/// Height of the best block. The backend doesn't serve it, so this always uses Esplora.
+pub fn fetch_tip_height(
+    ctx: &Context,
+    on_done: impl 'static + Send + FnOnce(Result<u32, ApiError>),
+) -> RequestHandle {
+    Client::fetch_external_json(
+        format!("{}/blocks/tip/height", esplora_url(ctx)),
+        ctx,
+        on_done,
+    )
+}
+
+/// The transaction spending output [vout] of [txid], if any. Always uses Esplora.
+pub fn fetch_outspend(
+    ctx: &Context,
+    txid: Txid,
+    vout: u32,
+    on_done: impl 'static + Send + FnOnce(Result<Option<Txid>, ApiError>),
+) -> RequestHandle {
+    let url = format!("{}/tx/{}/outspend/{}", esplora_url(ctx), txid, vout);
+    Client::fetch_external_json(url, ctx, move |result: Result<Outspend, ApiError>| {
+        on_done(result.map(|outspend| outspend.txid.filter(|_| outspend.spent)))
+    })
+}
+
+#[derive(Deserialize)]
+struct HistoryTx {
+    txid: Txid,
+    status: HistoryStatus,
+}
+
+#[derive(Deserialize)]
+struct HistoryStatus {
+    confirmed: bool,
+}
+
+pub struct HistoryPage {
+    pub txids: Vec<Txid>,
+    /// Where to continue, if there may be more.
+    pub next: Option<Txid>,
+}
+
+impl HistoryPage {
+    fn new(txs: Vec<HistoryTx>) -> Self {
+        let confirmed: Vec<Txid> = txs
+            .iter()
+            .filter(|tx| tx.status.confirmed)
+            .map(|tx| tx.txid)
+            .collect();
+        Self {
+            next: confirmed
+                .last()
+                .copied()
+                .filter(|_| confirmed.len() >= HISTORY_PAGE_SIZE),
+            txids: txs.into_iter().map(|tx| tx.txid).collect(),
+        }
+    }
+}
+
+type OnDone = Box<dyn FnOnce(Result<Transaction, ApiError>) + Send>;
+
+type Pending = (
+    Option<Result<EsploraTx, ApiError>>,
+    Option<Result<Vec<Outspend>, ApiError>>,
+);
+
+/// The transaction and the spending status of its outputs come from separate endpoints.
+fn esplora_fetch_tx(
+    ctx: &Context,
+    base_url: &str,
+    txid: Txid,
+    on_done: impl 'static + Send + FnOnce(Result<Transaction, ApiError>),
+) -> Vec<RequestHandle> {
+    let base_url = base_url.trim_end_matches('/');
+    let pending: Arc<Mutex<Pending>> = Arc::new(Mutex::new((None, None)));
+    let on_done: Arc<Mutex<Option<OnDone>>> = Arc::new(Mutex::new(Some(Box::new(on_done))));
+
+    let finish = move |pending: &mut Pending| {
+        let (Some(tx), Some(outspends)) = (pending.0.take(), pending.1.take()) else {
+            return;
+        };
+        let result = tx.and_then(|tx| {
+            let outspends = outspends?;
+            tx.into_transaction(outspends).map_err(ApiError::Decode)
+        });
+        if let Some(on_done) = on_done.lock().take() {
+            on_done(result);
+        }
+    };
+    let finish2 = finish.clone();
+    let pending2 = pending.clone();
+
+    vec![
+        Client::fetch_external_json(format!("{}/tx/{}", base_url, txid), ctx, move |result| {
+            let mut pending = pending.lock();
+            pending.0 = Some(result);
+            finish(&mut pending);
+        }),
+        Client::fetch_external_json(
+            format!("{}/tx/{}/outspends", base_url, txid),
+            ctx,
+            move |result| {
+                let mut pending = pending2.lock();
+                pending.1 = Some(result);
+                finish2(&mut pending);
+            },
+        ),
+    ]
+}
+
+#[derive(Deserialize)]
+struct EsploraTx {
+    txid: Txid,
+    vin: Vec<Vin>,
+    vout: Vec<Vout>,
+    status: Status,
+}
+
+#[derive(Deserialize)]
+struct Vin {
+    txid: Txid,
+    vout: u32,
+    is_coinbase: bool,
+    prevout: Option<Vout>,
+    #[serde(default)]
+    scriptsig: String,
+    #[serde(default)]
+    witness: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct Vout {
+    scriptpubkey: String,
+    scriptpubkey_type: String,
+    #[serde(default)]
+    scriptpubkey_address: Option<String>,
+    value: u64,
+}
+
+#[derive(Deserialize)]
+struct Status {
+    #[serde(default)]
+    block_height: Option<u32>,
+    #[serde(default)]
+    block_time: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct Outspend {
+    spent: bool,
+    #[serde(default)]
+    txid: Option<Txid>,
+}
+
+impl Vout {
+    fn address_type(&self) -> AddressType {
+        match self.scriptpubkey_type.as_str() {
+            "p2pkh" => AddressType::P2PKH,
+            "p2sh" => AddressType::P2SH,
+            "v0_p2wpkh" => AddressType::P2WPKH,
+            "v0_p2wsh" => AddressType::P2WSH,
+            "v1_p2tr" => AddressType::P2TR,
+            _ => AddressType::Unknown,
+        }
+    }
+}
+
+impl EsploraTx {
+    fn into_transaction(self, outspends: Vec<Outspend>) -> Result<Transaction, String> {
+        if outspends.len() != self.vout.len() {
+            return Err(format!(
+                "Got {} outspends for {} outputs",
+                outspends.len(),
+                self.vout.len()
+            ));
+        }
+
+        let inputs = self
+            .vin
+            .into_iter()
+            // Coinbase transactions have no inputs in our model.
+            .filter(|vin| !vin.is_coinbase)
+            .map(|vin| {
+                let prevout = vin
+                    .prevout
+                    .ok_or_else(|| format!("Input {}:{} has no prevout", vin.txid, vin.vout))?;
+                Ok(Input {
+                    txid: vin.txid,
+                    vout: vin.vout,
+                    value: prevout.value,
+                    address_type: prevout.address_type(),
+                    address: prevout.scriptpubkey_address.unwrap_or_default(),
+                    script_sig: Some(vin.scriptsig),
+                    witness: vin.witness,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+
+        let outputs = self
+            .vout
+            .into_iter()
+            .zip(outspends)
+            .map(|(vout, outspend)| Output {
+                spending_txid: outspend.txid.filter(|_| outspend.spent),
+                value: vout.value,
+                address_type: vout.address_type(),
+                address: vout.scriptpubkey_address.unwrap_or_default(),
+                script_pubkey: Some(vout.scriptpubkey),
+            })
+            .collect();
+
+        Ok(Transaction {
+            // Unconfirmed transactions are shown as of now.
+            timestamp: self
+                .status
+                .block_time
+                .unwrap_or_else(|| chrono::Utc::now().timestamp()),
+            txid: self.txid,
+            block_height: self.status.block_height.unwrap_or(0),
+            inputs,
+            outputs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EsploraTx, Network, Outspend, Source};
+    use crate::bitcoin::{AddressType, Txid};
+
+    #[test]
+    fn network_addresses() {
+        assert!(Network::Mainnet.matches_address("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"));
+        assert!(!Network::Mainnet.matches_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"));
+        assert!(Network::Signet.matches_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"));
+        assert!(Network::Regtest.matches_address("bcrt1q6rhpng9evdsfnn833a4f4vej0asu6dk5srld6x"));
+        assert_eq!(Network::from_name("signet"), Some(Network::Signet));
+
+        let ltc = "ltc1qg82tz5k0wd0vzm5dcyvsmvf2xvwsjxk2qq4cux";
+        assert!(Network::Litecoin.matches_address(ltc));
+        assert!(Network::Litecoin.matches_address("LVg2kJoFNg45Nbpy53h7Fe1wKyeXVRhMH9"));
+        assert!(!Network::Mainnet.matches_address(ltc));
+        assert!(!Network::Litecoin.matches_address("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"));
+        assert!(Network::LitecoinTestnet.matches_address("tltc1qw508d6qejxtdg4y5r3zarvary0c5xw7k"));
+    }
+
+    #[test]
+    fn esplora_url_per_network() {
+        let source = Source::Esplora {
+            base_url: "http://localhost:3000/".to_string(),
+            network: Some("testnet".to_string()),
+        };
+        assert_eq!(
+            source.esplora_url(Network::Testnet),
+            "http://localhost:3000"
+        );
+        assert_eq!(
+            source.esplora_url(Network::Litecoin),
+            Network::Litecoin.default_esplora_url()
+        );
+
+        // Older settings have no network.
+        let source: Source =
+            serde_json::from_str(r#"{"Esplora": {"base_url": "http://localhost:3000"}}"#).unwrap();
+        assert_eq!(
+            source.esplora_url(Network::default()),
+            "http://localhost:3000"
+        );
+    }
+
+    #[test]
+    fn esplora_tx() {
+        let txid = |n: &str| n.repeat(32);
+        let tx: EsploraTx = serde_json::from_str(&format!(
+            r#"{{
+                "txid": "{}",
+                "version": 2,
+                "vin": [{{
+                    "txid": "{}",
+                    "vout": 1,
+                    "is_coinbase": false,
+                    "prevout": {{
+                        "scriptpubkey": "0014aa",
+                        "scriptpubkey_type": "v0_p2wpkh",
+                        "scriptpubkey_address": "bc1qaa",
+                        "value": 5000
+                    }},
+                    "scriptsig": "",
+                    "witness": ["30", "02"]
+                }}],
+                "vout": [
+                    {{
+                        "scriptpubkey": "5120bb",
+                        "scriptpubkey_type": "v1_p2tr",
+                        "scriptpubkey_address": "bc1pbb",
+                        "value": 3000
+                    }},
+                    {{
+                        "scriptpubkey": "6a0161",
+                        "scriptpubkey_type": "op_return",
+                        "value": 0
+                    }}
+                ],
+                "status": {{ "confirmed": true, "block_height": 800000, "block_time": 1690000000 }}
+            }}"#,
+            txid("11"),
+            txid("22")
+        ))
+        .unwrap();
+        let outspends: Vec<Outspend> = serde_json::from_str(&format!(
+            r#"[{{ "spent": true, "txid": "{}", "vin": 0 }}, {{ "spent": false }}]"#,
+            txid("33")
+        ))
+        .unwrap();
+
+        let tx = tx.into_transaction(outspends).unwrap();
+        assert_eq!(tx.txid, Txid::new(&txid("11")).unwrap());
+        assert_eq!(tx.block_height, 800000);
+        assert_eq!(tx.timestamp, 1690000000);
+        assert_eq!(tx.inputs[0].value, 5000);
+        assert_eq!(tx.inputs[0].address_type, AddressType::P2WPKH);
+        assert_eq!(
+            tx.outputs[0].spending_txid,
+            Some(Txid::new(&txid("33")).unwrap())
+        );
+        assert_eq!(tx.outputs[0].address_type, AddressType::P2TR);
+        assert_eq!(tx.outputs[1].spending_txid, None);
+        assert_eq!(tx.outputs[1].op_return_data(), Some(b"a".to_vec()));
+        assert_eq!(tx.fees(), 2000);
+    }
+}
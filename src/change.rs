@@ -0,0 +1,202 @@
+//! Guessing which output of a transaction returns the change to the sender.
+
+use std::fmt::Display;
+
+use crate::bitcoin::AddressType;
+
+/// Payments tend to be round amounts, change is whatever is left.
+const ROUND_SATS: u64 = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reason {
+    /// The other outputs are round amounts.
+    RoundPayment,
+    /// Same script type as the inputs, unlike the other outputs.
+    ScriptType,
+    /// Sent back to an input address.
+    AddressReuse,
+    /// The only output to an address not seen before.
+    FreshAddress,
+}
+
+impl Reason {
+    fn weight(&self) -> u32 {
+        match self {
+            Reason::AddressReuse => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Reason::RoundPayment => "round payment",
+            Reason::ScriptType => "same script type as inputs",
+            Reason::AddressReuse => "back to an input address",
+            Reason::FreshAddress => "fresh address",
+        })
+    }
+}
+
+pub struct Output<'a> {
+    pub value: u64,
+    pub address_type: AddressType,
+    pub address: &'a str,
+    /// Whether the address appears in an earlier transaction.
+    pub seen_before: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Guess {
+    /// Index into the outputs passed to [detect].
+    pub index: usize,
+    pub reasons: Vec<Reason>,
+}
+
+/// The output most heuristics point at, if there's a single one.
+pub fn detect(inputs: &[(AddressType, &str)], outputs: &[Output<'_>]) -> Option<Guess> {
+    if outputs.len() < 2 || inputs.is_empty() {
+        return None;
+    }
+    let input_type = inputs[0].0;
+    let same_input_types = inputs.iter().all(|(t, _)| *t == input_type);
+    let round = |o: &Output<'_>| o.value % ROUND_SATS == 0;
+
+    let mut guesses: Vec<Guess> = outputs
+        .iter()
+        .enumerate()
+        .map(|(index, output)| {
+            let others = || {
+                outputs
+                    .iter()
+                    .enumerate()
+                    .filter(move |(i, _)| *i != index)
+                    .map(|(_, o)| o)
+            };
+            let mut reasons = vec![];
+            if !round(output) && others().all(round) {
+                reasons.push(Reason::RoundPayment);
+            }
+            if same_input_types
+                && output.address_type == input_type
+                && others().all(|o| o.address_type != input_type)
+            {
+                reasons.push(Reason::ScriptType);
+            }
+            // OP_RETURN and nonstandard outputs have no address to be reused or fresh.
+            let has_address = !output.address.is_empty();
+            if has_address && inputs.iter().any(|(_, a)| *a == output.address) {
+                reasons.push(Reason::AddressReuse);
+            }
+            if has_address
+                && !output.seen_before
+                && others()
+                    .filter(|o| !o.address.is_empty())
+                    .all(|o| o.seen_before)
+            {
+                reasons.push(Reason::FreshAddress);
+            }
+            Guess { index, reasons }
+        })
+        .collect();
+
+    let score = |g: &Guess| g.reasons.iter().map(Reason::weight).sum::<u32>();
+    guesses.sort_by_key(|g| std::cmp::Reverse(score(g)));
+    let best = score(&guesses[0]);
+    if best == 0 || score(&guesses[1]) == best {
+        return None;
+    }
+    Some(guesses.swap_remove(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect, Output, Reason};
+    use crate::bitcoin::AddressType;
+
+    fn output(value: u64, address_type: AddressType, address: &str) -> Output<'_> {
+        Output {
+            value,
+            address_type,
+            address,
+            seen_before: false,
+        }
+    }
+
+    #[test]
+    fn heuristics() {
+        let inputs = [(AddressType::P2WPKH, "in")];
+
+        // Round payment and script type agree.
+        let guess = detect(
+            &inputs,
+            &[
+                output(1_000_000, AddressType::P2PKH, "pay"),
+                output(123_456, AddressType::P2WPKH, "change"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(guess.index, 1);
+        assert_eq!(
+            guess.reasons,
+            vec![Reason::RoundPayment, Reason::ScriptType]
+        );
+
+        // Nothing to tell the outputs apart.
+        assert_eq!(
+            detect(
+                &inputs,
+                &[
+                    output(1_000, AddressType::P2WPKH, "a"),
+                    output(2_000, AddressType::P2WPKH, "b"),
+                ],
+            ),
+            None
+        );
+
+        // Reuse of the input address outweighs a round amount.
+        let guess = detect(
+            &inputs,
+            &[
+                output(123_456, AddressType::P2WPKH, "pay"),
+                output(500_000, AddressType::P2WPKH, "in"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(guess.index, 1);
+
+        // Fresh address.
+        let mut seen = output(1_234, AddressType::P2WPKH, "seen");
+        seen.seen_before = true;
+        let guess = detect(&inputs, &[seen, output(5_678, AddressType::P2WPKH, "new")]).unwrap();
+        assert_eq!(guess.reasons, vec![Reason::FreshAddress]);
+    }
+
+    #[test]
+    fn outputs_without_address() {
+        // An input without address doesn't make an OP_RETURN output "reused".
+        let inputs = [(AddressType::P2WPKH, "in"), (AddressType::P2WPKH, "")];
+        let mut seen = output(10_000, AddressType::P2WPKH, "seen");
+        seen.seen_before = true;
+        let guess = detect(&inputs, &[seen, output(0, AddressType::Unknown, "")]).unwrap();
+        assert_eq!(guess.index, 0);
+        assert_eq!(guess.reasons, vec![Reason::ScriptType]);
+
+        // Nor fresh, and it doesn't keep the other outputs from being fresh.
+        let inputs = [(AddressType::P2WPKH, "in")];
+        let mut seen = output(1_234, AddressType::P2WPKH, "seen");
+        seen.seen_before = true;
+        let guess = detect(
+            &inputs,
+            &[
+                seen,
+                output(0, AddressType::Unknown, ""),
+                output(5_678, AddressType::P2WPKH, "new"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(guess.index, 2);
+        assert_eq!(guess.reasons, vec![Reason::FreshAddress]);
+    }
+}
@@ -1,23 +1,140 @@
-use egui::{Context, Id};
+use chrono::{DateTime, Local, Utc};
+use egui::{mutex::Mutex, Color32, Context, Id, Ui};
+use lru::LruCache;
 use serde::Deserialize;
 
-use crate::{loading::Loading, notifications::NotifyExt};
+use std::{
+    collections::VecDeque,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    chain::{Network, Source},
+    export,
+    loading::{Loading, RequestId},
+    middleware::{self, Auth, Middleware, Pipeline, RequestLog},
+    notifications::NotifyExt,
+    platform::inner as platform,
+    widgets::StatusDot,
+};
+
+/// Delay before the first retry, doubled for every further attempt.
+const RETRY_BASE_DELAY_MS: u32 = 500;
+const RETRY_MAX_DELAY_MS: u32 = 30_000;
+/// A server asking to wait longer than this with `Retry-After` isn't retried at all.
+const RETRY_AFTER_MAX_MS: u32 = 15 * 60 * 1000;
+/// Rate limited requests are retried at least this often, regardless of the setting, so bulk
+/// operations get through eventually.
+const RATE_LIMIT_ATTEMPTS: u32 = 6;
+/// Minimum time between two "Slowing down" notifications.
+const RATE_LIMIT_NOTICE_INTERVAL: f64 = 10.0;
+/// Number of GET responses to keep for conditional requests.
+const HTTP_CACHE_SIZE: usize = 500;
+/// Number of recent requests the health indicator is based on.
+const HEALTH_WINDOW: usize = 10;
+/// Requests taking longer than this count as degraded.
+const SLOW_REQUEST_MS: i64 = 5_000;
+/// Seconds between two pings of the backend's `/health` endpoint.
+const HEALTH_PING_INTERVAL: f64 = 30.0;
+
+const API_TOKEN: &str = env!("API_TOKEN");
 
 #[derive(Clone)]
 pub struct Client {
     base_url: String,
+    /// Overrides `base_url` while a workspace pinned to a different backend is open.
+    pinned_url: Option<String>,
+    /// Including the first one.
+    max_attempts: u32,
+    source: Source,
+    /// Of the open workspace.
+    network: Network,
+    health: Arc<Mutex<Health>>,
+    request_log: RequestLog,
+    pipeline: Pipeline,
 }
 
 impl Client {
+    /// Can be changed at runtime in the settings.
+    pub const DEFAULT_BASE_URL: &'static str = env!("API_BASE");
+    pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
     pub fn new(base_url: &str) -> Self {
+        let request_log = RequestLog::default();
         Self {
             base_url: base_url.to_string(),
+            pinned_url: None,
+            max_attempts: Self::DEFAULT_MAX_ATTEMPTS,
+            source: Source::Backend,
+            network: Network::default(),
+            health: Arc::new(Mutex::new(Health::default())),
+            request_log: request_log.clone(),
+            pipeline: vec![
+                Arc::new(Auth::new(API_TOKEN)),
+                Arc::new(HttpCache::new()),
+                Arc::new(request_log),
+            ],
+        }
+    }
+
+    fn url(&self) -> &str {
+        self.pinned_url.as_deref().unwrap_or(&self.base_url)
+    }
+
+    /// The backend requests currently go to.
+    pub fn data_source(ctx: &Context) -> export::DataSource0 {
+        export::DataSource0 {
+            api_base: Self::load(ctx).url().to_string(),
+            network: Self::network(ctx).name().to_string(),
         }
     }
 
+    pub fn set_base_url(ctx: &Context, url: &str) {
+        let mut slf = Self::load(ctx);
+        slf.base_url = url.to_string();
+        slf.store(ctx);
+    }
+
+    pub fn set_max_attempts(ctx: &Context, max_attempts: u32) {
+        let mut slf = Self::load(ctx);
+        slf.max_attempts = max_attempts.max(1);
+        slf.store(ctx);
+    }
+
+    pub fn pin(ctx: &Context, url: Option<String>) {
+        let mut slf = Self::load(ctx);
+        slf.pinned_url = url;
+        slf.store(ctx);
+    }
+
+    pub fn set_source(ctx: &Context, source: Source) {
+        let mut slf = Self::load(ctx);
+        slf.source = source;
+        slf.store(ctx);
+    }
+
+    /// Where transactions are fetched from, see [crate::chain::fetch_tx].
+    pub fn source(ctx: &Context) -> Source {
+        Self::load(ctx).source
+    }
+
+    pub fn set_network(ctx: &Context, network: Network) {
+        let mut slf = Self::load(ctx);
+        slf.network = network;
+        slf.store(ctx);
+    }
+
+    pub fn network(ctx: &Context) -> Network {
+        Self::load(ctx).network
+    }
+
     fn load(ctx: &Context) -> Self {
         ctx.data(|d| d.get_temp(Id::NULL))
-            .unwrap_or(Self::new(env!("API_BASE")))
+            .unwrap_or(Self::new(Self::DEFAULT_BASE_URL))
     }
 
     fn store(self, ctx: &Context) {
@@ -27,62 +144,609 @@ impl Client {
     pub fn fetch_json<T: for<'de> Deserialize<'de>>(
         mk_request: impl FnOnce(&str) -> ehttp::Request,
         ctx: &Context,
-        on_done: impl 'static + Send + FnOnce(Result<T, FetchError>),
-    ) {
+        on_done: impl 'static + Send + FnOnce(Result<T, ApiError>),
+    ) -> RequestHandle {
+        let request = mk_request(Self::load(ctx).url());
+        Self::fetch(request, false, ctx, on_done)
+    }
+
+    /// Like [Client::fetch_json], but for a server other than the backend. Middleware that is
+    /// only meant for the backend, like authentication, is skipped.
+    pub fn fetch_external_json<T: for<'de> Deserialize<'de>>(
+        url: String,
+        ctx: &Context,
+        on_done: impl 'static + Send + FnOnce(Result<T, ApiError>),
+    ) -> RequestHandle {
+        Self::fetch(ehttp::Request::get(url), true, ctx, on_done)
+    }
+
+    fn fetch<T: for<'de> Deserialize<'de>>(
+        mut request: ehttp::Request,
+        external: bool,
+        ctx: &Context,
+        on_done: impl 'static + Send + FnOnce(Result<T, ApiError>),
+    ) -> RequestHandle {
         let slf = Self::load(ctx);
+        middleware::run_request(&slf.pipeline, &mut request, external);
+        let (id, cancelled) = Loading::start_request(ctx, &middleware::redacted_url(&request.url));
+
+        let ctx = ctx.clone();
+        let cancelled2 = cancelled.clone();
+        let request2 = request.clone();
+        let ctx2 = ctx.clone();
+        let started = Utc::now();
+        Self::fetch_with_retry(
+            &ctx2,
+            request,
+            slf.max_attempts,
+            1,
+            cancelled,
+            move |mut response, latency_ms| {
+                Loading::request_done(&ctx, id);
+                let now = Utc::now();
+                middleware::run_response(
+                    &slf.pipeline,
+                    &request2,
+                    &mut response,
+                    (now - started).num_milliseconds(),
+                );
+                let result = match response {
+                    _ if cancelled2.load(Ordering::Relaxed) => Err(ApiError::Cancelled),
+                    Ok(response) if response.status == 200 => match response.text() {
+                        Some(text) if !text.is_empty() => serde_json::from_str::<T>(text)
+                            .map_err(|err| ApiError::Decode(err.to_string())),
+                        _ => Err(ApiError::Empty),
+                    },
+                    Ok(response) => Err(ApiError::from_response(&response)),
+                    Err(err) => Err(ApiError::Network(err)),
+                };
+                match result {
+                    Err(ApiError::Cancelled) => {}
+                    Err(ref err) => err.notify(&ctx),
+                    Ok(_) => {}
+                }
+                // Other servers say nothing about the backend.
+                if !external {
+                    if let Some(outcome) = Outcome::new(&result, latency_ms) {
+                        slf.health.lock().record(outcome);
+                    }
+                }
+                on_done(result);
+            },
+        );
+
+        RequestHandle(id)
+    }
+
+    /// Recent requests, for the developer console.
+    pub fn request_log_ui(ui: &mut Ui) {
+        let log = Self::load(ui.ctx()).request_log;
+        if ui.button("Clear").clicked() {
+            log.clear();
+        }
+        ui.add_space(3.0);
+        log.ui(ui);
+    }
 
-        Loading::start_loading(ctx);
-        let request = mk_request(&slf.base_url);
+    /// Pings the backend every [HEALTH_PING_INTERVAL] seconds, so that its status is known even
+    /// while the app makes no requests. Nothing is pinged while transactions come from elsewhere.
+    pub fn tick(ctx: &Context) {
+        let slf = Self::load(ctx);
+        if slf.source != Source::Backend {
+            return;
+        }
+        let now = ctx.input(|i| i.time);
+        let id = Id::new("health_ping");
+        let last: Option<f64> = ctx.data(|d| d.get_temp(id));
+        if last.is_some_and(|last| now - last < HEALTH_PING_INTERVAL) {
+            return;
+        }
+        ctx.data_mut(|d| d.insert_temp(id, now));
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64(HEALTH_PING_INTERVAL));
 
+        let mut request = ehttp::Request::get(format!("{}/health", slf.url()));
+        middleware::run_request(&slf.pipeline, &mut request, false);
         let ctx = ctx.clone();
+        let started = Utc::now();
         ehttp::fetch(request, move |response| {
-            Loading::loading_done(&ctx);
             let result = match response {
-                Ok(response) => {
-                    if response.status == 200 {
-                        if let Some(text) = response.text() {
-                            match serde_json::from_str::<T>(text) {
-                                Ok(json) => Ok(json),
-                                Err(err) => Err(FetchError::DecodeFailed(err.to_string())),
-                            }
-                        } else {
-                            Err(FetchError::ResponseEmpty)
-                        }
-                    } else {
-                        Err(FetchError::RequestFailed(
-                            response.text().unwrap_or_default().to_string(),
-                        ))
+                Ok(response) if response.ok => Ok(()),
+                Ok(response) => Err(ApiError::from_response(&response)),
+                Err(err) => Err(ApiError::Network(err)),
+            };
+            let latency_ms = (Utc::now() - started).num_milliseconds();
+            if let Some(outcome) = Outcome::new(&result, latency_ms) {
+                slf.health.lock().record(outcome);
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    /// A dot showing whether the backend is reachable, based on the most recent requests and
+    /// pings.
+    pub fn health_ui(ui: &mut Ui) {
+        let health = Self::load(ui.ctx()).health.lock().clone();
+        let status = health.status();
+        let mut tooltip = status.name().to_string();
+        if let Some(latency) = health.latency_ms() {
+            tooltip += &format!("\nLatency: {} ms", latency);
+        }
+        tooltip += &format!(
+            "\nLast successful request: {}",
+            health.last_success.map_or("Never".to_string(), |t| {
+                t.with_timezone(&Local).format("%H:%M:%S").to_string()
+            })
+        );
+        ui.add(StatusDot::new(status.color(ui), tooltip));
+    }
+
+    /// Network errors and server errors are retried with exponential backoff and jitter, unless
+    /// the request was cancelled in the meantime. Rate limited requests wait for as long as the
+    /// server asks to. [on_done] also gets how long the last attempt took, in milliseconds.
+    fn fetch_with_retry(
+        ctx: &Context,
+        request: ehttp::Request,
+        max_attempts: u32,
+        attempt: u32,
+        cancelled: Arc<AtomicBool>,
+        on_done: impl 'static + Send + FnOnce(ehttp::Result<ehttp::Response>, i64),
+    ) {
+        let ctx = ctx.clone();
+        let started = Utc::now();
+        ehttp::fetch(request.clone(), move |response| {
+            let rate_limited = matches!(&response, Ok(response) if response.status == 429);
+            let max_attempts = if rate_limited {
+                max_attempts.max(RATE_LIMIT_ATTEMPTS)
+            } else {
+                max_attempts
+            };
+            let delay = match &response {
+                Ok(response) => retry_delay(response, attempt),
+                Err(_) => Some(backoff_delay(attempt)),
+            };
+            match delay {
+                Some(delay) if attempt < max_attempts && !cancelled.load(Ordering::Relaxed) => {
+                    let jitter = platform::get_random_int(delay as usize / 2 + 1) as u32;
+                    log::warn!("Request failed, retrying in {} ms", delay + jitter);
+                    if rate_limited {
+                        notify_rate_limited(&ctx);
                     }
+                    platform::set_timeout(delay + jitter, move || {
+                        Self::fetch_with_retry(
+                            &ctx,
+                            request,
+                            max_attempts,
+                            attempt + 1,
+                            cancelled,
+                            on_done,
+                        )
+                    });
                 }
-                Err(err) => Err(FetchError::RequestFailed(err)),
-            };
-            if let Err(ref err) = result {
-                err.notify(&ctx);
+                _ => on_done(response, (Utc::now() - started).num_milliseconds()),
             }
-            on_done(result);
         });
     }
 }
 
-#[derive(Debug)]
-pub enum FetchError {
-    RequestFailed(String),
-    DecodeFailed(String),
-    ResponseEmpty,
+fn backoff_delay(attempt: u32) -> u32 {
+    RETRY_BASE_DELAY_MS
+        .saturating_mul(1 << (attempt - 1).min(16))
+        .min(RETRY_MAX_DELAY_MS)
+}
+
+/// How long to wait before retrying after [response], if it's worth retrying at all.
+fn retry_delay(response: &ehttp::Response, attempt: u32) -> Option<u32> {
+    match ApiError::from_response(response) {
+        ApiError::RateLimited {
+            retry_after: Some(secs),
+        } => Some(secs.saturating_mul(1000)).filter(|delay| *delay <= RETRY_AFTER_MAX_MS),
+        ApiError::RateLimited { retry_after: None } | ApiError::Server { .. } => {
+            Some(backoff_delay(attempt))
+        }
+        _ => None,
+    }
+}
+
+/// Not an error as the request will still go through, and shown only once in a while so bulk
+/// operations don't flood the screen.
+fn notify_rate_limited(ctx: &Context) {
+    let now = ctx.input(|i| i.time);
+    let id = Id::new("rate_limit_notice");
+    let last: Option<f64> = ctx.data(|d| d.get_temp(id));
+    if last.map_or(true, |last| now - last >= RATE_LIMIT_NOTICE_INTERVAL) {
+        ctx.data_mut(|d| d.insert_temp(id, now));
+        ctx.notify_info("Slowing down… the server asked for fewer requests.");
+    }
+}
+
+#[derive(Clone)]
+struct CachedResponse {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    bytes: Vec<u8>,
 }
 
-impl FetchError {
+/// Remembers GET responses with an `ETag` or `Last-Modified` header, so that they can be
+/// requested conditionally and don't have to be downloaded again if unchanged.
+#[derive(Clone)]
+struct HttpCache(Arc<Mutex<LruCache<String, CachedResponse>>>);
+
+impl HttpCache {
+    fn new() -> Self {
+        Self(Arc::new(Mutex::new(LruCache::new(
+            NonZeroUsize::new(HTTP_CACHE_SIZE).unwrap(),
+        ))))
+    }
+
+    fn prepare(&self, request: &mut ehttp::Request) {
+        if request.method != "GET" {
+            return;
+        }
+        if let Some(cached) = self.0.lock().get(&request.url) {
+            if let Some(etag) = &cached.etag {
+                request.headers.insert("If-None-Match", etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request.headers.insert("If-Modified-Since", last_modified);
+            }
+        }
+    }
+
+    /// Turns a `304 Not Modified` into the cached response, and caches new responses.
+    fn resolve(&self, url: &str, response: &mut ehttp::Response) {
+        let mut cache = self.0.lock();
+        match response.status {
+            304 => {
+                if let Some(cached) = cache.get(url) {
+                    response.status = 200;
+                    response.ok = true;
+                    response.bytes = cached.bytes.clone();
+                }
+            }
+            200 => {
+                let etag = response.headers.get("etag").map(str::to_string);
+                let last_modified = response.headers.get("last-modified").map(str::to_string);
+                if etag.is_some() || last_modified.is_some() {
+                    cache.put(
+                        url.to_string(),
+                        CachedResponse {
+                            etag,
+                            last_modified,
+                            bytes: response.bytes.clone(),
+                        },
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Middleware for HttpCache {
+    fn on_request(&self, request: &mut ehttp::Request) {
+        self.prepare(request);
+    }
+
+    fn on_response(
+        &self,
+        request: &ehttp::Request,
+        response: &mut ehttp::Result<ehttp::Response>,
+        _elapsed_ms: i64,
+    ) {
+        if let Ok(response) = response {
+            self.resolve(&request.url, response);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Outcome {
+    Success {
+        latency_ms: i64,
+    },
+    /// The backend answered, but not usefully.
+    Failure,
+    /// The backend could not be reached.
+    Unreachable,
+}
+
+impl Outcome {
+    /// `None` for results that say nothing about the backend's health. [latency_ms] is of a
+    /// single attempt, without the waiting between retries.
+    fn new<T>(result: &Result<T, ApiError>, latency_ms: i64) -> Option<Self> {
+        match result {
+            Ok(_) | Err(ApiError::NotFound) | Err(ApiError::Validation { .. }) => {
+                Some(Outcome::Success { latency_ms })
+            }
+            Err(ApiError::Cancelled) => None,
+            Err(ApiError::Network(_)) => Some(Outcome::Unreachable),
+            Err(_) => Some(Outcome::Failure),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Status {
+    Unknown,
+    Connected,
+    Degraded,
+    Offline,
+}
+
+impl Status {
+    fn name(&self) -> &'static str {
+        match self {
+            Status::Unknown => "No requests yet",
+            Status::Connected => "Connected",
+            Status::Degraded => "Degraded: recent requests failed or were slow",
+            Status::Offline => "Offline: the server could not be reached",
+        }
+    }
+
+    fn color(&self, ui: &Ui) -> Color32 {
+        match self {
+            Status::Unknown => ui.visuals().weak_text_color(),
+            Status::Connected => Color32::from_rgb(0x4c, 0xaf, 0x50),
+            Status::Degraded => ui.visuals().warn_fg_color,
+            Status::Offline => ui.visuals().error_fg_color,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Health {
+    recent: VecDeque<Outcome>,
+    last_success: Option<DateTime<Utc>>,
+}
+
+impl Health {
+    fn record(&mut self, outcome: Outcome) {
+        if matches!(outcome, Outcome::Success { .. }) {
+            self.last_success = Some(Utc::now());
+        }
+        self.recent.push_back(outcome);
+        if self.recent.len() > HEALTH_WINDOW {
+            self.recent.pop_front();
+        }
+    }
+
+    fn latency_ms(&self) -> Option<i64> {
+        self.recent.iter().rev().find_map(|outcome| match outcome {
+            Outcome::Success { latency_ms } => Some(*latency_ms),
+            _ => None,
+        })
+    }
+
+    fn status(&self) -> Status {
+        match self.recent.back() {
+            None => Status::Unknown,
+            Some(Outcome::Unreachable) => Status::Offline,
+            Some(_) => {
+                let troubled = self.recent.iter().any(|outcome| match outcome {
+                    Outcome::Success { latency_ms } => *latency_ms > SLOW_REQUEST_MS,
+                    Outcome::Failure | Outcome::Unreachable => true,
+                });
+                if troubled {
+                    Status::Degraded
+                } else {
+                    Status::Connected
+                }
+            }
+        }
+    }
+}
+
+/// Returned by [Client::fetch_json] to cancel the request.
+pub struct RequestHandle(RequestId);
+
+impl RequestHandle {
+    pub fn cancel(&self, ctx: &Context) {
+        Loading::cancel(ctx, self.0);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ApiError {
+    /// The API token was missing or rejected.
+    Unauthorized,
+    NotFound,
+    /// The request was rejected, e.g. because of a malformed txid.
+    Validation {
+        field: Option<String>,
+        msg: String,
+    },
+    RateLimited {
+        retry_after: Option<u32>,
+    },
+    Server {
+        status: u16,
+        msg: String,
+    },
+    /// The server could not be reached.
+    Network(String),
+    Decode(String),
+    Empty,
+    /// See [RequestHandle::cancel].
+    Cancelled,
+}
+
+/// Error body as sent by the backend. Anything else is kept as plain text.
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(alias = "error")]
+    message: String,
+    field: Option<String>,
+}
+
+impl ApiError {
+    /// Classify a response with a non-200 status.
+    fn from_response(response: &ehttp::Response) -> Self {
+        let text = response.text().unwrap_or_default();
+        let (field, msg) = match serde_json::from_str::<ErrorBody>(text) {
+            Ok(body) => (body.field, body.message),
+            Err(_) if text.is_empty() => (None, response.status_text.clone()),
+            Err(_) => (None, text.to_string()),
+        };
+        match response.status {
+            401 | 403 => Self::Unauthorized,
+            404 => Self::NotFound,
+            429 => Self::RateLimited {
+                retry_after: response
+                    .headers
+                    .get("retry-after")
+                    .and_then(|s| s.trim().parse().ok()),
+            },
+            status if status >= 500 => Self::Server { status, msg },
+            _ => Self::Validation { field, msg },
+        }
+    }
+
     fn notify(&self, ctx: &Context) {
+        ctx.notify_error("API request failed", Some(self.to_string()));
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::RequestFailed(err) => {
-                ctx.notify_error("Api request failed", Some(err));
+            Self::Unauthorized => write!(f, "Not authorized, check the API token"),
+            Self::NotFound => write!(f, "Not found"),
+            Self::Validation {
+                field: Some(field),
+                msg,
+            } => write!(f, "Invalid {}: {}", field, msg),
+            Self::Validation { field: None, msg } => write!(f, "Invalid request: {}", msg),
+            Self::RateLimited {
+                retry_after: Some(secs),
+            } => write!(f, "Rate limited, try again in {} s", secs),
+            Self::RateLimited { retry_after: None } => write!(f, "Rate limited, try again later"),
+            Self::Server { status, msg } => write!(f, "Server error {}: {}", status, msg),
+            Self::Network(err) => write!(f, "Network error: {}", err),
+            Self::Decode(err) => write!(f, "Could not decode response: {}", err),
+            Self::Empty => write!(f, "Response was empty"),
+            Self::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        retry_delay, ApiError, Health, HttpCache, Outcome, Status, HEALTH_WINDOW,
+        RETRY_BASE_DELAY_MS,
+    };
+
+    fn response(status: u16, body: &str, headers: &[(&str, &str)]) -> ehttp::Response {
+        let mut response = ehttp::Response {
+            url: String::new(),
+            ok: false,
+            status,
+            status_text: String::new(),
+            headers: Default::default(),
+            bytes: body.as_bytes().to_vec(),
+        };
+        for (k, v) in headers {
+            response.headers.insert(k, v);
+        }
+        response
+    }
+
+    #[test]
+    fn classifies_responses() {
+        assert_eq!(
+            ApiError::from_response(&response(401, "", &[])),
+            ApiError::Unauthorized
+        );
+        assert_eq!(
+            ApiError::from_response(&response(
+                400,
+                r#"{"error": "not hex", "field": "txid"}"#,
+                &[]
+            )),
+            ApiError::Validation {
+                field: Some("txid".to_string()),
+                msg: "not hex".to_string()
             }
-            Self::DecodeFailed(err) => {
-                ctx.notify_error("Could not decode API response", Some(err));
+        );
+        assert_eq!(
+            ApiError::from_response(&response(429, "", &[("Retry-After", "7")])),
+            ApiError::RateLimited {
+                retry_after: Some(7)
             }
-            Self::ResponseEmpty => {
-                ctx.notify_error("API esponse was empty", None::<&str>);
+        );
+        assert_eq!(
+            ApiError::from_response(&response(502, "Bad gateway", &[])),
+            ApiError::Server {
+                status: 502,
+                msg: "Bad gateway".to_string()
             }
+        );
+    }
+
+    #[test]
+    fn retries() {
+        assert_eq!(
+            retry_delay(&response(429, "", &[("Retry-After", "7")]), 1),
+            Some(7000)
+        );
+        assert_eq!(
+            retry_delay(&response(429, "", &[("Retry-After", "120")]), 1),
+            Some(120_000)
+        );
+        assert_eq!(
+            retry_delay(&response(429, "", &[("Retry-After", "86400")]), 1),
+            None
+        );
+        assert_eq!(
+            retry_delay(&response(429, "", &[]), 2),
+            Some(2 * RETRY_BASE_DELAY_MS)
+        );
+        assert_eq!(
+            retry_delay(&response(503, "", &[]), 1),
+            Some(RETRY_BASE_DELAY_MS)
+        );
+        assert_eq!(retry_delay(&response(404, "", &[]), 1), None);
+        assert_eq!(retry_delay(&response(200, "{}", &[]), 1), None);
+    }
+
+    #[test]
+    fn health() {
+        let mut health = Health::default();
+        assert_eq!(health.status(), Status::Unknown);
+
+        health.record(Outcome::Success { latency_ms: 100 });
+        assert_eq!(health.status(), Status::Connected);
+
+        health.record(Outcome::Unreachable);
+        assert_eq!(health.status(), Status::Offline);
+
+        health.record(Outcome::Success { latency_ms: 120 });
+        assert_eq!(health.status(), Status::Degraded);
+        assert_eq!(health.latency_ms(), Some(120));
+
+        for _ in 0..HEALTH_WINDOW {
+            health.record(Outcome::Success { latency_ms: 80 });
         }
+        assert_eq!(health.status(), Status::Connected);
+    }
+
+    #[test]
+    fn http_cache() {
+        let cache = HttpCache::new();
+        let url = "https://example.com/tx/1";
+
+        let mut request = ehttp::Request::get(url);
+        cache.prepare(&mut request);
+        assert_eq!(request.headers.get("If-None-Match"), None);
+
+        cache.resolve(url, &mut response(200, "{}", &[("ETag", "\"v1\"")]));
+        let mut request = ehttp::Request::get(url);
+        cache.prepare(&mut request);
+        assert_eq!(request.headers.get("If-None-Match"), Some("\"v1\""));
+
+        let mut response = response(304, "", &[]);
+        cache.resolve(url, &mut response);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.text(), Some("{}"));
     }
 }
@@ -0,0 +1,202 @@
+//! Common-input-ownership heuristic: addresses spent together in one transaction are assumed to
+//! belong to the same entity.
+
+use std::collections::HashMap;
+
+use egui::{ecolor::Hsva, Color32, Grid, ScrollArea, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::widgets::UiExt;
+
+pub struct Cluster {
+    /// Sorted.
+    pub addresses: Vec<String>,
+}
+
+impl Cluster {
+    /// Derived from the first address, so it stays the same while the cluster grows, mostly.
+    pub fn color(&self) -> Color32 {
+        let hash = egui::Id::new(&self.addresses[0]).value();
+        Hsva::new((hash % 360) as f32 / 360.0, 0.7, 0.9, 1.0).into()
+    }
+}
+
+/// Clusters with more than one address, largest first.
+#[derive(Default)]
+pub struct Clusters {
+    clusters: Vec<Cluster>,
+    by_address: HashMap<String, usize>,
+}
+
+impl Clusters {
+    /// Takes the input addresses of each transaction.
+    pub fn new<'a>(inputs: impl Iterator<Item = Vec<&'a str>>) -> Self {
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        let mut parent: Vec<usize> = vec![];
+
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        for addresses in inputs {
+            let mut first = None;
+            for address in addresses {
+                let i = *index.entry(address).or_insert_with(|| {
+                    parent.push(parent.len());
+                    parent.len() - 1
+                });
+                match first {
+                    None => first = Some(i),
+                    Some(first) => {
+                        let (a, b) = (find(&mut parent, first), find(&mut parent, i));
+                        parent[b] = a;
+                    }
+                }
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<String>> = HashMap::new();
+        for (address, i) in index {
+            let root = find(&mut parent, i);
+            groups.entry(root).or_default().push(address.to_string());
+        }
+        let mut clusters: Vec<Cluster> = groups
+            .into_values()
+            .filter(|addresses| addresses.len() > 1)
+            .map(|mut addresses| {
+                addresses.sort_unstable();
+                Cluster { addresses }
+            })
+            .collect();
+        clusters.sort_by(|a, b| {
+            b.addresses
+                .len()
+                .cmp(&a.addresses.len())
+                .then_with(|| a.addresses[0].cmp(&b.addresses[0]))
+        });
+
+        let by_address = clusters
+            .iter()
+            .enumerate()
+            .flat_map(|(i, cluster)| cluster.addresses.iter().map(move |a| (a.clone(), i)))
+            .collect();
+
+        Self {
+            clusters,
+            by_address,
+        }
+    }
+
+    pub fn get(&self, address: &str) -> Option<&Cluster> {
+        self.by_address.get(address).map(|i| &self.clusters[*i])
+    }
+
+    pub fn color(&self, address: &str) -> Option<Color32> {
+        self.get(address).map(Cluster::color)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClusterView {
+    /// Color coins by cluster in the graph.
+    pub show_colors: bool,
+    /// Names being typed, by first address of the cluster.
+    #[serde(skip)]
+    drafts: HashMap<String, String>,
+}
+
+impl ClusterView {
+    /// [name_of] returns the current name of an address. Returns the addresses of a cluster and
+    /// the name it should get.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        clusters: &Clusters,
+        name_of: impl Fn(&str) -> Option<String>,
+    ) -> Option<(Vec<String>, String)> {
+        ui.checkbox(&mut self.show_colors, "Color coins by cluster");
+        if clusters.clusters.is_empty() {
+            ui.label("No transactions with inputs from several addresses yet.");
+            return None;
+        }
+        ui.label(format!(
+            "{} clusters of addresses spent together.",
+            clusters.clusters.len()
+        ));
+
+        let mut result = None;
+        ui.add_space(3.0);
+        ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            Grid::new("Clusters")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for title in ["", "Addresses", "Name"] {
+                        ui.bold(title);
+                    }
+                    ui.end_row();
+
+                    for cluster in &clusters.clusters {
+                        let first = &cluster.addresses[0];
+                        ui.colored_label(cluster.color(), "⏺");
+                        ui.label(cluster.addresses.len().to_string())
+                            .on_hover_ui(|ui| {
+                                for address in &cluster.addresses {
+                                    ui.monospace(address);
+                                }
+                            });
+                        let name = self.drafts.entry(first.clone()).or_insert_with(|| {
+                            cluster
+                                .addresses
+                                .iter()
+                                .find_map(|a| name_of(a))
+                                .unwrap_or_default()
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(TextEdit::singleline(name).desired_width(160.0));
+                            if ui
+                                .add_enabled(!name.trim().is_empty(), egui::Button::new("Name"))
+                                .on_hover_text("Label all addresses of the cluster.")
+                                .clicked()
+                            {
+                                result = Some((cluster.addresses.clone(), name.trim().to_string()));
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+        });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Clusters;
+
+    #[test]
+    fn common_inputs() {
+        let txs = vec![
+            vec!["a", "b"],
+            vec!["c"],
+            vec!["b", "d"],
+            vec!["e", "f"],
+            vec!["f"],
+        ];
+        let clusters = Clusters::new(txs.into_iter());
+
+        let addresses: Vec<_> = clusters
+            .clusters
+            .iter()
+            .map(|c| c.addresses.join(","))
+            .collect();
+        assert_eq!(addresses, vec!["a,b,d", "e,f"]);
+        assert!(clusters.get("c").is_none());
+        assert_eq!(clusters.color("a"), clusters.color("d"));
+    }
+}
@@ -0,0 +1,349 @@
+//! Simulation of wallet coin selection over the unspent outputs in the graph.
+
+use egui::{DragValue, Grid, Pos2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitcoin::{AddressType, Sats, Txid},
+    widgets::UiExt,
+};
+
+/// Version, locktime and in/out counts.
+const TX_OVERHEAD_VBYTES: u64 = 11;
+/// Both the payment and the change output are assumed to be P2WPKH.
+const OUTPUT_VBYTES: u64 = 31;
+const DUST_LIMIT: u64 = 546;
+/// Upper bound on the number of branches explored before branch-and-bound gives up.
+const BNB_MAX_TRIES: usize = 100_000;
+/// Litecoin's supply, the largest of the supported chains, in its smallest unit.
+const MAX_AMOUNT: u64 = 84_000_000 * 100_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Coin {
+    pub txid: Txid,
+    pub vout: usize,
+    pub value: u64,
+    pub address_type: AddressType,
+}
+
+impl Coin {
+    /// Estimated size of an input spending this coin, assuming single-sig scripts (and 2-of-3
+    /// multisig for P2WSH).
+    fn input_vbytes(&self) -> u64 {
+        match self.address_type {
+            AddressType::P2PKH => 148,
+            AddressType::P2SH => 91,
+            AddressType::P2WPKH => 68,
+            AddressType::P2WSH => 105,
+            AddressType::P2TR => 58,
+            AddressType::Unknown => 148,
+        }
+    }
+
+    /// Negative if spending the coin costs more than it's worth.
+    fn effective_value(&self, fee_rate: u64) -> i64 {
+        signed(self.value).saturating_sub(signed(self.input_vbytes().saturating_mul(fee_rate)))
+    }
+}
+
+/// Amounts beyond `i64::MAX` don't exist on any chain, but may come from bad data.
+fn signed(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// Search for an input set that avoids a change output (as in Bitcoin Core).
+    BranchAndBound,
+    LargestFirst,
+}
+
+impl Strategy {
+    const ALL: [Strategy; 2] = [Strategy::BranchAndBound, Strategy::LargestFirst];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Strategy::BranchAndBound => "Branch and bound",
+            Strategy::LargestFirst => "Largest first",
+        }
+    }
+
+    pub fn select(&self, coins: &[Coin], amount: u64, fee_rate: u64) -> Option<Selection> {
+        match self {
+            Strategy::BranchAndBound => branch_and_bound(coins, amount, fee_rate),
+            Strategy::LargestFirst => largest_first(coins, amount, fee_rate),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Selection {
+    pub coins: Vec<Coin>,
+    pub fee: u64,
+    /// Zero if no change output is created.
+    pub change: u64,
+}
+
+impl Selection {
+    fn new(coins: Vec<Coin>, amount: u64, fee_rate: u64, with_change: bool) -> Self {
+        let outputs = if with_change { 2 } else { 1 };
+        let vbytes = TX_OVERHEAD_VBYTES
+            + outputs * OUTPUT_VBYTES
+            + coins.iter().map(Coin::input_vbytes).sum::<u64>();
+        let total = coins
+            .iter()
+            .fold(0, |sum: u64, c| sum.saturating_add(c.value));
+        let fee = vbytes.saturating_mul(fee_rate);
+        if with_change {
+            Self {
+                coins,
+                fee,
+                change: total.saturating_sub(amount).saturating_sub(fee),
+            }
+        } else {
+            // Any excess goes to the miner.
+            Self {
+                coins,
+                fee: total.saturating_sub(amount),
+                change: 0,
+            }
+        }
+    }
+}
+
+fn target(amount: u64, fee_rate: u64) -> i64 {
+    let fee = (TX_OVERHEAD_VBYTES + OUTPUT_VBYTES).saturating_mul(fee_rate);
+    signed(amount).saturating_add(signed(fee))
+}
+
+/// Depth-first search over coins sorted by descending effective value for an input set whose
+/// effective value lies within `cost_of_change` above the target, so no change is needed.
+fn branch_and_bound(coins: &[Coin], amount: u64, fee_rate: u64) -> Option<Selection> {
+    let mut pool: Vec<Coin> = coins
+        .iter()
+        .filter(|c| c.effective_value(fee_rate) > 0)
+        .copied()
+        .collect();
+    pool.sort_by_key(|c| std::cmp::Reverse(c.effective_value(fee_rate)));
+
+    let target = target(amount, fee_rate);
+    let cost_of_change =
+        signed(OUTPUT_VBYTES.saturating_mul(fee_rate)).saturating_add(DUST_LIMIT as i64);
+
+    // Saturating, so coins beyond any supply may throw the sums off, but can't overflow them.
+    let mut remaining: i64 = pool
+        .iter()
+        .fold(0, |sum, c| sum.saturating_add(c.effective_value(fee_rate)));
+    let mut selected = vec![false; pool.len()];
+    let mut value: i64 = 0;
+    let mut best: Option<(i64, Vec<bool>)> = None;
+    let mut depth = 0;
+
+    for _ in 0..BNB_MAX_TRIES {
+        let backtrack = if value.saturating_add(remaining) < target
+            || value > target.saturating_add(cost_of_change)
+        {
+            true
+        } else if value >= target {
+            let waste = value - target;
+            if best.as_ref().map_or(true, |(w, _)| waste < *w) {
+                best = Some((waste, selected.clone()));
+            }
+            true
+        } else {
+            false
+        };
+
+        if backtrack {
+            // Undo trailing omissions, then turn the last inclusion into an omission.
+            loop {
+                if depth == 0 {
+                    return best.map(|(_, s)| finish(&pool, &s, amount, fee_rate));
+                }
+                depth -= 1;
+                if selected[depth] {
+                    selected[depth] = false;
+                    value = value.saturating_sub(pool[depth].effective_value(fee_rate));
+                    depth += 1;
+                    break;
+                }
+                remaining = remaining.saturating_add(pool[depth].effective_value(fee_rate));
+            }
+        } else {
+            let v = pool[depth].effective_value(fee_rate);
+            remaining = remaining.saturating_sub(v);
+            selected[depth] = true;
+            value = value.saturating_add(v);
+            depth += 1;
+        }
+    }
+
+    best.map(|(_, s)| finish(&pool, &s, amount, fee_rate))
+}
+
+fn finish(pool: &[Coin], selected: &[bool], amount: u64, fee_rate: u64) -> Selection {
+    let coins = pool
+        .iter()
+        .zip(selected)
+        .filter(|(_, &s)| s)
+        .map(|(c, _)| *c)
+        .collect();
+    Selection::new(coins, amount, fee_rate, false)
+}
+
+fn largest_first(coins: &[Coin], amount: u64, fee_rate: u64) -> Option<Selection> {
+    let mut pool = coins.to_vec();
+    pool.sort_by_key(|c| std::cmp::Reverse(c.value));
+
+    let target = target(amount, fee_rate);
+    let change_fee = signed(OUTPUT_VBYTES.saturating_mul(fee_rate));
+
+    let mut selected = vec![];
+    let mut value: i64 = 0;
+    for coin in pool {
+        value = value.saturating_add(coin.effective_value(fee_rate));
+        selected.push(coin);
+        if value >= target {
+            let with_change = (value - target).saturating_sub(change_fee) >= DUST_LIMIT as i64;
+            return Some(Selection::new(selected, amount, fee_rate, with_change));
+        }
+    }
+    None
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoinSelection {
+    amount: u64,
+    /// In sat/vB.
+    fee_rate: u64,
+}
+
+impl Default for CoinSelection {
+    fn default() -> Self {
+        Self {
+            amount: 100_000,
+            fee_rate: 10,
+        }
+    }
+}
+
+impl CoinSelection {
+    pub fn ui(&mut self, ui: &mut egui::Ui, coins: &[Coin], load_tx: impl Fn(Txid, Option<Pos2>)) {
+        ui.set_min_width(300.0);
+
+        ui.label(format!(
+            "Simulates paying from the {} unspent output(s) in the graph.",
+            coins.len()
+        ));
+        Grid::new("CoinSelectionParams")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Amount (sats):");
+                ui.add(
+                    DragValue::new(&mut self.amount)
+                        .range(0..=MAX_AMOUNT)
+                        .speed(1000),
+                );
+                ui.end_row();
+
+                ui.label("Fee rate (sat/vB):");
+                ui.add(DragValue::new(&mut self.fee_rate).range(1..=1000));
+                ui.end_row();
+            });
+
+        for strategy in Strategy::ALL {
+            ui.separator();
+            ui.bold(strategy.name());
+            let Some(selection) = strategy.select(coins, self.amount, self.fee_rate) else {
+                ui.label("No solution.");
+                continue;
+            };
+            Grid::new(("CoinSelection", strategy))
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Inputs:");
+                    ui.label(selection.coins.len().to_string());
+                    ui.end_row();
+
+                    ui.label("Fee:");
+                    ui.label(format!("{} BTC", Sats(selection.fee)));
+                    ui.end_row();
+
+                    ui.label("Change:");
+                    if selection.change > 0 {
+                        ui.label(format!("{} BTC", Sats(selection.change)));
+                    } else {
+                        ui.label("None");
+                    }
+                    ui.end_row();
+                });
+            for coin in &selection.coins {
+                let outpoint = format!("{}…:{}", &coin.txid.hex_string()[..16], coin.vout);
+                if ui
+                    .button(format!("{}  {} BTC", outpoint, Sats(coin.value)))
+                    .on_hover_text("Go to transaction")
+                    .clicked()
+                {
+                    load_tx(coin.txid, None);
+                    ui.close_menu();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Coin, Strategy};
+    use crate::bitcoin::{AddressType, Txid};
+
+    fn coin(vout: usize, value: u64) -> Coin {
+        Coin {
+            txid: Txid::new(&"00".repeat(32)).unwrap(),
+            vout,
+            value,
+            address_type: AddressType::P2WPKH,
+        }
+    }
+
+    #[test]
+    fn branch_and_bound_avoids_change() {
+        let coins = [coin(0, 100_000), coin(1, 50_000), coin(2, 30_680)];
+        // Fee rate 1: target 80_000 + 42 overhead, inputs cost 68 each.
+        let selection = Strategy::BranchAndBound.select(&coins, 80_000, 1).unwrap();
+        assert_eq!(selection.coins, vec![coins[1], coins[2]]);
+        assert_eq!(selection.change, 0);
+        assert_eq!(selection.fee, 680);
+    }
+
+    #[test]
+    fn largest_first_with_change() {
+        let coins = [coin(0, 100_000), coin(1, 50_000), coin(2, 30_680)];
+        let selection = Strategy::LargestFirst.select(&coins, 80_000, 1).unwrap();
+        assert_eq!(selection.coins, vec![coins[0]]);
+        assert_eq!(selection.fee, 11 + 2 * 31 + 68);
+        assert_eq!(selection.change, 100_000 - 80_000 - selection.fee);
+    }
+
+    #[test]
+    fn extreme_values() {
+        // Spending the first coin costs more than it's worth, the second is beyond any supply.
+        let coins = [coin(0, 500), coin(1, u64::MAX)];
+        for strategy in Strategy::ALL {
+            assert_eq!(strategy.select(&coins, u64::MAX, 1_000), None);
+            assert_eq!(strategy.select(&coins, 1_000, u64::MAX), None);
+        }
+
+        let selection = Strategy::LargestFirst.select(&coins, 1_000, 1_000).unwrap();
+        assert_eq!(selection.coins, vec![coins[1]]);
+        assert_eq!(selection.change, u64::MAX - 1_000 - selection.fee);
+    }
+
+    #[test]
+    fn insufficient_funds() {
+        let coins = [coin(0, 1_000)];
+        assert_eq!(Strategy::BranchAndBound.select(&coins, 5_000, 1), None);
+        assert_eq!(Strategy::LargestFirst.select(&coins, 5_000, 1), None);
+    }
+}
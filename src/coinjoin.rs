@@ -0,0 +1,113 @@
+//! Recognizing coinjoins by their structure of equal outputs.
+
+use std::{collections::HashMap, fmt};
+
+/// Denominations of the Whirlpool pools.
+const WHIRLPOOL_POOLS: [u64; 4] = [100_000, 1_000_000, 5_000_000, 50_000_000];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Whirlpool,
+    Wasabi,
+    JoinMarket,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Whirlpool => write!(f, "Whirlpool"),
+            Kind::Wasabi => write!(f, "Wasabi"),
+            Kind::JoinMarket => write!(f, "JoinMarket"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CoinJoin {
+    pub kind: Kind,
+    /// The most common output value.
+    pub denomination: u64,
+    pub equal_outputs: usize,
+}
+
+/// Guesses whether a transaction with the given input and output values (without fees) is a
+/// coinjoin.
+pub fn detect(inputs: &[u64], outputs: &[u64]) -> Option<CoinJoin> {
+    let mut counts: HashMap<u64, usize> = HashMap::new();
+    for value in outputs {
+        *counts.entry(*value).or_default() += 1;
+    }
+    // Ties go to the larger value, change is usually smaller.
+    let (denomination, equal_outputs) = counts
+        .iter()
+        .map(|(value, count)| (*value, *count))
+        .max_by_key(|(value, count)| (*count, *value))?;
+    if equal_outputs < 2 {
+        return None;
+    }
+    let repeated: usize = counts.values().filter(|count| **count > 1).sum();
+
+    let kind = if inputs.len() == 5
+        && outputs.len() == 5
+        && equal_outputs == 5
+        && WHIRLPOOL_POOLS.contains(&denomination)
+    {
+        Kind::Whirlpool
+    } else if inputs.len() >= 20 && repeated >= 20 {
+        // Wasabi 2 mixes several standard denominations in one round.
+        Kind::Wasabi
+    } else if equal_outputs >= 3
+        && inputs.len() >= equal_outputs
+        && outputs.len() <= 2 * equal_outputs
+    {
+        // One equal output per participant, plus at most one change output each.
+        Kind::JoinMarket
+    } else {
+        return None;
+    };
+
+    Some(CoinJoin {
+        kind,
+        denomination,
+        equal_outputs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect, CoinJoin, Kind};
+
+    #[test]
+    fn kinds() {
+        assert_eq!(
+            detect(&[1_000_170; 5], &[1_000_000; 5]),
+            Some(CoinJoin {
+                kind: Kind::Whirlpool,
+                denomination: 1_000_000,
+                equal_outputs: 5,
+            })
+        );
+
+        let mut outputs = vec![10_000_000; 4];
+        outputs.extend([123_456, 2_345_678, 987_654]);
+        assert_eq!(
+            detect(&[20_000_000, 15_000_000, 11_000_000, 12_000_000], &outputs).map(|cj| cj.kind),
+            Some(Kind::JoinMarket)
+        );
+
+        let mut outputs = vec![];
+        for denomination in [5_000, 10_000, 20_000, 50_000] {
+            outputs.extend([denomination; 6]);
+        }
+        outputs.extend([1_234, 5_678]);
+        assert_eq!(
+            detect(&[70_000; 25], &outputs).map(|cj| cj.kind),
+            Some(Kind::Wasabi)
+        );
+
+        // Payment with change
+        assert_eq!(detect(&[150_000, 60_000], &[100_000, 104_321]), None);
+        // Batch payout from a single input
+        assert_eq!(detect(&[10_000_000], &[50_000; 4]), None);
+    }
+}
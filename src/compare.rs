@@ -0,0 +1,106 @@
+//! Before/after comparison of the graph restricted to two date ranges.
+
+use chrono::NaiveDate;
+use egui::{Grid, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::widgets::UiExt;
+
+/// Inclusive range of days.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeWindow {
+    from: NaiveDate,
+    to: NaiveDate,
+}
+
+impl TimeWindow {
+    /// Expects a timestamp formatted as `%Y-%m-%d %H:%M:%S`.
+    pub fn contains(&self, timestamp: &str) -> bool {
+        NaiveDate::parse_from_str(&timestamp[..10.min(timestamp.len())], "%Y-%m-%d")
+            .map_or(true, |date| self.from <= date && date <= self.to)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Compare {
+    active: bool,
+    show_after: bool,
+    before: [String; 2],
+    after: [String; 2],
+}
+
+fn parse(range: &[String; 2]) -> Option<TimeWindow> {
+    let from = NaiveDate::parse_from_str(range[0].trim(), "%Y-%m-%d").ok()?;
+    let to = NaiveDate::parse_from_str(range[1].trim(), "%Y-%m-%d").ok()?;
+    Some(TimeWindow { from, to })
+}
+
+impl Compare {
+    /// The window the graph should currently be restricted to.
+    pub fn window(&self) -> Option<TimeWindow> {
+        if !self.active {
+            return None;
+        }
+        parse(if self.show_after {
+            &self.after
+        } else {
+            &self.before
+        })
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.bold("Compare time windows:");
+        ui.checkbox(&mut self.active, "Active");
+
+        Grid::new("Compare").num_columns(4).show(ui, |ui| {
+            for (name, range) in [("Before:", &mut self.before), ("After:", &mut self.after)] {
+                ui.label(name);
+                for date in range.iter_mut() {
+                    ui.add(
+                        TextEdit::singleline(date)
+                            .hint_text("YYYY-MM-DD")
+                            .desired_width(90.0),
+                    );
+                }
+                if parse(range).is_none() {
+                    ui.colored_label(ui.visuals().warn_fg_color, "Invalid");
+                }
+                ui.end_row();
+            }
+        });
+
+        ui.add_enabled_ui(self.active, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Showing:");
+                ui.selectable_value(&mut self.show_after, false, "Before");
+                ui.selectable_value(&mut self.show_after, true, "After");
+            });
+        });
+    }
+
+    /// Short description of the active window, for display on the canvas.
+    pub fn status(&self) -> Option<String> {
+        let window = self.window()?;
+        Some(format!(
+            "{}: {} to {}",
+            if self.show_after { "After" } else { "Before" },
+            window.from,
+            window.to
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn window_contains() {
+        let window = parse(&["2020-01-01".to_string(), "2020-12-31".to_string()]).unwrap();
+        assert!(window.contains("2020-01-01 00:00:00"));
+        assert!(window.contains("2020-12-31 23:59:59"));
+        assert!(!window.contains("2021-01-01 00:00:00"));
+        assert!(parse(&["2020-01-01".to_string(), String::new()]).is_none());
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::mpsc::Sender;
+
+use egui::{Button, Context, DragValue, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::Update,
+    client::Client,
+    components::history_fetch::{Addresses, HistoryFetch},
+    notifications::NotifyExt,
+};
+
+/// Adds all transactions of an address to the graph, page by page.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct AddressHistory {
+    address: String,
+    /// Stop after this many transactions.
+    limit: usize,
+    #[serde(skip)]
+    fetch: HistoryFetch<Address>,
+}
+
+impl Default for AddressHistory {
+    fn default() -> Self {
+        Self {
+            address: String::new(),
+            limit: 500,
+            fetch: Default::default(),
+        }
+    }
+}
+
+struct Address {
+    address: String,
+    fetched: bool,
+}
+
+impl Addresses for Address {
+    fn next(&mut self) -> Result<Option<String>, String> {
+        Ok((!self.fetched).then(|| self.address.clone()))
+    }
+
+    fn fetched(&mut self, _used: bool) {
+        self.fetched = true;
+    }
+
+    fn finish(&self, ctx: &Context, fetched: usize, _sender: &Sender<Update>) {
+        ctx.notify_success(format!(
+            "Added {} transaction(s) of {}.",
+            fetched, self.address
+        ));
+    }
+
+    fn status(&self, fetched: usize, added: bool) -> String {
+        let what = if added { "Added" } else { "Fetched" };
+        format!("{} {} transaction(s) of {}", what, fetched, self.address)
+    }
+}
+
+impl AddressHistory {
+    /// Stops adding transactions, e.g. when another workspace is opened.
+    pub fn cancel(&self, ctx: &Context) {
+        self.fetch.cancel(ctx);
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, sender: Sender<Update>) {
+        let running = self.fetch.running();
+
+        ui.add(
+            TextEdit::singleline(&mut self.address)
+                .hint_text("Bitcoin address")
+                .desired_width(360.0),
+        );
+        let network = Client::network(ui.ctx());
+        let address = self.address.trim().to_string();
+        if !address.is_empty() && !network.matches_address(&address) {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!("Doesn't look like a {} address.", network.name()),
+            );
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("At most:");
+            ui.add(
+                DragValue::new(&mut self.limit)
+                    .range(1..=10_000)
+                    .suffix(" transactions"),
+            );
+            if ui
+                .add_enabled(!running && !address.is_empty(), Button::new("Add History"))
+                .clicked()
+            {
+                let address = Address {
+                    address,
+                    fetched: false,
+                };
+                self.fetch.start(ui.ctx(), address, self.limit, sender);
+            }
+        });
+
+        self.fetch.status_ui(ui);
+    }
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+use crate::widgets::{BulletPoint, UiExt};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Newest first.
+const ENTRIES: &[(&str, &[&str])] = &[(
+    "0.1.0",
+    &[
+        "Workspaces with tags, notes, backups and duplicate name checks.",
+        "Entity report cards and flow matrices, under \"Entities\".",
+        "Import transactions from GraphML and Maltego CSV files.",
+        "Export transactions as CSV in BTC or sats.",
+        "Running requests can be cancelled from the loading indicator.",
+        "Rate limited requests are retried after the time the server asks for.",
+        "Experimental parts of the app can be switched on and off in Settings.",
+    ],
+)];
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Changelog {
+    /// The version whose changelog the user has seen.
+    seen_version: Option<String>,
+    #[serde(skip)]
+    open: bool,
+}
+
+impl Changelog {
+    /// Open the window if the app was updated since the last start. New users get the About
+    /// window instead.
+    pub fn on_start(&mut self) {
+        if self.seen_version.as_ref().is_some_and(|v| v != VERSION) {
+            self.open = true;
+        }
+        self.seen_version = Some(VERSION.to_string());
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn show_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new("What's New")
+            .open(&mut self.open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for (version, changes) in ENTRIES {
+                            ui.bold(format!("Version {}", version));
+                            for change in *changes {
+                                ui.add(BulletPoint::new(*change));
+                            }
+                            ui.add_space(3.0);
+                        }
+                    });
+            });
+    }
+}
@@ -1,4 +1,4 @@
-use egui::{Button, Pos2, TextEdit, TextStyle, Vec2};
+use egui::{Button, Key, Pos2, TextEdit, TextStyle, Vec2};
 use serde::{Deserialize, Serialize};
 
 use crate::bitcoin::Txid;
@@ -32,4 +32,30 @@ impl CustomTx {
             }
         });
     }
+
+    /// Compact input for the toolbar: pressing Enter loads the tx at the center of the view.
+    pub fn quick_add_ui(&mut self, ui: &mut egui::Ui, load_tx: impl Fn(Txid, Option<Pos2>)) {
+        let txid = Txid::new(self.tx.trim());
+        let invalid = txid.is_err() && !self.tx.is_empty();
+        let mut edit = TextEdit::singleline(&mut self.tx)
+            .hint_text("Paste Txid + Enter")
+            .desired_width(180.0);
+        if invalid {
+            edit = edit.text_color(ui.visuals().error_fg_color);
+        }
+        let response = ui.add(edit);
+
+        match txid {
+            Ok(txid) => {
+                if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                    load_tx(txid, None);
+                    self.tx.clear();
+                }
+            }
+            Err(e) if invalid => {
+                response.on_hover_text(format!("Invalid Txid: {}", e));
+            }
+            Err(_) => {}
+        }
+    }
 }
@@ -0,0 +1,195 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::Sender,
+    Arc,
+};
+
+use egui::{mutex::Mutex, Context};
+
+use crate::{
+    app::Update, bitcoin::Txid, chain, client::RequestHandle, import::ImportedTx,
+    notifications::NotifyExt,
+};
+
+/// The addresses whose histories a [HistoryFetch] adds, one after the other.
+pub trait Addresses: 'static + Send {
+    /// The address to fetch next, `None` once all are done.
+    fn next(&mut self) -> Result<Option<String>, String>;
+    /// The history of the last address from [Self::next] is fetched. [used] tells whether it
+    /// had any transactions.
+    fn fetched(&mut self, used: bool);
+    /// All addresses are fetched, or the limit is reached.
+    fn finish(&self, ctx: &Context, fetched: usize, sender: &Sender<Update>);
+    /// What's been fetched so far, [added] once done.
+    fn status(&self, fetched: usize, added: bool) -> String;
+}
+
+/// Adds the transactions of [Addresses] to the graph, page by page, up to a limit.
+pub struct HistoryFetch<A>(Arc<Mutex<Option<Progress<A>>>>);
+
+impl<A> Default for HistoryFetch<A> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+struct Progress<A> {
+    addresses: A,
+    /// Stop after this many transactions.
+    limit: usize,
+    fetched: usize,
+    done: bool,
+    failed: bool,
+    cancelled: Arc<AtomicBool>,
+    request: Option<RequestHandle>,
+}
+
+impl<A: Addresses> HistoryFetch<A> {
+    pub fn start(&self, ctx: &Context, addresses: A, limit: usize, sender: Sender<Update>) {
+        self.cancel(ctx);
+        *self.0.lock() = Some(Progress {
+            addresses,
+            limit,
+            fetched: 0,
+            done: false,
+            failed: false,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            request: None,
+        });
+        fetch_address(ctx, self.0.clone(), sender);
+    }
+
+    pub fn cancel(&self, ctx: &Context) {
+        if let Some(progress) = self.0.lock().as_mut() {
+            progress.cancelled.store(true, Ordering::Relaxed);
+            if let Some(request) = progress.request.take() {
+                request.cancel(ctx);
+            }
+            progress.done = true;
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        self.0
+            .lock()
+            .as_ref()
+            .is_some_and(|progress| !progress.done)
+    }
+
+    /// The status of the last fetch, with a button to cancel it while it's running.
+    pub fn status_ui(&self, ui: &mut egui::Ui) {
+        let running = self.running();
+        let status = self.0.lock().as_ref().map(|progress| {
+            let added = progress.done && !progress.failed;
+            let mut status = progress.addresses.status(progress.fetched, added);
+            if progress.failed {
+                status += ", then failed.";
+            }
+            status
+        });
+        if let Some(status) = status {
+            ui.horizontal(|ui| {
+                if running {
+                    ui.spinner();
+                }
+                ui.label(status);
+                if running && ui.small_button("Cancel").clicked() {
+                    self.cancel(ui.ctx());
+                }
+            });
+        }
+    }
+}
+
+/// Fetches the history of the next address of [progress], or finishes.
+fn fetch_address<A: Addresses>(
+    ctx: &Context,
+    progress: Arc<Mutex<Option<Progress<A>>>>,
+    sender: Sender<Update>,
+) {
+    let address = {
+        let mut guard = progress.lock();
+        let Some(progress) = guard.as_mut() else {
+            return;
+        };
+        match progress.addresses.next() {
+            Ok(Some(address)) => address,
+            Ok(None) => {
+                progress.done = true;
+                progress.addresses.finish(ctx, progress.fetched, &sender);
+                return;
+            }
+            Err(e) => {
+                progress.failed = true;
+                progress.done = true;
+                ctx.notify_error("Could not derive the next address", Some(e));
+                return;
+            }
+        }
+    };
+    fetch_page(ctx, address, None, progress, sender);
+}
+
+fn fetch_page<A: Addresses>(
+    ctx: &Context,
+    address: String,
+    after: Option<Txid>,
+    progress: Arc<Mutex<Option<Progress<A>>>>,
+    sender: Sender<Update>,
+) {
+    let ctx2 = ctx.clone();
+    let progress2 = progress.clone();
+    let address2 = address.clone();
+    let request = chain::fetch_address_history(ctx, &address, after, move |result| {
+        let mut guard = progress2.lock();
+        let Some(progress) = guard.as_mut() else {
+            return;
+        };
+        if progress.cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+        progress.request = None;
+        let page = match result {
+            Ok(page) => page,
+            Err(_) => {
+                progress.failed = true;
+                progress.done = true;
+                return;
+            }
+        };
+
+        let used = after.is_some() || !page.txids.is_empty();
+        let txs: Vec<ImportedTx> = page
+            .txids
+            .into_iter()
+            .take(progress.limit - progress.fetched)
+            .map(|txid| ImportedTx { txid, label: None })
+            .collect();
+        progress.fetched += txs.len();
+        if !txs.is_empty() {
+            sender.send(Update::ImportTxs { txs, quiet: true }).unwrap();
+        }
+        ctx2.request_repaint();
+
+        if let Some(next) = page.next.filter(|_| progress.fetched < progress.limit) {
+            drop(guard);
+            fetch_page(&ctx2, address2, Some(next), progress2.clone(), sender);
+            return;
+        }
+        progress.addresses.fetched(used);
+        if progress.fetched < progress.limit {
+            drop(guard);
+            fetch_address(&ctx2, progress2.clone(), sender);
+        } else {
+            progress.done = true;
+            progress.addresses.finish(&ctx2, progress.fetched, &sender);
+        }
+    });
+
+    if let Some(progress) = progress.lock().as_mut() {
+        // Unless the page is done already, or the next one has been requested.
+        if !progress.done && progress.request.is_none() {
+            progress.request = Some(request);
+        }
+    }
+}
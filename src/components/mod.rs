@@ -1,2 +1,6 @@
 pub mod about;
+pub mod address_history;
+pub mod changelog;
 pub mod custom_tx;
+pub mod history_fetch;
+pub mod wallet_import;
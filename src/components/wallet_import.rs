@@ -0,0 +1,196 @@
+use std::sync::{mpsc::Sender, Arc};
+
+use egui::{Button, Context, DragValue, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::Update,
+    chain::Network,
+    client::Client,
+    components::history_fetch::{Addresses, HistoryFetch},
+    notifications::NotifyExt,
+    xpub::Wallet,
+};
+
+/// Adds the transactions of all used addresses of a wallet, derived from its extended public key,
+/// and names them as one entity.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct WalletImport {
+    /// Not kept, an xpub reveals the whole wallet.
+    #[serde(skip)]
+    key: String,
+    label: String,
+    /// Stop a chain after this many unused addresses in a row.
+    gap_limit: u32,
+    /// Stop after this many transactions.
+    limit: usize,
+    #[serde(skip)]
+    fetch: HistoryFetch<WalletAddresses>,
+}
+
+impl Default for WalletImport {
+    fn default() -> Self {
+        Self {
+            key: String::new(),
+            label: "Wallet".to_string(),
+            gap_limit: 20,
+            limit: 500,
+            fetch: Default::default(),
+        }
+    }
+}
+
+/// The addresses of a wallet, chain by chain, until [Self::gap_limit] unused ones in a row.
+struct WalletAddresses {
+    wallet: Arc<Wallet>,
+    network: Network,
+    label: String,
+    gap_limit: u32,
+    /// Address being fetched.
+    chain: usize,
+    index: u32,
+    /// Unused addresses in a row on [Self::chain].
+    gap: u32,
+    current: Option<String>,
+    /// Addresses with transactions.
+    used: Vec<String>,
+}
+
+impl Addresses for WalletAddresses {
+    fn next(&mut self) -> Result<Option<String>, String> {
+        if self.chain >= self.wallet.num_chains() {
+            return Ok(None);
+        }
+        let address = self
+            .wallet
+            .address(self.network, self.chain, self.index)
+            .map_err(|e| format!("`{}`: {}", self.label, e))?;
+        self.current = Some(address.clone());
+        Ok(Some(address))
+    }
+
+    /// Moves on to the next address.
+    fn fetched(&mut self, used: bool) {
+        if used {
+            self.gap = 0;
+            self.used.extend(self.current.take());
+        } else {
+            self.gap += 1;
+        }
+        self.index += 1;
+        if self.gap >= self.gap_limit {
+            self.chain += 1;
+            self.index = 0;
+            self.gap = 0;
+        }
+    }
+
+    fn finish(&self, ctx: &Context, fetched: usize, sender: &Sender<Update>) {
+        if !self.used.is_empty() {
+            sender
+                .send(Update::NameCluster {
+                    addresses: self.used.clone(),
+                    name: self.label.clone(),
+                })
+                .unwrap();
+        }
+        ctx.notify_success(format!(
+            "Added {} transaction(s) of {} used address(es) in `{}`.",
+            fetched,
+            self.used.len(),
+            self.label
+        ));
+    }
+
+    fn status(&self, fetched: usize, _added: bool) -> String {
+        format!(
+            "{} transaction(s) of {} used address(es) in `{}`",
+            fetched,
+            self.used.len(),
+            self.label
+        )
+    }
+}
+
+impl WalletImport {
+    fn start(&self, ctx: &Context, wallet: Wallet, sender: Sender<Update>) {
+        let addresses = WalletAddresses {
+            wallet: Arc::new(wallet),
+            network: Client::network(ctx),
+            label: self.label.trim().to_string(),
+            gap_limit: self.gap_limit,
+            chain: 0,
+            index: 0,
+            gap: 0,
+            current: None,
+            used: vec![],
+        };
+        self.fetch.start(ctx, addresses, self.limit, sender);
+    }
+
+    /// Stops adding transactions, e.g. when another workspace is opened.
+    pub fn cancel(&self, ctx: &Context) {
+        self.fetch.cancel(ctx);
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, sender: Sender<Update>) {
+        let running = self.fetch.running();
+
+        ui.add(
+            TextEdit::multiline(&mut self.key)
+                .hint_text("xpub, ypub, zpub or descriptor, e.g. wpkh([…]xpub…/<0;1>/*)")
+                .desired_rows(2)
+                .desired_width(360.0),
+        );
+        let network = Client::network(ui.ctx());
+        let wallet = (!self.key.trim().is_empty()).then(|| Wallet::parse(&self.key));
+        match &wallet {
+            Some(Ok(wallet)) if !wallet.matches_network(network) => {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("Not a key for {}.", network.name()),
+                );
+            }
+            Some(Ok(wallet)) => {
+                let first = wallet.address(network, 0, 0).unwrap_or_default();
+                ui.label(format!(
+                    "{}, first address {}",
+                    wallet.script_type.name(),
+                    first
+                ));
+            }
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            None => {}
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.add(TextEdit::singleline(&mut self.label).desired_width(160.0));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Gap limit:");
+            ui.add(DragValue::new(&mut self.gap_limit).range(1..=1000));
+            ui.label("At most:");
+            ui.add(
+                DragValue::new(&mut self.limit)
+                    .range(1..=10_000)
+                    .suffix(" transactions"),
+            );
+        });
+        let ready = matches!(&wallet, Some(Ok(wallet)) if wallet.matches_network(network))
+            && !self.label.trim().is_empty();
+        if ui
+            .add_enabled(!running && ready, Button::new("Add Wallet"))
+            .clicked()
+        {
+            if let Some(Ok(wallet)) = wallet {
+                self.start(ui.ctx(), wallet, sender);
+            }
+        }
+
+        self.fetch.status_ui(ui);
+    }
+}
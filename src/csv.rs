@@ -0,0 +1,102 @@
+//! Minimal CSV reading, enough for files exported from spreadsheets and other tools.
+
+/// Split [contents] into records of fields. Fields may be quoted with `"`, in which case they
+/// can contain separators, newlines and escaped quotes (`""`). Empty lines are skipped.
+pub fn parse(contents: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                line += 1;
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|f| !f.is_empty()) {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(format!("Unterminated quote in line {}", line));
+    }
+    record.push(field);
+    if record.iter().any(|f| !f.is_empty()) {
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Join [fields] into one line, quoting where necessary.
+pub fn write_record<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, write_record};
+
+    #[test]
+    fn quoted_fields() {
+        let records =
+            parse("address,label\r\nbc1q,\"Cold, \"\"old\"\" storage\"\n\n1A1z,Genesis").unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["address", "label"],
+                vec!["bc1q", "Cold, \"old\" storage"],
+                vec!["1A1z", "Genesis"],
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        assert!(parse("a,\"b\nc").is_err());
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let record = vec!["bc1q", "Cold, \"old\" storage", "-0.5"];
+        assert_eq!(parse(&write_record(&record)).unwrap(), vec![record]);
+    }
+}
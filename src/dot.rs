@@ -0,0 +1,86 @@
+//! Writing GraphViz DOT files.
+
+use std::fmt::Write;
+
+use egui::Color32;
+
+/// A directed graph, built statement by statement.
+pub struct Dot {
+    out: String,
+}
+
+impl Dot {
+    pub fn new(name: &str) -> Self {
+        let mut out = String::new();
+        writeln!(out, "digraph {} {{", quote(name)).unwrap();
+        Self { out }
+    }
+
+    /// Default attributes, e.g. `node [shape=box]`.
+    pub fn defaults(&mut self, kind: &str, attrs: &[(&str, String)]) {
+        writeln!(self.out, "  {}{};", kind, attr_list(attrs)).unwrap();
+    }
+
+    pub fn node(&mut self, id: &str, attrs: &[(&str, String)]) {
+        writeln!(self.out, "  {}{};", quote(id), attr_list(attrs)).unwrap();
+    }
+
+    pub fn edge(&mut self, from: &str, to: &str, attrs: &[(&str, String)]) {
+        writeln!(
+            self.out,
+            "  {} -> {}{};",
+            quote(from),
+            quote(to),
+            attr_list(attrs)
+        )
+        .unwrap();
+    }
+
+    pub fn finish(mut self) -> String {
+        self.out.push_str("}\n");
+        self.out
+    }
+}
+
+/// E.g. `#ff8000`.
+pub fn color(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+fn attr_list(attrs: &[(&str, String)]) -> String {
+    if attrs.is_empty() {
+        return String::new();
+    }
+    let attrs: Vec<_> = attrs
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, quote(value)))
+        .collect();
+    format!(" [{}]", attrs.join(", "))
+}
+
+/// Line breaks become centered line breaks in labels.
+fn quote(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dot;
+
+    #[test]
+    fn statements() {
+        let mut dot = Dot::new("txgraph");
+        dot.defaults("node", &[("shape", "box".to_string())]);
+        dot.node("a", &[("label", "Say \"hi\"\n1 BTC".to_string())]);
+        dot.node("b", &[]);
+        dot.edge("a", "b", &[]);
+        assert_eq!(
+            dot.finish(),
+            "digraph \"txgraph\" {\n  node [shape=\"box\"];\n  \"a\" [label=\"Say \\\"hi\\\"\\n1 BTC\"];\n  \"b\";\n  \"a\" -> \"b\";\n}\n"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Passphrase encryption for exports that are sent around, e.g. by email.
+
+use argon2::{Algorithm, Argon2, Params};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use serde::{Deserialize, Serialize};
+
+const SALT_LEN: usize = 16;
+
+/// Version of the algorithms in [Sealed]. Exports of other versions aren't opened.
+const VERSION: u32 = 1;
+
+/// Encrypted data and everything apart from the passphrase that's needed to decrypt it. The
+/// binary values are in URL-safe base64.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Sealed {
+    version: u32,
+    kdf: Kdf,
+    /// For deriving the key from the passphrase with Argon2id.
+    salt: String,
+    nonce: String,
+    /// ChaCha20-Poly1305, including the authentication tag.
+    ciphertext: String,
+}
+
+/// Costs of Argon2id, kept with the data so that the defaults can change without breaking older
+/// exports.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+struct Kdf {
+    /// Memory in KiB.
+    m_cost: u32,
+    /// Iterations.
+    t_cost: u32,
+    /// Parallelism.
+    p_cost: u32,
+}
+
+impl Default for Kdf {
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl Kdf {
+    /// 1 GiB. Higher costs in a file are refused, as they could keep the app busy for ages.
+    const MAX_M_COST: u32 = 1024 * 1024;
+    const MAX_T_COST: u32 = 64;
+    const MAX_P_COST: u32 = 16;
+
+    fn key(&self, passphrase: &str, salt: &[u8]) -> Result<Key, String> {
+        if self.m_cost > Self::MAX_M_COST
+            || self.t_cost > Self::MAX_T_COST
+            || self.p_cost > Self::MAX_P_COST
+        {
+            return Err("The key derivation is too costly.".to_string());
+        }
+        let mut key = Key::default();
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(key.len()))
+            .map_err(|e| e.to_string())?;
+        Argon2::new(Algorithm::Argon2id, argon2::Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| e.to_string())?;
+        Ok(key)
+    }
+}
+
+/// Takes a moment, as deriving the key is slow on purpose.
+pub fn seal(plaintext: &[u8], passphrase: &str) -> Sealed {
+    seal_with(plaintext, passphrase, Kdf::default())
+}
+
+fn seal_with(plaintext: &[u8], passphrase: &str, kdf: Kdf) -> Sealed {
+    let mut salt = [0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let key = kdf.key(passphrase, &salt).unwrap();
+    let ciphertext = ChaCha20Poly1305::new(&key)
+        .encrypt(&nonce, plaintext)
+        .unwrap();
+    Sealed {
+        version: VERSION,
+        kdf,
+        salt: encode(&salt),
+        nonce: encode(&nonce),
+        ciphertext: encode(&ciphertext),
+    }
+}
+
+impl Sealed {
+    /// Takes a moment, like [seal].
+    pub fn open(&self, passphrase: &str) -> Result<Vec<u8>, String> {
+        if self.version != VERSION {
+            return Err(format!(
+                "Unsupported encryption version: {}. This version of txgraph reads version {}.",
+                self.version, VERSION
+            ));
+        }
+        let salt = decode(&self.salt)?;
+        let nonce = decode(&self.nonce)?;
+        if nonce.len() != Nonce::default().len() {
+            return Err("Invalid nonce.".to_string());
+        }
+        let key = self.kdf.key(passphrase, &salt)?;
+        ChaCha20Poly1305::new(&key)
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                decode(&self.ciphertext)?.as_slice(),
+            )
+            .map_err(|_| "Wrong passphrase, or the data was changed.".to_string())
+    }
+}
+
+fn encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+fn decode(string: &str) -> Result<Vec<u8>, String> {
+    base64::decode_config(string, base64::URL_SAFE_NO_PAD).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{seal, seal_with, Kdf};
+
+    #[test]
+    fn roundtrip() {
+        let sealed = seal(b"case notes", "correct horse");
+        assert_eq!(sealed.open("correct horse").unwrap(), b"case notes");
+        assert!(sealed.open("wrong horse").is_err());
+
+        let other = seal(b"case notes", "correct horse");
+        assert_ne!(sealed.salt, other.salt);
+        assert_ne!(sealed.ciphertext, other.ciphertext);
+    }
+
+    #[test]
+    fn recorded_parameters() {
+        let kdf = Kdf {
+            m_cost: 64,
+            t_cost: 1,
+            p_cost: 2,
+        };
+        let sealed = seal_with(b"case notes", "correct horse", kdf);
+        let json = serde_json::to_string(&sealed).unwrap();
+        assert!(json.starts_with(r#"{"version":1,"kdf":{"m_cost":64,"t_cost":1,"p_cost":2},"#));
+        assert_eq!(sealed.open("correct horse").unwrap(), b"case notes");
+
+        let mut changed: super::Sealed = serde_json::from_str(&json).unwrap();
+        changed.kdf = Kdf::default();
+        assert!(changed.open("correct horse").is_err());
+        changed.kdf.m_cost = u32::MAX;
+        assert!(changed.open("correct horse").is_err());
+        changed.kdf = kdf;
+        changed.version = 2;
+        assert!(changed
+            .open("correct horse")
+            .unwrap_err()
+            .starts_with("Unsupported encryption version: 2."));
+    }
+}
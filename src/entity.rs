@@ -0,0 +1,537 @@
+//! One-page summary of an entity, i.e. all coins that share a label.
+
+use std::{collections::HashMap, fmt::Write};
+
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use egui::{ComboBox, Grid, Rect, Sense, Stroke, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitcoin::{Sats, SatsDisplay, Txid},
+    notifications::NotifyExt,
+    style,
+    widgets::UiExt,
+    workspaces::WorkspacesHandle,
+};
+
+/// How many counterparties to list in each direction.
+const TOP_COUNTERPARTIES: usize = 5;
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// What an entity received and sent in one transaction.
+pub struct Flow {
+    pub txid: Txid,
+    pub timestamp: i64,
+    pub received: u64,
+    pub sent: u64,
+    /// Where the funds came from, by label.
+    pub sources: Vec<(String, u64)>,
+    /// Where the funds went to, by label.
+    pub destinations: Vec<(String, u64)>,
+    pub tx_label: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ReportCard {
+    pub entity: String,
+    pub received: u64,
+    pub sent: u64,
+    /// Balance after each transaction, oldest first.
+    pub balance_history: Vec<(i64, i64)>,
+    /// Largest first.
+    pub sources: Vec<(String, u64)>,
+    /// Largest first.
+    pub destinations: Vec<(String, u64)>,
+    /// Number of transactions by weekday (Monday first) and hour, in UTC.
+    pub activity: [[u32; 24]; 7],
+    /// Labels of the transactions the entity took part in, oldest first.
+    pub notes: Vec<(i64, String)>,
+}
+
+fn top(totals: HashMap<String, u64>) -> Vec<(String, u64)> {
+    let mut totals: Vec<_> = totals.into_iter().collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    totals.truncate(TOP_COUNTERPARTIES);
+    totals
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    NaiveDateTime::from_timestamp_opt(timestamp, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+impl ReportCard {
+    pub fn new(entity: String, mut flows: Vec<Flow>) -> Self {
+        flows.sort_by_key(|flow| (flow.timestamp, flow.txid));
+
+        let mut received = 0;
+        let mut sent = 0;
+        let mut balance_history = vec![];
+        let mut sources = HashMap::new();
+        let mut destinations = HashMap::new();
+        let mut activity = [[0; 24]; 7];
+        let mut notes = vec![];
+
+        for flow in flows {
+            received += flow.received;
+            sent += flow.sent;
+            balance_history.push((flow.timestamp, received as i64 - sent as i64));
+            for (label, value) in flow.sources {
+                *sources.entry(label).or_default() += value;
+            }
+            for (label, value) in flow.destinations {
+                *destinations.entry(label).or_default() += value;
+            }
+            if let Some(time) = NaiveDateTime::from_timestamp_opt(flow.timestamp, 0) {
+                activity[time.weekday().num_days_from_monday() as usize][time.hour() as usize] += 1;
+            }
+            if let Some(label) = flow.tx_label {
+                notes.push((flow.timestamp, label));
+            }
+        }
+
+        Self {
+            entity,
+            received,
+            sent,
+            balance_history,
+            sources: top(sources),
+            destinations: top(destinations),
+            activity,
+            notes,
+        }
+    }
+
+    pub fn balance(&self) -> i64 {
+        self.received as i64 - self.sent as i64
+    }
+
+    /// For pasting into the workspace notes or a case report.
+    pub fn to_markdown(&self) -> String {
+        let mut s = String::new();
+        let btc = |sats: u64| format!("{}.{:08} BTC", sats / 100_000_000, sats % 100_000_000);
+
+        writeln!(s, "## Entity: {}\n", self.entity).unwrap();
+        writeln!(s, "- Received: {}", btc(self.received)).unwrap();
+        writeln!(s, "- Sent: {}", btc(self.sent)).unwrap();
+        writeln!(s, "- Balance: {}", btc(self.balance().max(0) as u64)).unwrap();
+        writeln!(s, "- Transactions: {}", self.balance_history.len()).unwrap();
+
+        for (title, counterparties) in [
+            ("Funds came from", &self.sources),
+            ("Funds went to", &self.destinations),
+        ] {
+            writeln!(s, "\n### {}\n", title).unwrap();
+            for (label, value) in counterparties {
+                writeln!(s, "- {}: {}", label, btc(*value)).unwrap();
+            }
+        }
+
+        writeln!(s, "\n### Balance history\n").unwrap();
+        for (timestamp, balance) in &self.balance_history {
+            writeln!(
+                s,
+                "- {}: {}",
+                format_timestamp(*timestamp),
+                btc((*balance).max(0) as u64)
+            )
+            .unwrap();
+        }
+
+        if !self.notes.is_empty() {
+            writeln!(s, "\n### Notes\n").unwrap();
+            for (timestamp, note) in &self.notes {
+                writeln!(s, "- {}: {}", format_timestamp(*timestamp), note).unwrap();
+            }
+        }
+        s
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        let style = style::get(ui);
+
+        Grid::new("ReportCard").num_columns(2).show(ui, |ui| {
+            ui.label("Received:");
+            ui.add(SatsDisplay::new(Sats(self.received), &style));
+            ui.end_row();
+
+            ui.label("Sent:");
+            ui.add(SatsDisplay::new(Sats(self.sent), &style));
+            ui.end_row();
+
+            ui.label("Balance:");
+            ui.add(SatsDisplay::new(Sats(self.balance().max(0) as u64), &style));
+            ui.end_row();
+
+            ui.label("Transactions:");
+            ui.label(self.balance_history.len().to_string());
+            ui.end_row();
+        });
+
+        ui.separator();
+        ui.bold("Balance history:");
+        self.balance_chart_ui(ui);
+
+        ui.separator();
+        Grid::new("Counterparties").num_columns(2).show(ui, |ui| {
+            ui.bold("Funds came from:");
+            ui.bold("Funds went to:");
+            ui.end_row();
+            for i in 0..self.sources.len().max(self.destinations.len()) {
+                for counterparties in [&self.sources, &self.destinations] {
+                    match counterparties.get(i) {
+                        Some((label, value)) => {
+                            ui.horizontal(|ui| {
+                                ui.label(label);
+                                ui.add(SatsDisplay::new(Sats(*value), &style));
+                            });
+                        }
+                        None => {
+                            ui.label("");
+                        }
+                    }
+                }
+                ui.end_row();
+            }
+        });
+
+        ui.separator();
+        ui.bold("Activity (UTC):");
+        self.activity_ui(ui);
+
+        if !self.notes.is_empty() {
+            ui.separator();
+            ui.bold("Notes:");
+            for (timestamp, note) in &self.notes {
+                ui.label(format!("{}: {}", format_timestamp(*timestamp), note));
+            }
+        }
+    }
+
+    fn balance_chart_ui(&self, ui: &mut egui::Ui) {
+        let (rect, _) = ui.allocate_exact_size(Vec2::new(360.0, 80.0), Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_stroke(rect, 0.0, ui.visuals().widgets.noninteractive.bg_stroke);
+
+        let (Some(first), Some(last)) = (self.balance_history.first(), self.balance_history.last())
+        else {
+            return;
+        };
+        let max = self
+            .balance_history
+            .iter()
+            .map(|(_, b)| *b)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let duration = (last.0 - first.0).max(1);
+        let to_screen = |(timestamp, balance): (i64, i64)| {
+            rect.left_bottom()
+                + Vec2::new(
+                    rect.width() * (timestamp - first.0) as f32 / duration as f32,
+                    -rect.height() * balance.max(0) as f32 / max as f32,
+                )
+        };
+
+        // Step function: the balance stays constant until the next transaction.
+        let mut points = vec![to_screen((first.0, 0))];
+        let mut previous = 0;
+        for &(timestamp, balance) in &self.balance_history {
+            points.push(to_screen((timestamp, previous)));
+            points.push(to_screen((timestamp, balance)));
+            previous = balance;
+        }
+        points.push(to_screen((last.0, previous)));
+        painter.add(egui::Shape::line(
+            points,
+            Stroke::new(1.5, ui.visuals().hyperlink_color),
+        ));
+    }
+
+    fn activity_ui(&self, ui: &mut egui::Ui) {
+        let cell = 12.0;
+        let label_width = 30.0;
+        let max = self
+            .activity
+            .iter()
+            .flatten()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let color = ui.visuals().hyperlink_color;
+
+        let (rect, _) = ui.allocate_exact_size(
+            Vec2::new(label_width + 24.0 * cell, 7.0 * cell),
+            Sense::hover(),
+        );
+        let painter = ui.painter_at(rect);
+        for (day, hours) in self.activity.iter().enumerate() {
+            let top = rect.top() + day as f32 * cell;
+            painter.text(
+                egui::pos2(rect.left(), top + cell / 2.0),
+                egui::Align2::LEFT_CENTER,
+                WEEKDAYS[day],
+                egui::FontId::monospace(9.0),
+                ui.visuals().text_color(),
+            );
+            for (hour, count) in hours.iter().enumerate() {
+                let cell_rect = Rect::from_min_size(
+                    egui::pos2(rect.left() + label_width + hour as f32 * cell, top),
+                    Vec2::splat(cell - 1.0),
+                );
+                let fill = if *count == 0 {
+                    ui.visuals().faint_bg_color
+                } else {
+                    color.gamma_multiply(0.2 + 0.8 * *count as f32 / max as f32)
+                };
+                painter.rect_filled(cell_rect, 0.0, fill);
+            }
+        }
+    }
+}
+
+/// Inputs and outputs of a transaction, as entity (if labelled) and value.
+pub type Transfer = (Vec<(Option<String>, u64)>, Vec<(Option<String>, u64)>);
+
+/// Total value moved between each pair of entities.
+#[derive(Debug, PartialEq)]
+pub struct FlowMatrix {
+    /// Sorted.
+    pub entities: Vec<String>,
+    /// `values[from][to]`, indices into [Self::entities].
+    pub values: Vec<Vec<u64>>,
+}
+
+impl FlowMatrix {
+    /// Takes the inputs and (non-fee) outputs of each transaction. As it's not known which input
+    /// paid for which output, every output is attributed to the inputs pro rata. Unlabelled coins
+    /// take their share but aren't an entity, so what they send or receive is left out.
+    pub fn new(txs: Vec<Transfer>) -> Self {
+        let mut entities: Vec<String> = txs
+            .iter()
+            .flat_map(|(inputs, outputs)| inputs.iter().chain(outputs))
+            .filter_map(|(entity, _)| entity.clone())
+            .collect();
+        entities.sort_unstable();
+        entities.dedup();
+        let index: HashMap<&String, usize> =
+            entities.iter().enumerate().map(|(i, e)| (e, i)).collect();
+
+        let mut values = vec![vec![0; entities.len()]; entities.len()];
+        for (inputs, outputs) in &txs {
+            let total_in: u64 = inputs.iter().map(|(_, value)| value).sum();
+            if total_in == 0 {
+                continue;
+            }
+            for (from, input) in inputs {
+                let Some(from) = from else {
+                    continue;
+                };
+                for (to, output) in outputs {
+                    let Some(to) = to else {
+                        continue;
+                    };
+                    values[index[from]][index[to]] +=
+                        (*input as u128 * *output as u128 / total_in as u128) as u64;
+                }
+            }
+        }
+
+        Self { entities, values }
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui, hide_self_transfers: bool) {
+        let style = style::get(ui);
+        ui.label("Rows send to columns.");
+        egui::ScrollArea::both().max_height(400.0).show(ui, |ui| {
+            Grid::new("FlowMatrix")
+                .striped(true)
+                .num_columns(self.entities.len() + 1)
+                .show(ui, |ui| {
+                    ui.label("");
+                    for entity in &self.entities {
+                        ui.bold(entity);
+                    }
+                    ui.end_row();
+
+                    for (from, row) in self.values.iter().enumerate() {
+                        ui.bold(&self.entities[from]);
+                        for (to, value) in row.iter().enumerate() {
+                            if *value == 0 || (hide_self_transfers && from == to) {
+                                ui.label("");
+                            } else {
+                                ui.add(SatsDisplay::new(Sats(*value), &style));
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlowMatrixSettings {
+    hide_self_transfers: bool,
+}
+
+impl Default for FlowMatrixSettings {
+    fn default() -> Self {
+        Self {
+            hide_self_transfers: true,
+        }
+    }
+}
+
+impl FlowMatrixSettings {
+    pub fn ui(&mut self, ui: &mut egui::Ui, matrix: FlowMatrix) {
+        if matrix.entities.is_empty() {
+            ui.label("No transactions yet.");
+            return;
+        }
+        ui.checkbox(
+            &mut self.hide_self_transfers,
+            "Hide transfers within an entity",
+        );
+        matrix.ui(ui, self.hide_self_transfers);
+    }
+}
+
+/// Picks the entity to show a report card for.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntityReport {
+    entity: Option<String>,
+}
+
+impl EntityReport {
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        entities: &[String],
+        report: impl FnOnce(&str) -> ReportCard,
+    ) {
+        if entities.is_empty() {
+            ui.label("Label some coins first. Coins with the same label form an entity.");
+            return;
+        }
+
+        ComboBox::from_label("Entity")
+            .selected_text(self.entity.as_deref().unwrap_or("Choose…"))
+            .show_ui(ui, |ui| {
+                for entity in entities {
+                    ui.selectable_value(&mut self.entity, Some(entity.clone()), entity);
+                }
+            });
+
+        let Some(entity) = self.entity.as_ref().filter(|e| entities.contains(e)) else {
+            return;
+        };
+        let report = report(entity);
+
+        ui.separator();
+        report.ui(ui);
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Copy as Markdown").clicked() {
+                ui.output_mut(|o| o.copied_text = report.to_markdown());
+                ui.close_menu();
+            }
+            if ui
+                .button("Append to Notes")
+                .on_hover_text("Add to the notes of the current workspace.")
+                .clicked()
+            {
+                WorkspacesHandle::append_notes(ui.ctx(), report.to_markdown());
+                ui.ctx().notify_success("Added report card to the notes.");
+                ui.close_menu();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Flow, FlowMatrix, ReportCard};
+    use crate::bitcoin::Txid;
+
+    fn flow(n: u8, timestamp: i64, received: u64, sent: u64, counterparty: &str) -> Flow {
+        let counterparty = vec![(counterparty.to_string(), received.max(sent))];
+        Flow {
+            txid: Txid::new(&format!("{:02x}", n).repeat(32)).unwrap(),
+            timestamp,
+            received,
+            sent,
+            sources: if received > 0 {
+                counterparty.clone()
+            } else {
+                vec![]
+            },
+            destinations: if sent > 0 { counterparty } else { vec![] },
+            tx_label: None,
+        }
+    }
+
+    #[test]
+    fn report_card() {
+        // 2024-01-01 was a Monday.
+        let monday_noon = 1_704_110_400;
+        let report = ReportCard::new(
+            "Alice".to_string(),
+            vec![
+                flow(2, monday_noon + 86_400, 0, 300, "Bob"),
+                flow(1, monday_noon, 1000, 0, "Exchange"),
+                flow(3, monday_noon + 2 * 86_400, 500, 0, "Exchange"),
+            ],
+        );
+        assert_eq!(report.received, 1500);
+        assert_eq!(report.sent, 300);
+        assert_eq!(
+            report.balance_history,
+            vec![
+                (monday_noon, 1000),
+                (monday_noon + 86_400, 700),
+                (monday_noon + 2 * 86_400, 1200),
+            ]
+        );
+        assert_eq!(report.sources, vec![("Exchange".to_string(), 1500)]);
+        assert_eq!(report.destinations, vec![("Bob".to_string(), 300)]);
+        assert_eq!(report.activity[0][12], 1);
+        assert_eq!(report.activity[1][12], 1);
+    }
+
+    #[test]
+    fn flow_matrix_pro_rata() {
+        let s = |e: &str, v: u64| (Some(e.to_string()), v);
+        let matrix = FlowMatrix::new(vec![
+            (
+                vec![s("A", 300), s("B", 100)],
+                vec![s("C", 200), s("A", 200)],
+            ),
+            (vec![s("C", 50)], vec![s("B", 50)]),
+        ]);
+        assert_eq!(matrix.entities, vec!["A", "B", "C"]);
+        assert_eq!(
+            matrix.values,
+            vec![vec![150, 0, 150], vec![50, 0, 50], vec![0, 50, 0]]
+        );
+    }
+
+    #[test]
+    fn flow_matrix_leaves_out_unlabelled() {
+        let s = |e: &str, v: u64| (Some(e.to_string()), v);
+        let matrix = FlowMatrix::new(vec![
+            (
+                vec![s("A", 100), (None, 100)],
+                vec![s("B", 100), (None, 100)],
+            ),
+            (vec![(None, 50)], vec![s("A", 50)]),
+        ]);
+        assert_eq!(matrix.entities, vec!["A", "B"]);
+        assert_eq!(matrix.values, vec![vec![0, 50], vec![0, 0]]);
+    }
+}
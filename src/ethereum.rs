@@ -0,0 +1,907 @@
+//! Tracing on Ethereum, where funds move between accounts instead of coins. The transfers of an
+//! address, in ether and ERC-20 tokens, come from an Etherscan-compatible API.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    f32::consts::TAU,
+    sync::Arc,
+};
+
+use chrono::NaiveDateTime;
+use egui::{
+    mutex::Mutex, vec2, Align2, CollapsingHeader, FontId, Grid, Pos2, ScrollArea, Sense, Shape,
+    Stroke, TextEdit, Vec2,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotations::Annotations,
+    client::{ApiError, Client},
+    csv,
+    export::{Ethereum0, Transfer0},
+    notifications::NotifyExt,
+    platform::inner as platform,
+    widgets::UiExt,
+};
+
+/// Etherscan's multichain API, on Ethereum mainnet.
+pub const DEFAULT_API: &str = "https://api.etherscan.io/v2/api?chainid=1";
+
+/// Transfers per request. Some APIs allow more, but not all of them.
+const PAGE_SIZE: usize = 1000;
+
+/// Pages of each list, so that the busiest addresses don't take forever.
+const MAX_PAGES: usize = 50;
+
+/// Busiest edges drawn in the flow graph.
+const MAX_EDGES: usize = 60;
+
+/// The result is an error message instead if the request failed.
+#[derive(Deserialize)]
+struct Response {
+    message: String,
+    result: serde_json::Value,
+}
+
+/// Entry of `txlist` (ether) or `tokentx` (ERC-20).
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawTransfer {
+    hash: String,
+    block_number: String,
+    time_stamp: String,
+    from: String,
+    to: String,
+    value: String,
+    #[serde(default)]
+    is_error: Option<String>,
+    #[serde(default)]
+    token_symbol: Option<String>,
+    #[serde(default)]
+    token_decimal: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Transfer {
+    pub hash: String,
+    pub timestamp: i64,
+    /// Lowercase. Empty for contract creations.
+    pub from: String,
+    pub to: String,
+    /// In the smallest unit, e.g. wei.
+    pub value: u128,
+    /// `ETH` or the token symbol.
+    pub asset: String,
+    pub decimals: u32,
+}
+
+impl Transfer {
+    fn export(&self) -> Transfer0 {
+        Transfer0 {
+            hash: self.hash.clone(),
+            timestamp: self.timestamp,
+            from: self.from.clone(),
+            to: self.to.clone(),
+            value: self.value.to_string(),
+            asset: self.asset.clone(),
+            decimals: self.decimals,
+        }
+    }
+
+    fn import(t: &Transfer0) -> Result<Self, String> {
+        Ok(Self {
+            hash: t.hash.clone(),
+            timestamp: t.timestamp,
+            from: t.from.clone(),
+            to: t.to.clone(),
+            value: t
+                .value
+                .parse()
+                .map_err(|_| format!("Invalid value: {}", t.value))?,
+            asset: t.asset.clone(),
+            decimals: t.decimals,
+        })
+    }
+}
+
+/// One response of a list, oldest first.
+struct Page {
+    transfers: Vec<Transfer>,
+    /// Where the next page starts, if this one was full.
+    next_block: Option<u64>,
+}
+
+/// Failed transactions and calls that don't move ether are left out. A full page may have cut
+/// off the transfers of its last block, which are left to the next page.
+fn parse(response: Response, page_size: usize) -> Result<Page, String> {
+    let mut raw: Vec<RawTransfer> = match response.result {
+        serde_json::Value::String(msg) => return Err(format!("{}: {}", response.message, msg)),
+        result => serde_json::from_value(result).map_err(|e| e.to_string())?,
+    };
+    let mut next_block = None;
+    if raw.len() >= page_size {
+        let block = |raw: &RawTransfer| raw.block_number.parse::<u64>().unwrap_or(0);
+        let (first, last) = (block(&raw[0]), block(&raw[raw.len() - 1]));
+        if first == last {
+            // A block with more transfers than fit on a page can't be split up.
+            next_block = Some(last + 1);
+        } else {
+            raw.retain(|raw| block(raw) != last);
+            next_block = Some(last);
+        }
+    }
+    let mut transfers = vec![];
+    for raw in raw {
+        if raw.is_error.as_deref() == Some("1") {
+            continue;
+        }
+        let value: u128 = raw
+            .value
+            .parse()
+            .map_err(|_| format!("Invalid value {} in {}", raw.value, raw.hash))?;
+        if value == 0 {
+            continue;
+        }
+        let (asset, decimals) = match raw.token_symbol {
+            Some(symbol) => (
+                symbol,
+                raw.token_decimal.and_then(|d| d.parse().ok()).unwrap_or(0),
+            ),
+            None => ("ETH".to_string(), 18),
+        };
+        transfers.push(Transfer {
+            hash: raw.hash,
+            timestamp: raw.time_stamp.parse().unwrap_or(0),
+            from: raw.from.to_ascii_lowercase(),
+            to: raw.to.to_ascii_lowercase(),
+            value,
+            asset,
+            decimals,
+        });
+    }
+    Ok(Page {
+        transfers,
+        next_block,
+    })
+}
+
+/// Exact decimal amount, without trailing zeros.
+pub fn format_amount(value: u128, decimals: u32) -> String {
+    let Some(unit) = 10u128.checked_pow(decimals) else {
+        return value.to_string();
+    };
+    let fraction = format!("{:0width$}", value % unit, width = decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (value / unit).to_string()
+    } else {
+        format!("{}.{}", value / unit, fraction)
+    }
+}
+
+/// Everything that moved between the traced address and one counterparty, in one asset.
+#[derive(Debug, PartialEq)]
+pub struct Counterparty {
+    pub address: String,
+    pub asset: String,
+    pub decimals: u32,
+    pub received: u128,
+    pub sent: u128,
+    pub transfers: usize,
+}
+
+/// Busiest counterparties first.
+pub fn counterparties(address: &str, transfers: &[Transfer]) -> Vec<Counterparty> {
+    let mut totals: BTreeMap<(&str, &str), Counterparty> = BTreeMap::new();
+    for transfer in transfers {
+        let (other, received) = if transfer.to == address {
+            (&transfer.from, true)
+        } else if transfer.from == address {
+            (&transfer.to, false)
+        } else {
+            continue;
+        };
+        let entry = totals
+            .entry((other, &transfer.asset))
+            .or_insert_with(|| Counterparty {
+                address: other.clone(),
+                asset: transfer.asset.clone(),
+                decimals: transfer.decimals,
+                received: 0,
+                sent: 0,
+                transfers: 0,
+            });
+        if received {
+            entry.received += transfer.value;
+        } else {
+            entry.sent += transfer.value;
+        }
+        entry.transfers += 1;
+    }
+    let mut counterparties: Vec<_> = totals.into_values().collect();
+    counterparties.sort_by_key(|c| Reverse(c.transfers));
+    counterparties
+}
+
+/// Everything that moved from one address to another in one asset.
+#[derive(Debug, PartialEq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub asset: String,
+    pub decimals: u32,
+    pub value: u128,
+    pub transfers: usize,
+}
+
+/// Busiest edges first. Contract creations and transfers to the sender are left out.
+pub fn edges<'a>(transfers: impl IntoIterator<Item = &'a Transfer>) -> Vec<Edge> {
+    let mut totals: BTreeMap<(&str, &str, &str), Edge> = BTreeMap::new();
+    for transfer in transfers {
+        if transfer.from.is_empty() || transfer.to.is_empty() || transfer.from == transfer.to {
+            continue;
+        }
+        let entry = totals
+            .entry((&transfer.from, &transfer.to, &transfer.asset))
+            .or_insert_with(|| Edge {
+                from: transfer.from.clone(),
+                to: transfer.to.clone(),
+                asset: transfer.asset.clone(),
+                decimals: transfer.decimals,
+                value: 0,
+                transfers: 0,
+            });
+        entry.value += transfer.value;
+        entry.transfers += 1;
+    }
+    let mut edges: Vec<_> = totals.into_values().collect();
+    edges.sort_by_key(|e| Reverse(e.transfers));
+    edges
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    NaiveDateTime::from_timestamp_opt(timestamp, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// One row per transfer, with address labels.
+fn transfers_csv(transfers: &[Transfer], annotations: &Annotations) -> String {
+    let label = |address: &str| annotations.address_label(address).unwrap_or_default();
+    let mut s = csv::write_record(&[
+        "date",
+        "hash",
+        "from",
+        "from_label",
+        "to",
+        "to_label",
+        "asset",
+        "amount",
+    ]);
+    for t in transfers {
+        s.push_str(&csv::write_record(&[
+            format_timestamp(t.timestamp),
+            t.hash.clone(),
+            t.from.clone(),
+            label(&t.from),
+            t.to.clone(),
+            label(&t.to),
+            t.asset.clone(),
+            format_amount(t.value, t.decimals),
+        ]));
+    }
+    s
+}
+
+#[derive(Default)]
+struct Fetched {
+    /// Requests were sent, which isn't the case for an address restored from a workspace.
+    started: bool,
+    /// Responses for an address traced before are dropped.
+    address: String,
+    ether: Option<Result<Vec<Transfer>, String>>,
+    tokens: Option<Result<Vec<Transfer>, String>>,
+    /// A list had more than [MAX_PAGES].
+    truncated: bool,
+    /// Added to [Flows].
+    merged: bool,
+}
+
+impl Fetched {
+    /// Oldest first, once both lists arrived.
+    fn transfers(&self) -> Option<Vec<Transfer>> {
+        let (Some(ether), Some(tokens)) = (&self.ether, &self.tokens) else {
+            return None;
+        };
+        let mut transfers = ether.clone().ok()?;
+        transfers.extend(tokens.clone().ok()?);
+        transfers.sort_by_key(|t| t.timestamp);
+        Some(transfers)
+    }
+
+    fn error(&self) -> Option<&String> {
+        [&self.ether, &self.tokens]
+            .into_iter()
+            .find_map(|result| result.as_ref()?.as_ref().err())
+    }
+}
+
+/// `txlist` or `tokentx` of one address, fetched page by page.
+struct List {
+    /// Without the block range and paging.
+    url: String,
+    address: String,
+    tokens: bool,
+    fetched: Arc<Mutex<Fetched>>,
+}
+
+impl List {
+    /// [pages] have been fetched into [transfers] so far.
+    fn fetch(self, ctx: &egui::Context, start_block: u64, pages: usize, transfers: Vec<Transfer>) {
+        let url = format!(
+            "{}&startblock={}&endblock=99999999&page=1&offset={}",
+            self.url, start_block, PAGE_SIZE
+        );
+        let ctx2 = ctx.clone();
+        Client::fetch_external_json(url, ctx, move |result: Result<Response, ApiError>| {
+            let mut transfers = transfers;
+            let page = result
+                .map_err(|e| e.to_string())
+                .and_then(|response| parse(response, PAGE_SIZE));
+            let mut fetched = self.fetched.lock();
+            if fetched.address != self.address {
+                return;
+            }
+            let result = page.map(|page| {
+                transfers.extend(page.transfers);
+                page.next_block
+            });
+            let result = match result {
+                Ok(Some(next_block)) if pages + 1 < MAX_PAGES => {
+                    drop(fetched);
+                    self.fetch(&ctx2, next_block, pages + 1, transfers);
+                    return;
+                }
+                Ok(next_block) => {
+                    fetched.truncated |= next_block.is_some();
+                    Ok(transfers)
+                }
+                Err(e) => Err(e),
+            };
+            if self.tokens {
+                fetched.tokens = Some(result);
+            } else {
+                fetched.ether = Some(result);
+            }
+            ctx2.request_repaint();
+        });
+    }
+}
+
+/// The transfers of all addresses traced in this workspace, as one graph.
+#[derive(Default)]
+struct Flows {
+    traced: Vec<String>,
+    /// Ordered, so that the workspace doesn't change when nothing was traced.
+    transfers: BTreeSet<Transfer>,
+    edges: Vec<Edge>,
+}
+
+impl Flows {
+    fn add(&mut self, address: &str, transfers: &[Transfer]) {
+        if !self.traced.iter().any(|traced| traced == address) {
+            self.traced.push(address.to_string());
+        }
+        self.transfers.extend(transfers.iter().cloned());
+        self.edges = edges(&self.transfers);
+    }
+}
+
+fn distance_to_segment(p: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let t = ((p - a).dot(ab) / ab.length_sq()).clamp(0.0, 1.0);
+    p.distance(a + ab * t)
+}
+
+/// Traced addresses in the middle, their counterparties around them. Returns a clicked address.
+fn flow_graph(ui: &mut egui::Ui, flows: &Flows, annotations: &Annotations) -> Option<String> {
+    const NODE_RADIUS: f32 = 6.0;
+    let edges = &flows.edges[..flows.edges.len().min(MAX_EDGES)];
+    let inner: Vec<&str> = flows.traced.iter().map(String::as_str).collect();
+    let mut outer: Vec<&str> = vec![];
+    for edge in edges {
+        for address in [edge.from.as_str(), edge.to.as_str()] {
+            if !inner.contains(&address) && !outer.contains(&address) {
+                outer.push(address);
+            }
+        }
+    }
+
+    let (response, painter) =
+        ui.allocate_painter(vec2(ui.available_width(), 400.0), Sense::click());
+    let center = response.rect.center();
+    let radius = response.rect.width().min(response.rect.height()) / 2.0 - 30.0;
+    let inner_radius = if inner.len() > 1 { radius * 0.35 } else { 0.0 };
+    let mut positions: HashMap<&str, Pos2> = HashMap::new();
+    for (addresses, radius) in [(&inner, inner_radius), (&outer, radius)] {
+        for (i, address) in addresses.iter().enumerate() {
+            let angle = i as f32 / addresses.len() as f32 * TAU;
+            positions.insert(address, center + radius * Vec2::angled(angle));
+        }
+    }
+
+    let name = |address: &str| match annotations.address_label(address) {
+        Some(label) => label,
+        None => format!("{}…", &address[..address.len().min(10)]),
+    };
+    let visuals = ui.visuals();
+    let pointer = response.hover_pos();
+    let mut hovered_edge = None;
+    for edge in edges {
+        let (a, b) = (positions[edge.from.as_str()], positions[edge.to.as_str()]);
+        let direction = (b - a).normalized();
+        // Edges in opposite directions side by side.
+        let (a, b) = (a + direction.rot90() * 3.0, b + direction.rot90() * 3.0);
+        let width = 1.0 + (edge.transfers as f32).ln();
+        let hovered = pointer.is_some_and(|p| distance_to_segment(p, a, b) < width + 3.0);
+        let color = if hovered {
+            visuals.selection.bg_fill
+        } else {
+            visuals.weak_text_color()
+        };
+        painter.line_segment([a, b], Stroke::new(width, color));
+        let mid = a + (b - a) * 0.5;
+        painter.add(Shape::convex_polygon(
+            vec![
+                mid + direction * 6.0,
+                mid - direction * 4.0 + direction.rot90() * 4.0,
+                mid - direction * 4.0 - direction.rot90() * 4.0,
+            ],
+            color,
+            Stroke::NONE,
+        ));
+        if hovered {
+            hovered_edge = Some(edge);
+        }
+    }
+
+    let mut hovered_node = None;
+    for (address, pos) in &positions {
+        let hovered = pointer.is_some_and(|p| p.distance(*pos) < NODE_RADIUS + 2.0);
+        let fill = if inner.contains(address) {
+            visuals.selection.bg_fill
+        } else {
+            visuals.widgets.inactive.bg_fill
+        };
+        let stroke = Stroke::new(if hovered { 2.0 } else { 1.0 }, visuals.text_color());
+        painter.circle(*pos, NODE_RADIUS, fill, stroke);
+        painter.text(
+            *pos + vec2(0.0, NODE_RADIUS + 2.0),
+            Align2::CENTER_TOP,
+            name(address),
+            FontId::proportional(11.0),
+            visuals.text_color(),
+        );
+        if hovered {
+            hovered_node = Some(*address);
+        }
+    }
+
+    if let Some(address) = hovered_node {
+        let clicked = response.clicked();
+        response.on_hover_text(format!("{}\nClick to trace.", address));
+        clicked.then(|| address.to_string())
+    } else {
+        if let Some(edge) = hovered_edge {
+            response.on_hover_text(format!(
+                "{} → {}\n{} {} in {} transfer(s)",
+                name(&edge.from),
+                name(&edge.to),
+                format_amount(edge.value, edge.decimals),
+                edge.asset,
+                edge.transfers
+            ));
+        }
+        None
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct EthereumView {
+    api_base: String,
+    /// Not kept, it would be stored in plain text.
+    #[serde(skip)]
+    api_key: String,
+    /// The traced address, lowercase. Saved with the workspace, see [Self::export].
+    #[serde(skip)]
+    address: String,
+    #[serde(skip)]
+    input: String,
+    #[serde(skip)]
+    label_input: String,
+    #[serde(skip)]
+    fetched: Arc<Mutex<Fetched>>,
+    #[serde(skip)]
+    flows: Flows,
+    #[serde(skip)]
+    open: bool,
+}
+
+impl Default for EthereumView {
+    fn default() -> Self {
+        Self {
+            api_base: DEFAULT_API.to_string(),
+            api_key: String::new(),
+            address: String::new(),
+            input: String::new(),
+            label_input: String::new(),
+            fetched: Default::default(),
+            flows: Flows::default(),
+            open: false,
+        }
+    }
+}
+
+impl EthereumView {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// The trace of the workspace, `None` if nothing was traced.
+    pub fn export(&self) -> Option<Ethereum0> {
+        if self.address.is_empty() && self.flows.traced.is_empty() {
+            return None;
+        }
+        Some(Ethereum0 {
+            address: self.address.clone(),
+            traced: self.flows.traced.clone(),
+            transfers: self.flows.transfers.iter().map(Transfer::export).collect(),
+        })
+    }
+
+    /// Shows the trace of a workspace that was loaded. The address is fetched again once the
+    /// window is open, the flows are kept as they were.
+    pub fn import(&mut self, ctx: &egui::Context, trace: Option<&Ethereum0>) {
+        let trace = trace.cloned().unwrap_or_default();
+        let transfers: Result<BTreeSet<Transfer>, String> =
+            trace.transfers.iter().map(Transfer::import).collect();
+        let transfers = transfers.unwrap_or_else(|e| {
+            ctx.notify_error("Could not load the Ethereum transfers", Some(e));
+            BTreeSet::new()
+        });
+        self.address = trace.address.clone();
+        self.input = trace.address;
+        *self.fetched.lock() = Fetched::default();
+        self.flows = Flows {
+            traced: trace.traced,
+            edges: edges(&transfers),
+            transfers,
+        };
+    }
+
+    fn trace(&mut self, ctx: &egui::Context, address: &str) {
+        self.address = address.trim().to_ascii_lowercase();
+        self.input = self.address.clone();
+        *self.fetched.lock() = Fetched {
+            started: true,
+            address: self.address.clone(),
+            ..Default::default()
+        };
+
+        let base = self.api_base.trim();
+        let separator = if base.contains('?') { '&' } else { '?' };
+        for (action, tokens) in [("txlist", false), ("tokentx", true)] {
+            let list = List {
+                url: format!(
+                    "{}{}module=account&action={}&address={}&sort=asc&apikey={}",
+                    base, separator, action, self.address, self.api_key
+                ),
+                address: self.address.clone(),
+                tokens,
+                fetched: self.fetched.clone(),
+            };
+            list.fetch(ctx, 0, 0, vec![]);
+        }
+    }
+
+    /// Returns an address and the label the user gave it.
+    pub fn show_window(
+        &mut self,
+        ctx: &egui::Context,
+        annotations: &Annotations,
+    ) -> Option<(String, String)> {
+        let mut open = self.open;
+        let mut label = None;
+        egui::Window::new("Ethereum")
+            .open(&mut open)
+            .default_width(600.0)
+            .show(ctx, |ui| label = self.ui(ui, annotations));
+        self.open = open;
+        label
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, annotations: &Annotations) -> Option<(String, String)> {
+        ui.collapsing("API", |ui| {
+            Grid::new("EthereumApi").num_columns(2).show(ui, |ui| {
+                ui.label("Etherscan-compatible API:");
+                ui.add(TextEdit::singleline(&mut self.api_base).desired_width(300.0));
+                ui.end_row();
+                ui.label("API key:");
+                ui.add(TextEdit::singleline(&mut self.api_key).password(true))
+                    .on_hover_text("Only kept until the app is closed.");
+                ui.end_row();
+            });
+        });
+
+        let mut trace = None;
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                TextEdit::singleline(&mut self.input)
+                    .hint_text("0x…")
+                    .desired_width(360.0),
+            );
+            let valid = self.input.trim().starts_with("0x") && self.input.trim().len() == 42;
+            let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.add_enabled(valid, egui::Button::new("Trace")).clicked() || (valid && enter) {
+                trace = Some(self.input.clone());
+            }
+        });
+        if let Some(address) = trace {
+            self.trace(ui.ctx(), &address);
+        }
+        if self.address.is_empty() {
+            ui.label("Enter an address to see where its funds came from and went to.");
+            return None;
+        }
+
+        if !self.fetched.lock().started {
+            let address = self.address.clone();
+            self.trace(ui.ctx(), &address);
+        }
+        let mut fetched = self.fetched.lock();
+        if let Some(error) = fetched.error() {
+            ui.label(format!("Could not load transfers: {}", error));
+            return None;
+        }
+        let Some(transfers) = fetched.transfers() else {
+            ui.weak("Loading transfers…");
+            return None;
+        };
+        if !fetched.merged {
+            fetched.merged = true;
+            self.flows.add(&self.address, &transfers);
+        }
+        let truncated = fetched.truncated;
+        drop(fetched);
+
+        let name = |address: &str| match annotations.address_label(address) {
+            Some(label) => format!("{} ({}…)", label, &address[..address.len().min(10)]),
+            None => address.to_string(),
+        };
+
+        let mut labelled = None;
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.bold(name(&self.address));
+            ui.label(format!("{} transfers", transfers.len()));
+            if truncated {
+                ui.weak(format!("(the first {} pages)", MAX_PAGES));
+            }
+        });
+
+        let mut next = None;
+        ui.bold("Counterparties:");
+        ScrollArea::vertical()
+            .id_source("EthereumCounterparties")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                Grid::new("EthereumCounterparties")
+                    .striped(true)
+                    .num_columns(5)
+                    .show(ui, |ui| {
+                        ui.bold("Address");
+                        ui.bold("Asset");
+                        ui.bold("Received");
+                        ui.bold("Sent");
+                        ui.bold("Transfers");
+                        ui.end_row();
+                        for c in counterparties(&self.address, &transfers) {
+                            ui.menu_button(name(&c.address), |ui| {
+                                if ui.button("Trace").clicked() {
+                                    next = Some(c.address.clone());
+                                    ui.close_menu();
+                                }
+                                if ui.button("Copy Address").clicked() {
+                                    ui.output_mut(|o| o.copied_text = c.address.clone());
+                                    ui.close_menu();
+                                }
+                                ui.separator();
+                                ui.label("Label:");
+                                let response = ui.text_edit_singleline(&mut self.label_input);
+                                if response.lost_focus()
+                                    && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                                    && !self.label_input.trim().is_empty()
+                                {
+                                    labelled = Some((
+                                        c.address.clone(),
+                                        self.label_input.trim().to_string(),
+                                    ));
+                                    self.label_input.clear();
+                                    ui.close_menu();
+                                }
+                            });
+                            ui.label(&c.asset);
+                            ui.label(format_amount(c.received, c.decimals));
+                            ui.label(format_amount(c.sent, c.decimals));
+                            ui.label(c.transfers.to_string());
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        CollapsingHeader::new("Flows")
+            .id_source("EthereumFlows")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.weak(format!(
+                        "Between {} traced address(es) and their counterparties.",
+                        self.flows.traced.len()
+                    ));
+                    if ui.small_button("Clear").clicked() {
+                        self.flows = Flows::default();
+                        self.fetched.lock().merged = false;
+                    }
+                });
+                if let Some(address) = flow_graph(ui, &self.flows, annotations) {
+                    next = Some(address);
+                }
+            });
+        if let Some(address) = next {
+            self.trace(ui.ctx(), &address);
+        }
+
+        ui.separator();
+        if ui.button("Export CSV…").clicked() {
+            let contents = transfers_csv(&transfers, annotations);
+            let file_name = format!(
+                "ethereum-{}.csv",
+                &self.address[..10.min(self.address.len())]
+            );
+            if platform::save_file(ui.ctx(), &file_name, &contents) {
+                ui.ctx().notify_success("Exported transfers.");
+            }
+        }
+        labelled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{counterparties, edges, format_amount, parse, Response, Transfer, PAGE_SIZE};
+
+    const ME: &str = "0x00000000000000000000000000000000000000aa";
+    const EXCHANGE: &str = "0x00000000000000000000000000000000000000bb";
+
+    #[test]
+    fn amounts() {
+        assert_eq!(format_amount(1_500_000_000_000_000_000, 18), "1.5");
+        assert_eq!(format_amount(42, 0), "42");
+        assert_eq!(format_amount(100_000_001, 8), "1.00000001");
+        assert_eq!(format_amount(5, 6), "0.000005");
+    }
+
+    #[test]
+    fn transfers() {
+        let response: Response = serde_json::from_str(&format!(
+            r#"{{"status": "1", "message": "OK", "result": [
+                {{"hash": "0x1", "blockNumber": "1", "timeStamp": "1700000000", "from": "{exchange}", "to": "{me}", "value": "2000000000000000000", "isError": "0"}},
+                {{"hash": "0x2", "blockNumber": "2", "timeStamp": "1700000100", "from": "{me}", "to": "{exchange}", "value": "500000000000000000", "isError": "0"}},
+                {{"hash": "0x3", "blockNumber": "3", "timeStamp": "1700000200", "from": "{me}", "to": "{exchange}", "value": "1", "isError": "1"}},
+                {{"hash": "0x4", "blockNumber": "4", "timeStamp": "1700000300", "from": "{me}", "to": "{exchange}", "value": "0", "isError": "0"}},
+                {{"hash": "0x5", "blockNumber": "5", "timeStamp": "1700000400", "from": "{me}", "to": "{exchange}", "value": "150000000", "tokenSymbol": "WBTC", "tokenDecimal": "8"}}
+            ]}}"#,
+            me = ME.to_uppercase().replace("0X", "0x"),
+            exchange = EXCHANGE
+        ))
+        .unwrap();
+        let transfers = parse(response, PAGE_SIZE).unwrap().transfers;
+        assert_eq!(transfers.len(), 3);
+        assert_eq!(transfers[0].to, ME);
+
+        let counterparties = counterparties(ME, &transfers);
+        let totals: Vec<_> = counterparties
+            .iter()
+            .map(|c| (c.asset.as_str(), c.received, c.sent, c.transfers))
+            .collect();
+        assert_eq!(
+            totals,
+            vec![
+                ("ETH", 2_000_000_000_000_000_000, 500_000_000_000_000_000, 2),
+                ("WBTC", 0, 150_000_000, 1),
+            ]
+        );
+
+        let error: Response = serde_json::from_str(
+            r#"{"status": "0", "message": "NOTOK", "result": "Invalid API Key"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parse(error, PAGE_SIZE).err(),
+            Some("NOTOK: Invalid API Key".to_string())
+        );
+    }
+
+    fn page(blocks: &[u64]) -> Response {
+        let result: Vec<_> = blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| {
+                serde_json::json!({
+                    "hash": format!("0x{}", i),
+                    "blockNumber": block.to_string(),
+                    "timeStamp": "1700000000",
+                    "from": ME,
+                    "to": EXCHANGE,
+                    "value": "1",
+                })
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({"message": "OK", "result": result})).unwrap()
+    }
+
+    #[test]
+    fn paging() {
+        // The last block may go on in the next page, so it's left to that one.
+        let full = parse(page(&[10, 11, 12, 12]), 4).unwrap();
+        assert_eq!(full.transfers.len(), 2);
+        assert_eq!(full.next_block, Some(12));
+        // Unless the page has nothing else.
+        let one_block = parse(page(&[12, 12, 12, 12]), 4).unwrap();
+        assert_eq!(one_block.transfers.len(), 4);
+        assert_eq!(one_block.next_block, Some(13));
+        let last = parse(page(&[12, 13]), 4).unwrap();
+        assert_eq!(last.transfers.len(), 2);
+        assert_eq!(last.next_block, None);
+        let empty: Response = serde_json::from_str(
+            r#"{"status": "0", "message": "No transactions found", "result": []}"#,
+        )
+        .unwrap();
+        assert!(parse(empty, 4).unwrap().transfers.is_empty());
+    }
+
+    #[test]
+    fn flow_edges() {
+        let transfer = |from: &str, to: &str, value: u128| Transfer {
+            hash: format!("0x{}", value),
+            timestamp: 0,
+            from: from.to_string(),
+            to: to.to_string(),
+            value,
+            asset: "ETH".to_string(),
+            decimals: 18,
+        };
+        let other = "0x00000000000000000000000000000000000000cc";
+        let transfers = [
+            transfer(EXCHANGE, ME, 1),
+            transfer(EXCHANGE, ME, 2),
+            transfer(ME, other, 4),
+            // Contract creation
+            transfer(ME, "", 8),
+            transfer(ME, ME, 16),
+        ];
+        let totals: Vec<_> = edges(&transfers)
+            .into_iter()
+            .map(|e| (e.from, e.to, e.value, e.transfers))
+            .collect();
+        assert_eq!(
+            totals,
+            vec![
+                (EXCHANGE.to_string(), ME.to_string(), 3, 2),
+                (ME.to_string(), other.to_string(), 4, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Growing the graph several hops at once from a transaction.
+
+use egui::{Context, DragValue, Grid, Pos2};
+use serde::{Deserialize, Serialize};
+
+use crate::{bitcoin::Txid, client::RequestHandle, graph::Graph};
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Direction {
+    /// Follow outputs to the transactions spending them.
+    #[default]
+    Forward,
+    /// Follow inputs to the transactions funding them.
+    Backward,
+    Both,
+}
+
+impl Direction {
+    pub fn forward(&self) -> bool {
+        matches!(self, Direction::Forward | Direction::Both)
+    }
+
+    pub fn backward(&self) -> bool {
+        matches!(self, Direction::Backward | Direction::Both)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExpandSettings {
+    pub depth: usize,
+    pub direction: Direction,
+    /// Coins below this value aren't followed.
+    pub min_value: u64,
+    /// Safety cap on the number of transactions added.
+    pub max_nodes: usize,
+}
+
+impl Default for ExpandSettings {
+    fn default() -> Self {
+        Self {
+            depth: 2,
+            direction: Direction::Forward,
+            min_value: 0,
+            max_nodes: 100,
+        }
+    }
+}
+
+impl ExpandSettings {
+    /// Returns whether the expansion should start.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        self.settings_ui(ui);
+        ui.button("Expand").clicked()
+    }
+
+    pub fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        Grid::new("ExpandSettings").num_columns(2).show(ui, |ui| {
+            ui.label("Hops:");
+            ui.add(DragValue::new(&mut self.depth).range(1..=10));
+            ui.end_row();
+
+            ui.label("Direction:");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.direction, Direction::Forward, "Spends");
+                ui.selectable_value(&mut self.direction, Direction::Backward, "Funding");
+                ui.selectable_value(&mut self.direction, Direction::Both, "Both");
+            });
+            ui.end_row();
+
+            ui.label("Min. coin value:");
+            ui.add(DragValue::new(&mut self.min_value).suffix(" sats"));
+            ui.end_row();
+
+            ui.label("Stop after:");
+            ui.add(
+                DragValue::new(&mut self.max_nodes)
+                    .range(1..=2000)
+                    .suffix(" transactions"),
+            );
+            ui.end_row();
+        });
+    }
+}
+
+/// A running expansion. Each hop is fetched as a batch, then the next hop is started from the
+/// transactions that were added.
+pub struct Expansion {
+    /// Distinguishes results of this expansion from those of a cancelled one.
+    pub id: u64,
+    pub origin: Txid,
+    pub settings: ExpandSettings,
+    pub depth: usize,
+    pub added: usize,
+    pub requests: Vec<RequestHandle>,
+}
+
+impl Expansion {
+    pub fn new(id: u64, origin: Txid, settings: ExpandSettings) -> Self {
+        Self {
+            id,
+            origin,
+            settings,
+            depth: 0,
+            added: 0,
+            requests: vec![],
+        }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.settings.max_nodes.saturating_sub(self.added)
+    }
+
+    /// The transactions to fetch for the next hop from [frontier], with their positions. Empty
+    /// once the expansion is done.
+    pub fn next_hop(&self, graph: &Graph, frontier: Vec<Txid>) -> Vec<(Txid, Pos2)> {
+        let mut next: Vec<(Txid, Pos2)> = vec![];
+        if self.depth < self.settings.depth {
+            for txid in frontier {
+                for (neighbor, pos) in
+                    graph.neighbors(txid, self.settings.direction, self.settings.min_value)
+                {
+                    if !next.iter().any(|(t, _)| *t == neighbor) {
+                        next.push((neighbor, pos));
+                    }
+                }
+            }
+        }
+        next.truncate(self.remaining());
+        next
+    }
+
+    pub fn cancel(&mut self, ctx: &Context) {
+        for request in self.requests.drain(..) {
+            request.cancel(ctx);
+        }
+    }
+
+    /// Returns whether the user cancelled.
+    pub fn status_ui(&self, ui: &mut egui::Ui) -> bool {
+        ui.spinner();
+        ui.label(format!(
+            "Expanding: hop {}/{}, {} added",
+            self.depth + 1,
+            self.settings.depth,
+            self.added
+        ))
+        .on_hover_text(format!("From {}", self.origin));
+        ui.small_button("Cancel").clicked()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::Pos2;
+
+    use super::{Direction, ExpandSettings, Expansion};
+    use crate::{
+        bitcoin::{AddressType, Output, Transaction, Txid},
+        graph::Graph,
+    };
+
+    fn txid(n: u8) -> Txid {
+        Txid::new(&format!("{:02x}", n).repeat(32)).unwrap()
+    }
+
+    /// Paying one coin to each of [spending].
+    fn tx(n: u8, spending: &[u8]) -> Transaction {
+        Transaction {
+            timestamp: 0,
+            txid: txid(n),
+            block_height: 1,
+            inputs: vec![],
+            outputs: spending
+                .iter()
+                .map(|&spending| Output {
+                    spending_txid: Some(txid(spending)),
+                    value: 1000,
+                    address: format!("out{}", spending),
+                    address_type: AddressType::P2WPKH,
+                    script_pubkey: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn hops() {
+        let mut graph = Graph::default();
+        graph.add_tx(txid(1), tx(1, &[3, 4]), Pos2::new(0.0, 0.0));
+        graph.add_tx(txid(2), tx(2, &[4, 5]), Pos2::new(100.0, 0.0));
+        let settings = ExpandSettings {
+            depth: 2,
+            direction: Direction::Forward,
+            min_value: 0,
+            max_nodes: 3,
+        };
+        let mut expansion = Expansion::new(1, txid(1), settings);
+        let hop = |expansion: &Expansion, frontier| -> Vec<Txid> {
+            expansion
+                .next_hop(&graph, frontier)
+                .into_iter()
+                .map(|(txid, _)| txid)
+                .collect()
+        };
+
+        // Each transaction once, even if several in the frontier spend into it.
+        assert_eq!(
+            hop(&expansion, vec![txid(1), txid(2)]),
+            vec![txid(3), txid(4), txid(5)]
+        );
+
+        // Up to the transaction limit.
+        expansion.added = 2;
+        assert_eq!(hop(&expansion, vec![txid(1), txid(2)]), vec![txid(3)]);
+
+        // Up to the number of hops.
+        expansion.added = 0;
+        expansion.depth = 2;
+        assert!(hop(&expansion, vec![txid(1)]).is_empty());
+    }
+}
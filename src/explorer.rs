@@ -0,0 +1,157 @@
+//! Links to transactions and addresses on a block explorer.
+
+use egui::{Context, Id, OpenUrl, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::{bitcoin::Txid, chain::Network, client::Client};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Explorer {
+    /// Or litecoinspace.org, which runs the same software, for Litecoin.
+    #[default]
+    Mempool,
+    Blockstream,
+    /// With `{kind}` (`tx` or `address`), `{id}` and `{network}` placeholders.
+    Custom {
+        template: String,
+    },
+}
+
+impl Explorer {
+    const DEFAULT_TEMPLATE: &'static str = "https://mempool.space/{kind}/{id}";
+
+    fn name(&self) -> &'static str {
+        match self {
+            Explorer::Mempool => "mempool.space",
+            Explorer::Blockstream => "blockstream.info",
+            Explorer::Custom { .. } => "Custom",
+        }
+    }
+
+    /// `None` if the explorer doesn't know the network.
+    fn url(&self, network: Network, kind: &str, id: &str) -> Option<String> {
+        let prefix = match (self, network) {
+            (Explorer::Mempool, Network::Mainnet) => "https://mempool.space",
+            (Explorer::Mempool, Network::Testnet) => "https://mempool.space/testnet",
+            (Explorer::Mempool, Network::Signet) => "https://mempool.space/signet",
+            (Explorer::Mempool, Network::Litecoin) => "https://litecoinspace.org",
+            (Explorer::Mempool, Network::LitecoinTestnet) => "https://litecoinspace.org/testnet",
+            (Explorer::Blockstream, Network::Mainnet) => "https://blockstream.info",
+            (Explorer::Blockstream, Network::Testnet) => "https://blockstream.info/testnet",
+            (Explorer::Custom { template }, _) => {
+                return Some(
+                    template
+                        .replace("{kind}", kind)
+                        .replace("{id}", id)
+                        .replace("{network}", network.name()),
+                );
+            }
+            _ => return None,
+        };
+        Some(format!("{}/{}/{}", prefix, kind, id))
+    }
+
+    pub fn tx_url(&self, network: Network, txid: Txid) -> Option<String> {
+        self.url(network, "tx", &txid.hex_string())
+    }
+
+    pub fn address_url(&self, network: Network, address: &str) -> Option<String> {
+        self.url(network, "address", address)
+    }
+
+    pub fn set(ctx: &Context, explorer: Explorer) {
+        ctx.data_mut(|d| d.insert_temp(Id::NULL, explorer));
+    }
+
+    pub fn get(ctx: &Context) -> Explorer {
+        ctx.data(|d| d.get_temp(Id::NULL)).unwrap_or_default()
+    }
+
+    /// Returns whether it changed.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let before = self.clone();
+        ui.horizontal(|ui| {
+            ui.label("Block explorer:");
+            ui.radio_value(self, Explorer::Mempool, Explorer::Mempool.name());
+            ui.radio_value(self, Explorer::Blockstream, Explorer::Blockstream.name());
+            let custom = match self {
+                Explorer::Custom { .. } => self.clone(),
+                _ => Explorer::Custom {
+                    template: Self::DEFAULT_TEMPLATE.to_string(),
+                },
+            };
+            let name = custom.name();
+            ui.radio_value(self, custom, name);
+        });
+        if let Explorer::Custom { template } = self {
+            ui.add(
+                TextEdit::singleline(template)
+                    .hint_text(Self::DEFAULT_TEMPLATE)
+                    .desired_width(300.0),
+            )
+            .on_hover_text("Placeholders: {kind} (tx or address), {id} and {network}.");
+        }
+        *self != before
+    }
+}
+
+/// Menu button opening [txid] in the configured explorer, for the workspace's network.
+pub fn tx_button(ui: &mut egui::Ui, txid: Txid) {
+    let url = Explorer::get(ui.ctx()).tx_url(Client::network(ui.ctx()), txid);
+    open_button(ui, url);
+}
+
+pub fn address_button(ui: &mut egui::Ui, address: &str) {
+    if address.is_empty() {
+        return;
+    }
+    let url = Explorer::get(ui.ctx()).address_url(Client::network(ui.ctx()), address);
+    open_button(ui, url);
+}
+
+fn open_button(ui: &mut egui::Ui, url: Option<String>) {
+    let response = ui.add_enabled(url.is_some(), egui::Button::new("Open in Explorer"));
+    let response = match &url {
+        Some(url) => response.on_hover_text(url),
+        None => response.on_disabled_hover_text("The explorer doesn't support this network."),
+    };
+    if response.clicked() {
+        if let Some(url) = url {
+            ui.ctx().open_url(OpenUrl::new_tab(url));
+        }
+        ui.close_menu();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Explorer;
+    use crate::{bitcoin::Txid, chain::Network};
+
+    #[test]
+    fn urls() {
+        let txid = Txid::new(&"ab".repeat(32)).unwrap();
+        assert_eq!(
+            Explorer::Mempool.tx_url(Network::Signet, txid),
+            Some(format!(
+                "https://mempool.space/signet/tx/{}",
+                "ab".repeat(32)
+            ))
+        );
+        assert_eq!(
+            Explorer::Blockstream.address_url(Network::Regtest, "x"),
+            None
+        );
+        assert_eq!(
+            Explorer::Mempool.address_url(Network::Litecoin, "ltc1q"),
+            Some("https://litecoinspace.org/address/ltc1q".to_string())
+        );
+        let custom = Explorer::Custom {
+            template: "https://example.com/{network}/{kind}/{id}".to_string(),
+        };
+        assert_eq!(
+            custom.address_url(Network::Testnet, "tb1q"),
+            Some("https://example.com/testnet/address/tb1q".to_string())
+        );
+    }
+}
@@ -1,9 +1,12 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
-use egui::Pos2;
+use egui::{ahash::HashSet, Pos2, Rect};
 use serde::{Deserialize, Serialize};
 
-use crate::{annotations, bitcoin::Txid, graph::Graph, layout::Layout, transform::Transform};
+use crate::{
+    annotations, bitcoin::Txid, encryption, graph::Graph, layout::Layout, prices::Fiat, tax,
+    transform::Transform, validation,
+};
 
 // Public interface
 
@@ -13,36 +16,135 @@ pub struct Workspace {
     pub layout: Layout0,
     pub transform: Transform0,
     pub transactions: Vec<Transaction>,
+    /// Free-form markdown.
+    pub notes: String,
+    /// Where the transaction data was fetched from. Missing in older exports.
+    pub source: Option<DataSource0>,
+    /// Fiat currency to show values in, e.g. `usd`.
+    pub currency: Option<String>,
+    /// For the capital gains report.
+    pub cost_basis_method: tax::Method,
+    /// Traced Ethereum addresses and their transfers. Missing in older exports.
+    pub ethereum: Option<Ethereum0>,
 }
 
+/// Stickies this far around the extracted transactions are taken along, in graph coordinates.
+const EXTRACT_MARGIN: f32 = 100.0;
+
 impl Workspace {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         graph: &Graph,
         annotations: &annotations::Annotations,
         layout: &Layout,
         transform: &Transform,
+        source: Option<DataSource0>,
+        currency: Option<String>,
+        cost_basis_method: tax::Method,
+        ethereum: Option<Ethereum0>,
     ) -> Self {
         Self {
             annotations: (*annotations).clone(),
             layout: layout.export(),
             transform: transform.export(),
             transactions: graph.export(),
+            notes: String::new(),
+            source,
+            currency,
+            cost_basis_method,
+            ethereum,
+        }
+    }
+
+    /// Only [txids] and the annotations attached to them or to [addresses], the addresses they
+    /// touch, with the positions unchanged. The Ethereum trace isn't part of the selection.
+    pub fn extract(&self, txids: &HashSet<Txid>, addresses: &HashSet<&str>) -> Self {
+        let transactions: Vec<Transaction> = self
+            .transactions
+            .iter()
+            .filter(|t| txids.contains(&t.txid))
+            .cloned()
+            .collect();
+        let area = Rect::from_points(&transactions.iter().map(|t| t.position).collect::<Vec<_>>())
+            .expand(EXTRACT_MARGIN);
+        Self {
+            annotations: self.annotations.extract(txids, addresses, area),
+            layout: self.layout.clone(),
+            transform: self.transform.clone(),
+            transactions,
+            notes: String::new(),
+            source: self.source.clone(),
+            currency: self.currency.clone(),
+            cost_basis_method: self.cost_basis_method,
+            ethereum: None,
         }
     }
+
+    /// Add the transactions, annotations, notes and Ethereum trace of [other]. Existing positions and
+    /// annotations are kept.
+    pub fn merge(&mut self, other: Workspace) {
+        for tx in other.transactions {
+            if !self.transactions.iter().any(|t| t.txid == tx.txid) {
+                self.transactions.push(tx);
+            }
+        }
+        self.annotations.merge(other.annotations);
+        if self.currency.is_none() {
+            self.currency = other.currency;
+        }
+        match (&mut self.ethereum, other.ethereum) {
+            (Some(ethereum), Some(other)) => ethereum.merge(other),
+            (ethereum @ None, other) => *ethereum = other,
+            _ => {}
+        }
+        let notes = other.notes.trim();
+        if !notes.is_empty() && !self.notes.contains(notes) {
+            self.notes.truncate(self.notes.trim_end().len());
+            if !self.notes.is_empty() {
+                self.notes.push_str("\n\n");
+            }
+            self.notes.push_str(notes);
+        }
+    }
+}
+
+impl Workspace {
+    fn from_workspace1(workspace1: Workspace1) -> Result<Self, String> {
+        Ok(Self {
+            annotations: annotations::Annotations::import(&workspace1.annotations)?,
+            layout: workspace1.layout,
+            transform: workspace1.transform,
+            transactions: workspace1
+                .transactions
+                .into_iter()
+                .map(Transaction::from_transaction1)
+                .collect(),
+            notes: workspace1.notes,
+            source: workspace1.source,
+            currency: workspace1.currency,
+            cost_basis_method: workspace1.cost_basis_method,
+            ethereum: workspace1.ethereum,
+        })
+    }
 }
 
 impl Serialize for Workspace {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        Workspace0 {
-            version: 0,
+        Workspace1 {
+            version: VERSION,
             annotations: self.annotations.export(),
             layout: self.layout.clone(),
             transform: self.transform.clone(),
             transactions: self
                 .transactions
                 .iter()
-                .map(Transaction::to_transaction0)
+                .map(Transaction::to_transaction1)
                 .collect(),
+            notes: self.notes.clone(),
+            source: self.source.clone(),
+            currency: self.currency.clone(),
+            cost_basis_method: self.cost_basis_method,
+            ethereum: self.ethereum.clone(),
         }
         .serialize(serializer)
     }
@@ -50,21 +152,146 @@ impl Serialize for Workspace {
 
 impl<'de> Deserialize<'de> for Workspace {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let workspace0 = Workspace0::deserialize(deserializer)?;
-        Ok(Self {
-            annotations: annotations::Annotations::import(&workspace0.annotations)
-                .map_err(serde::de::Error::custom)?,
-            layout: workspace0.layout,
-            transform: workspace0.transform,
-            transactions: workspace0
-                .transactions
-                .into_iter()
-                .map(Transaction::from_transaction0)
-                .collect(),
-        })
+        let (workspace1, _) = Versioned::deserialize(deserializer)?.migrate();
+        Workspace::from_workspace1(workspace1).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Version of the workspace file format that's written. Older versions are migrated when read.
+pub const VERSION: u32 = 1;
+
+/// A workspace read from an export, and how it was brought up to the current format.
+pub struct Imported {
+    pub workspace: Workspace,
+    /// Format version of the export.
+    pub version: u32,
+    /// What each migration did, oldest first.
+    pub migrations: Vec<&'static str>,
+}
+
+/// Reads an exported workspace of any supported format version, given as JSON or in the compact
+/// encoding, also when that's pasted as the fragment of a URL.
+pub fn import(text: &str) -> Result<Imported, String> {
+    let json = decode(text)?;
+    let Header { version } = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    let versioned = match version {
+        0 => serde_json::from_str(&json).map(Versioned::V0),
+        1 => serde_json::from_str(&json).map(Versioned::V1),
+        _ => return Err(unsupported_version(version)),
+    }
+    .map_err(|e| e.to_string())?;
+    let (workspace1, migrations) = versioned.migrate();
+    Ok(Imported {
+        workspace: Workspace::from_workspace1(workspace1)?,
+        version,
+        migrations,
+    })
+}
+
+/// Everything that keeps [text] from being imported, with where it is in the document.
+pub fn validate(text: &str) -> Vec<validation::Problem> {
+    let json = match decode(text) {
+        Ok(json) => json,
+        Err(e) => return vec![validation::Problem::document(e)],
+    };
+    let problems = validation::problems(&json, |json| {
+        let Header { version } = serde_json::from_str(json)?;
+        match version {
+            0 => serde_json::from_str::<Workspace0>(json).map(drop),
+            1 => serde_json::from_str::<Workspace1>(json).map(drop),
+            _ => Err(serde::de::Error::custom(unsupported_version(version))),
+        }
+    });
+    if problems.is_empty() {
+        // Serde is happy, but the annotations may still have malformed keys.
+        if let Err(e) = import(&json) {
+            return vec![validation::Problem::document(e)];
+        }
+    }
+    problems
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+/// The JSON in [text], which may be in the compact encoding.
+fn decode(text: &str) -> Result<Cow<'_, str>, String> {
+    let text = text.trim();
+    if is_encrypted(text) {
+        Err("The export is encrypted. Enter the passphrase to import it.".to_string())
+    } else if text.starts_with('{') {
+        Ok(Cow::Borrowed(text))
+    } else {
+        let fragment = text.rsplit_once('#').map_or(text, |(_, fragment)| fragment);
+        decode_compact(fragment).map(Cow::Owned)
+    }
+}
+
+/// Starts the compact encoding so that it can be told apart from other text.
+const COMPACT_PREFIX: &str = "txg:";
+
+/// Decompressing stops here, so that a short string can't fill up the memory.
+const COMPACT_MAX_SIZE: usize = 64 * 1024 * 1024;
+
+impl Workspace {
+    /// Deflated JSON in URL-safe base64, short enough to paste in a chat or to put in the
+    /// fragment of a URL. [import] reads it back.
+    pub fn to_compact(&self) -> String {
+        format!(
+            "{COMPACT_PREFIX}{}",
+            base64::encode_config(self.compress(), base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    /// Deflated JSON encrypted with [passphrase], in a small JSON document. [decrypt] reads it
+    /// back.
+    pub fn to_encrypted(&self, passphrase: &str) -> String {
+        let encrypted = Encrypted0 {
+            encrypted: encryption::seal(&self.compress(), passphrase),
+        };
+        serde_json::to_string_pretty(&encrypted).unwrap()
+    }
+
+    fn compress(&self) -> Vec<u8> {
+        let json = serde_json::to_vec(self).unwrap();
+        miniz_oxide::deflate::compress_to_vec(&json, 10)
     }
 }
 
+fn decode_compact(text: &str) -> Result<String, String> {
+    let encoded = text
+        .strip_prefix(COMPACT_PREFIX)
+        .ok_or("Neither JSON nor a compact export.")?;
+    let compressed =
+        base64::decode_config(encoded, base64::URL_SAFE_NO_PAD).map_err(|e| e.to_string())?;
+    decompress(&compressed)
+}
+
+fn decompress(compressed: &[u8]) -> Result<String, String> {
+    let json = miniz_oxide::inflate::decompress_to_vec_with_limit(compressed, COMPACT_MAX_SIZE)
+        .map_err(|e| e.to_string())?;
+    String::from_utf8(json).map_err(|e| e.to_string())
+}
+
+/// Written instead of the workspace when exporting with a passphrase.
+#[derive(Serialize, Deserialize)]
+struct Encrypted0 {
+    encrypted: encryption::Sealed,
+}
+
+/// Whether [text] is an export made with [Workspace::to_encrypted].
+pub fn is_encrypted(text: &str) -> bool {
+    text.trim_start().starts_with('{') && serde_json::from_str::<Encrypted0>(text).is_ok()
+}
+
+/// The JSON inside an export made with [Workspace::to_encrypted], for [import].
+pub fn decrypt(text: &str, passphrase: &str) -> Result<String, String> {
+    let Encrypted0 { encrypted } = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    decompress(&encrypted.open(passphrase)?)
+}
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Transaction {
     pub txid: Txid,
@@ -76,45 +303,218 @@ impl Transaction {
         Self { txid, position }
     }
 
-    fn from_transaction0(t: Transaction0) -> Self {
+    fn from_transaction1(t: Transaction1) -> Self {
         Self {
             txid: t.txid,
-            position: t.position.to_pos2(),
+            position: Pos2::new(t.position.x, t.position.y),
         }
     }
 
-    fn to_transaction0(&self) -> Transaction0 {
-        Transaction0 {
+    fn to_transaction1(&self) -> Transaction1 {
+        Transaction1 {
             txid: self.txid,
-            position: Position0::from_pos2(self.position),
+            position: Point0 {
+                x: self.position.x,
+                y: self.position.y,
+            },
         }
     }
 }
 
-// Version 0 of the workspace file format
+// Versions of the workspace file format. Each is read in its own shape, then migrated one
+// version at a time to the current one.
 
-fn validate_version<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
-    let version = u32::deserialize(deserializer)?;
-    if version == 0 {
-        Ok(version)
-    } else {
-        Err(serde::de::Error::custom(format!(
-            "Unsupported version: {}",
-            version
-        )))
+fn unsupported_version(version: u32) -> String {
+    format!(
+        "Unsupported version: {}. This version of txgraph reads versions up to {}.",
+        version, VERSION
+    )
+}
+
+enum Versioned {
+    V0(Workspace0),
+    V1(Workspace1),
+}
+
+impl Versioned {
+    /// Brings the workspace to the current version, with what each migration did.
+    fn migrate(self) -> (Workspace1, Vec<&'static str>) {
+        let mut migrations = vec![];
+        let mut versioned = self;
+        loop {
+            versioned = match versioned {
+                Versioned::V0(workspace0) => {
+                    migrations.push(Workspace0::MIGRATION);
+                    Versioned::V1(workspace0.migrate())
+                }
+                Versioned::V1(workspace1) => return (workspace1, migrations),
+            };
+        }
+    }
+}
+
+impl Versioned {
+    fn read<'de, D: serde::Deserializer<'de>>(
+        version: u32,
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        match version {
+            0 => Workspace0::deserialize(deserializer).map(Versioned::V0),
+            1 => Workspace1::deserialize(deserializer).map(Versioned::V1),
+            _ => Err(serde::de::Error::custom(unsupported_version(version))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Versioned {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Key(String);
+
+        impl<'de> Deserialize<'de> for Key {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct KeyVisitor;
+
+                impl serde::de::Visitor<'_> for KeyVisitor {
+                    type Value = Key;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        f.write_str("a field name")
+                    }
+
+                    fn visit_str<E: serde::de::Error>(self, key: &str) -> Result<Key, E> {
+                        Ok(Key(key.to_string()))
+                    }
+                }
+
+                deserializer.deserialize_identifier(KeyVisitor)
+            }
+        }
+
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Versioned;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("a workspace")
+            }
+
+            // The rest is read in the shape of the version. Everything txgraph writes starts
+            // with it, so that's read as it comes, also from the app's RON storage. Otherwise,
+            // e.g. in hand-edited JSON, the fields are kept until the version is known.
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Versioned, A::Error> {
+                let mut fields = serde_json::Map::new();
+                while let Some(Key(key)) = map.next_key()? {
+                    if key == "version" && fields.is_empty() {
+                        let version: u32 = map.next_value()?;
+                        let rest = serde::de::value::MapAccessDeserializer::new(map);
+                        return Versioned::read(version, rest);
+                    }
+                    fields.insert(key, map.next_value()?);
+                }
+                let fields = serde_json::Value::Object(fields);
+                let Header { version } =
+                    Header::deserialize(&fields).map_err(serde::de::Error::custom)?;
+                Versioned::read(version, fields).map_err(serde::de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_struct("Workspace", &["version"], Visitor)
     }
 }
 
 #[derive(Serialize, Deserialize)]
-struct Workspace0 {
-    #[serde(deserialize_with = "validate_version")]
+struct Workspace1 {
+    /// Read by [Versioned].
+    #[serde(skip_deserializing)]
     version: u32,
+    annotations: Annotations0,
+    #[serde(default)]
+    layout: Layout0,
+    #[serde(default)]
+    transform: Transform0,
+    transactions: Vec<Transaction1>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    notes: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<DataSource0>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    currency: Option<String>,
+    #[serde(default, skip_serializing_if = "tax::Method::is_default")]
+    cost_basis_method: tax::Method,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ethereum: Option<Ethereum0>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Transaction1 {
+    txid: Txid,
+    position: Point0,
+}
+
+/// Positions rounded to whole units.
+#[derive(Deserialize)]
+struct Workspace0 {
     annotations: Annotations0,
     #[serde(default)]
     layout: Layout0,
     #[serde(default)]
     transform: Transform0,
     transactions: Vec<Transaction0>,
+    #[serde(default)]
+    notes: String,
+    #[serde(default)]
+    source: Option<DataSource0>,
+    #[serde(default)]
+    currency: Option<String>,
+    #[serde(default)]
+    cost_basis_method: tax::Method,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct DataSource0 {
+    pub api_base: String,
+    pub network: String,
+}
+
+/// See [crate::ethereum::EthereumView].
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct Ethereum0 {
+    /// The address shown, lowercase.
+    pub address: String,
+    /// Whose transfers are in the flows, in the order they were traced.
+    pub traced: Vec<String>,
+    pub transfers: Vec<Transfer0>,
+}
+
+impl Ethereum0 {
+    fn merge(&mut self, other: Ethereum0) {
+        for address in other.traced {
+            if !self.traced.contains(&address) {
+                self.traced.push(address);
+            }
+        }
+        for transfer in other.transfers {
+            if !self.transfers.contains(&transfer) {
+                self.transfers.push(transfer);
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct Transfer0 {
+    pub hash: String,
+    pub timestamp: i64,
+    pub from: String,
+    pub to: String,
+    /// Decimal, since it may not fit into a JSON number.
+    pub value: String,
+    pub asset: String,
+    pub decimals: u32,
 }
 
 // This is public because it's used in the conversion code in annotations.rs
@@ -124,6 +524,98 @@ pub struct Annotations0 {
     pub tx_label: HashMap<String, String>,
     pub coin_color: HashMap<String, [u8; 3]>,
     pub coin_label: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub address_label: HashMap<String, String>,
+    /// Whether an output is change, where the guess was corrected.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub change: HashMap<String, bool>,
+    /// What was paid for the bitcoin received in a transaction, by txid.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub cost_basis: HashMap<String, Fiat>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<Group0>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stickies: Vec<Sticky0>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<Category0>,
+    /// Category names by txid.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tx_category: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub coin_category: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub address_category: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<Bookmark0>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Group0 {
+    pub name: String,
+    pub txids: Vec<String>,
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Category0 {
+    pub name: String,
+    pub color: [u8; 3],
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+// Public so that conversion code in bookmark.rs can use it.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Bookmark0 {
+    pub name: String,
+    pub center: Point0,
+    pub zoom: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filters: Option<Filters0>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Filters0 {
+    pub hidden_categories: Vec<String>,
+    pub edge_filter: EdgeFilter0,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EdgeFilter0 {
+    pub active: bool,
+    /// In sats, or in the workspace currency if [Self::fiat].
+    pub threshold: f64,
+    pub fiat: bool,
+    pub hide: bool,
+}
+
+// Public so that conversion code in sticky.rs can use it.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Sticky0 {
+    pub kind: StickyKind0,
+    pub start: Point0,
+    pub end: Point0,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+    pub color: [u8; 3],
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum StickyKind0 {
+    Note,
+    Frame,
+    Arrow,
+}
+
+/// Graph coordinates.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct Point0 {
+    pub x: f32,
+    pub y: f32,
 }
 
 // Public so that conversion code in layout.rs can use it.
@@ -165,34 +657,55 @@ impl Default for Transform0 {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+impl Workspace0 {
+    const MIGRATION: &'static str = "Positions keep their fractional part.";
+
+    fn migrate(self) -> Workspace1 {
+        Workspace1 {
+            version: 1,
+            annotations: self.annotations,
+            layout: self.layout,
+            transform: self.transform,
+            transactions: self
+                .transactions
+                .into_iter()
+                .map(|t| Transaction1 {
+                    txid: t.txid,
+                    position: Point0 {
+                        x: t.position.x as f32,
+                        y: t.position.y as f32,
+                    },
+                })
+                .collect(),
+            notes: self.notes,
+            source: self.source,
+            currency: self.currency,
+            cost_basis_method: self.cost_basis_method,
+            ethereum: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
 struct Transaction0 {
     txid: Txid,
     position: Position0,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Deserialize)]
 struct Position0 {
     x: i32,
     y: i32,
 }
 
-impl Position0 {
-    fn from_pos2(pos: Pos2) -> Self {
-        Self {
-            x: pos.x.round() as i32,
-            y: pos.y.round() as i32,
-        }
-    }
-
-    fn to_pos2(&self) -> Pos2 {
-        Pos2::new(self.x as f32, self.y as f32)
-    }
-}
-
 #[cfg(test)]
 mod test {
     use self::annotations::Annotations;
+    use crate::{
+        bookmark::{Bookmark, Filters},
+        layout::EdgeFilter,
+        sticky::Sticky,
+    };
     use egui::Color32;
 
     use super::*;
@@ -281,6 +794,11 @@ mod test {
                     position: Pos2::new(755.0, 242.0),
                 },
             ],
+            notes: String::new(),
+            source: None,
+            currency: None,
+            cost_basis_method: Default::default(),
+            ethereum: None,
         }
     }
 
@@ -290,6 +808,254 @@ mod test {
         assert_eq!(workspace_expected(), actual);
     }
 
+    #[test]
+    fn test_import_migrates() {
+        let imported = import(WORKSPACE_FIXTURE_0).unwrap();
+        assert_eq!(imported.version, 0);
+        assert_eq!(imported.migrations, vec![Workspace0::MIGRATION]);
+        assert_eq!(imported.workspace, workspace_expected());
+
+        let string = serde_json::to_string(&imported.workspace).unwrap();
+        assert!(string.starts_with(r#"{"version":1,"#));
+        assert!(string.contains(r#""position":{"x":711.0,"y":351.0}"#));
+        let imported = import(&string).unwrap();
+        assert_eq!(imported.version, VERSION);
+        assert!(imported.migrations.is_empty());
+    }
+
+    #[test]
+    fn test_import_version_anywhere() {
+        let json = r#"
+            {
+                "annotations": {"tx_color": {}, "tx_label": {}, "coin_color": {}, "coin_label": {}},
+                "transactions": [{
+                    "txid": "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+                    "position": {"x": 1.5, "y": -2}
+                }],
+                "version": 1
+            }
+        "#;
+        let imported = import(json).unwrap();
+        assert_eq!(
+            imported.workspace.transactions[0].position,
+            Pos2::new(1.5, -2.0)
+        );
+        // Also when restored as part of something else, e.g. a bundle of workspaces.
+        let workspace: Workspace = serde_json::from_str(json).unwrap();
+        assert_eq!(workspace, imported.workspace);
+
+        let error = import(r#"{"version": 7}"#).err().unwrap();
+        assert!(error.starts_with("Unsupported version: 7."));
+    }
+
+    #[test]
+    fn test_import_compact() {
+        let expected = workspace_expected();
+        let compact = expected.to_compact();
+        assert!(compact.starts_with(COMPACT_PREFIX));
+        assert!(compact.len() < serde_json::to_string(&expected).unwrap().len());
+
+        assert_eq!(import(&compact).unwrap().workspace, expected);
+        let link = format!("https://example.com/#{compact}\n");
+        assert_eq!(import(&link).unwrap().workspace, expected);
+
+        assert!(import("txg:not compressed").is_err());
+        assert!(import("hello").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let json = r#"
+            {
+                "version": 1,
+                "annotations": {"tx_color": {}, "tx_label": {"abc": 5}, "coin_color": {}, "coin_label": {}},
+                "transactions": [
+                    {
+                        "txid": "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+                        "position": {"x": 1, "y": "two"}
+                    },
+                    {"txid": "nope", "position": {"x": 1, "y": 2}}
+                ]
+            }
+        "#;
+        let problems = validate(json);
+        let paths: Vec<&str> = problems.iter().map(|p| p.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "$.annotations.tx_label.abc",
+                "$.transactions[0].position.y",
+                "$.transactions[1]"
+            ]
+        );
+        assert_eq!(problems[0].expected.as_deref(), Some("a string"));
+        assert_eq!(problems[1].value.as_deref(), Some(r#""two""#));
+        assert!(problems[2].message.starts_with(r#"invalid txid "nope""#));
+
+        assert!(validate(WORKSPACE_FIXTURE_0).is_empty());
+        assert_eq!(validate(r#"{"version": 7}"#).len(), 1);
+    }
+
+    #[test]
+    fn test_import_encrypted() {
+        let expected = workspace_expected();
+        let encrypted = expected.to_encrypted("passphrase");
+        assert!(is_encrypted(&encrypted));
+        assert!(!is_encrypted(WORKSPACE_FIXTURE_0));
+        assert!(import(&encrypted).is_err());
+        assert_eq!(validate(&encrypted).len(), 1);
+
+        let json = decrypt(&encrypted, "passphrase").unwrap();
+        assert_eq!(import(&json).unwrap().workspace, expected);
+        assert!(decrypt(&encrypted, "wrong").is_err());
+    }
+
+    #[test]
+    fn test_workspace_extract() {
+        let mut workspace = workspace_expected();
+        let [other, txid] = [0, 1].map(|i| workspace.transactions[i].txid);
+        workspace
+            .annotations
+            .set_tx_label(other, "Other".to_string());
+        workspace.annotations.add_to_group(other, None);
+        workspace.annotations.add_to_group(txid, Some(0));
+        let stickies = workspace.annotations.stickies_mut();
+        stickies.push(Sticky::new(
+            crate::sticky::Kind::Arrow,
+            Pos2::new(680.0, 230.0),
+        ));
+        stickies.push(Sticky::new(crate::sticky::Kind::Note, Pos2::new(0.0, 0.0)));
+        workspace.annotations.bookmarks_mut().push(Bookmark {
+            name: "Overview".to_string(),
+            center: Pos2::new(0.0, 0.0),
+            zoom: 1.0,
+            filters: None,
+        });
+        let mut annotations0 = workspace.annotations.export();
+        for address in ["bc1qtouched", "bc1qelsewhere"] {
+            annotations0
+                .address_label
+                .insert(address.to_string(), "Label".to_string());
+            annotations0
+                .address_category
+                .insert(address.to_string(), "Exchange".to_string());
+        }
+        workspace.annotations = Annotations::import(&annotations0).unwrap();
+        workspace
+            .annotations
+            .add_address_to_group("bc1qtouched", Some(0));
+        workspace
+            .annotations
+            .add_address_to_group("bc1qelsewhere", Some(0));
+
+        let addresses = ["bc1qtouched"].into_iter().collect();
+        let extracted = workspace.extract(&[txid].into_iter().collect(), &addresses);
+        assert_eq!(extracted.transactions, workspace.transactions[1..]);
+        let annotations = &extracted.annotations;
+        assert_eq!(annotations.tx_label(txid), Some("First Tx".to_string()));
+        assert_eq!(annotations.tx_label(other), None);
+        assert_eq!(
+            annotations.coin_label((txid, 0)),
+            Some("Output".to_string())
+        );
+        assert_eq!(annotations.groups()[0].txids, vec![txid]);
+        assert_eq!(annotations.groups()[0].addresses, vec!["bc1qtouched"]);
+        let annotations0 = annotations.export();
+        assert_eq!(annotations0.stickies.len(), 1);
+        // Nothing about addresses the selection doesn't touch.
+        assert_eq!(
+            annotations0.address_label.keys().collect::<Vec<_>>(),
+            vec!["bc1qtouched"]
+        );
+        assert_eq!(
+            annotations0.address_category.keys().collect::<Vec<_>>(),
+            vec!["bc1qtouched"]
+        );
+        assert!(annotations0.bookmarks.is_empty());
+    }
+
+    #[test]
+    fn test_groups_roundtrip() {
+        let mut expected = workspace_expected();
+        let txid = expected.transactions[0].txid;
+        expected.annotations.add_to_group(txid, None);
+        expected
+            .annotations
+            .add_address_to_group("bc1qhot", Some(0));
+        expected.annotations.set_collapsed(0, true);
+        let string = serde_json::to_string(&expected).unwrap();
+        let actual: Workspace = serde_json::from_str(&string).unwrap();
+        assert_eq!(actual.annotations.groups()[0].addresses, vec!["bc1qhot"]);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_stickies_roundtrip() {
+        let mut expected = workspace_expected();
+        let mut sticky = Sticky::new(crate::sticky::Kind::Frame, Pos2::new(10.0, -20.5));
+        sticky.text = "Funds split here".to_string();
+        expected.annotations.stickies_mut().push(sticky);
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(string.contains(r#""kind":"frame","start":{"x":10.0,"y":-20.5}"#));
+        let actual = serde_json::from_str(&string).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_bookmarks_roundtrip() {
+        let mut expected = workspace_expected();
+        expected.annotations.bookmarks_mut().push(Bookmark {
+            name: "Overview".to_string(),
+            center: Pos2::new(120.5, -40.0),
+            zoom: 1.5,
+            filters: Some(Filters {
+                hidden_categories: vec!["Exchange".to_string()],
+                edge_filter: EdgeFilter {
+                    active: true,
+                    threshold: 10_000.0,
+                    ..Default::default()
+                },
+            }),
+        });
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(string.contains(r#""center":{"x":120.5,"y":-40.0},"zoom":1.5"#));
+        let actual = serde_json::from_str(&string).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_ethereum_roundtrip() {
+        let transfer = |hash: &str| Transfer0 {
+            hash: hash.to_string(),
+            timestamp: 1_700_000_000,
+            from: "0xaa".to_string(),
+            to: "0xbb".to_string(),
+            value: "340282366920938463463374607431768211455".to_string(),
+            asset: "ETH".to_string(),
+            decimals: 18,
+        };
+        let mut expected = workspace_expected();
+        expected.ethereum = Some(Ethereum0 {
+            address: "0xaa".to_string(),
+            traced: vec!["0xaa".to_string()],
+            transfers: vec![transfer("0x01")],
+        });
+        let string = serde_json::to_string(&expected).unwrap();
+        assert_eq!(import(&string).unwrap().workspace, expected);
+
+        let mut other = workspace_expected();
+        other.ethereum = Some(Ethereum0 {
+            address: "0xbb".to_string(),
+            traced: vec!["0xbb".to_string(), "0xaa".to_string()],
+            transfers: vec![transfer("0x01"), transfer("0x02")],
+        });
+        expected.merge(other);
+        let ethereum = expected.ethereum.unwrap();
+        assert_eq!(ethereum.address, "0xaa");
+        assert_eq!(ethereum.traced, vec!["0xaa", "0xbb"]);
+        assert_eq!(ethereum.transfers.len(), 2);
+    }
+
     #[test]
     fn test_workspace_roundtrip() {
         let expected = workspace_expected();
@@ -0,0 +1,114 @@
+//! Toggles for experimental parts of the app, so they can ship before they're finished.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Feature {
+    /// The "Entities" menu: report cards, the flow matrix and the Sankey diagram.
+    EntityAnalysis,
+    /// The "Import" menu for GraphML and Maltego files.
+    ForeignImport,
+    /// Transfers of Ethereum addresses, next to the Bitcoin graph.
+    Ethereum,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 3] = [
+        Feature::EntityAnalysis,
+        Feature::ForeignImport,
+        Feature::Ethereum,
+    ];
+
+    /// Stable name used for persisting.
+    fn key(&self) -> &'static str {
+        match self {
+            Feature::EntityAnalysis => "entity_analysis",
+            Feature::ForeignImport => "foreign_import",
+            Feature::Ethereum => "ethereum",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Feature::EntityAnalysis => "Entity analysis",
+            Feature::ForeignImport => "Import from other tools",
+            Feature::Ethereum => "Ethereum tracing",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Feature::EntityAnalysis => {
+                "Report cards, flow matrices and Sankey diagrams for labelled entities."
+            }
+            Feature::ForeignImport => "Load transactions from GraphML and Maltego CSV files.",
+            Feature::Ethereum => "Follow ether and token transfers of Ethereum addresses.",
+        }
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        match self {
+            Feature::EntityAnalysis => true,
+            Feature::ForeignImport => true,
+            Feature::Ethereum => false,
+        }
+    }
+}
+
+/// Only the flags the user changed are stored, by name, so that flags can be added and removed
+/// without breaking the saved settings.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Features(BTreeMap<String, bool>);
+
+impl Features {
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        self.0
+            .get(feature.key())
+            .copied()
+            .unwrap_or(feature.enabled_by_default())
+    }
+
+    fn set(&mut self, feature: Feature, enabled: bool) {
+        if enabled == feature.enabled_by_default() {
+            self.0.remove(feature.key());
+        } else {
+            self.0.insert(feature.key().to_string(), enabled);
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        for feature in Feature::ALL {
+            let mut enabled = self.is_enabled(feature);
+            if ui
+                .checkbox(&mut enabled, feature.name())
+                .on_hover_text(feature.description())
+                .changed()
+            {
+                self.set(feature, enabled);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Feature, Features};
+
+    #[test]
+    fn overrides() {
+        let mut features: Features =
+            serde_json::from_str(r#"{"ethereum": true, "removed_flag": true}"#).unwrap();
+        assert!(features.is_enabled(Feature::Ethereum));
+        assert!(features.is_enabled(Feature::EntityAnalysis));
+
+        features.set(Feature::Ethereum, false);
+        features.set(Feature::EntityAnalysis, false);
+        assert_eq!(
+            serde_json::to_string(&features).unwrap(),
+            r#"{"entity_analysis":false,"removed_flag":true}"#
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Fees paid by the transactions in the graph.
+
+use egui::{Grid, Pos2, ScrollArea};
+
+use crate::{
+    annotations::Annotations,
+    bitcoin::{Sats, Txid},
+    widgets::UiExt,
+};
+
+pub struct TxFee {
+    pub txid: Txid,
+    pub fees: u64,
+    /// `None` if unknown.
+    pub vsize: Option<u64>,
+}
+
+impl TxFee {
+    /// In sat/vB.
+    pub fn rate(&self) -> Option<f64> {
+        self.vsize.map(|vsize| self.fees as f64 / vsize as f64)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FeeSummary {
+    pub total: u64,
+    /// Of the transactions with known size.
+    pub total_vsize: u64,
+    pub median_rate: Option<f64>,
+    /// Transactions with known size, highest fee rate first.
+    pub by_rate: Vec<(Txid, u64, u64)>,
+}
+
+impl FeeSummary {
+    /// Coinbase transactions don't pay fees and are left out.
+    pub fn new(txs: Vec<TxFee>) -> Self {
+        let total = txs.iter().map(|tx| tx.fees).sum();
+        let mut by_rate: Vec<_> = txs
+            .iter()
+            .filter_map(|tx| Some((tx.txid, tx.fees, tx.vsize?)))
+            .collect();
+        let rate = |(_, fees, vsize): &(Txid, u64, u64)| *fees as f64 / *vsize as f64;
+        by_rate.sort_by(|a, b| rate(b).total_cmp(&rate(a)).then(a.0.cmp(&b.0)));
+
+        let median_rate = match by_rate.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(rate(&by_rate[n / 2])),
+            n => Some((rate(&by_rate[n / 2 - 1]) + rate(&by_rate[n / 2])) / 2.0),
+        };
+
+        Self {
+            total,
+            total_vsize: by_rate.iter().map(|(_, _, vsize)| vsize).sum(),
+            median_rate,
+            by_rate,
+        }
+    }
+
+    pub fn ui(
+        &self,
+        ui: &mut egui::Ui,
+        annotations: &Annotations,
+        load_tx: impl Fn(Txid, Option<Pos2>),
+    ) {
+        ui.bold("Fees:");
+        Grid::new("FeeSummary").num_columns(2).show(ui, |ui| {
+            ui.label("Total paid:");
+            ui.label(format!("{} sats", Sats(self.total)));
+            ui.end_row();
+
+            ui.label("Median rate:");
+            ui.label(
+                self.median_rate
+                    .map_or("unknown".to_string(), |r| format!("{:.1} sat/vB", r)),
+            );
+            ui.end_row();
+
+            ui.label("Size:");
+            ui.label(format!(
+                "{} vB in {} transaction(s)",
+                self.total_vsize,
+                self.by_rate.len()
+            ));
+            ui.end_row();
+        });
+
+        if self.by_rate.is_empty() {
+            return;
+        }
+        ui.add_space(3.0);
+        ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+            Grid::new("FeeRates")
+                .num_columns(4)
+                .striped(true)
+                .show(ui, |ui| {
+                    for title in ["sat/vB", "Fee", "vB", "Transaction"] {
+                        ui.bold(title);
+                    }
+                    ui.end_row();
+
+                    for (txid, fees, vsize) in &self.by_rate {
+                        ui.label(format!("{:.1}", *fees as f64 / *vsize as f64));
+                        ui.label(Sats(*fees).to_string());
+                        ui.label(vsize.to_string());
+                        let name = annotations
+                            .tx_label(*txid)
+                            .unwrap_or_else(|| txid.hex_string()[..16].to_string());
+                        if ui.button(name).on_hover_text("Go to transaction").clicked() {
+                            load_tx(*txid, None);
+                            ui.close_menu();
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FeeSummary, TxFee};
+    use crate::bitcoin::Txid;
+
+    #[test]
+    fn summary() {
+        let txid = |b: &str| Txid::new(&b.repeat(32)).unwrap();
+        let summary = FeeSummary::new(vec![
+            TxFee {
+                txid: txid("11"),
+                fees: 1000,
+                vsize: Some(200),
+            },
+            TxFee {
+                txid: txid("22"),
+                fees: 500,
+                vsize: None,
+            },
+            TxFee {
+                txid: txid("33"),
+                fees: 3000,
+                vsize: Some(100),
+            },
+        ]);
+        assert_eq!(summary.total, 4500);
+        assert_eq!(summary.total_vsize, 300);
+        assert_eq!(summary.median_rate, Some(17.5));
+        assert_eq!(
+            summary.by_rate,
+            vec![(txid("33"), 3000, 100), (txid("11"), 1000, 200)]
+        );
+    }
+}
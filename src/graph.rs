@@ -1,22 +1,48 @@
-use std::{collections::HashMap, fmt::Write, sync::mpsc::Sender};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::Sender,
+    },
+};
 
 use egui::{
-    ahash::HashSet, text::LayoutJob, Align, Color32, CursorIcon, FontId, Mesh, Pos2, Rect,
-    RichText, Rounding, Sense, Stroke, TextFormat, Vec2,
+    ahash::HashSet, text::LayoutJob, Align, Color32, CursorIcon, FontId, Mesh, Painter, Pos2, Rect,
+    RichText, Rounding, Sense, Shape, Stroke, TextFormat, Vec2,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    annotations::Annotations,
+    annotations::{Annotations, Stamps},
     app::Update,
-    bezier::Edge,
-    bitcoin::{AddressType, AmountComponents, Sats, SatsDisplay, Transaction, Txid},
-    export,
+    arrange::{Algorithm, Alignment, Animation},
+    balance::Change,
+    bezier::{Cubic, Edge},
+    bitcoin::{AddressType, AmountComponents, Sats, Transaction, Txid},
+    change::{self, Reason},
+    cluster::Clusters,
+    coin_selection::Coin,
+    coinjoin,
+    compare::TimeWindow,
+    csv, dot,
+    entity::{Flow, Transfer},
+    expand::Direction,
+    explorer, export,
+    fees::{FeeSummary, TxFee},
     layout::{Layout, Scale},
     loading::Loading,
+    omni::OmniTransfer,
     platform::inner::push_history_state,
+    prices::Prices,
+    script,
+    settings::ExportUnit,
+    stats::GraphStats,
+    sticky::Sticky,
     style::{self, Style},
+    svg::{Segment, Svg},
     transform::Transform,
+    utxos::Utxo,
 };
 
 #[derive(Serialize, Deserialize, Default)]
@@ -24,6 +50,33 @@ pub struct Graph {
     nodes: HashMap<Txid, DrawableNode>,
     edges: Vec<DrawableEdge>,
     selected_node: Option<Txid>,
+    /// For confirmation counts.
+    #[serde(skip)]
+    tip_height: Option<u32>,
+    #[serde(skip)]
+    animation: Option<Animation>,
+    /// Transaction to highlight briefly, e.g. a search result, with the start time as in
+    /// [egui::InputState::time].
+    #[serde(skip)]
+    flash: Option<(Txid, f64)>,
+    /// Output of the selected transaction that Tab moved to.
+    #[serde(skip)]
+    focused_output: Option<usize>,
+    /// Found by the path finder, from source to destination.
+    #[serde(skip)]
+    path: Vec<Txid>,
+    /// Shift-clicked or rubber-banded transactions, for bulk actions.
+    #[serde(skip)]
+    multi_selection: HashSet<Txid>,
+    /// Changes when transactions are added or removed, for caching what's computed from them.
+    #[serde(skip, default = "next_revision")]
+    revision: u64,
+}
+
+/// Unique across graphs, so that a cache can't mistake one graph for another.
+fn next_revision() -> u64 {
+    static REVISION: AtomicU64 = AtomicU64::new(1);
+    REVISION.fetch_add(1, Ordering::Relaxed)
 }
 
 #[derive(Serialize, Deserialize)]
@@ -35,12 +88,32 @@ pub struct DrawableNode {
     size: f32,
     tx_value: u64,
     tx_timestamp: String,
+    #[serde(default)]
+    unix_timestamp: i64,
     block_height: u32,
+    #[serde(default)]
+    fees: u64,
+    /// Unknown if the scripts are missing.
+    #[serde(default)]
+    vsize: Option<u64>,
+    omni: Option<OmniTransfer>,
     inputs: Vec<DrawableInput>,
     outputs: Vec<DrawableOutput>,
 }
 
 impl DrawableNode {
+    fn coinjoin(&self) -> Option<coinjoin::CoinJoin> {
+        coinjoin::detect(
+            &self.inputs.iter().map(|i| i.value).collect::<Vec<_>>(),
+            &self
+                .outputs
+                .iter()
+                .filter(|o| !matches!(o.output_type, OutputType::Fees))
+                .map(|o| o.value)
+                .collect::<Vec<_>>(),
+        )
+    }
+
     fn scale(&mut self, scale: &Scale) {
         self.size = scale.apply(self.tx_value) as f32;
 
@@ -75,6 +148,62 @@ impl DrawableNode {
         }
     }
 
+    /// One row per input and output, inputs negative.
+    /// With [fiat], there are columns for the price of the day and the value of each row in that
+    /// currency, empty while the price isn't known.
+    fn export_csv(
+        &self,
+        txid: &Txid,
+        label: Option<String>,
+        unit: ExportUnit,
+        fiat: Option<(&str, Option<f64>)>,
+    ) -> String {
+        let label = label.unwrap_or_default();
+        let txid = txid.hex_string();
+        let mut header: Vec<String> = ["date", "txid", "label", "kind", "address"]
+            .into_iter()
+            .chain(unit.headers())
+            .map(String::from)
+            .collect();
+        if let Some((currency, _)) = fiat {
+            header.push(format!("price_{}", currency));
+            header.push(format!("value_{}", currency));
+        }
+        let mut s = csv::write_record(&header);
+
+        let mut row = |kind: &str, address: &str, value: i64| {
+            let mut record = vec![
+                self.tx_timestamp.clone(),
+                txid.clone(),
+                label.clone(),
+                kind.to_string(),
+                address.to_string(),
+            ];
+            record.extend(unit.values(value));
+            match fiat {
+                Some((_, Some(rate))) => {
+                    record.push(format!("{:.2}", rate));
+                    record.push(format!("{:.2}", value as f64 / 100_000_000.0 * rate));
+                }
+                Some((_, None)) => record.extend([String::new(), String::new()]),
+                None => {}
+            }
+            s.push_str(&csv::write_record(&record));
+        };
+        for input in &self.inputs {
+            row("input", &input.address, -(input.value as i64));
+        }
+        for output in &self.outputs {
+            match &output.output_type {
+                OutputType::Fees => row("fee", "", output.value as i64),
+                OutputType::Spent { address, .. } | OutputType::Utxo { address, .. } => {
+                    row("output", address, output.value as i64)
+                }
+            }
+        }
+        s
+    }
+
     fn export_beancount(&self, txid: &Txid, label: Option<String>) -> String {
         let mut s = String::new();
         writeln!(
@@ -121,6 +250,24 @@ impl DrawableNode {
     }
 }
 
+#[derive(Default)]
+struct Rects {
+    inputs: HashMap<(Txid, usize), Rect>,
+    outputs: HashMap<(Txid, usize), Rect>,
+    inner: HashMap<Txid, Rect>,
+    outer: HashMap<Txid, Rect>,
+}
+
+/// A collapsed group, drawn instead of its members.
+struct GroupNode {
+    /// Index into [Annotations::groups].
+    group: usize,
+    txids: Vec<Txid>,
+    value_in: u64,
+    value_out: u64,
+    rect: Rect,
+}
+
 #[derive(Clone, Hash, Serialize, Deserialize)]
 pub struct DrawableEdge {
     source: Txid,
@@ -138,6 +285,21 @@ pub struct DrawableInput {
     address_type: AddressType,
     funding_txid: Txid, // TODO: coinbase tx?
     funding_vout: u32,
+    script_sig: Option<Vec<u8>>,
+    witness: Vec<Vec<u8>>,
+}
+
+impl DrawableInput {
+    fn scripts(&self) -> Vec<(&'static str, Vec<u8>)> {
+        let mut scripts = vec![];
+        if let Some(script_sig) = &self.script_sig {
+            scripts.push(("scriptSig", script_sig.clone()));
+        }
+        for item in &self.witness {
+            scripts.push(("Witness item", item.clone()));
+        }
+        scripts
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -146,49 +308,1087 @@ pub struct DrawableOutput {
     end: f32,
     value: u64,
     output_type: OutputType,
+    script_pubkey: Option<Vec<u8>>,
+}
+
+impl DrawableOutput {
+    /// From the scriptPubKey, or else from the address.
+    fn script_type(&self) -> Option<&'static str> {
+        if let Some(script_pubkey) = &self.script_pubkey {
+            return script::Template::classify(script_pubkey).short_name();
+        }
+        match &self.output_type {
+            OutputType::Utxo { address_type, .. } | OutputType::Spent { address_type, .. } => {
+                match address_type {
+                    AddressType::P2PKH => Some("P2PKH"),
+                    AddressType::P2SH => Some("P2SH"),
+                    AddressType::P2WPKH => Some("P2WPKH"),
+                    AddressType::P2WSH => Some("P2WSH"),
+                    AddressType::P2TR => Some("P2TR"),
+                    AddressType::Unknown => None,
+                }
+            }
+            OutputType::Fees => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum OutputType {
+    Utxo {
+        address: String,
+        address_type: AddressType,
+    },
+    Spent {
+        spending_txid: Txid,
+        address: String,
+        address_type: AddressType,
+    },
+    Fees,
 }
 
-#[derive(Serialize, Deserialize)]
-pub enum OutputType {
-    Utxo {
-        address: String,
-        address_type: AddressType,
-    },
-    Spent {
-        spending_txid: Txid,
-        address: String,
-        address_type: AddressType,
-    },
-    Fees,
-}
+impl Graph {
+    pub fn export(&self) -> Vec<export::Transaction> {
+        self.nodes
+            .iter()
+            .map(|(txid, node)| export::Transaction::new(*txid, node.pos))
+            .collect()
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Over the labelled coins, with an edge from each entity that spent coins in a transaction
+    /// to each other entity that received coins in it.
+    pub fn stats(&self, annotations: &Annotations) -> GraphStats {
+        let nodes = self.entities(annotations);
+        let index = |coin| {
+            let label = annotations.coin_label(coin)?;
+            nodes.binary_search(&label).ok()
+        };
+        let mut edges = vec![];
+        for (txid, node) in &self.nodes {
+            let senders: HashSet<usize> = node
+                .inputs
+                .iter()
+                .filter_map(|input| index((input.funding_txid, input.funding_vout as usize)))
+                .collect();
+            let receivers: HashSet<usize> = (0..node.outputs.len())
+                .filter_map(|vout| index((*txid, vout)))
+                .collect();
+            for &sender in &senders {
+                for &receiver in &receivers {
+                    if sender != receiver {
+                        edges.push((sender, receiver));
+                    }
+                }
+            }
+        }
+        edges.sort_unstable();
+        edges.dedup();
+        GraphStats::compute(&nodes, &edges)
+    }
+
+    fn add_edge(&mut self, edge: DrawableEdge) {
+        self.edges.push(edge);
+    }
+
+    pub fn utxos(&self) -> Vec<Coin> {
+        self.nodes
+            .iter()
+            .flat_map(|(txid, node)| {
+                node.outputs
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(vout, output)| match output.output_type {
+                        OutputType::Utxo { address_type, .. } => Some(Coin {
+                            txid: *txid,
+                            vout,
+                            value: output.value,
+                            address_type,
+                        }),
+                        _ => None,
+                    })
+            })
+            .collect()
+    }
+
+    /// Unspent outputs with the details for [crate::utxos::UtxoDashboard].
+    pub fn utxo_details(&self, annotations: &Annotations) -> Vec<Utxo> {
+        let mut utxos = vec![];
+        for (txid, node) in &self.nodes {
+            for (vout, output) in node.outputs.iter().enumerate() {
+                if let OutputType::Utxo {
+                    address,
+                    address_type,
+                } = &output.output_type
+                {
+                    let address_label = annotations.address_label(address);
+                    utxos.push(Utxo {
+                        txid: *txid,
+                        vout,
+                        value: output.value,
+                        address: address.clone(),
+                        address_type: *address_type,
+                        timestamp: node.unix_timestamp,
+                        tracked: address_label.is_some(),
+                        label: address_label.or_else(|| annotations.coin_label((*txid, vout))),
+                    });
+                }
+            }
+        }
+        utxos
+    }
+
+    /// All coins touched by the given transactions (or all transactions), with their address.
+    pub fn coin_addresses(&self, txid: Option<Txid>) -> Vec<((Txid, usize), &str)> {
+        let mut coins = vec![];
+        for (node_txid, node) in &self.nodes {
+            if txid.is_some_and(|t| t != *node_txid) {
+                continue;
+            }
+            for input in &node.inputs {
+                coins.push((
+                    (input.funding_txid, input.funding_vout as usize),
+                    input.address.as_str(),
+                ));
+            }
+            for (vout, output) in node.outputs.iter().enumerate() {
+                match &output.output_type {
+                    OutputType::Utxo { address, .. } | OutputType::Spent { address, .. } => {
+                        coins.push(((*node_txid, vout), address.as_str()));
+                    }
+                    OutputType::Fees => {}
+                }
+            }
+        }
+        coins.sort_unstable();
+        coins.dedup();
+        coins
+    }
+
+    /// Input addresses of each transaction, for [Clusters]. Coinjoins are left out, their inputs
+    /// belong to different owners.
+    pub fn input_addresses(&self) -> impl Iterator<Item = Vec<&str>> {
+        self.nodes
+            .values()
+            .filter(|node| node.coinjoin().is_none())
+            .map(|node| {
+                node.inputs
+                    .iter()
+                    .map(|input| input.address.as_str())
+                    .filter(|address| !address.is_empty())
+                    .collect()
+            })
+    }
+
+    /// Empty if [txid] isn't in the graph.
+    pub fn input_addresses_of(&self, txid: Txid) -> Vec<&str> {
+        self.nodes.get(&txid).map_or(vec![], |node| {
+            node.inputs
+                .iter()
+                .map(|input| input.address.as_str())
+                .filter(|address| !address.is_empty())
+                .collect()
+        })
+    }
+
+    /// Change output of each transaction, with the reasons for the guess (none if set manually).
+    pub fn change_outputs(&self, annotations: &Annotations) -> HashMap<(Txid, usize), Vec<Reason>> {
+        let mut first_seen: HashMap<&str, i64> = HashMap::new();
+        for (_, address) in self.coin_addresses(None) {
+            first_seen.insert(address, i64::MAX);
+        }
+        for node in self.nodes.values() {
+            let addresses = node.inputs.iter().map(|i| i.address.as_str()).chain(
+                node.outputs.iter().filter_map(|o| match &o.output_type {
+                    OutputType::Utxo { address, .. } | OutputType::Spent { address, .. } => {
+                        Some(address.as_str())
+                    }
+                    OutputType::Fees => None,
+                }),
+            );
+            for address in addresses {
+                if let Some(seen) = first_seen.get_mut(address) {
+                    *seen = (*seen).min(node.unix_timestamp);
+                }
+            }
+        }
+
+        let mut result = HashMap::new();
+        for (txid, node) in &self.nodes {
+            let inputs: Vec<_> = node
+                .inputs
+                .iter()
+                .map(|i| (i.address_type, i.address.as_str()))
+                .collect();
+            let (vouts, outputs): (Vec<usize>, Vec<change::Output<'_>>) = node
+                .outputs
+                .iter()
+                .enumerate()
+                .filter_map(|(vout, o)| match &o.output_type {
+                    OutputType::Utxo {
+                        address,
+                        address_type,
+                    }
+                    | OutputType::Spent {
+                        address,
+                        address_type,
+                        ..
+                    } => Some((
+                        vout,
+                        change::Output {
+                            value: o.value,
+                            address_type: *address_type,
+                            address,
+                            seen_before: first_seen[address.as_str()] < node.unix_timestamp,
+                        },
+                    )),
+                    OutputType::Fees => None,
+                })
+                .unzip();
+
+            let guess = change::detect(&inputs, &outputs);
+            for (index, vout) in vouts.into_iter().enumerate() {
+                let coin = (*txid, vout);
+                match annotations.change(coin) {
+                    Some(true) => {
+                        result.insert(coin, vec![]);
+                    }
+                    Some(false) => {}
+                    None => {
+                        if let Some(guess) = guess.as_ref().filter(|g| g.index == index) {
+                            result.insert(coin, guess.reasons.clone());
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    pub fn fee_summary(&self) -> FeeSummary {
+        FeeSummary::new(
+            self.nodes
+                .iter()
+                .filter(|(_, node)| !node.inputs.is_empty())
+                .map(|(txid, node)| TxFee {
+                    txid: *txid,
+                    fees: node.fees,
+                    vsize: node.vsize,
+                })
+                .collect(),
+        )
+    }
+
+    /// Address labels of the addresses in the graph, e.g. wallet names.
+    pub fn wallets(&self, annotations: &Annotations) -> Vec<String> {
+        let mut wallets: Vec<String> = self
+            .coin_addresses(None)
+            .into_iter()
+            .filter_map(|(_, address)| annotations.address_label(address))
+            .collect();
+        wallets.sort_unstable();
+        wallets.dedup();
+        wallets
+    }
+
+    /// Net change of the balance of the [tracked] addresses in every confirmed transaction that
+    /// touches them.
+    pub fn balance_changes(&self, tracked: impl Fn(&str) -> bool) -> Vec<Change> {
+        let mut changes = vec![];
+        for (txid, node) in &self.nodes {
+            if node.block_height == 0 {
+                continue;
+            }
+            let mut touched = false;
+            let mut delta = 0;
+            for input in &node.inputs {
+                if tracked(&input.address) {
+                    touched = true;
+                    delta -= input.value as i64;
+                }
+            }
+            for output in &node.outputs {
+                if let OutputType::Spent { address, .. } | OutputType::Utxo { address, .. } =
+                    &output.output_type
+                {
+                    if tracked(address) {
+                        touched = true;
+                        delta += output.value as i64;
+                    }
+                }
+            }
+            if touched {
+                changes.push(Change {
+                    txid: *txid,
+                    timestamp: node.unix_timestamp,
+                    delta,
+                });
+            }
+        }
+        changes
+    }
+
+    /// Labels of coins in the graph. Coins sharing a label are treated as one entity.
+    pub fn entities(&self, annotations: &Annotations) -> Vec<String> {
+        let mut entities: Vec<String> = self
+            .coin_addresses(None)
+            .into_iter()
+            .filter_map(|(coin, _)| annotations.coin_label(coin))
+            .collect();
+        entities.sort_unstable();
+        entities.dedup();
+        entities
+    }
+
+    /// Funds received and sent by the entity in each transaction it took part in. Like in
+    /// [crate::entity::FlowMatrix], counterparties are credited pro rata: a source with the
+    /// share of what the entity received that its inputs paid for, a destination with the share
+    /// of its output that the entity's inputs paid for.
+    pub fn entity_flows(&self, annotations: &Annotations, entity: &str) -> Vec<Flow> {
+        let label = |coin| coin_entity(annotations, coin);
+
+        let mut flows = vec![];
+        for (txid, node) in &self.nodes {
+            let mut flow = Flow {
+                txid: *txid,
+                timestamp: node.unix_timestamp,
+                received: 0,
+                sent: 0,
+                sources: vec![],
+                destinations: vec![],
+                tx_label: annotations.tx_label(*txid),
+            };
+            let mut counterparty_inputs = vec![];
+            for input in &node.inputs {
+                let coin = (input.funding_txid, input.funding_vout as usize);
+                if label(coin) == entity {
+                    flow.sent += input.value;
+                } else {
+                    counterparty_inputs.push((label(coin), input.value));
+                }
+            }
+            let mut counterparty_outputs = vec![];
+            for (vout, output) in node.outputs.iter().enumerate() {
+                if matches!(output.output_type, OutputType::Fees) {
+                    continue;
+                }
+                if label((*txid, vout)) == entity {
+                    flow.received += output.value;
+                } else {
+                    counterparty_outputs.push((label((*txid, vout)), output.value));
+                }
+            }
+            if flow.received == 0 && flow.sent == 0 {
+                continue;
+            }
+            let total_in: u64 = node.inputs.iter().map(|input| input.value).sum();
+            let pro_rata = |parts: Vec<(String, u64)>, entity_value: u64| {
+                parts
+                    .into_iter()
+                    .map(|(label, value)| {
+                        let share = value as u128 * entity_value as u128 / total_in as u128;
+                        (label, share as u64)
+                    })
+                    .collect()
+            };
+            if flow.received > 0 && total_in > 0 {
+                flow.sources = pro_rata(counterparty_inputs, flow.received);
+            }
+            if flow.sent > 0 {
+                flow.destinations = pro_rata(counterparty_outputs, flow.sent);
+            }
+            flows.push(flow);
+        }
+        flows
+    }
+
+    /// Inputs and non-fee outputs of every transaction, by entity.
+    pub fn entity_transfers(&self, annotations: &Annotations) -> Vec<Transfer> {
+        self.nodes
+            .iter()
+            .map(|(txid, node)| {
+                let inputs = node
+                    .inputs
+                    .iter()
+                    .map(|input| {
+                        let coin = (input.funding_txid, input.funding_vout as usize);
+                        (annotations.coin_label(coin), input.value)
+                    })
+                    .collect();
+                let outputs = node
+                    .outputs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, output)| !matches!(output.output_type, OutputType::Fees))
+                    .map(|(vout, output)| (annotations.coin_label((*txid, vout)), output.value))
+                    .collect();
+                (inputs, outputs)
+            })
+            .collect()
+    }
+
+    /// Values in [currency] are at the price of the day of the transaction. Returns whether that
+    /// price was known.
+    pub fn export_csv(
+        &self,
+        ctx: &egui::Context,
+        txid: Txid,
+        label: Option<String>,
+        unit: ExportUnit,
+        currency: Option<&str>,
+    ) -> Option<(String, bool)> {
+        let node = self.nodes.get(&txid)?;
+        let fiat = currency.map(|currency| {
+            let rate = Prices::quote(ctx, currency, node.unix_timestamp).map(|quote| quote.rate);
+            (currency, rate)
+        });
+        let priced = fiat.map_or(true, |(_, rate)| rate.is_some());
+        Some((node.export_csv(&txid, label, unit, fiat), priced))
+    }
+
+    /// Where the transactions and their inputs and outputs are, in graph coordinates. Nodes
+    /// outside the [time_window] are left out.
+    fn rects(&self, style: &Style, time_window: Option<TimeWindow>) -> Rects {
+        let mut rects = Rects::default();
+
+        for (txid, node) in &self.nodes {
+            if time_window.is_some_and(|w| !w.contains(&node.tx_timestamp)) {
+                continue;
+            }
+
+            let outer_rect = Rect::from_center_size(
+                node.pos,
+                Vec2::new(node.size, style.tx_width + 2.0 * style.io_width),
+            );
+            let inner_rect = Rect::from_center_size(node.pos, Vec2::new(node.size, style.tx_width));
+
+            rects.outer.insert(*txid, outer_rect);
+            rects.inner.insert(*txid, inner_rect);
+
+            let left_top = outer_rect.left_top();
+            for (i, input) in node.inputs.iter().enumerate() {
+                let rect = Rect::from_min_max(
+                    Pos2::new(left_top.x + input.start, left_top.y),
+                    Pos2::new(left_top.x + input.end, left_top.y + style.io_width),
+                );
+                rects.inputs.insert((*txid, i), rect);
+            }
+
+            let left_bot = outer_rect.left_bottom();
+            for (o, output) in node.outputs.iter().enumerate() {
+                let rect = Rect::from_min_max(
+                    Pos2::new(left_bot.x + output.start, left_bot.y - style.io_width),
+                    Pos2::new(left_bot.x + output.end, left_bot.y),
+                );
+                rects.outputs.insert((*txid, o), rect);
+            }
+        }
+
+        rects
+    }
+
+    /// Transaction rects in graph coordinates, e.g. for the minimap.
+    pub fn node_rects(&self, style: &Style) -> Vec<Rect> {
+        self.rects(style, None).outer.into_values().collect()
+    }
+
+    /// Everything within [view] (graph coordinates) as an image, or the whole graph without a
+    /// view. Includes labels, colors and stickies, but not what's only shown on hover.
+    pub fn to_svg(
+        &self,
+        annotations: &Annotations,
+        style: &Style,
+        background: Color32,
+        view: Option<Rect>,
+    ) -> String {
+        let rects = self.rects(style, None);
+        let stickies = annotations.stickies();
+        let view = view.unwrap_or_else(|| {
+            rects
+                .outer
+                .values()
+                .copied()
+                .chain(stickies.iter().map(Sticky::rect))
+                .reduce(Rect::union)
+                .unwrap_or(Rect::from_min_size(Pos2::ZERO, Vec2::splat(100.0)))
+                .expand(20.0)
+        });
+        let mut svg = Svg::new(view, background);
+        // Below the graph, like on the canvas.
+        for sticky in stickies {
+            sticky.to_svg(&mut svg);
+        }
+
+        let mut edges: Vec<_> = self.edges.iter().collect();
+        edges.sort_by_key(|e| (e.source, e.source_pos, e.target, e.target_pos));
+        for edge in edges {
+            let (Some(from), Some(to)) = (
+                rects.outputs.get(&(edge.source, edge.source_pos)),
+                rects.inputs.get(&(edge.target, edge.target_pos)),
+            ) else {
+                continue;
+            };
+            let [_, left1, left2, left3] =
+                Cubic::sankey(from.left_bottom(), to.left_top()).points();
+            let [right0, right1, right2, _] =
+                Cubic::sankey(to.right_top(), from.right_bottom()).points();
+            let color = annotations
+                .coin_color((edge.source, edge.source_pos))
+                .unwrap_or(Color32::GOLD)
+                .gamma_multiply(0.4);
+            svg.path(
+                from.left_bottom(),
+                &[
+                    Segment::Cubic(left1, left2, left3),
+                    Segment::Line(right0),
+                    Segment::Cubic(right1, right2, from.right_bottom()),
+                ],
+                color,
+            );
+        }
+
+        let mut txids: Vec<_> = rects.inner.keys().copied().collect();
+        txids.sort();
+        for txid in txids {
+            let node = &self.nodes[&txid];
+            let rect = rects.inner[&txid];
+            let fill = annotations.tx_color(txid).unwrap_or(style.tx_bg);
+            svg.rect(rect, fill.gamma_multiply(0.4), style.tx_stroke());
+
+            for (i, input) in node.inputs.iter().enumerate() {
+                let coin = (input.funding_txid, input.funding_vout as usize);
+                let fill = annotations.coin_color(coin).unwrap_or(style.io_bg);
+                svg.rect(
+                    rects.inputs[&(txid, i)],
+                    fill.gamma_multiply(0.4),
+                    style.tx_stroke(),
+                );
+            }
+            for (o, output) in node.outputs.iter().enumerate() {
+                let coin = (txid, o);
+                let fill = match output.output_type {
+                    OutputType::Utxo { .. } => annotations
+                        .coin_color(coin)
+                        .unwrap_or(style.utxo_fill())
+                        .gamma_multiply(0.4),
+                    OutputType::Spent { .. } => annotations
+                        .coin_color(coin)
+                        .unwrap_or(style.io_bg)
+                        .gamma_multiply(0.4),
+                    OutputType::Fees => style.fees_fill(),
+                };
+                let output_rect = rects.outputs[&coin];
+                svg.rect(output_rect, fill, style.tx_stroke());
+                if let Some(label) = annotations.coin_label(coin) {
+                    svg.text(
+                        output_rect.left_bottom() + Vec2::new(1.0, 2.0),
+                        &[&label],
+                        7.0,
+                        style.black_text_color(),
+                        None,
+                    );
+                }
+            }
+
+            let name = annotations
+                .tx_label(txid)
+                .unwrap_or_else(|| txid.hex_string()[..16].to_string());
+            let value = format!("{} sats", Sats(node.tx_value));
+            svg.text(
+                rect.left_top() + Vec2::new(2.0, 2.0),
+                &[&name, &value, &node.tx_timestamp[2..]],
+                10.0,
+                style.black_text_color(),
+                Some(rect),
+            );
+        }
+
+        svg.finish()
+    }
+
+    /// Transactions, flows between them, and outputs that leave the graph, with labels and
+    /// amounts. Positions are kept for layout engines that respect them (`neato -n`).
+    pub fn to_dot(&self, annotations: &Annotations) -> String {
+        let mut dot = dot::Dot::new("txgraph");
+        dot.defaults("node", &[("fontname", "monospace".to_string())]);
+        dot.defaults("edge", &[("fontname", "monospace".to_string())]);
+
+        let mut txids = self.txids();
+        txids.sort();
+        let tx_id = |txid: &Txid| format!("tx_{}", txid);
+        for txid in &txids {
+            let node = &self.nodes[txid];
+            let name = annotations
+                .tx_label(*txid)
+                .unwrap_or_else(|| txid.hex_string()[..16].to_string());
+            let mut attrs = vec![
+                ("shape", "box".to_string()),
+                (
+                    "label",
+                    format!(
+                        "{}\n{}\n{} sats",
+                        name,
+                        node.tx_timestamp,
+                        Sats(node.tx_value)
+                    ),
+                ),
+                ("tooltip", txid.hex_string()),
+                ("pos", format!("{:.0},{:.0}!", node.pos.x, -node.pos.y)),
+            ];
+            if let Some(color) = annotations.tx_color(*txid) {
+                attrs.push(("style", "filled".to_string()));
+                attrs.push(("fillcolor", dot::color(color)));
+            }
+            dot.node(&tx_id(txid), &attrs);
+
+            for (vout, output) in node.outputs.iter().enumerate() {
+                let address = match &output.output_type {
+                    OutputType::Spent { spending_txid, .. }
+                        if self.nodes.contains_key(spending_txid) =>
+                    {
+                        continue;
+                    }
+                    OutputType::Fees => continue,
+                    OutputType::Spent { address, .. } | OutputType::Utxo { address, .. } => address,
+                };
+                let coin = (*txid, vout);
+                let out_id = format!("out_{}_{}", txid, vout);
+                let mut label = format!("{}\n{} sats", address, Sats(output.value));
+                if let Some(coin_label) = annotations.coin_label(coin) {
+                    label = format!("{}\n{}", coin_label, label);
+                }
+                let mut attrs = vec![("shape", "ellipse".to_string()), ("label", label)];
+                if matches!(output.output_type, OutputType::Utxo { .. }) {
+                    attrs.push(("peripheries", "2".to_string()));
+                }
+                if let Some(color) = annotations.coin_color(coin) {
+                    attrs.push(("style", "filled".to_string()));
+                    attrs.push(("fillcolor", dot::color(color)));
+                }
+                dot.node(&out_id, &attrs);
+                dot.edge(&tx_id(txid), &out_id, &[]);
+            }
+        }
+
+        let mut edges: Vec<_> = self.edges.iter().collect();
+        edges.sort_by_key(|e| (e.source, e.source_pos, e.target, e.target_pos));
+        for edge in edges {
+            let value = self.nodes[&edge.target].inputs[edge.target_pos].value;
+            let mut label = format!("{} sats", Sats(value));
+            if let Some(coin_label) = annotations.coin_label((edge.source, edge.source_pos)) {
+                label = format!("{}\n{}", coin_label, label);
+            }
+            dot.edge(
+                &tx_id(&edge.source),
+                &tx_id(&edge.target),
+                &[("label", label)],
+            );
+        }
+
+        dot.finish()
+    }
+
+    pub fn txids(&self) -> Vec<Txid> {
+        self.nodes.keys().copied().collect()
+    }
+
+    /// Swap in updated transaction data, keeping the node where it is.
+    pub fn replace_tx(&mut self, txid: Txid, tx: Transaction) {
+        if let Some(pos) = self.get_tx_pos(txid) {
+            self.remove_tx(txid);
+            self.add_tx(txid, tx, pos);
+        }
+    }
+
+    /// Transactions one hop away from [txid] that aren't in the graph yet, with a position next
+    /// to it: spending transactions below, funding transactions above.
+    pub fn neighbors(&self, txid: Txid, direction: Direction, min_value: u64) -> Vec<(Txid, Pos2)> {
+        const HOP: f32 = 70.0;
+        const SPACING: f32 = 40.0;
+
+        let Some(node) = self.nodes.get(&txid) else {
+            return vec![];
+        };
+        let spread = |txids: Vec<Txid>, dy: f32| {
+            let offset = (txids.len() as f32 - 1.0) / 2.0;
+            txids
+                .into_iter()
+                .enumerate()
+                .map(|(i, txid)| {
+                    (
+                        txid,
+                        node.pos + Vec2::new((i as f32 - offset) * SPACING, dy),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut result = vec![];
+        if direction.forward() {
+            let mut spending = vec![];
+            for output in node.outputs.iter().filter(|o| o.value >= min_value) {
+                if let OutputType::Spent { spending_txid, .. } = &output.output_type {
+                    if !self.nodes.contains_key(spending_txid) && !spending.contains(spending_txid)
+                    {
+                        spending.push(*spending_txid);
+                    }
+                }
+            }
+            result.extend(spread(spending, HOP));
+        }
+        if direction.backward() {
+            let mut funding = vec![];
+            for input in node.inputs.iter().filter(|i| i.value >= min_value) {
+                let txid = input.funding_txid;
+                if !self.nodes.contains_key(&txid) && !funding.contains(&txid) {
+                    funding.push(txid);
+                }
+            }
+            result.extend(spread(funding, -HOP));
+        }
+        result
+    }
+
+    pub fn unconfirmed(&self) -> Vec<Txid> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.block_height == 0)
+            .map(|(txid, _)| *txid)
+            .collect()
+    }
+
+    /// Outpoint spent by the first input of [txid].
+    pub fn first_input(&self, txid: Txid) -> Option<(Txid, u32)> {
+        let input = self.nodes.get(&txid)?.inputs.first()?;
+        Some((input.funding_txid, input.funding_vout))
+    }
+
+    pub fn set_tip_height(&mut self, height: u32) {
+        self.tip_height = Some(height);
+    }
+
+    pub fn get_tx_pos(&self, txid: Txid) -> Option<Pos2> {
+        self.nodes.get(&txid).map(|node| node.pos)
+    }
+
+    /// Transactions connected to [txid] through any number of edges, including itself.
+    pub fn component(&self, txid: Txid) -> HashSet<Txid> {
+        let mut component = HashSet::default();
+        let mut stack = vec![txid];
+        while let Some(txid) = stack.pop() {
+            if !self.nodes.contains_key(&txid) || !component.insert(txid) {
+                continue;
+            }
+            for edge in &self.edges {
+                if edge.source == txid {
+                    stack.push(edge.target);
+                } else if edge.target == txid {
+                    stack.push(edge.source);
+                }
+            }
+        }
+        component
+    }
+
+    /// Transactions matching [query], a txid or an address. An address matches the transactions
+    /// spending from it if [spending], otherwise those paying to it.
+    pub fn resolve(&self, query: &str, spending: bool) -> Vec<Txid> {
+        if let Ok(txid) = Txid::new(query) {
+            return self
+                .nodes
+                .contains_key(&txid)
+                .then_some(txid)
+                .into_iter()
+                .collect();
+        }
+        self.nodes
+            .iter()
+            .filter(|(_, node)| {
+                if spending {
+                    node.inputs.iter().any(|input| input.address == query)
+                } else {
+                    node.outputs.iter().any(|output| {
+                        matches!(
+                            &output.output_type,
+                            OutputType::Spent { address, .. } | OutputType::Utxo { address, .. }
+                                if address == query
+                        )
+                    })
+                }
+            })
+            .map(|(txid, _)| *txid)
+            .collect()
+    }
+
+    /// Transactions in the graph spending coins of [txid], with the total value they spend.
+    pub fn spends(&self, txid: Txid) -> Vec<(Txid, u64)> {
+        let mut result: Vec<(Txid, u64)> = vec![];
+        for edge in self.edges.iter().filter(|edge| edge.source == txid) {
+            let Some(target) = self.nodes.get(&edge.target) else {
+                continue;
+            };
+            let value = target.inputs[edge.target_pos].value;
+            match result.iter_mut().find(|(t, _)| *t == edge.target) {
+                Some((_, total)) => *total += value,
+                None => result.push((edge.target, value)),
+            }
+        }
+        result
+    }
+
+    /// Highlights the chain of transactions [path], or nothing if it's empty.
+    pub fn set_path(&mut self, path: Vec<Txid>) {
+        self.path = path;
+    }
+
+    /// Value entering [txids] from outside, and leaving them (excluding fees).
+    fn group_flows(&self, txids: &[Txid]) -> (u64, u64) {
+        let mut value_in = 0;
+        let mut value_out = 0;
+        for node in txids.iter().filter_map(|txid| self.nodes.get(txid)) {
+            for input in &node.inputs {
+                if !txids.contains(&input.funding_txid) {
+                    value_in += input.value;
+                }
+            }
+            for output in &node.outputs {
+                match &output.output_type {
+                    OutputType::Fees => {}
+                    OutputType::Spent { spending_txid, .. } if txids.contains(spending_txid) => {}
+                    _ => value_out += output.value,
+                }
+            }
+        }
+        (value_in, value_out)
+    }
+
+    /// What the search finds, by transaction: txids, addresses, amounts and labels.
+    pub fn search_items<'a>(
+        &'a self,
+        annotations: &'a Annotations,
+    ) -> impl Iterator<Item = (Txid, String)> + 'a {
+        self.nodes.iter().flat_map(move |(txid, node)| {
+            let hex = txid.hex_string();
+            let short = &hex[..12];
+            let coin = |address: &str, value: u64, label: Option<String>, relation: &str| {
+                let label = label.map(|l| format!("[{}] ", l)).unwrap_or_default();
+                format!(
+                    "{}{} {:.8} BTC ({} sats) {} {}",
+                    label,
+                    address,
+                    value as f64 / 100_000_000.0,
+                    value,
+                    relation,
+                    short
+                )
+            };
+
+            let mut items = vec![];
+            if let Some(label) = annotations.tx_label(*txid) {
+                items.push((*txid, format!("[{}] Tx {}", label, short)));
+            }
+            items.push((*txid, format!("Tx {}", hex)));
+            for (vout, output) in node.outputs.iter().enumerate() {
+                if let OutputType::Utxo { address, .. } | OutputType::Spent { address, .. } =
+                    &output.output_type
+                {
+                    let label = annotations.coin_label((*txid, vout));
+                    items.push((*txid, coin(address, output.value, label, "out of")));
+                }
+            }
+            // Inputs from transactions in the graph are found as their outputs.
+            for input in &node.inputs {
+                if !self.nodes.contains_key(&input.funding_txid) {
+                    let label =
+                        annotations.coin_label((input.funding_txid, input.funding_vout as usize));
+                    items.push((*txid, coin(&input.address, input.value, label, "into")));
+                }
+            }
+            items
+        })
+    }
+
+    /// Highlights [txid] for a moment, starting at [time] as in [egui::InputState::time].
+    pub fn flash(&mut self, txid: Txid, time: f64) {
+        self.flash = Some((txid, time));
+    }
+
+    pub fn selected(&self) -> Option<Txid> {
+        self.selected_node
+    }
+
+    /// Moves the transactions in [only] (all if `None`) to the positions computed by [algorithm],
+    /// animated over the next frames.
+    pub fn arrange(&mut self, algorithm: Algorithm, style: &Style, only: Option<&HashSet<Txid>>) {
+        let rects = self.rects(style, None);
+        let txids: Vec<Txid> = rects
+            .outer
+            .keys()
+            .filter(|txid| only.map_or(true, |only| only.contains(txid)))
+            .copied()
+            .collect();
+        let index: HashMap<Txid, usize> = txids.iter().enumerate().map(|(i, t)| (*t, i)).collect();
+        let positions: Vec<Pos2> = txids.iter().map(|txid| self.nodes[txid].pos).collect();
+        let sizes: Vec<Vec2> = txids.iter().map(|txid| rects.outer[txid].size()).collect();
+        let edges: Vec<(usize, usize)> = self
+            .edges
+            .iter()
+            .filter_map(|edge| Some((*index.get(&edge.source)?, *index.get(&edge.target)?)))
+            .collect();
+
+        let result = algorithm.apply(&positions, &sizes, &edges);
+        self.animation = Some(Animation::new(
+            txids
+                .into_iter()
+                .zip(positions)
+                .zip(result)
+                .map(|((txid, from), to)| (txid, from, to))
+                .collect(),
+        ));
+    }
+
+    /// Also ends a multi-selection.
+    pub fn select(&mut self, txid: Txid) {
+        if self.selected_node != Some(txid) {
+            self.focused_output = None;
+        }
+        self.selected_node = Some(txid);
+        self.multi_selection.clear();
+    }
+
+    pub fn deselect(&mut self) {
+        self.selected_node = None;
+        self.focused_output = None;
+        self.multi_selection.clear();
+    }
+
+    pub fn multi_selection(&self) -> &HashSet<Txid> {
+        &self.multi_selection
+    }
+
+    /// Adds or removes [txid], starting from the selected transaction if there's no
+    /// multi-selection yet.
+    pub fn toggle_selected(&mut self, txid: Txid) {
+        if self.multi_selection.is_empty() {
+            self.multi_selection.extend(self.selected_node);
+        }
+        if !self.multi_selection.remove(&txid) {
+            self.multi_selection.insert(txid);
+        }
+    }
+
+    /// Adds the transactions overlapping [rect], in graph coordinates.
+    pub fn select_in(&mut self, rect: Rect, style: &Style) {
+        if self.multi_selection.is_empty() {
+            self.multi_selection.extend(self.selected_node);
+        }
+        let rects = self.rects(style, None);
+        self.multi_selection.extend(
+            rects
+                .outer
+                .iter()
+                .filter(|(_, r)| r.intersects(rect))
+                .map(|(txid, _)| *txid),
+        );
+    }
+
+    pub fn clear_multi_selection(&mut self) {
+        self.multi_selection.clear();
+    }
 
-impl Graph {
-    pub fn export(&self) -> Vec<export::Transaction> {
-        self.nodes
-            .iter()
-            .map(|(txid, node)| export::Transaction::new(*txid, node.pos))
-            .collect()
+    pub fn select_all(&mut self) {
+        self.multi_selection = self.nodes.keys().copied().collect();
     }
 
-    fn add_edge(&mut self, edge: DrawableEdge) {
-        self.edges.push(edge);
+    /// Moves the multi-selected transactions into line, animated like [Graph::arrange].
+    pub fn align(&mut self, alignment: Alignment, style: &Style) {
+        let rects = self.rects(style, None);
+        let txids: Vec<Txid> = self
+            .multi_selection
+            .iter()
+            .filter(|txid| rects.outer.contains_key(txid))
+            .copied()
+            .collect();
+        let selected: Vec<Rect> = txids.iter().map(|txid| rects.outer[txid]).collect();
+        let offsets = alignment.apply(&selected);
+        self.animation = Some(Animation::new(
+            txids
+                .into_iter()
+                .zip(offsets)
+                .map(|(txid, offset)| {
+                    let pos = self.nodes[&txid].pos;
+                    (txid, pos, pos + offset)
+                })
+                .collect(),
+        ));
     }
 
-    pub fn get_tx_pos(&self, txid: Txid) -> Option<Pos2> {
-        self.nodes.get(&txid).map(|node| node.pos)
+    /// Focuses the next (or previous) output of the selected transaction, skipping fees.
+    pub fn cycle_output(&mut self, backwards: bool) {
+        let Some(node) = self.selected_node.and_then(|txid| self.nodes.get(&txid)) else {
+            return;
+        };
+        let outputs: Vec<usize> = (0..node.outputs.len())
+            .filter(|o| !matches!(node.outputs[*o].output_type, OutputType::Fees))
+            .collect();
+        let current = self
+            .focused_output
+            .and_then(|focused| outputs.iter().position(|o| *o == focused));
+        let next = match (current, backwards) {
+            (None, false) => 0,
+            (None, true) => outputs.len().saturating_sub(1),
+            (Some(i), false) => (i + 1) % outputs.len(),
+            (Some(i), true) => (i + outputs.len() - 1) % outputs.len(),
+        };
+        self.focused_output = outputs.get(next).copied();
     }
 
-    pub fn select(&mut self, txid: Txid) {
-        self.selected_node = Some(txid);
+    /// Transaction connected to [txid] that lies in [direction] on the canvas, the closest one if
+    /// there are several. Going down follows the focused output if it's spent in the graph.
+    pub fn connected_in_direction(&self, txid: Txid, direction: Vec2) -> Option<Txid> {
+        let node = self.nodes.get(&txid)?;
+        if direction.y > 0.0 && self.selected_node == Some(txid) {
+            if let Some(OutputType::Spent { spending_txid, .. }) = self
+                .focused_output
+                .and_then(|o| node.outputs.get(o))
+                .map(|output| &output.output_type)
+            {
+                if self.nodes.contains_key(spending_txid) {
+                    return Some(*spending_txid);
+                }
+            }
+        }
+
+        self.edges
+            .iter()
+            .filter_map(|edge| {
+                if edge.source == txid {
+                    Some(edge.target)
+                } else if edge.target == txid {
+                    Some(edge.source)
+                } else {
+                    None
+                }
+            })
+            .filter_map(|other| Some((other, self.nodes.get(&other)?.pos - node.pos)))
+            // Within 60° of the direction.
+            .filter(|(_, diff)| diff.normalized().dot(direction) > 0.5)
+            .min_by(|(_, a), (_, b)| a.length_sq().total_cmp(&b.length_sq()))
+            .map(|(other, _)| other)
     }
 
     pub fn remove_tx(&mut self, txid: Txid) {
+        self.revision = next_revision();
         self.nodes.remove(&txid);
         self.edges
             .retain(|edge| edge.source != txid && edge.target != txid);
     }
 
     pub fn add_tx(&mut self, txid: Txid, tx: Transaction, pos: Pos2) {
+        self.revision = next_revision();
         // Add node
 
         let inputs = tx
@@ -202,6 +1402,8 @@ impl Graph {
                 address_type: i.address_type,
                 funding_txid: i.txid,
                 funding_vout: i.vout,
+                script_sig: i.script_sig_bytes(),
+                witness: i.witness_bytes(),
             })
             .collect();
 
@@ -223,6 +1425,7 @@ impl Graph {
                         address_type: o.address_type,
                     },
                 },
+                script_pubkey: o.script_pubkey_bytes(),
             })
             .collect();
 
@@ -233,6 +1436,7 @@ impl Graph {
                 end: 0.0,
                 value: tx.fees(),
                 output_type: OutputType::Fees,
+                script_pubkey: None,
             });
         }
 
@@ -248,7 +1452,11 @@ impl Graph {
                     .unwrap()
                     .format("%Y-%m-%d %H:%M:%S")
                     .to_string(),
+                unix_timestamp: tx.timestamp,
                 block_height: tx.block_height,
+                fees: if tx.is_coinbase() { 0 } else { tx.fees() },
+                vsize: tx.vsize(),
+                omni: OmniTransfer::from_tx(&tx),
                 inputs,
                 outputs,
             },
@@ -287,6 +1495,7 @@ impl Graph {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn draw(
         &mut self,
         ui: &egui::Ui,
@@ -294,64 +1503,177 @@ impl Graph {
         update_sender: Sender<Update>,
         layout: &Layout,
         annotations: &mut Annotations,
+        stamps: &Stamps,
+        time_window: Option<TimeWindow>,
+        clusters: Option<&Clusters>,
+        currency: Option<&str>,
     ) {
+        let cluster_color = |address: &str| clusters.and_then(|clusters| clusters.color(address));
+        let change = self.change_outputs(annotations);
+        let change_ui = |ui: &mut egui::Ui, coin| {
+            if let Some(reasons) = change.get(&coin) {
+                if reasons.is_empty() {
+                    ui.label("Change (set manually)");
+                } else {
+                    let reasons: Vec<_> = reasons.iter().map(Reason::to_string).collect();
+                    ui.label(format!("Likely change: {}", reasons.join(", ")));
+                }
+            }
+        };
+
         let style = style::get(ui);
 
         let clip_rect = ui.clip_rect();
 
+        let animating = if let Some(animation) = &mut self.animation {
+            let (positions, done) = animation.step();
+            for (txid, pos) in positions {
+                if let Some(node) = self.nodes.get_mut(&txid) {
+                    node.pos = pos;
+                    node.velocity = Vec2::ZERO;
+                }
+            }
+            if done {
+                self.animation = None;
+            }
+            ui.ctx().request_repaint();
+            true
+        } else {
+            false
+        };
+
         for node in self.nodes.values_mut() {
             node.scale(&layout.scale);
         }
 
-        // PREPARE RECTS //
-
-        let mut input_rects: HashMap<(Txid, usize), Rect> = HashMap::new();
-        let mut output_rects: HashMap<(Txid, usize), Rect> = HashMap::new();
-        let mut inner_rects: HashMap<Txid, Rect> = HashMap::new();
-        let mut outer_rects: HashMap<Txid, Rect> = HashMap::new();
-
-        for (txid, node) in &self.nodes {
-            let outer_rect = Rect::from_center_size(
-                node.pos,
-                Vec2::new(node.size, style.tx_width + 2.0 * style.io_width),
-            );
-            let inner_rect = Rect::from_center_size(node.pos, Vec2::new(node.size, style.tx_width));
-
-            outer_rects.insert(*txid, outer_rect);
-            inner_rects.insert(*txid, inner_rect);
-
-            let left_top = outer_rect.left_top();
-            for (i, input) in node.inputs.iter().enumerate() {
-                let rect = Rect::from_min_max(
-                    Pos2::new(left_top.x + input.start, left_top.y),
-                    Pos2::new(left_top.x + input.end, left_top.y + style.io_width),
-                );
-                input_rects.insert((*txid, i), rect);
-            }
-
-            let left_bot = outer_rect.left_bottom();
-            for (o, output) in node.outputs.iter().enumerate() {
-                let rect = Rect::from_min_max(
-                    Pos2::new(left_bot.x + output.start, left_bot.y - style.io_width),
-                    Pos2::new(left_bot.x + output.end, left_bot.y),
-                );
-                output_rects.insert((*txid, o), rect);
+        let Rects {
+            inputs: mut input_rects,
+            outputs: mut output_rects,
+            inner: mut inner_rects,
+            outer: mut outer_rects,
+        } = self.rects(&style, time_window);
+
+        // Collapsed groups replace their visible members with a single node.
+        let collapsed = annotations.collapsed(
+            self.nodes
+                .keys()
+                .map(|txid| (*txid, self.input_addresses_of(*txid))),
+        );
+        let mut members: HashMap<usize, Vec<Txid>> = HashMap::new();
+        for (txid, group) in &collapsed {
+            if outer_rects.contains_key(txid) {
+                members.entry(*group).or_default().push(*txid);
             }
         }
+        let group_nodes: Vec<GroupNode> = members
+            .into_iter()
+            .map(|(group, txids)| {
+                let (value_in, value_out) = self.group_flows(&txids);
+                let center = txids
+                    .iter()
+                    .fold(Vec2::ZERO, |sum, txid| sum + self.nodes[txid].pos.to_vec2())
+                    / txids.len() as f32;
+                let width = layout.scale.apply(value_in.max(value_out)) as f32;
+                GroupNode {
+                    group,
+                    txids,
+                    value_in,
+                    value_out,
+                    rect: Rect::from_center_size(
+                        center.to_pos2(),
+                        Vec2::new(width, style.tx_width),
+                    ),
+                }
+            })
+            .collect();
+        let hidden = |txid: &Txid| collapsed.contains_key(txid) || annotations.hidden_tx(*txid);
+        outer_rects.retain(|txid, _| !hidden(txid));
+        inner_rects.retain(|txid, _| !hidden(txid));
+        input_rects.retain(|(txid, _), _| !hidden(txid));
+        output_rects.retain(|(txid, _), _| !hidden(txid));
+
+        let fiat = |job: &mut LayoutJob, ctx: &egui::Context, sats: u64, timestamp: i64| {
+            fiat_layout(job, ctx, currency, sats, timestamp, &style)
+        };
+
+        let small = |sats: u64, timestamp: i64| {
+            layout.edge_filter.filters(sats, currency, |currency| {
+                Some(Prices::quote(ui.ctx(), currency, timestamp)?.rate)
+            })
+        };
+        let small_flow = |edge: &DrawableEdge| {
+            let Some(target) = self.nodes.get(&edge.target) else {
+                return false;
+            };
+            small(target.inputs[edge.target_pos].value, target.unix_timestamp)
+        };
+
+        let on_path = |edge: &DrawableEdge| {
+            self.path
+                .windows(2)
+                .any(|pair| pair[0] == edge.source && pair[1] == edge.target)
+        };
+        // Flows off the found path fade into the background, and the path isn't filtered.
+        let dimmed = |edge: &DrawableEdge| {
+            let filtered = small_flow(edge) && !on_path(edge);
+            (
+                filtered,
+                filtered || !self.path.is_empty() && !on_path(edge),
+            )
+        };
+
+        // Opacity by age: the oldest visible tx gets MIN_AGE_OPACITY, the newest is fully opaque.
+        let age_range = if layout.fade_by_age {
+            let visible = self
+                .nodes
+                .iter()
+                .filter(|(txid, _)| outer_rects.contains_key(txid))
+                .map(|(_, node)| node.unix_timestamp);
+            visible.clone().min().zip(visible.max())
+        } else {
+            None
+        };
+        let opacity = |txid: &Txid| -> f32 {
+            match (age_range, self.nodes.get(txid)) {
+                (Some((oldest, newest)), Some(node)) if newest > oldest => {
+                    let age = (newest - node.unix_timestamp) as f32 / (newest - oldest) as f32;
+                    1.0 - age * (1.0 - MIN_AGE_OPACITY)
+                }
+                _ => 1.0,
+            }
+        };
+        let opacities: HashMap<Txid, f32> = outer_rects.keys().map(|t| (*t, opacity(t))).collect();
 
         // DRAW EDGES //
 
         for edge in &self.edges {
-            let from_rect = output_rects.get(&(edge.source, edge.source_pos)).unwrap();
-            let to_rect = input_rects.get(&(edge.target, edge.target_pos)).unwrap();
+            let (Some(from_rect), Some(to_rect)) = (
+                output_rects.get(&(edge.source, edge.source_pos)),
+                input_rects.get(&(edge.target, edge.target_pos)),
+            ) else {
+                // One end is hidden.
+                continue;
+            };
+            if annotations.hidden_coin((edge.source, edge.source_pos)) {
+                continue;
+            }
 
             let bounding_rect = transform.rect_to_screen(from_rect.union(*to_rect));
             if !clip_rect.intersects(bounding_rect) {
                 continue;
             }
 
+            let (filtered, dimmed) = dimmed(edge);
+            if filtered && layout.edge_filter.hide {
+                continue;
+            }
+
             let coin = (edge.source, edge.source_pos);
-            let color = annotations.coin_color(coin).unwrap_or(Color32::GOLD);
+            let color = annotations
+                .coin_color(coin)
+                .unwrap_or(Color32::GOLD)
+                .gamma_multiply(opacities[&edge.source])
+                .gamma_multiply(if dimmed { FILTERED_OPACITY } else { 1.0 });
 
             let flow = Edge {
                 from: from_rect.left_bottom(),
@@ -366,14 +1688,21 @@ impl Graph {
                     if let Some(label) = annotations.coin_label(coin) {
                         ui.label(RichText::new(format!("[{}]", label)).heading().monospace());
                     }
-                    let input = &self.nodes.get(&edge.target).unwrap().inputs[edge.target_pos];
+                    let target = self.nodes.get(&edge.target).unwrap();
+                    let input = &target.inputs[edge.target_pos];
                     let mut job = LayoutJob::default();
                     sats_layout(&mut job, &Sats(input.value), &style);
+                    fiat(&mut job, ui.ctx(), input.value, target.unix_timestamp);
                     newline(&mut job, &style.font_id());
                     address_layout(&mut job, &input.address, input.address_type, &style);
                     ui.label(job);
                 });
-            response.context_menu(|ui| annotations.coin_menu(coin, ui));
+            response.context_menu(|ui| {
+                annotations.coin_menu(coin, ui);
+                ui.separator();
+                let target = self.nodes.get(&edge.target).unwrap();
+                explorer::address_button(ui, &target.inputs[edge.target_pos].address);
+            });
 
             if response.clicked {
                 ui.output_mut(|o| {
@@ -384,20 +1713,118 @@ impl Graph {
             }
         }
 
+        let group_rect = |txid: &Txid| {
+            let group = collapsed.get(txid)?;
+            group_nodes
+                .iter()
+                .find(|n| n.group == *group)
+                .map(|n| n.rect)
+        };
+        for edge in &self.edges {
+            match (collapsed.get(&edge.source), collapsed.get(&edge.target)) {
+                (None, None) => continue,
+                (Some(a), Some(b)) if a == b => continue,
+                _ => {}
+            }
+            if annotations.hidden_coin((edge.source, edge.source_pos)) {
+                continue;
+            }
+            let (filtered, dimmed) = dimmed(edge);
+            if filtered && layout.edge_filter.hide {
+                continue;
+            }
+            let source = output_rects.get(&(edge.source, edge.source_pos)).copied();
+            let target = input_rects.get(&(edge.target, edge.target_pos)).copied();
+            let width = source.or(target).map_or(4.0, |rect| rect.width());
+            let offset = Vec2::new(width / 2.0, 0.0);
+            let Some(from) = source
+                .map(|rect| rect.left_bottom())
+                .or_else(|| group_rect(&edge.source).map(|rect| rect.center_bottom() - offset))
+            else {
+                continue;
+            };
+            let Some(to) = target
+                .map(|rect| rect.left_top())
+                .or_else(|| group_rect(&edge.target).map(|rect| rect.center_top() - offset))
+            else {
+                continue;
+            };
+
+            let coin = (edge.source, edge.source_pos);
+            let flow = Edge {
+                from,
+                from_width: width,
+                to,
+                to_width: width,
+            };
+            flow.draw(
+                ui,
+                annotations
+                    .coin_color(coin)
+                    .unwrap_or(Color32::GOLD)
+                    .gamma_multiply(if dimmed { FILTERED_OPACITY } else { 1.0 }),
+                layout.show_arrows,
+                transform,
+                &coin,
+            )
+            .on_hover_ui_at_pointer(|ui| {
+                if let Some(label) = annotations.coin_label(coin) {
+                    ui.label(RichText::new(format!("[{}]", label)).heading().monospace());
+                }
+                let target = &self.nodes[&edge.target];
+                let input = &target.inputs[edge.target_pos];
+                let mut job = LayoutJob::default();
+                sats_layout(&mut job, &Sats(input.value), &style);
+                fiat(&mut job, ui.ctx(), input.value, target.unix_timestamp);
+                ui.label(job);
+            });
+        }
+
         // DRAW NODES //
 
+        let now = ui.input(|i| i.time);
+        let flash = self.flash.filter(|(_, start)| now - start < FLASH_SECONDS);
+        self.flash = flash;
+
         let initial_dist = Vec2::new(0.0, style.io_width + style.tx_width / 2.0 + 5.0);
         let painter = ui.painter();
         let txids: HashSet<Txid> = self.nodes.keys().copied().collect();
+        let mut toggled = None;
+        // Dragging one of the multi-selected transactions moves all of them.
+        let mut dragged_selection = None;
 
         for (txid, node) in &mut self.nodes {
-            let outer_rect = transform.rect_to_screen(*outer_rects.get(txid).unwrap());
+            let Some(outer_rect) = outer_rects.get(txid) else {
+                continue;
+            };
+            let outer_rect = transform.rect_to_screen(*outer_rect);
+            let opacity = opacities[txid];
 
             if !clip_rect.intersects(outer_rect.expand(style.selected_stroke_width * 2.0)) {
                 continue;
             }
 
-            if Some(*txid) == self.selected_node {
+            if let Some((_, start)) = flash.filter(|(flashed, _)| flashed == txid) {
+                // Pulses a few times, fading out.
+                let t = ((now - start) / FLASH_SECONDS) as f32;
+                let pulse = (t * 3.0 * std::f32::consts::TAU).cos() * 0.5 + 0.5;
+                painter.rect_stroke(
+                    outer_rect.expand(4.0 + 4.0 * pulse),
+                    Rounding::same(2.0),
+                    Stroke::new(3.0, ui.visuals().warn_fg_color.gamma_multiply(1.0 - t)),
+                );
+                ui.ctx().request_repaint();
+            }
+
+            if self.path.contains(txid) {
+                painter.rect_stroke(
+                    outer_rect.expand(style.selected_stroke_width * 1.5),
+                    Rounding::same(2.0),
+                    Stroke::new(style.selected_stroke_width, style.io_highlight_color),
+                );
+            }
+
+            if Some(*txid) == self.selected_node || self.multi_selection.contains(txid) {
                 painter.rect(
                     outer_rect.expand(style.selected_stroke_width / 2.0),
                     Rounding::ZERO,
@@ -407,6 +1834,7 @@ impl Graph {
             }
 
             let label = annotations.tx_label(*txid);
+            let coinjoin = node.coinjoin();
             let rect = transform.rect_to_screen(*inner_rects.get(txid).unwrap());
             let response = ui
                 .interact(rect, ui.id().with(txid), Sense::click_and_drag())
@@ -427,15 +1855,61 @@ impl Graph {
                     }
                     newline(&mut job, &FontId::monospace(5.0));
                     sats_layout(&mut job, &Sats(node.tx_value), &style);
+                    fiat(&mut job, ui.ctx(), node.tx_value, node.unix_timestamp);
+                    let status = match (node.block_height, self.tip_height) {
+                        (0, _) => "unconfirmed".to_string(),
+                        (height, Some(tip)) if tip >= height => {
+                            format!("block {}, {} confirmations", height, tip - height + 1)
+                        }
+                        (height, _) => format!("block {}", height),
+                    };
                     job.append(
-                        &format!("\n{} (block {})", node.tx_timestamp, node.block_height),
+                        &format!("\n{} ({})", node.tx_timestamp, status),
                         0.0,
                         format.clone(),
                     );
+                    if !node.inputs.is_empty() {
+                        let fee = TxFee {
+                            txid: *txid,
+                            fees: node.fees,
+                            vsize: node.vsize,
+                        };
+                        let size = match (fee.rate(), fee.vsize) {
+                            (Some(rate), Some(vsize)) => {
+                                format!(", {:.1} sat/vB, {} vB", rate, vsize)
+                            }
+                            _ => String::new(),
+                        };
+                        job.append(
+                            &format!("\nFee: {} sats{}", Sats(fee.fees), size),
+                            0.0,
+                            format.clone(),
+                        );
+                    }
+                    if let Some(omni) = &node.omni {
+                        job.append(&format!("\nOmni: {}", omni), 0.0, format.clone());
+                    }
                     ui.label(job);
+                    if let Some(cost_basis) = annotations.cost_basis(*txid) {
+                        ui.label(format!("Cost basis: {}", cost_basis));
+                    }
+                    if let Some(coinjoin) = &coinjoin {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!(
+                                "Likely {} coinjoin: {} equal outputs of {} sats.\n\
+                                 Tracing funds through it is unreliable.",
+                                coinjoin.kind,
+                                coinjoin.equal_outputs,
+                                Sats(coinjoin.denomination)
+                            ),
+                        );
+                    }
                 });
             response.context_menu(|ui| {
                 ui.menu_button("Annotate", |ui| annotations.tx_menu(*txid, ui));
+                ui.menu_button("Stamp", |ui| stamps.tx_menu(annotations, *txid, ui));
+                ui.menu_button("Group", |ui| annotations.group_menu(*txid, ui));
                 ui.menu_button("Export to Clipboard", |ui| {
                     if ui.button("Beancount").clicked() {
                         ui.ctx().output_mut(|o| {
@@ -443,11 +1917,22 @@ impl Graph {
                         });
                         ui.close_menu();
                     }
+                    if ui.button("CSV").clicked() {
+                        update_sender
+                            .send(Update::CopyTxCsv { txid: *txid })
+                            .unwrap();
+                        ui.close_menu();
+                    }
                 });
+                if ui.button("Expand…").clicked() {
+                    update_sender.send(Update::Expand { txid: *txid }).unwrap();
+                    ui.close_menu();
+                }
                 if ui.button("Copy Txid").clicked() {
                     ui.output_mut(|o| o.copied_text = txid.hex_string());
                     ui.close_menu();
                 }
+                explorer::tx_button(ui, *txid);
                 if ui.button("Remove").clicked() {
                     update_sender
                         .send(Update::RemoveTx { txid: *txid })
@@ -456,7 +1941,9 @@ impl Graph {
                 }
             });
 
-            if response.clicked() {
+            if response.clicked() && ui.input(|i| i.modifiers.shift) {
+                toggled = Some(*txid);
+            } else if response.clicked() {
                 push_history_state(&format!("tx/{}", txid.hex_string()));
                 update_sender
                     .send(Update::SelectTx { txid: *txid })
@@ -470,7 +1957,11 @@ impl Graph {
             if response.dragged() {
                 node.dragged = true;
                 node.velocity = Vec2::ZERO;
-                node.pos += transform.vec_from_screen(response.drag_delta());
+                let delta = transform.vec_from_screen(response.drag_delta());
+                node.pos += delta;
+                if self.multi_selection.contains(txid) {
+                    dragged_selection = Some((*txid, delta));
+                }
                 ui.output_mut(|o| o.cursor_icon = CursorIcon::Grabbing);
             } else {
                 node.dragged = false;
@@ -482,11 +1973,45 @@ impl Graph {
                 annotations
                     .tx_color(*txid)
                     .unwrap_or(style.tx_bg)
-                    .gamma_multiply(0.4),
+                    .gamma_multiply(0.4 * opacity),
                 style.tx_stroke(),
             );
 
+            if node.block_height == 0 {
+                let r = rect.expand(3.0);
+                painter.add(Shape::dashed_line(
+                    &[
+                        r.left_top(),
+                        r.right_top(),
+                        r.right_bottom(),
+                        r.left_bottom(),
+                        r.left_top(),
+                    ],
+                    Stroke::new(style.tx_stroke_width, ui.visuals().warn_fg_color),
+                    4.0,
+                    3.0,
+                ));
+            }
+
             let tx_painter = painter.with_clip_rect(rect);
+            if coinjoin.is_some() {
+                // Hatched, so that coinjoins stand out regardless of the fill color.
+                let stroke = Stroke::new(
+                    1.0,
+                    ui.visuals().warn_fg_color.gamma_multiply(0.3 * opacity),
+                );
+                let mut x = rect.left() - rect.height();
+                while x < rect.right() {
+                    tx_painter.line_segment(
+                        [
+                            Pos2::new(x, rect.bottom()),
+                            Pos2::new(x + rect.height(), rect.top()),
+                        ],
+                        stroke,
+                    );
+                    x += 8.0;
+                }
+            }
             tx_painter.galley(
                 rect.left_top() + Vec2::new(2.0, 2.0),
                 tx_painter.layout_job(tx_content(
@@ -494,6 +2019,9 @@ impl Graph {
                     &label,
                     &node.tx_timestamp,
                     &Sats(node.tx_value),
+                    currency.map(|currency| {
+                        Prices::fiat_text(ui.ctx(), currency, node.tx_value, node.unix_timestamp)
+                    }),
                     &style,
                 )),
                 Color32::TRANSPARENT,
@@ -519,6 +2047,7 @@ impl Graph {
                         );
                         let mut job = LayoutJob::default();
                         sats_layout(&mut job, &Sats(input.value), &style);
+                        fiat(&mut job, ui.ctx(), input.value, node.unix_timestamp);
                         newline(&mut job, &style.font_id());
                         address_layout(&mut job, &input.address, input.address_type, &style);
                         newline(&mut job, &style.font_id());
@@ -526,7 +2055,23 @@ impl Graph {
                         txid_layout(&mut job, &input.funding_txid, &style);
                         ui.label(job);
                     });
-                response.context_menu(|ui| annotations.coin_menu(coin, ui));
+                response.context_menu(|ui| {
+                    annotations.coin_menu(coin, ui);
+                    if !input.address.is_empty() {
+                        ui.menu_button("Group Address", |ui| {
+                            annotations.address_group_menu(&input.address, ui)
+                        });
+                    }
+                    ui.separator();
+                    explorer::address_button(ui, &input.address);
+                    let scripts = input.scripts();
+                    if !scripts.is_empty() {
+                        ui.separator();
+                        ui.menu_button("Inspect Script", |ui| {
+                            script::inspector_ui(ui, &scripts);
+                        });
+                    }
+                });
 
                 if response.clicked() {
                     if txids.contains(&input.funding_txid) {
@@ -550,8 +2095,9 @@ impl Graph {
                     Rounding::ZERO,
                     annotations
                         .coin_color(coin)
+                        .or_else(|| cluster_color(&input.address))
                         .unwrap_or(style.io_bg)
-                        .gamma_multiply(0.4),
+                        .gamma_multiply(0.4 * opacity),
                     Stroke::NONE,
                 );
 
@@ -577,6 +2123,16 @@ impl Graph {
             // hover boxes of the outpus.
             for (o, output) in node.outputs.iter().enumerate().rev() {
                 let coin = (*txid, o);
+                let filtered = !matches!(output.output_type, OutputType::Fees)
+                    && small(output.value, node.unix_timestamp);
+                if filtered && layout.edge_filter.hide {
+                    continue;
+                }
+                let opacity = if filtered {
+                    opacity * FILTERED_OPACITY
+                } else {
+                    opacity
+                };
 
                 let rect = *output_rects.get(&(*txid, o)).unwrap();
                 let screen_rect = transform.rect_to_screen(rect);
@@ -598,9 +2154,19 @@ impl Graph {
                             );
                             let mut job = LayoutJob::default();
                             sats_layout(&mut job, &Sats(output.value), &style);
+                            fiat(&mut job, ui.ctx(), output.value, node.unix_timestamp);
                             newline(&mut job, &style.font_id());
                             address_layout(&mut job, address, *address_type, &style);
                             ui.label(job);
+                            change_ui(ui, coin);
+                            if let Some(data) = output
+                                .script_pubkey
+                                .as_deref()
+                                .and_then(script::op_return_data)
+                            {
+                                ui.separator();
+                                script::payload_ui(ui, &data);
+                            }
                         }
                         OutputType::Spent {
                             spending_txid,
@@ -618,23 +2184,45 @@ impl Graph {
                             );
                             let mut job = LayoutJob::default();
                             sats_layout(&mut job, &Sats(output.value), &style);
+                            fiat(&mut job, ui.ctx(), output.value, node.unix_timestamp);
                             newline(&mut job, &style.font_id());
                             address_layout(&mut job, address, *address_type, &style);
                             newline(&mut job, &style.font_id());
                             newline(&mut job, &FontId::monospace(5.0));
                             txid_layout(&mut job, spending_txid, &style);
                             ui.label(job);
+                            change_ui(ui, coin);
                         }
                         OutputType::Fees => {
                             ui.label(RichText::new("Fees").heading().monospace());
-                            ui.add(SatsDisplay::new(Sats(output.value), &style));
+                            let mut job = LayoutJob::default();
+                            sats_layout(&mut job, &Sats(output.value), &style);
+                            fiat(&mut job, ui.ctx(), output.value, node.unix_timestamp);
+                            ui.label(job);
                         }
                     });
 
-                match output.output_type {
+                match &output.output_type {
                     OutputType::Fees => {}
-                    _ => {
-                        response.context_menu(|ui| annotations.coin_menu(coin, ui));
+                    OutputType::Spent { address, .. } | OutputType::Utxo { address, .. } => {
+                        response.context_menu(|ui| {
+                            annotations.coin_menu(coin, ui);
+                            annotations.change_menu(coin, ui);
+                            ui.menu_button("Group Address", |ui| {
+                                annotations.address_group_menu(address, ui)
+                            });
+                            ui.separator();
+                            explorer::address_button(ui, address);
+                            if let Some(script_pubkey) = &output.script_pubkey {
+                                ui.separator();
+                                ui.menu_button("Inspect Script", |ui| {
+                                    script::inspector_ui(
+                                        ui,
+                                        &[("scriptPubKey", script_pubkey.clone())],
+                                    );
+                                });
+                            }
+                        });
                     }
                 }
 
@@ -665,22 +2253,24 @@ impl Graph {
                 painter.rect(
                     screen_rect,
                     Rounding::ZERO,
-                    match output.output_type {
+                    match &output.output_type {
                         OutputType::Utxo {
-                            address: _,
+                            address,
                             address_type: _,
                         } => annotations
                             .coin_color(coin)
+                            .or_else(|| cluster_color(address))
                             .unwrap_or(style.utxo_fill())
-                            .gamma_multiply(0.4),
+                            .gamma_multiply(0.4 * opacity),
                         OutputType::Spent {
                             spending_txid: _,
-                            address: _,
+                            address,
                             address_type: _,
                         } => annotations
                             .coin_color(coin)
+                            .or_else(|| cluster_color(address))
                             .unwrap_or(style.io_bg)
-                            .gamma_multiply(0.4),
+                            .gamma_multiply(0.4 * opacity),
                         OutputType::Fees => style.fees_fill(),
                     },
                     Stroke::NONE,
@@ -701,7 +2291,7 @@ impl Graph {
                     screen_rect,
                     Rounding::ZERO,
                     Color32::TRANSPARENT,
-                    match output.output_type {
+                    match &output.output_type {
                         OutputType::Spent {
                             spending_txid: _,
                             address: _,
@@ -710,12 +2300,135 @@ impl Graph {
                         _ => style.tx_stroke(),
                     },
                 );
+
+                if Some(*txid) == self.selected_node && Some(o) == self.focused_output {
+                    painter.rect_stroke(
+                        screen_rect.expand(style.selected_stroke_width / 2.0),
+                        Rounding::ZERO,
+                        style.selected_tx_stroke(),
+                    );
+                }
+
+                if let Some(script_type) = output.script_type() {
+                    badge(painter, screen_rect, script_type, &style, opacity);
+                }
+
+                if change.contains_key(&coin) {
+                    let r = screen_rect.shrink(2.0);
+                    painter.add(Shape::dashed_line(
+                        &[
+                            r.left_top(),
+                            r.right_top(),
+                            r.right_bottom(),
+                            r.left_bottom(),
+                            r.left_top(),
+                        ],
+                        style.change_stroke(),
+                        3.0,
+                        2.0,
+                    ));
+                }
+            }
+        }
+
+        if let Some(txid) = toggled {
+            self.toggle_selected(txid);
+        }
+        if let Some((dragged, delta)) = dragged_selection {
+            for txid in &self.multi_selection {
+                if let Some(node) = self.nodes.get_mut(txid).filter(|_| *txid != dragged) {
+                    node.pos += delta;
+                    node.velocity = Vec2::ZERO;
+                }
+            }
+        }
+
+        // DRAW GROUPS //
+
+        for group_node in &group_nodes {
+            let rect = transform.rect_to_screen(group_node.rect);
+            if !clip_rect.intersects(rect) {
+                continue;
+            }
+            let name = annotations.groups()[group_node.group].name.clone();
+            let response = ui
+                .interact(
+                    rect,
+                    ui.id().with("group").with(group_node.group),
+                    Sense::click_and_drag(),
+                )
+                .on_hover_ui(|ui| {
+                    ui.label(RichText::new(format!("[{}]", name)).heading().monospace());
+                    ui.label(format!("{} transactions", group_node.txids.len()));
+                    let flows = [
+                        ("In: ", group_node.value_in),
+                        ("Out:", group_node.value_out),
+                    ];
+                    for (text, value) in flows {
+                        let mut job = LayoutJob::default();
+                        job.append(
+                            text,
+                            0.0,
+                            TextFormat {
+                                font_id: style.font_id(),
+                                color: style.black_text_color(),
+                                ..Default::default()
+                            },
+                        );
+                        sats_layout(&mut job, &Sats(value), &style);
+                        ui.label(job);
+                    }
+                    ui.weak("Click to expand.");
+                });
+
+            if response.clicked() {
+                annotations.set_collapsed(group_node.group, false);
+            }
+            if response.hovered() {
+                ui.output_mut(|o| o.cursor_icon = CursorIcon::Grab);
+            }
+            if response.dragged() {
+                let delta = transform.vec_from_screen(response.drag_delta());
+                for txid in &group_node.txids {
+                    if let Some(node) = self.nodes.get_mut(txid) {
+                        node.velocity = Vec2::ZERO;
+                        node.pos += delta;
+                    }
+                }
+                ui.output_mut(|o| o.cursor_icon = CursorIcon::Grabbing);
             }
+
+            painter.rect_stroke(rect.expand(3.0), Rounding::same(3.0), style.tx_stroke());
+            painter.rect(
+                rect,
+                Rounding::ZERO,
+                style.tx_bg.gamma_multiply(0.4),
+                style.tx_stroke(),
+            );
+            let font_id = FontId::monospace(10.0);
+            let mut job = LayoutJob::default();
+            job.append(
+                &name,
+                0.0,
+                TextFormat {
+                    font_id: font_id.clone(),
+                    color: style.black_text_color(),
+                    ..Default::default()
+                },
+            );
+            newline(&mut job, &font_id);
+            sats_layout(&mut job, &Sats(group_node.value_out), &style);
+            let group_painter = painter.with_clip_rect(rect);
+            group_painter.galley(
+                rect.left_top() + Vec2::new(2.0, 2.0),
+                group_painter.layout_job(job),
+                Color32::TRANSPARENT,
+            );
         }
 
         // CALCULATE FORCES AND UPDATE VELOCITY //
 
-        if !layout.force_params.active {
+        if !layout.force_params.active || animating {
             return;
         }
 
@@ -741,9 +2454,8 @@ impl Graph {
                 }
 
                 let diff = other_rect.center() - rect.center();
-                let force = -scale2 / spacing
-                    * kernel(tx_repulsion_radius, spacing)
-                    * diff.normalized();
+                let force =
+                    -scale2 / spacing * kernel(tx_repulsion_radius, spacing) * diff.normalized();
 
                 self.nodes.get_mut(txid).unwrap().velocity += force * layout.force_params.dt;
             }
@@ -758,8 +2470,13 @@ impl Graph {
         }
 
         for edge in &self.edges {
-            let from_rect = output_rects.get(&(edge.source, edge.source_pos)).unwrap();
-            let to_rect = input_rects.get(&(edge.target, edge.target_pos)).unwrap();
+            let (Some(from_rect), Some(to_rect)) = (
+                output_rects.get(&(edge.source, edge.source_pos)),
+                input_rects.get(&(edge.target, edge.target_pos)),
+            ) else {
+                // One end is hidden.
+                continue;
+            };
 
             // Attraction force between nodes
             let diff = to_rect.center_top() - from_rect.center_bottom();
@@ -789,6 +2506,12 @@ impl Graph {
     }
 }
 
+/// Of flows below the threshold of the edge filter, unless they're hidden.
+const FILTERED_OPACITY: f32 = 0.15;
+
+/// How long a transaction is highlighted after [Graph::flash].
+const FLASH_SECONDS: f64 = 1.5;
+
 fn clear_spacing(a: &Rect, b: &Rect) -> f32 {
     let x = (a.center().x - b.center().x).abs() - (b.width() + a.width()) / 2.0;
     let y = (a.center().y - b.center().y).abs() - (b.height() + a.height()) / 2.0;
@@ -800,6 +2523,7 @@ fn tx_content(
     label: &Option<String>,
     timestamp: &str,
     sats: &Sats,
+    fiat: Option<String>,
     style: &Style,
 ) -> LayoutJob {
     let mut job = LayoutJob::default();
@@ -817,6 +2541,9 @@ fn tx_content(
     }
     newline(&mut job, &font_id);
     sats_layout(&mut job, sats, style);
+    if let Some(fiat) = fiat {
+        job.append(&fiat, 2.0 * SPACING, format.clone());
+    }
     newline(&mut job, &font_id);
     job.append(&timestamp[2..], 0.0, format);
     job
@@ -835,6 +2562,15 @@ fn newline(job: &mut LayoutJob, font_id: &FontId) {
 
 const SPACING: f32 = 3.0;
 
+fn coin_entity(annotations: &Annotations, coin: (Txid, usize)) -> String {
+    annotations
+        .coin_label(coin)
+        .unwrap_or_else(|| "Unlabelled".to_string())
+}
+
+/// Opacity of the oldest transaction when fading by age.
+const MIN_AGE_OPACITY: f32 = 0.2;
+
 fn txid_layout(job: &mut LayoutJob, txid: &Txid, style: &Style) {
     let black_format = TextFormat {
         font_id: style.font_id(),
@@ -865,6 +2601,28 @@ fn txid_layout(job: &mut LayoutJob, txid: &Txid, style: &Style) {
     }
 }
 
+/// The value in [currency] after the BTC amount, on the same line.
+fn fiat_layout(
+    job: &mut LayoutJob,
+    ctx: &egui::Context,
+    currency: Option<&str>,
+    sats: u64,
+    timestamp: i64,
+    style: &Style,
+) {
+    if let Some(currency) = currency {
+        job.append(
+            &Prices::fiat_text(ctx, currency, sats, timestamp),
+            2.0 * SPACING,
+            TextFormat {
+                font_id: style.font_id(),
+                color: style.black_text_color(),
+                ..Default::default()
+            },
+        );
+    }
+}
+
 pub fn sats_layout(job: &mut LayoutJob, sats: &Sats, style: &Style) {
     let font_id = style.font_id();
     let btc_font = FontId::new(font_id.size, egui::FontFamily::Name("btc".into()));
@@ -1071,6 +2829,30 @@ fn address_layout(job: &mut LayoutJob, address: &str, address_type: AddressType,
     job.append(&format!(" ({})", type_), 0.0, type_format);
 }
 
+/// Small label in the bottom left corner of [rect], if it fits.
+fn badge(painter: &Painter, rect: Rect, text: &str, style: &Style, opacity: f32) {
+    let galley = painter.layout_no_wrap(
+        text.to_string(),
+        FontId::monospace(7.0),
+        style.black_text_color().gamma_multiply(opacity),
+    );
+    let size = galley.size() + Vec2::new(4.0, 2.0);
+    if size.x > rect.width() - 4.0 || size.y > rect.height() - 4.0 {
+        return;
+    }
+    let badge = Rect::from_min_size(rect.left_bottom() + Vec2::new(2.0, -size.y - 2.0), size);
+    painter.rect_filled(
+        badge,
+        Rounding::same(2.0),
+        style.tx_bg.gamma_multiply(0.3 * opacity),
+    );
+    painter.galley(
+        badge.min + Vec2::new(2.0, 1.0),
+        galley,
+        Color32::TRANSPARENT,
+    );
+}
+
 /// Fill the given rect with an animated striped pattern.
 fn rect_striped(ui: &egui::Ui, rect: Rect, color: Color32) {
     let width: f32 = 6.;
@@ -1104,3 +2886,140 @@ fn rect_striped(ui: &egui::Ui, rect: Rect, color: Color32) {
 
     ui.painter().with_clip_rect(rect).add(mesh);
 }
+
+#[cfg(test)]
+mod tests {
+    use egui::{Color32, Pos2};
+
+    use super::Graph;
+    use crate::{
+        annotations::Annotations,
+        bitcoin::{AddressType, Input, Output, Transaction, Txid},
+        entity::FlowMatrix,
+        expand::Direction,
+        sticky::{Kind, Sticky},
+        style::Style,
+    };
+
+    fn txid(n: u8) -> Txid {
+        Txid::new(&format!("{:02x}", n).repeat(32)).unwrap()
+    }
+
+    /// Funded by [inputs] (txid, value), paying [outputs] (value, spending txid).
+    fn tx(n: u8, inputs: &[(u8, u64)], outputs: &[(u64, Option<u8>)]) -> Transaction {
+        Transaction {
+            timestamp: 0,
+            txid: txid(n),
+            block_height: 1,
+            inputs: inputs
+                .iter()
+                .enumerate()
+                .map(|(vout, &(funding, value))| Input {
+                    txid: txid(funding),
+                    vout: vout as u32,
+                    value,
+                    address: format!("in{}", funding),
+                    address_type: AddressType::P2WPKH,
+                    script_sig: None,
+                    witness: None,
+                })
+                .collect(),
+            outputs: outputs
+                .iter()
+                .enumerate()
+                .map(|(i, &(value, spending))| Output {
+                    spending_txid: spending.map(txid),
+                    value,
+                    address: format!("out{}_{}", n, i),
+                    address_type: AddressType::P2WPKH,
+                    script_pubkey: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn neighbors() {
+        let mut graph = Graph::default();
+        let a = tx(
+            1,
+            &[(3, 1100)],
+            &[(1000, Some(2)), (50, Some(4)), (40, None)],
+        );
+        graph.add_tx(txid(1), a, Pos2::new(100.0, 100.0));
+
+        assert_eq!(
+            graph.neighbors(txid(1), Direction::Forward, 0),
+            vec![
+                (txid(2), Pos2::new(80.0, 170.0)),
+                (txid(4), Pos2::new(120.0, 170.0))
+            ]
+        );
+        assert_eq!(
+            graph.neighbors(txid(1), Direction::Forward, 100),
+            vec![(txid(2), Pos2::new(100.0, 170.0))]
+        );
+        assert_eq!(
+            graph.neighbors(txid(1), Direction::Backward, 0),
+            vec![(txid(3), Pos2::new(100.0, 30.0))]
+        );
+
+        // Transactions in the graph aren't neighbors to fetch.
+        graph.add_tx(txid(2), tx(2, &[(1, 1000)], &[]), Pos2::new(80.0, 170.0));
+        let both: Vec<Txid> = graph
+            .neighbors(txid(1), Direction::Both, 0)
+            .into_iter()
+            .map(|(txid, _)| txid)
+            .collect();
+        assert_eq!(both, vec![txid(4), txid(3)]);
+        assert!(graph.neighbors(txid(9), Direction::Both, 0).is_empty());
+    }
+
+    #[test]
+    fn svg_stickies() {
+        let mut graph = Graph::default();
+        graph.add_tx(txid(1), tx(1, &[(2, 600)], &[(500, None)]), Pos2::ZERO);
+        let mut annotations = Annotations::default();
+        let mut note = Sticky::new(Kind::Note, Pos2::new(-400.0, 0.0));
+        note.text = "Funds of the victim".to_string();
+        annotations.stickies_mut().push(note);
+        let style = Style::light(Default::default());
+
+        let svg = graph.to_svg(&annotations, &style, Color32::WHITE, None);
+        assert!(svg.contains(">Funds of the victim</tspan>"));
+        // The whole graph includes the stickies.
+        assert!(svg.contains(r#"viewBox="-420.0"#));
+    }
+
+    #[test]
+    fn entity_flows_pro_rata() {
+        let mut graph = Graph::default();
+        // Exchange and Alice pay Bob and Exchange, with a fee of 100.
+        graph.add_tx(
+            txid(1),
+            tx(1, &[(2, 600), (3, 400)], &[(500, None), (400, None)]),
+            Pos2::ZERO,
+        );
+        let mut annotations = Annotations::default();
+        annotations.set_coin_label((txid(2), 0), "Alice".to_string());
+        annotations.set_coin_label((txid(3), 1), "Exchange".to_string());
+        annotations.set_coin_label((txid(1), 0), "Exchange".to_string());
+        annotations.set_coin_label((txid(1), 1), "Bob".to_string());
+
+        let flows = graph.entity_flows(&annotations, "Exchange");
+        assert_eq!(flows.len(), 1);
+        let flow = &flows[0];
+        assert_eq!((flow.received, flow.sent), (500, 400));
+        // Alice paid for 60% of what the exchange received, the exchange for 40% of Bob's coin.
+        assert_eq!(flow.sources, vec![("Alice".to_string(), 300)]);
+        assert_eq!(flow.destinations, vec![("Bob".to_string(), 160)]);
+
+        let matrix = FlowMatrix::new(graph.entity_transfers(&annotations));
+        let value = |from: &str, to: &str| {
+            let index = |entity| matrix.entities.iter().position(|e| e == entity).unwrap();
+            matrix.values[index(from)][index(to)]
+        };
+        assert_eq!(value("Alice", "Exchange"), 300);
+        assert_eq!(value("Exchange", "Bob"), 160);
+    }
+}
@@ -0,0 +1,328 @@
+//! Importers for graphs exported from other coin-tracing tools. Only the transaction ids and
+//! labels are taken over; the transactions themselves are fetched from the backend.
+
+use std::collections::HashMap;
+
+use crate::{bitcoin::Txid, csv};
+
+#[derive(Debug, PartialEq)]
+pub struct ImportedTx {
+    pub txid: Txid,
+    pub label: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    GraphMl,
+    MaltegoCsv,
+}
+
+impl Format {
+    pub const ALL: [Format; 2] = [Format::GraphMl, Format::MaltegoCsv];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::GraphMl => "GraphML",
+            Format::MaltegoCsv => "Maltego CSV",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::GraphMl => "graphml",
+            Format::MaltegoCsv => "csv",
+        }
+    }
+
+    pub fn parse(&self, contents: &str) -> Result<Vec<ImportedTx>, String> {
+        let txs = match self {
+            Format::GraphMl => parse_graphml(contents)?,
+            Format::MaltegoCsv => parse_maltego_csv(contents)?,
+        };
+        if txs.is_empty() {
+            return Err("No transaction ids found".to_string());
+        }
+        Ok(txs)
+    }
+}
+
+fn push(txs: &mut Vec<ImportedTx>, txid: Txid, label: Option<String>) {
+    let label = label
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty());
+    match txs.iter_mut().find(|tx| tx.txid == txid) {
+        Some(tx) => {
+            if tx.label.is_none() {
+                tx.label = label;
+            }
+        }
+        None => txs.push(ImportedTx { txid, label }),
+    }
+}
+
+/// Attributes that make a label, the most fitting first.
+const LABEL_ATTRIBUTES: [&str; 4] = ["label", "name", "notes", "description"];
+
+/// Lower is more fitting, `None` if [name] isn't a label attribute.
+fn label_priority(name: &str) -> Option<usize> {
+    LABEL_ATTRIBUTES
+        .iter()
+        .position(|n| name.eq_ignore_ascii_case(n))
+}
+
+/// Nodes with a `txid` data attribute, or whose id is a txid. A `label` (or `name`, ...)
+/// attribute becomes the transaction label.
+fn parse_graphml(contents: &str) -> Result<Vec<ImportedTx>, String> {
+    // Key id -> attribute name
+    let mut keys: HashMap<String, String> = HashMap::new();
+    let mut txs = vec![];
+
+    let mut node: Option<HashMap<String, String>> = None;
+    let mut data_key: Option<String> = None;
+    let mut text = String::new();
+
+    for token in xml_tokens(contents)? {
+        match token {
+            Token::Open {
+                name,
+                attrs,
+                self_closing,
+            } => match name {
+                "key" => {
+                    if let (Some(id), Some(name)) = (attrs.get("id"), attrs.get("attr.name")) {
+                        keys.insert(id.clone(), name.clone());
+                    }
+                }
+                "node" => {
+                    let mut values = HashMap::new();
+                    if let Some(id) = attrs.get("id") {
+                        values.insert("id".to_string(), id.clone());
+                    }
+                    if self_closing {
+                        finish_node(&mut txs, &values);
+                    } else {
+                        node = Some(values);
+                    }
+                }
+                "data" if node.is_some() && !self_closing => {
+                    data_key = attrs.get("key").cloned();
+                    text.clear();
+                }
+                _ => {}
+            },
+            Token::Text(t) => text.push_str(&t),
+            Token::Close { name } => match name {
+                "data" => {
+                    if let (Some(key), Some(values)) = (data_key.take(), node.as_mut()) {
+                        let name = keys.get(&key).unwrap_or(&key).clone();
+                        values.insert(name, std::mem::take(&mut text));
+                    }
+                }
+                "node" => {
+                    if let Some(values) = node.take() {
+                        finish_node(&mut txs, &values);
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    Ok(txs)
+}
+
+fn finish_node(txs: &mut Vec<ImportedTx>, values: &HashMap<String, String>) {
+    // Keys are compared as well, so that the pick doesn't depend on the order of the map.
+    let txid = values
+        .iter()
+        .filter(|(k, _)| k.eq_ignore_ascii_case("txid"))
+        .min()
+        .or_else(|| values.get_key_value("id"))
+        .and_then(|(_, v)| Txid::new(v.trim()).ok());
+    if let Some(txid) = txid {
+        let label = values
+            .iter()
+            .filter_map(|(k, v)| Some((label_priority(k)?, k, v)))
+            .min()
+            .map(|(_, _, v)| v.clone());
+        push(txs, txid, label);
+    }
+}
+
+/// Maltego entity tables: every field that is a txid becomes a transaction. If there's a header
+/// row with a label-like column (`label`, `notes`, ...), that column becomes the label.
+fn parse_maltego_csv(contents: &str) -> Result<Vec<ImportedTx>, String> {
+    let records = csv::parse(contents)?;
+    let Some(first) = records.first() else {
+        return Ok(vec![]);
+    };
+
+    let has_header = first.iter().all(|f| Txid::new(f.trim()).is_err());
+    let label_column = has_header
+        .then(|| {
+            first
+                .iter()
+                .enumerate()
+                .filter_map(|(i, f)| Some((label_priority(f.trim())?, i)))
+                .min()
+                .map(|(_, i)| i)
+        })
+        .flatten();
+
+    let mut txs = vec![];
+    for record in records.iter().skip(has_header as usize) {
+        for field in record {
+            if let Ok(txid) = Txid::new(field.trim()) {
+                let label = label_column.and_then(|i| record.get(i)).cloned();
+                push(&mut txs, txid, label);
+            }
+        }
+    }
+    Ok(txs)
+}
+
+enum Token<'a> {
+    Open {
+        name: &'a str,
+        attrs: HashMap<String, String>,
+        self_closing: bool,
+    },
+    Close {
+        name: &'a str,
+    },
+    Text(String),
+}
+
+/// Just enough XML for GraphML: elements, attributes and text (including CDATA). Namespace
+/// prefixes are kept as part of the name. Comments and declarations are skipped.
+fn xml_tokens(contents: &str) -> Result<Vec<Token<'_>>, String> {
+    let mut tokens = vec![];
+    let mut rest = contents;
+
+    while let Some(start) = rest.find('<') {
+        if start > 0 {
+            tokens.push(Token::Text(unescape(&rest[..start])));
+        }
+        rest = &rest[start..];
+
+        let skip_until = |rest: &str, end: &str| -> Result<usize, String> {
+            rest.find(end)
+                .map(|i| i + end.len())
+                .ok_or_else(|| format!("Unterminated `{}`", &rest[..rest.len().min(20)]))
+        };
+        if rest.starts_with("<!--") {
+            rest = &rest[skip_until(rest, "-->")?..];
+            continue;
+        }
+        if rest.starts_with("<![CDATA[") {
+            let end = skip_until(rest, "]]>")?;
+            tokens.push(Token::Text(rest[9..end - 3].to_string()));
+            rest = &rest[end..];
+            continue;
+        }
+        if rest.starts_with("<?") || rest.starts_with("<!") {
+            rest = &rest[skip_until(rest, ">")?..];
+            continue;
+        }
+
+        let end = skip_until(rest, ">")?;
+        let tag = &rest[1..end - 1];
+        rest = &rest[end..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            tokens.push(Token::Close { name: name.trim() });
+            continue;
+        }
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let (name, mut attrs_str) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+
+        let mut attrs = HashMap::new();
+        loop {
+            attrs_str = attrs_str.trim_start();
+            let Some((key, value)) = attrs_str.split_once('=') else {
+                break;
+            };
+            let value = value.trim_start();
+            let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+                return Err(format!("Malformed attribute in `<{}>`", name));
+            };
+            let Some(close) = value[1..].find(quote) else {
+                return Err(format!("Unterminated attribute in `<{}>`", name));
+            };
+            attrs.insert(key.trim().to_string(), unescape(&value[1..close + 1]));
+            attrs_str = &value[close + 2..];
+        }
+
+        tokens.push(Token::Open {
+            name,
+            attrs,
+            self_closing,
+        });
+    }
+
+    Ok(tokens)
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_graphml, parse_maltego_csv, ImportedTx};
+    use crate::bitcoin::Txid;
+
+    fn tx(n: &str, label: Option<&str>) -> ImportedTx {
+        ImportedTx {
+            txid: Txid::new(&n.repeat(32)).unwrap(),
+            label: label.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn graphml() {
+        let contents = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="d0" for="node" attr.name="txid" attr.type="string"/>
+  <key id="d1" for="node" attr.name="label" attr.type="string"/>
+  <key id="d2" for="node" attr.name="Description" attr.type="string"/>
+  <graph id="G" edgedefault="directed">
+    <!-- A comment <node> -->
+    <node id="n0">
+      <data key="d0">{}</data>
+      <data key="d2">Seen in a report</data>
+      <data key="d1">Mixer &amp; co</data>
+    </node>
+    <node id="{}"/>
+    <node id="n2"><data key="d1">Not a tx</data></node>
+    <edge source="n0" target="n1"/>
+  </graph>
+</graphml>"#,
+            "11".repeat(32),
+            "22".repeat(32)
+        );
+        assert_eq!(
+            parse_graphml(&contents).unwrap(),
+            vec![tx("11", Some("Mixer & co")), tx("22", None)]
+        );
+    }
+
+    #[test]
+    fn maltego_csv() {
+        let contents = format!(
+            "Type,Notes,Value,Label\nmaltego.BTCTransaction,x,{},Exchange deposit\nmaltego.BTCAddress,x,bc1q,x\nmaltego.BTCTransaction,x,{},\n",
+            "11".repeat(32),
+            "22".repeat(32)
+        );
+        assert_eq!(
+            parse_maltego_csv(&contents).unwrap(),
+            vec![tx("11", Some("Exchange deposit")), tx("22", None)]
+        );
+    }
+}
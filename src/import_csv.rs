@@ -0,0 +1,391 @@
+//! Importing transactions from exchange exports, by mapping the columns of a CSV file. Only rows
+//! with an on-chain txid can be added to the graph; they're labelled with what the exchange says
+//! about them, and where the export has fiat amounts these become the cost basis.
+
+use egui::{ComboBox, Grid, ScrollArea, TextEdit};
+
+use crate::{bitcoin::Txid, csv, prices::Fiat, widgets::UiExt};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Preset {
+    Kraken,
+    Coinbase,
+    Generic,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Kraken, Preset::Coinbase, Preset::Generic];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Kraken => "Kraken",
+            Preset::Coinbase => "Coinbase",
+            Preset::Generic => "Generic",
+        }
+    }
+
+    /// Lowercase header names, in order of preference.
+    fn candidates(&self, field: Field) -> &'static [&'static str] {
+        match (self, field) {
+            (Preset::Kraken, Field::Txid) => &["blockchain txid", "tx hash", "txid"],
+            (Preset::Kraken, Field::Kind) => &["type"],
+            (Preset::Kraken, Field::Asset) => &["asset"],
+            (Preset::Kraken, Field::Amount) => &["amount"],
+            (Preset::Kraken, _) => &[],
+            (Preset::Coinbase, Field::Txid) => &["transaction hash", "txid", "notes"],
+            (Preset::Coinbase, Field::Kind) => &["transaction type"],
+            (Preset::Coinbase, Field::Asset) => &["asset"],
+            (Preset::Coinbase, Field::Amount) => &["quantity transacted"],
+            (Preset::Coinbase, Field::Fiat) => &[
+                "total (inclusive of fees and/or spread)",
+                "total (inclusive of fees)",
+                "subtotal",
+            ],
+            (Preset::Coinbase, Field::Currency) => &["spot price currency"],
+            (Preset::Coinbase, Field::Label) => &[],
+            (Preset::Generic, Field::Txid) => &["txid", "transaction id", "tx hash", "hash"],
+            (Preset::Generic, Field::Label) => &["label", "description", "notes"],
+            (Preset::Generic, Field::Kind) => &["type"],
+            (Preset::Generic, Field::Asset) => &["asset", "currency"],
+            (Preset::Generic, Field::Amount) => &["amount", "quantity"],
+            (Preset::Generic, Field::Fiat) => &["cost basis", "value", "total"],
+            (Preset::Generic, Field::Currency) => &["fiat currency"],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Txid,
+    Label,
+    Kind,
+    Asset,
+    Amount,
+    Fiat,
+    Currency,
+}
+
+impl Field {
+    const ALL: [Field; 7] = [
+        Field::Txid,
+        Field::Label,
+        Field::Kind,
+        Field::Asset,
+        Field::Amount,
+        Field::Fiat,
+        Field::Currency,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Field::Txid => "Txid",
+            Field::Label => "Label",
+            Field::Kind => "Type",
+            Field::Asset => "Asset",
+            Field::Amount => "Amount",
+            Field::Fiat => "Fiat value",
+            Field::Currency => "Fiat currency",
+        }
+    }
+
+    fn hint(&self) -> &'static str {
+        match self {
+            Field::Txid => "The first 64 hex digits anywhere in the column, e.g. in a URL.",
+            Field::Label => "Without a label column, it's made up from type and amount.",
+            Field::Kind => "E.g. deposit or withdrawal.",
+            Field::Asset => "Only rows with BTC (or XBT) are imported.",
+            Field::Amount => "In BTC.",
+            Field::Fiat => "Becomes the cost basis of the transaction.",
+            Field::Currency => "Of the fiat value.",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Row {
+    pub txid: Txid,
+    pub label: Option<String>,
+    pub cost_basis: Option<Fiat>,
+}
+
+pub struct CsvImport {
+    file_name: String,
+    records: Vec<Vec<String>>,
+    /// Index of the header row. Exports sometimes start with a note.
+    header: usize,
+    preset: Preset,
+    /// Column per [Field::ALL].
+    columns: [Option<usize>; 7],
+    /// Of the fiat values, without a currency column.
+    currency: String,
+}
+
+impl CsvImport {
+    pub fn new(file_name: String, contents: &str) -> Result<Self, String> {
+        let records = csv::parse(contents)?;
+        if records.is_empty() {
+            return Err("The file is empty".to_string());
+        }
+        let mut import = Self {
+            file_name,
+            records,
+            header: 0,
+            preset: Preset::Generic,
+            columns: [None; 7],
+            currency: "USD".to_string(),
+        };
+        // Recognize the exchange by the header row, which has to be among the first rows.
+        let detected = [Preset::Kraken, Preset::Coinbase]
+            .into_iter()
+            .find_map(|preset| {
+                (0..import.records.len().min(10))
+                    .find(|row| import.matches(preset, *row) >= 3)
+                    .map(|row| (preset, row))
+            });
+        match detected {
+            Some((preset, header)) => {
+                import.header = header;
+                import.apply_preset(preset);
+            }
+            None => import.apply_preset(Preset::Generic),
+        }
+        Ok(import)
+    }
+
+    /// Number of fields the header [row] has columns for.
+    fn matches(&self, preset: Preset, row: usize) -> usize {
+        Field::ALL
+            .iter()
+            .filter(|field| find_column(&self.records[row], preset.candidates(**field)).is_some())
+            .count()
+    }
+
+    fn apply_preset(&mut self, preset: Preset) {
+        self.preset = preset;
+        for (i, field) in Field::ALL.iter().enumerate() {
+            self.columns[i] = find_column(self.headers(), preset.candidates(*field));
+        }
+    }
+
+    fn headers(&self) -> &[String] {
+        &self.records[self.header]
+    }
+
+    fn column(&self, field: Field) -> Option<usize> {
+        self.columns[Field::ALL.iter().position(|f| *f == field).unwrap()]
+    }
+
+    fn cell<'a>(&self, record: &'a [String], field: Field) -> Option<&'a str> {
+        let value = record.get(self.column(field)?)?.trim();
+        (!value.is_empty()).then_some(value)
+    }
+
+    /// Rows to import, and the number of rows without a txid or of other assets.
+    pub fn rows(&self) -> (Vec<Row>, usize) {
+        let mut rows: Vec<Row> = vec![];
+        let mut skipped = 0;
+        for record in &self.records[self.header + 1..] {
+            let asset = self.cell(record, Field::Asset);
+            let is_btc = asset.map_or(true, |asset| {
+                ["BTC", "XBT", "XXBT"].contains(&asset.to_uppercase().as_str())
+            });
+            let txid = self.cell(record, Field::Txid).and_then(find_txid);
+            let (Some(txid), true) = (txid, is_btc) else {
+                skipped += 1;
+                continue;
+            };
+            if rows.iter().any(|row| row.txid == txid) {
+                continue;
+            }
+
+            let label = self
+                .cell(record, Field::Label)
+                .map(str::to_string)
+                .or_else(|| {
+                    let parts: Vec<_> = [Field::Kind, Field::Amount, Field::Asset]
+                        .into_iter()
+                        .filter_map(|field| self.cell(record, field))
+                        .collect();
+                    (!parts.is_empty())
+                        .then(|| format!("{}: {}", self.preset.name(), parts.join(" ")))
+                });
+            let cost_basis = self
+                .cell(record, Field::Fiat)
+                .and_then(parse_fiat)
+                .map(|value| Fiat {
+                    cents: (value.abs() * 100.0).round() as i64,
+                    currency: self
+                        .cell(record, Field::Currency)
+                        .unwrap_or(&self.currency)
+                        .to_uppercase(),
+                });
+            rows.push(Row {
+                txid,
+                label,
+                cost_basis,
+            });
+        }
+        (rows, skipped)
+    }
+
+    /// Returns the rows when the user confirms.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<Vec<Row>> {
+        ui.label(format!("Map the columns of `{}`.", self.file_name));
+        ui.add_space(3.0);
+
+        ui.horizontal(|ui| {
+            ui.label("Format:");
+            for preset in Preset::ALL {
+                if ui
+                    .selectable_label(self.preset == preset, preset.name())
+                    .clicked()
+                {
+                    self.apply_preset(preset);
+                }
+            }
+        });
+
+        let headers = self.headers().to_vec();
+        let name = |column: Option<usize>| match column {
+            Some(i) => headers[i].clone(),
+            None => "—".to_string(),
+        };
+        Grid::new("CsvColumns").num_columns(2).show(ui, |ui| {
+            ui.label("Header row:");
+            let max = self.records.len().saturating_sub(1);
+            if ui
+                .add(egui::DragValue::new(&mut self.header).range(0..=max))
+                .changed()
+            {
+                self.apply_preset(self.preset);
+            }
+            ui.end_row();
+
+            for (i, field) in Field::ALL.iter().enumerate() {
+                ui.label(format!("{}:", field.name()))
+                    .on_hover_text(field.hint());
+                ComboBox::from_id_source(("CsvColumn", i))
+                    .selected_text(name(self.columns[i]))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.columns[i], None, "—");
+                        for (column, header) in headers.iter().enumerate() {
+                            ui.selectable_value(&mut self.columns[i], Some(column), header);
+                        }
+                    });
+                ui.end_row();
+            }
+
+            if self.column(Field::Fiat).is_some() && self.column(Field::Currency).is_none() {
+                ui.label("Currency of all rows:");
+                ui.add(TextEdit::singleline(&mut self.currency).desired_width(50.0));
+                ui.end_row();
+            }
+        });
+
+        let (rows, skipped) = self.rows();
+        ui.add_space(3.0);
+        ui.bold(format!(
+            "{} transaction(s), {} row(s) skipped",
+            rows.len(),
+            skipped
+        ));
+        ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+            Grid::new("CsvPreview")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for row in rows.iter().take(100) {
+                        ui.monospace(&row.txid.hex_string()[..16]);
+                        ui.label(row.label.as_deref().unwrap_or_default());
+                        ui.label(
+                            row.cost_basis
+                                .as_ref()
+                                .map_or(String::new(), Fiat::to_string),
+                        );
+                        ui.end_row();
+                    }
+                });
+        });
+
+        ui.add_space(3.0);
+        let import = ui
+            .add_enabled(!rows.is_empty(), egui::Button::new("Import"))
+            .clicked();
+        import.then_some(rows)
+    }
+}
+
+fn find_column(headers: &[String], candidates: &[&str]) -> Option<usize> {
+    candidates.iter().find_map(|candidate| {
+        headers
+            .iter()
+            .position(|header| header.trim().eq_ignore_ascii_case(candidate))
+    })
+}
+
+/// The first run of exactly 64 hex digits.
+fn find_txid(text: &str) -> Option<Txid> {
+    text.split(|c: char| !c.is_ascii_hexdigit())
+        .find(|run| run.len() == 64)
+        .and_then(|run| Txid::new(run).ok())
+}
+
+/// Ignores currency symbols and thousands separators, e.g. `$1,234.50`.
+fn parse_fiat(text: &str) -> Option<f64> {
+    let number: String = text
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
+        .collect();
+    number.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CsvImport, Preset, Row};
+    use crate::{bitcoin::Txid, prices::Fiat};
+
+    #[test]
+    fn coinbase() {
+        let a = "ab".repeat(32);
+        let b = "cd".repeat(32);
+        let contents = format!(
+            "You can use this report for your taxes.\n\
+             Timestamp,Transaction Type,Asset,Quantity Transacted,Spot Price Currency,Total (inclusive of fees and/or spread),Notes\n\
+             2024-01-02T10:00:00Z,Buy,BTC,0.01,EUR,\"€1,234.50\",Bought\n\
+             2024-01-03T10:00:00Z,Send,BTC,0.01,EUR,,https://mempool.space/tx/{a}\n\
+             2024-01-04T10:00:00Z,Send,ETH,1,EUR,,{b}\n\
+             2024-01-05T10:00:00Z,Receive,BTC,0.5,EUR,\"20,000\",{b}\n"
+        );
+        let import = CsvImport::new("coinbase.csv".to_string(), &contents).unwrap();
+        assert_eq!(import.preset, Preset::Coinbase);
+        assert_eq!(import.header, 1);
+        let (rows, skipped) = import.rows();
+        assert_eq!(skipped, 2);
+        assert_eq!(
+            rows,
+            vec![
+                Row {
+                    txid: Txid::new(&a).unwrap(),
+                    label: Some("Coinbase: Send 0.01 BTC".to_string()),
+                    cost_basis: None,
+                },
+                Row {
+                    txid: Txid::new(&b).unwrap(),
+                    label: Some("Coinbase: Receive 0.5 BTC".to_string()),
+                    cost_basis: Some(Fiat {
+                        cents: 2_000_000,
+                        currency: "EUR".to_string(),
+                    }),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn generic() {
+        let contents = format!("txid,label\n{},Salary\n", "ef".repeat(32));
+        let import = CsvImport::new("export.csv".to_string(), &contents).unwrap();
+        assert_eq!(import.preset, Preset::Generic);
+        assert_eq!(import.rows().0[0].label.as_deref(), Some("Salary"));
+    }
+}
@@ -1,15 +1,23 @@
 use egui::Grid;
 use serde::{Deserialize, Serialize};
 
-use crate::{bitcoin::Sats, export, widgets::UiExt};
+use crate::{arrange::Algorithm, bitcoin::Sats, export, widgets::UiExt};
 
-#[derive(Deserialize, Serialize, Default)]
+#[derive(Clone, Deserialize, Serialize, Default)]
 #[serde(default)]
 pub struct Layout {
     pub force_params: ForceParams,
     pub scale: Scale,
     #[serde(default = "default_as_true")]
     pub show_arrows: bool,
+    /// Fade out older transactions, relative to the newest one in the graph.
+    pub fade_by_age: bool,
+    #[serde(default = "default_as_true")]
+    pub show_minimap: bool,
+    /// Only arrange the multi-selection, or else the selected transaction and everything
+    /// connected to it.
+    pub arrange_selected_only: bool,
+    pub edge_filter: EdgeFilter,
 }
 
 fn default_as_true() -> bool {
@@ -17,13 +25,39 @@ fn default_as_true() -> bool {
 }
 
 impl Layout {
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
+    /// Returns the algorithm to arrange the graph with, if one was chosen. [currency] is the one
+    /// of the workspace.
+    pub fn ui(&mut self, ui: &mut egui::Ui, currency: Option<&str>) -> Option<Algorithm> {
+        let mut arrange = None;
+        ui.bold("Arrange:");
+        ui.horizontal(|ui| {
+            for algorithm in Algorithm::ALL {
+                if ui
+                    .button(algorithm.name())
+                    .on_hover_text(algorithm.description())
+                    .clicked()
+                {
+                    arrange = Some(algorithm);
+                }
+            }
+        });
+        ui.checkbox(&mut self.arrange_selected_only, "Selection only")
+            .on_hover_text(
+                "The selected transactions, or with a single one selected, \
+                 everything connected to it.",
+            );
+        ui.separator();
         self.force_params.ui(ui);
         ui.separator();
         self.scale.ui(ui);
         ui.separator();
+        self.edge_filter.ui(ui, currency);
+        ui.separator();
         ui.bold("Misc:");
         ui.checkbox(&mut self.show_arrows, "Show arrows on edges");
+        ui.checkbox(&mut self.fade_by_age, "Fade older transactions");
+        ui.checkbox(&mut self.show_minimap, "Show minimap");
+        arrange
     }
 
     pub fn import(&mut self, layout: &export::Layout0) {
@@ -40,12 +74,12 @@ impl Layout {
             x1: self.scale.x1,
             y1: self.scale.y1,
             x2: self.scale.x2,
-            y2: self.scale.y2
+            y2: self.scale.y2,
         }
     }
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct ForceParams {
     pub scale: u64,
@@ -89,8 +123,108 @@ impl ForceParams {
     }
 }
 
+/// Dims or hides flows below an amount, so that the larger ones stand out.
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct EdgeFilter {
+    pub active: bool,
+    /// In sats, or in the workspace currency if [Self::fiat].
+    pub threshold: f64,
+    pub fiat: bool,
+    /// Hide instead of dimming.
+    pub hide: bool,
+}
+
+impl EdgeFilter {
+    /// Whether a flow or output of [sats] is below the threshold. [rate] is the price of one
+    /// bitcoin in [currency] at the time of the flow, if known. A fiat threshold needs a
+    /// currency, without one nothing is filtered, which [Self::ui] points out.
+    pub fn filters(
+        &self,
+        sats: u64,
+        currency: Option<&str>,
+        rate: impl FnOnce(&str) -> Option<f64>,
+    ) -> bool {
+        if !self.active {
+            return false;
+        }
+        if !self.fiat {
+            return (sats as f64) < self.threshold;
+        }
+        let Some(currency) = currency else {
+            return false;
+        };
+        rate(currency).is_some_and(|rate| sats as f64 / 100_000_000.0 * rate < self.threshold)
+    }
+
+    pub fn export(&self) -> export::EdgeFilter0 {
+        export::EdgeFilter0 {
+            active: self.active,
+            threshold: self.threshold,
+            fiat: self.fiat,
+            hide: self.hide,
+        }
+    }
+
+    pub fn import(filter: export::EdgeFilter0) -> Self {
+        Self {
+            active: filter.active,
+            threshold: filter.threshold,
+            fiat: filter.fiat,
+            hide: filter.hide,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, currency: Option<&str>) {
+        ui.bold("Edge filter:");
+
+        Grid::new("EdgeFilter").num_columns(2).show(ui, |ui| {
+            ui.label("Small flows:");
+            ui.checkbox(&mut self.active, "Filter");
+            ui.end_row();
+
+            ui.label("Below:");
+            ui.add_enabled_ui(self.active, |ui| {
+                ui.horizontal(|ui| {
+                    let speed = if self.fiat { 1.0 } else { 1000.0 };
+                    ui.add(
+                        egui::DragValue::new(&mut self.threshold)
+                            .speed(speed)
+                            .range(0.0..=f64::MAX),
+                    );
+                    ui.selectable_value(&mut self.fiat, false, "sats");
+                    let fiat = currency.map_or("fiat".to_string(), str::to_uppercase);
+                    ui.selectable_value(&mut self.fiat, true, fiat)
+                        .on_hover_text(
+                            "In the currency of the workspace, at the time of the flow.",
+                        );
+                });
+            });
+            ui.end_row();
+
+            if self.active && self.fiat && currency.is_none() {
+                ui.label("");
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    "The workspace has no currency, nothing is filtered.",
+                );
+                ui.end_row();
+            }
+
+            ui.label("Filtered flows:");
+            ui.add_enabled_ui(self.active, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.hide, false, "Dim");
+                    ui.selectable_value(&mut self.hide, true, "Hide");
+                });
+            });
+            ui.end_row();
+        });
+    }
+}
+
 /// Fit `y = a x^b` through `(x1, y1)` and `(x2, y2)`.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Scale {
     x1: u64,
     y1: u64,
@@ -112,7 +246,8 @@ impl Default for Scale {
 
 impl Scale {
     pub fn apply(&self, x: u64) -> f64 {
-        let b = -(self.y2 as f64 / self.y1 as f64).ln() / ((self.x1 as f64).ln() - (self.x2 as f64).ln());
+        let b = -(self.y2 as f64 / self.y1 as f64).ln()
+            / ((self.x1 as f64).ln() - (self.x2 as f64).ln());
         let a = self.y1 as f64 / (self.x1 as f64).powf(b);
 
         (a * (x as f64).powf(b)).max(10.0)
@@ -155,3 +290,37 @@ impl Scale {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::EdgeFilter;
+
+    #[test]
+    fn edge_threshold() {
+        let rate = |currency: &str| (currency == "usd").then_some(50_000.0);
+        let sats = EdgeFilter {
+            active: true,
+            threshold: 10_000.0,
+            ..Default::default()
+        };
+        assert!(sats.filters(9_999, None, rate));
+        assert!(!sats.filters(10_000, None, rate));
+        let inactive = EdgeFilter {
+            active: false,
+            ..sats.clone()
+        };
+        assert!(!inactive.filters(1, None, rate));
+
+        // 20,000 sats are 10 USD.
+        let fiat = EdgeFilter {
+            threshold: 10.0,
+            fiat: true,
+            ..sats
+        };
+        assert!(fiat.filters(19_999, Some("usd"), rate));
+        assert!(!fiat.filters(20_000, Some("usd"), rate));
+        // Unknown rate, or no currency to compare with.
+        assert!(!fiat.filters(1, Some("eur"), rate));
+        assert!(!fiat.filters(1, None, rate));
+    }
+}
@@ -2,22 +2,63 @@
 
 mod annotations;
 mod app;
+mod arrange;
+mod backup;
+mod balance;
 mod bezier;
+mod bip329;
 mod bitcoin;
+mod bookmark;
+mod chain;
+mod change;
 mod client;
+mod cluster;
+mod coin_selection;
+mod coinjoin;
+mod compare;
 mod components;
+mod csv;
+mod dot;
+mod encryption;
+mod entity;
+mod ethereum;
+mod expand;
+mod explorer;
 mod export;
+mod features;
+mod fees;
 mod flight;
 mod framerate;
 mod graph;
+mod import;
+mod import_csv;
 mod layout;
 mod loading;
+mod mempool;
+mod middleware;
+mod minimap;
 mod modal;
 mod notifications;
+mod omni;
+mod palette;
+mod path;
 mod platform;
+mod prices;
+mod repair;
+mod sankey;
+mod script;
+mod settings;
+mod snapshot;
+mod stats;
+mod sticky;
 mod style;
+mod svg;
+mod tax;
 mod transform;
 mod tx_cache;
+mod utxos;
+mod validation;
 mod widgets;
 mod workspaces;
+mod xpub;
 pub use app::App;
@@ -1,4 +1,10 @@
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use egui::{
     ahash::{HashSet, HashSetExt},
@@ -8,17 +14,29 @@ use egui::{
 
 use crate::bitcoin::Txid;
 
+/// Identifies a running request, see [Loading::start_request].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct RequestId(u64);
+
+struct InFlight {
+    url: String,
+    /// Shared with the request, which checks it before retrying and when the response arrives.
+    cancelled: Arc<AtomicBool>,
+}
+
 #[derive(Clone)]
 struct State {
     txids: Arc<Mutex<HashSet<Txid>>>,
-    other: bool,
+    requests: Arc<Mutex<BTreeMap<RequestId, InFlight>>>,
+    next_id: u64,
 }
 
 impl State {
     fn new() -> Self {
         Self {
             txids: Arc::new(Mutex::new(HashSet::new())),
-            other: false,
+            requests: Arc::new(Mutex::new(BTreeMap::new())),
+            next_id: 0,
         }
     }
 
@@ -39,7 +57,13 @@ impl State {
     }
 
     fn is_loading(&self) -> bool {
-        !self.txids.lock().is_empty() || self.other
+        !self.txids.lock().is_empty() || !self.requests.lock().is_empty()
+    }
+
+    fn cancel(&self, id: RequestId) {
+        if let Some(request) = self.requests.lock().remove(&id) {
+            request.cancelled.store(true, Ordering::Relaxed);
+        }
     }
 
     fn is_txid_loading(&self, txid: &Txid) -> bool {
@@ -64,19 +88,73 @@ impl Loading {
         Self::modify(ctx, |store| store.loading_txid_done(txid));
     }
 
-    pub fn start_loading(ctx: &Context) {
-        Self::modify(ctx, |store| store.other = true);
+    /// Register a request. The returned flag is set when it's cancelled.
+    pub fn start_request(ctx: &Context, url: &str) -> (RequestId, Arc<AtomicBool>) {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut id = RequestId(0);
+        Self::modify(ctx, |store| {
+            id = RequestId(store.next_id);
+            store.next_id += 1;
+            store.requests.lock().insert(
+                id,
+                InFlight {
+                    url: url.to_string(),
+                    cancelled: cancelled.clone(),
+                },
+            );
+        });
+        (id, cancelled)
     }
 
-    pub fn loading_done(ctx: &Context) {
-        Self::modify(ctx, |store| store.other = false);
+    pub fn request_done(ctx: &Context, id: RequestId) {
+        State::load(ctx).requests.lock().remove(&id);
     }
 
+    /// The request's callback still runs, but with [crate::client::ApiError::Cancelled].
+    pub fn cancel(ctx: &Context, id: RequestId) {
+        State::load(ctx).cancel(id);
+    }
+
+    pub fn cancel_all(ctx: &Context) {
+        let state = State::load(ctx);
+        let ids: Vec<_> = state.requests.lock().keys().copied().collect();
+        for id in ids {
+            state.cancel(id);
+        }
+    }
+
+    /// Shows a spinner while something is loading, with a menu to cancel running requests.
     pub fn spinner(ui: &mut Ui) {
         let state = State::load(ui.ctx());
-        if state.is_loading() {
-            ui.spinner();
+        if !state.is_loading() {
+            return;
+        }
+        ui.spinner();
+
+        let requests: Vec<(RequestId, String)> = state
+            .requests
+            .lock()
+            .iter()
+            .map(|(id, request)| (*id, request.url.clone()))
+            .collect();
+        if requests.is_empty() {
+            return;
         }
+        ui.menu_button(format!("{} running", requests.len()), |ui| {
+            for (id, url) in &requests {
+                ui.horizontal(|ui| {
+                    if ui.small_button("Cancel").clicked() {
+                        Self::cancel(ui.ctx(), *id);
+                    }
+                    ui.monospace(url);
+                });
+            }
+            ui.separator();
+            if ui.button("Cancel All").clicked() {
+                Self::cancel_all(ui.ctx());
+                ui.close_menu();
+            }
+        });
     }
 
     pub fn is_txid_loading(ui: &Ui, txid: &Txid) -> bool {
@@ -0,0 +1,75 @@
+//! Following unconfirmed transactions in the graph until they confirm or get replaced.
+
+use std::{collections::HashSet, sync::mpsc::Sender, time::Duration};
+
+use egui::Context;
+
+use crate::{app::Update, bitcoin::Txid};
+
+/// Seconds between checks while there are unconfirmed transactions.
+const POLL_INTERVAL: f64 = 60.0;
+
+#[derive(Default)]
+pub struct MempoolWatch {
+    last_poll: Option<f64>,
+    /// Replaced or dropped transactions, which aren't checked anymore.
+    gone: HashSet<Txid>,
+}
+
+impl MempoolWatch {
+    /// Asks for the [unconfirmed] transactions to be checked when it's due.
+    pub fn tick(&mut self, ctx: &Context, unconfirmed: Vec<Txid>, sender: &Sender<Update>) {
+        let now = ctx.input(|i| i.time);
+        if let Some(txids) = self.due(now, unconfirmed) {
+            sender.send(Update::PollMempool { txids }).unwrap();
+        }
+        if let Some(last_poll) = self.last_poll {
+            ctx.request_repaint_after(Duration::from_secs_f64(
+                (POLL_INTERVAL - (now - last_poll)).max(0.0),
+            ));
+        }
+    }
+
+    fn due(&mut self, now: f64, unconfirmed: Vec<Txid>) -> Option<Vec<Txid>> {
+        let txids: Vec<Txid> = unconfirmed
+            .into_iter()
+            .filter(|txid| !self.gone.contains(txid))
+            .collect();
+        if txids.is_empty() {
+            self.last_poll = None;
+            return None;
+        }
+        // The transactions were just fetched, so start counting.
+        let last_poll = *self.last_poll.get_or_insert(now);
+        if now - last_poll < POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = Some(now);
+        Some(txids)
+    }
+
+    pub fn mark_gone(&mut self, txid: Txid) {
+        self.gone.insert(txid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MempoolWatch, POLL_INTERVAL};
+    use crate::bitcoin::Txid;
+
+    #[test]
+    fn polling() {
+        let a = Txid::new(&"11".repeat(32)).unwrap();
+        let b = Txid::new(&"22".repeat(32)).unwrap();
+        let mut watch = MempoolWatch::default();
+
+        assert_eq!(watch.due(0.0, vec![a, b]), None);
+        assert_eq!(watch.due(POLL_INTERVAL - 1.0, vec![a, b]), None);
+        assert_eq!(watch.due(POLL_INTERVAL, vec![a, b]), Some(vec![a, b]));
+
+        watch.mark_gone(a);
+        assert_eq!(watch.due(2.0 * POLL_INTERVAL, vec![a, b]), Some(vec![b]));
+        assert_eq!(watch.due(3.0 * POLL_INTERVAL, vec![a]), None);
+    }
+}
@@ -0,0 +1,243 @@
+//! Hooks that every request of the [crate::client::Client] passes through.
+
+use std::{collections::VecDeque, sync::Arc};
+
+use chrono::{DateTime, Local, Utc};
+use egui::{mutex::Mutex, Ui};
+use egui_extras::{Column, TableBuilder};
+
+/// Number of requests kept for the developer console.
+const LOG_SIZE: usize = 200;
+
+/// Requests go through [Middleware::on_request] in order, responses through
+/// [Middleware::on_response] in reverse order, so the first middleware sees the final request
+/// and the response as returned to the caller.
+pub trait Middleware: Send + Sync {
+    /// Skipped for requests to other servers.
+    fn backend_only(&self) -> bool {
+        false
+    }
+
+    fn on_request(&self, _request: &mut ehttp::Request) {}
+
+    fn on_response(
+        &self,
+        _request: &ehttp::Request,
+        _response: &mut ehttp::Result<ehttp::Response>,
+        _elapsed_ms: i64,
+    ) {
+    }
+}
+
+pub type Pipeline = Vec<Arc<dyn Middleware>>;
+
+pub fn run_request(pipeline: &Pipeline, request: &mut ehttp::Request, external: bool) {
+    for middleware in pipeline {
+        if !(external && middleware.backend_only()) {
+            middleware.on_request(request);
+        }
+    }
+}
+
+pub fn run_response(
+    pipeline: &Pipeline,
+    request: &ehttp::Request,
+    response: &mut ehttp::Result<ehttp::Response>,
+    elapsed_ms: i64,
+) {
+    for middleware in pipeline.iter().rev() {
+        middleware.on_response(request, response, elapsed_ms);
+    }
+}
+
+/// Adds the API token.
+pub struct Auth {
+    token: &'static str,
+}
+
+impl Auth {
+    pub fn new(token: &'static str) -> Self {
+        Self { token }
+    }
+}
+
+impl Middleware for Auth {
+    fn backend_only(&self) -> bool {
+        true
+    }
+
+    fn on_request(&self, request: &mut ehttp::Request) {
+        request
+            .headers
+            .insert("Authorization", format!("Bearer {}", self.token));
+    }
+}
+
+struct LogEntry {
+    time: DateTime<Utc>,
+    method: String,
+    url: String,
+    /// Status code or error message.
+    result: String,
+    elapsed_ms: i64,
+    request_headers: Vec<(String, String)>,
+    response_headers: Vec<(String, String)>,
+}
+
+/// Records recent requests for the developer console, and logs them at debug level.
+#[derive(Clone, Default)]
+pub struct RequestLog(Arc<Mutex<VecDeque<LogEntry>>>);
+
+impl Middleware for RequestLog {
+    fn on_response(
+        &self,
+        request: &ehttp::Request,
+        response: &mut ehttp::Result<ehttp::Response>,
+        elapsed_ms: i64,
+    ) {
+        let result = match response {
+            Ok(response) => format!("{} {}", response.status, response.status_text),
+            Err(err) => err.clone(),
+        };
+        log::debug!(
+            "{} {} -> {} ({} ms)",
+            request.method,
+            redacted_url(&request.url),
+            result,
+            elapsed_ms
+        );
+
+        let mut log = self.0.lock();
+        log.push_back(LogEntry {
+            time: Utc::now(),
+            method: request.method.clone(),
+            url: redacted_url(&request.url),
+            result,
+            elapsed_ms,
+            request_headers: redacted(request.headers.headers.iter()),
+            response_headers: response
+                .as_ref()
+                .map(|r| redacted(r.headers.headers.iter()))
+                .unwrap_or_default(),
+        });
+        if log.len() > LOG_SIZE {
+            log.pop_front();
+        }
+    }
+}
+
+/// Query parameters that hold credentials, e.g. the key of an Etherscan-compatible API.
+const SECRET_PARAMS: [&str; 5] = ["apikey", "api_key", "key", "token", "access_token"];
+
+/// [url] with the values of [SECRET_PARAMS] hidden, to be shown or logged.
+pub fn redacted_url(url: &str) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let params: Vec<String> = query
+        .split('&')
+        .map(|param| match param.split_once('=') {
+            Some((name, _)) if SECRET_PARAMS.iter().any(|s| name.eq_ignore_ascii_case(s)) => {
+                format!("{}=…", name)
+            }
+            _ => param.to_string(),
+        })
+        .collect();
+    format!("{}?{}", base, params.join("&"))
+}
+
+fn redacted<'a>(headers: impl Iterator<Item = &'a (String, String)>) -> Vec<(String, String)> {
+    headers
+        .map(|(k, v)| {
+            if k.eq_ignore_ascii_case("authorization") {
+                (k.clone(), "…".to_string())
+            } else {
+                (k.clone(), v.clone())
+            }
+        })
+        .collect()
+}
+
+impl RequestLog {
+    pub fn clear(&self) {
+        self.0.lock().clear();
+    }
+
+    pub fn ui(&self, ui: &mut Ui) {
+        let log = self.0.lock();
+        if log.is_empty() {
+            ui.weak("No requests yet.");
+            return;
+        }
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::remainder())
+            .header(20.0, |mut header| {
+                for title in ["Time", "Method", "Result", "ms", "URL"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|mut body| {
+                for entry in log.iter().rev() {
+                    body.row(18.0, |mut row| {
+                        row.col(|ui| {
+                            ui.label(
+                                entry
+                                    .time
+                                    .with_timezone(&Local)
+                                    .format("%H:%M:%S")
+                                    .to_string(),
+                            );
+                        });
+                        row.col(|ui| {
+                            ui.monospace(&entry.method);
+                        });
+                        row.col(|ui| {
+                            ui.label(&entry.result);
+                        });
+                        row.col(|ui| {
+                            ui.label(entry.elapsed_ms.to_string());
+                        });
+                        row.col(|ui| {
+                            ui.monospace(&entry.url).on_hover_ui(|ui| {
+                                ui.strong("Request headers");
+                                for (k, v) in &entry.request_headers {
+                                    ui.monospace(format!("{}: {}", k, v));
+                                }
+                                ui.strong("Response headers");
+                                for (k, v) in &entry.response_headers {
+                                    ui.monospace(format!("{}: {}", k, v));
+                                }
+                            });
+                        });
+                    });
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::redacted_url;
+
+    #[test]
+    fn secrets_hidden() {
+        assert_eq!(
+            redacted_url(
+                "https://api.example.com/api?chainid=1&action=txlist&apikey=SECRET&page=2"
+            ),
+            "https://api.example.com/api?chainid=1&action=txlist&apikey=…&page=2"
+        );
+        assert_eq!(
+            redacted_url("https://example.com/tx/1"),
+            "https://example.com/tx/1"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Overview of the whole graph in a corner of the canvas, to find your way around large graphs.
+
+use egui::{CursorIcon, Id, Pos2, Rect, Sense, Stroke, Vec2};
+
+use crate::transform::Transform;
+
+const SIZE: Vec2 = Vec2::new(180.0, 120.0);
+const MARGIN: f32 = 10.0;
+
+/// Maps between graph coordinates and the minimap, keeping the aspect ratio.
+#[derive(Debug, PartialEq)]
+pub struct Projection {
+    extent: Rect,
+    map: Rect,
+    scale: f32,
+}
+
+impl Projection {
+    /// Fits [extent] (graph coordinates) into the center of [map].
+    pub fn new(extent: Rect, map: Rect) -> Self {
+        let scale =
+            (map.width() / extent.width().max(1.0)).min(map.height() / extent.height().max(1.0));
+        Self { extent, map, scale }
+    }
+
+    pub fn to_map(&self, pos: Pos2) -> Pos2 {
+        self.map.center() + (pos - self.extent.center()) * self.scale
+    }
+
+    pub fn to_graph(&self, pos: Pos2) -> Pos2 {
+        self.extent.center() + (pos - self.map.center()) / self.scale
+    }
+
+    pub fn rect_to_map(&self, rect: Rect) -> Rect {
+        Rect::from_min_max(self.to_map(rect.min), self.to_map(rect.max))
+    }
+}
+
+/// Draws the minimap in the bottom right of [canvas] and pans [transform] when it's clicked or
+/// dragged. Returns whether the view was moved.
+pub fn ui(ui: &mut egui::Ui, canvas: Rect, nodes: &[Rect], transform: &mut Transform) -> bool {
+    let Some(extent) = nodes.iter().copied().reduce(Rect::union) else {
+        return false;
+    };
+
+    let rect = Rect::from_min_size(canvas.right_bottom() - SIZE - Vec2::splat(MARGIN), SIZE);
+    let response = ui.interact(rect, Id::new("minimap"), Sense::click_and_drag());
+
+    let visuals = ui.visuals();
+    let painter = ui.painter_at(rect);
+    painter.rect(
+        rect,
+        2.0,
+        visuals.extreme_bg_color.gamma_multiply(0.9),
+        visuals.widgets.noninteractive.bg_stroke,
+    );
+
+    // Include the viewport in the extent, so it stays visible when looking away from the graph.
+    let view = Rect::from_min_max(
+        transform.pos_from_screen(canvas.min),
+        transform.pos_from_screen(canvas.max),
+    );
+    let projection = Projection::new(extent.union(view), rect.shrink(4.0));
+
+    let node_color = visuals.text_color().gamma_multiply(0.6);
+    for node in nodes {
+        let node = projection.rect_to_map(*node);
+        painter.rect_filled(node.expand(0.5), 0.0, node_color);
+    }
+
+    let highlight = visuals.selection.stroke.color;
+    painter.rect(
+        projection.rect_to_map(view),
+        0.0,
+        highlight.gamma_multiply(0.1),
+        Stroke::new(1.0, highlight),
+    );
+
+    let mut moved = false;
+    if let Some(pointer) = response.interact_pointer_pos() {
+        if response.clicked() || response.dragged() {
+            transform.pan_to(projection.to_graph(pointer), canvas.center());
+            moved = true;
+        }
+    }
+    if response.hovered() {
+        ui.ctx().set_cursor_icon(if response.dragged() {
+            CursorIcon::Grabbing
+        } else {
+            CursorIcon::Crosshair
+        });
+    }
+
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{Pos2, Rect};
+
+    use super::Projection;
+
+    #[test]
+    fn projection() {
+        let extent = Rect::from_min_max(Pos2::new(-100.0, 0.0), Pos2::new(300.0, 100.0));
+        let map = Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(200.0, 200.0));
+        let projection = Projection::new(extent, map);
+
+        // The width limits the scale, the height is centered.
+        assert_eq!(projection.to_map(extent.min), Pos2::new(0.0, 75.0));
+        assert_eq!(projection.to_map(extent.max), Pos2::new(200.0, 125.0));
+        assert_eq!(
+            projection.to_graph(Pos2::new(100.0, 100.0)),
+            Pos2::new(100.0, 50.0)
+        );
+    }
+}
@@ -261,22 +261,42 @@ impl Notifications {
 }
 
 pub trait NotifyExt {
-    fn notify(&self, kind: Kind, message: impl ToString, detail: Option<impl ToString>, ttl_sec: f32);
+    fn notify(
+        &self,
+        kind: Kind,
+        message: impl ToString,
+        detail: Option<impl ToString>,
+        ttl_sec: f32,
+    );
 
     fn notify_error(&self, message: impl ToString, detail: Option<impl ToString>) {
         self.notify(Kind::Error, message, detail, 8.0);
     }
 
+    fn notify_warn(&self, message: impl ToString, detail: Option<impl ToString>) {
+        self.notify(Kind::Warn, message, detail, 8.0);
+    }
+
     fn notify_success(&self, message: impl ToString) {
         self.notify(Kind::Success, message, None::<&str>, 6.0);
     }
+
+    fn notify_info(&self, message: impl ToString) {
+        self.notify(Kind::Info, message, None::<&str>, 6.0);
+    }
 }
 
 #[derive(Clone)]
 struct NotificationSender(Sender<Toast>);
 
 impl NotifyExt for Context {
-    fn notify(&self, kind: Kind, message: impl ToString, detail: Option<impl ToString>, ttl_sec: f32) {
+    fn notify(
+        &self,
+        kind: Kind,
+        message: impl ToString,
+        detail: Option<impl ToString>,
+        ttl_sec: f32,
+    ) {
         if let Some(NotificationSender(sender)) = self.data(|d| d.get_temp(Id::NULL)) {
             sender
                 .send(Toast::new(
@@ -0,0 +1,111 @@
+//! Decoding of Omni Layer (e.g. USDT) transfers embedded in `OP_RETURN` outputs.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bitcoin::{Sats, Transaction};
+
+const MARKER: &[u8] = b"omni";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OmniTransfer {
+    pub property: u32,
+    /// In the smallest unit of the property.
+    pub amount: u64,
+}
+
+impl OmniTransfer {
+    /// Find the first Omni "simple send" carried by the transaction.
+    pub fn from_tx(tx: &Transaction) -> Option<Self> {
+        tx.outputs
+            .iter()
+            .filter_map(|o| o.op_return_data())
+            .find_map(|data| Self::decode(&data))
+    }
+
+    /// Decode a class C payload: marker, version (u16), type (u16), property (u32), amount (u64).
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let payload = data.strip_prefix(MARKER)?;
+        if payload.len() < 16 {
+            return None;
+        }
+        let tx_type = u16::from_be_bytes(payload[2..4].try_into().ok()?);
+        // Only simple sends are supported for now.
+        if tx_type != 0 {
+            return None;
+        }
+        Some(Self {
+            property: u32::from_be_bytes(payload[4..8].try_into().ok()?),
+            amount: u64::from_be_bytes(payload[8..16].try_into().ok()?),
+        })
+    }
+
+    pub fn symbol(&self) -> String {
+        match self.property {
+            1 => "OMNI".to_string(),
+            2 => "TOMNI".to_string(),
+            3 => "MAID".to_string(),
+            31 => "USDT".to_string(),
+            p => format!("#{}", p),
+        }
+    }
+
+    /// Whether amounts have 8 decimals like bitcoin. Only known for the well-known properties.
+    pub fn divisible(&self) -> Option<bool> {
+        match self.property {
+            1 | 2 | 31 => Some(true),
+            3 => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl Display for OmniTransfer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.divisible() {
+            Some(true) => write!(f, "{} {}", Sats(self.amount), self.symbol()),
+            Some(false) => write!(f, "{} {}", self.amount, self.symbol()),
+            None => write!(f, "{} base units of {}", self.amount, self.symbol()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OmniTransfer;
+
+    #[test]
+    fn usdt_simple_send() {
+        let data = hex::decode("6f6d6e69000000000000001f0000002e90edd000").unwrap();
+        let transfer = OmniTransfer::decode(&data).unwrap();
+        assert_eq!(
+            transfer,
+            OmniTransfer {
+                property: 31,
+                amount: 200_000_000_000
+            }
+        );
+        assert_eq!(transfer.symbol(), "USDT");
+        assert_eq!(transfer.divisible(), Some(true));
+    }
+
+    #[test]
+    fn divisibility() {
+        let maid = OmniTransfer {
+            property: 3,
+            amount: 1500,
+        };
+        assert_eq!(maid.to_string(), "1500 MAID");
+        let unknown = OmniTransfer {
+            property: 400,
+            amount: 7,
+        };
+        assert_eq!(unknown.to_string(), "7 base units of #400");
+    }
+
+    #[test]
+    fn not_omni() {
+        assert_eq!(OmniTransfer::decode(b"hello world, this is no omni"), None);
+    }
+}
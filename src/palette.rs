@@ -0,0 +1,195 @@
+//! A command-palette style overlay for quickly picking an item by name.
+
+use egui::{Align2, Area, Context, Frame, Id, Key, Modifiers, Order, TextEdit, Vec2};
+
+const MAX_RESULTS: usize = 10;
+
+pub struct Palette {
+    /// Toggles the palette together with Ctrl (Cmd on Mac).
+    key: Key,
+    /// Match substrings instead of subsequences, e.g. for txids and addresses.
+    substring: bool,
+    open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl Palette {
+    pub fn new(key: Key) -> Self {
+        Self {
+            key,
+            substring: false,
+            open: false,
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn substring(mut self) -> Self {
+        self.substring = true;
+        self
+    }
+
+    /// Toggles on Ctrl+[key] (Cmd on Mac). Returns the chosen item, if any. [items] is only
+    /// consumed while the palette is open.
+    pub fn show<T: Copy, S: AsRef<str>>(
+        &mut self,
+        ctx: &Context,
+        hint: &str,
+        items: impl Iterator<Item = (T, S)>,
+    ) -> Option<T> {
+        if ctx.input_mut(|i| i.consume_key(Modifiers::COMMAND, self.key)) {
+            self.open = !self.open;
+            self.query.clear();
+            self.selected = 0;
+        }
+        if !self.open {
+            return None;
+        }
+
+        let score = |name: &str| {
+            if self.substring {
+                substring_score(&self.query, name)
+            } else {
+                fuzzy_score(&self.query, name)
+            }
+        };
+        let mut matches: Vec<_> = items
+            .filter_map(|(item, name)| Some((score(name.as_ref())?, item, name)))
+            .collect();
+        // Stable sort keeps the original order among equally good matches.
+        matches.sort_by_key(|(score, _, _)| std::cmp::Reverse(*score));
+        matches.truncate(MAX_RESULTS);
+
+        let (escape, enter, up, down) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::Escape),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+            )
+        });
+        if escape {
+            self.open = false;
+            return None;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if down {
+            self.selected += 1;
+        }
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut chosen = if enter {
+            matches.get(self.selected).map(|(_, item, _)| *item)
+        } else {
+            None
+        };
+
+        Area::new(Id::new("Palette").with(self.key))
+            .anchor(Align2::CENTER_TOP, Vec2::new(0.0, 60.0))
+            .movable(false)
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                Frame::popup(&ctx.style()).show(ui, |ui| {
+                    ui.set_width(400.0);
+
+                    let resp = ui.add(
+                        TextEdit::singleline(&mut self.query)
+                            .hint_text(hint)
+                            .desired_width(f32::INFINITY),
+                    );
+                    resp.request_focus();
+                    if resp.changed() {
+                        self.selected = 0;
+                    }
+
+                    if matches.is_empty() {
+                        ui.weak("No matches");
+                    }
+                    for (i, (_, item, name)) in matches.iter().enumerate() {
+                        if ui
+                            .selectable_label(i == self.selected, name.as_ref())
+                            .clicked()
+                        {
+                            chosen = Some(*item);
+                        }
+                    }
+                });
+            });
+
+        if chosen.is_some() {
+            self.open = false;
+        }
+        chosen
+    }
+}
+
+/// Case-insensitive substring match, earlier matches score higher.
+fn substring_score(query: &str, candidate: &str) -> Option<i32> {
+    let query = query.trim().to_lowercase();
+    let position = candidate.to_lowercase().find(&query)?;
+    Some(-(position as i32))
+}
+
+/// Case-insensitive subsequence match. Consecutive matches and matches at the start of words
+/// score higher. `None` if not all characters of `query` appear in order.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut chars = candidate.chars().flat_map(char::to_lowercase).enumerate();
+    let mut prev: Option<usize> = None;
+    let mut last_char = ' ';
+
+    for q in query.chars().flat_map(char::to_lowercase) {
+        if q.is_whitespace() {
+            continue;
+        }
+        loop {
+            let (i, c) = chars.next()?;
+            let before = std::mem::replace(&mut last_char, c);
+            if c == q {
+                score += 1;
+                if prev.is_some_and(|j| j + 1 == i) {
+                    score += 5;
+                }
+                if !before.is_alphanumeric() {
+                    score += 3;
+                }
+                prev = Some(i);
+                break;
+            }
+        }
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzzy_score, substring_score};
+
+    #[test]
+    fn subsequence() {
+        assert!(fuzzy_score("mtgx", "MTGox hack").is_some());
+        assert!(fuzzy_score("", "anything").is_some());
+        assert_eq!(fuzzy_score("xm", "MTGox"), None);
+    }
+
+    #[test]
+    fn prefers_consecutive_and_word_starts() {
+        let a = fuzzy_score("wasabi", "Wasabi post-mix").unwrap();
+        let b = fuzzy_score("wasabi", "What a sad rabbit").unwrap();
+        assert!(a > b);
+        let c = fuzzy_score("pm", "Wasabi post-mix").unwrap();
+        let d = fuzzy_score("pm", "Wasabi upmix").unwrap();
+        assert!(c > d);
+    }
+
+    #[test]
+    fn substring() {
+        assert_eq!(substring_score("ABC", "xxabc"), Some(-2));
+        assert_eq!(substring_score(" 0.5 ", "0.50000000 BTC"), Some(0));
+        assert_eq!(substring_score("acb", "abc"), None);
+    }
+}
@@ -0,0 +1,258 @@
+//! Finding how coins got from one transaction or address to another.
+
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
+use egui::{DragValue, Grid, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitcoin::{Sats, Txid},
+    widgets::UiExt,
+};
+
+#[derive(Debug, PartialEq)]
+pub struct Path {
+    /// From source to destination.
+    pub txids: Vec<Txid>,
+    /// Value moved from each transaction to the next one.
+    pub amounts: Vec<u64>,
+}
+
+impl Path {
+    pub fn hops(&self) -> usize {
+        self.amounts.len()
+    }
+
+    /// Share of the first hop's value that's still moved in the last one.
+    pub fn retained(&self) -> Option<f64> {
+        let first = *self.amounts.first()?;
+        let last = *self.amounts.last()?;
+        (first > 0).then(|| last as f64 / first as f64)
+    }
+}
+
+/// Fewest hops from any of [from] to any of [to], following coins to the transactions spending
+/// them. [spends] lists those transactions together with the value they spend.
+pub fn shortest(
+    from: &[Txid],
+    to: &[Txid],
+    spends: impl Fn(Txid) -> Vec<(Txid, u64)>,
+) -> Option<Path> {
+    let mut previous: HashMap<Txid, Option<(Txid, u64)>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    for txid in from {
+        if previous.insert(*txid, None).is_none() {
+            queue.push_back(*txid);
+        }
+    }
+
+    while let Some(txid) = queue.pop_front() {
+        if to.contains(&txid) {
+            let mut txids = vec![txid];
+            let mut amounts = vec![];
+            while let Some(Some((prev, amount))) = previous.get(txids.last().unwrap()) {
+                txids.push(*prev);
+                amounts.push(*amount);
+            }
+            txids.reverse();
+            amounts.reverse();
+            return Some(Path { txids, amounts });
+        }
+        for (next, amount) in spends(txid) {
+            if let Entry::Vacant(entry) = previous.entry(next) {
+                entry.insert(Some((txid, amount)));
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+pub enum Action {
+    Find,
+    /// Fetch up to this many hops from the source if there's no path yet.
+    Fetch(usize),
+    /// Stop highlighting the path.
+    Clear,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathFinder {
+    /// Txid or address.
+    from: String,
+    to: String,
+    fetch: bool,
+    depth: usize,
+    #[serde(skip)]
+    open: bool,
+    #[serde(skip)]
+    result: Option<Result<Path, String>>,
+    /// Waiting for missing hops to be fetched.
+    #[serde(skip)]
+    fetching: bool,
+}
+
+impl Default for PathFinder {
+    fn default() -> Self {
+        Self {
+            from: String::new(),
+            to: String::new(),
+            fetch: false,
+            depth: 3,
+            open: false,
+            result: None,
+            fetching: false,
+        }
+    }
+}
+
+impl PathFinder {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn endpoints(&self) -> (String, String) {
+        (self.from.trim().to_string(), self.to.trim().to_string())
+    }
+
+    pub fn set_result(&mut self, result: Result<Path, String>) {
+        self.fetching = false;
+        self.result = Some(result);
+    }
+
+    pub fn start_fetching(&mut self) {
+        self.fetching = true;
+        self.result = None;
+    }
+
+    /// Whether the search should be repeated now that the fetching is done.
+    pub fn finish_fetching(&mut self) -> bool {
+        std::mem::take(&mut self.fetching)
+    }
+
+    pub fn show_window(&mut self, ctx: &egui::Context) -> Option<Action> {
+        let mut action = None;
+        let mut open = self.open;
+        egui::Window::new("Find Path")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                Grid::new("PathFinder").num_columns(2).show(ui, |ui| {
+                    ui.label("From:");
+                    ui.add(
+                        TextEdit::singleline(&mut self.from)
+                            .hint_text("Txid or address")
+                            .desired_width(400.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("To:");
+                    ui.add(
+                        TextEdit::singleline(&mut self.to)
+                            .hint_text("Txid or address")
+                            .desired_width(400.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Missing hops:");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.fetch, "Fetch up to");
+                        ui.add_enabled(self.fetch, DragValue::new(&mut self.depth).range(1..=10));
+                        ui.label("hops from the source");
+                    });
+                    ui.end_row();
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(!self.fetching, |ui| {
+                        if ui.button("Find").clicked() {
+                            action = Some(if self.fetch {
+                                Action::Fetch(self.depth)
+                            } else {
+                                Action::Find
+                            });
+                        }
+                    });
+                    if ui
+                        .add_enabled(self.result.is_some(), egui::Button::new("Clear"))
+                        .clicked()
+                    {
+                        self.result = None;
+                        action = Some(Action::Clear);
+                    }
+                });
+
+                if self.fetching {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Fetching missing hops…");
+                    });
+                }
+                match &self.result {
+                    Some(Ok(path)) => {
+                        ui.separator();
+                        ui.bold(format!(
+                            "{} hop(s) through {} transactions.",
+                            path.hops(),
+                            path.txids.len()
+                        ));
+                        if let (Some(first), Some(last)) =
+                            (path.amounts.first(), path.amounts.last())
+                        {
+                            ui.label(format!("First hop: {} sats", Sats(*first)));
+                            ui.label(format!("Last hop: {} sats", Sats(*last)));
+                        }
+                        if let Some(retained) = path.retained() {
+                            ui.label(format!("{:.1}% of the value is left.", retained * 100.0));
+                        }
+                        ui.weak("The path is highlighted in the graph.");
+                    }
+                    Some(Err(e)) => {
+                        ui.separator();
+                        ui.colored_label(ui.visuals().warn_fg_color, e);
+                    }
+                    None => {}
+                }
+            });
+        if self.open && !open {
+            self.result = None;
+            action = Some(Action::Clear);
+        }
+        self.open = open;
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shortest, Path};
+    use crate::bitcoin::Txid;
+
+    #[test]
+    fn fewest_hops() {
+        let txid = |n: u8| Txid::new(&format!("{:02x}", n).repeat(32)).unwrap();
+        // 1 -> 2 -> 3 -> 4 and a shortcut 1 -> 5 -> 4.
+        let spends = |t: Txid| match t {
+            t if t == txid(1) => vec![(txid(2), 100), (txid(5), 50)],
+            t if t == txid(2) => vec![(txid(3), 90)],
+            t if t == txid(3) => vec![(txid(4), 80)],
+            t if t == txid(5) => vec![(txid(4), 20)],
+            _ => vec![],
+        };
+
+        let path = shortest(&[txid(1)], &[txid(4)], spends).unwrap();
+        assert_eq!(
+            path,
+            Path {
+                txids: vec![txid(1), txid(5), txid(4)],
+                amounts: vec![50, 20],
+            }
+        );
+        assert_eq!(path.retained(), Some(0.4));
+
+        assert_eq!(shortest(&[txid(4)], &[txid(1)], spends), None);
+        let same = shortest(&[txid(3)], &[txid(3)], spends).unwrap();
+        assert_eq!(same.hops(), 0);
+    }
+}
@@ -3,7 +3,7 @@ pub mod inner {
     use std::sync::mpsc::Sender;
 
     use egui::Vec2;
-    use wasm_bindgen::{closure::Closure, prelude::wasm_bindgen};
+    use wasm_bindgen::{closure::Closure, prelude::wasm_bindgen, JsValue};
 
     use crate::app::Update;
     use crate::bitcoin::Txid;
@@ -19,6 +19,27 @@ pub mod inner {
 
         #[wasm_bindgen(js_name = getRandom)]
         fn get_random() -> f64;
+
+        #[wasm_bindgen(js_name = openFile)]
+        fn open_file_impl(accept: &str, callback: JsValue);
+
+        #[wasm_bindgen(js_name = downloadFile)]
+        fn download_file(name: &str, contents: &str);
+
+        #[wasm_bindgen(js_name = downloadFile)]
+        fn download_binary_file(name: &str, contents: &[u8]);
+
+        #[wasm_bindgen(js_name = setTimeout)]
+        fn set_timeout_impl(callback: JsValue, millis: u32);
+
+        #[wasm_bindgen(js_name = readJournal)]
+        pub fn read_journal() -> Option<String>;
+
+        #[wasm_bindgen(js_name = writeJournal)]
+        pub fn write_journal(contents: &str);
+
+        #[wasm_bindgen(js_name = clearJournal)]
+        pub fn clear_journal();
     }
 
     #[wasm_bindgen]
@@ -71,6 +92,63 @@ pub mod inner {
     pub fn get_random_int(max: usize) -> usize {
         (get_random() * max as f64) as usize
     }
+
+    /// Let the user pick a file with the given extension. [on_done] receives the file name and
+    /// contents. It's not called if the user cancels.
+    pub fn open_file(
+        _ctx: &egui::Context,
+        extension: &str,
+        on_done: impl 'static + FnOnce(String, String),
+    ) {
+        let callback = Closure::once_into_js(on_done);
+        open_file_impl(&format!(".{extension}"), callback);
+    }
+
+    /// Offer [contents] as a download. Returns whether the file was saved.
+    pub fn save_file(_ctx: &egui::Context, file_name: &str, contents: &str) -> bool {
+        download_file(file_name, contents);
+        true
+    }
+
+    pub fn save_binary_file(_ctx: &egui::Context, file_name: &str, contents: &[u8]) -> bool {
+        download_binary_file(file_name, contents);
+        true
+    }
+
+    /// The web backend doesn't support `ViewportCommand::Screenshot` yet.
+    pub const CAN_SCREENSHOT: bool = false;
+
+    /// [set_timeout] runs on the main thread, there are no threads without a Web Worker build.
+    /// Long work blocks the page until it's done.
+    pub const OFF_MAIN_THREAD: bool = false;
+
+    /// Call [f] once after [millis] milliseconds.
+    pub fn set_timeout(millis: u32, f: impl 'static + Send + FnOnce()) {
+        set_timeout_impl(Closure::once_into_js(f), millis);
+    }
+
+    /// `None` as there's no file system access in the browser.
+    pub fn default_backup_dir() -> Option<String> {
+        None
+    }
+
+    const NO_FILE_SYSTEM: &str = "No file system access in the browser";
+
+    pub fn write_file(_dir: &str, _name: &str, _contents: &str) -> Result<(), String> {
+        Err(NO_FILE_SYSTEM.to_string())
+    }
+
+    pub fn read_file(_dir: &str, _name: &str) -> Result<String, String> {
+        Err(NO_FILE_SYSTEM.to_string())
+    }
+
+    pub fn list_files(_dir: &str, _prefix: &str) -> Result<Vec<String>, String> {
+        Err(NO_FILE_SYSTEM.to_string())
+    }
+
+    pub fn remove_file(_dir: &str, _name: &str) -> Result<(), String> {
+        Err(NO_FILE_SYSTEM.to_string())
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -81,6 +159,7 @@ pub mod inner {
     use rand::{rngs::ThreadRng, Rng};
 
     use crate::app::Update;
+    use crate::notifications::NotifyExt;
 
     pub fn push_history_state(_url: &str) {}
 
@@ -100,4 +179,116 @@ pub mod inner {
         let mut rng = ThreadRng::default();
         rng.gen_range(0..max)
     }
+
+    /// Let the user pick a file with the given extension. [on_done] receives the file name and
+    /// contents. It's not called if the user cancels.
+    pub fn open_file(
+        ctx: &egui::Context,
+        extension: &str,
+        on_done: impl 'static + FnOnce(String, String),
+    ) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(extension, &[extension])
+            .pick_file()
+        else {
+            return;
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                on_done(name, contents);
+            }
+            Err(err) => ctx.notify_error("Could not read file", Some(err)),
+        }
+    }
+
+    /// Let the user choose where to save [contents]. Returns whether the file was saved.
+    pub fn save_file(ctx: &egui::Context, file_name: &str, contents: &str) -> bool {
+        save_binary_file(ctx, file_name, contents.as_bytes())
+    }
+
+    pub fn save_binary_file(ctx: &egui::Context, file_name: &str, contents: &[u8]) -> bool {
+        let Some(path) = rfd::FileDialog::new().set_file_name(file_name).save_file() else {
+            return false;
+        };
+        match std::fs::write(path, contents) {
+            Ok(()) => true,
+            Err(err) => {
+                ctx.notify_error("Could not write file", Some(err));
+                false
+            }
+        }
+    }
+
+    pub const CAN_SCREENSHOT: bool = true;
+
+    pub const OFF_MAIN_THREAD: bool = true;
+
+    /// Call [f] once after [millis] milliseconds.
+    pub fn set_timeout(millis: u32, f: impl 'static + Send + FnOnce()) {
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(millis as u64));
+            f();
+        });
+    }
+
+    fn journal_path() -> Option<std::path::PathBuf> {
+        Some(eframe::storage_dir("txgraph.info")?.join("journal.json"))
+    }
+
+    /// Recent changes that haven't made it into the regular app storage yet.
+    pub fn read_journal() -> Option<String> {
+        std::fs::read_to_string(journal_path()?).ok()
+    }
+
+    pub fn write_journal(contents: &str) {
+        if let Some(path) = journal_path() {
+            if let Err(err) = std::fs::write(path, contents) {
+                log::warn!("Could not write journal: {}", err);
+            }
+        }
+    }
+
+    pub fn clear_journal() {
+        if let Some(path) = journal_path() {
+            std::fs::remove_file(path).ok();
+        }
+    }
+
+    pub fn default_backup_dir() -> Option<String> {
+        let dir = eframe::storage_dir("txgraph.info")?.join("backups");
+        Some(dir.to_string_lossy().to_string())
+    }
+
+    /// Creates [dir] if necessary.
+    pub fn write_file(dir: &str, name: &str, contents: &str) -> Result<(), String> {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        std::fs::write(std::path::Path::new(dir).join(name), contents).map_err(|e| e.to_string())
+    }
+
+    pub fn read_file(dir: &str, name: &str) -> Result<String, String> {
+        std::fs::read_to_string(std::path::Path::new(dir).join(name)).map_err(|e| e.to_string())
+    }
+
+    /// Names of the files in [dir] starting with [prefix], sorted. Empty if [dir] doesn't exist.
+    pub fn list_files(dir: &str, prefix: &str) -> Result<Vec<String>, String> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.to_string()),
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn remove_file(dir: &str, name: &str) -> Result<(), String> {
+        std::fs::remove_file(std::path::Path::new(dir).join(name)).map_err(|e| e.to_string())
+    }
 }
@@ -0,0 +1,374 @@
+//! Historical BTC exchange rates, for showing fiat values next to amounts.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+use chrono::{NaiveDateTime, Utc};
+use egui::{mutex::Mutex, Context, Id};
+use serde::{Deserialize, Serialize};
+
+use crate::client::Client;
+
+/// A CoinGecko-compatible API.
+pub const DEFAULT_API: &str = "https://api.coingecko.com/api/v3";
+
+pub const CURRENCIES: [&str; 8] = ["usd", "eur", "gbp", "jpy", "chf", "cad", "aud", "cny"];
+
+#[derive(Deserialize)]
+struct History {
+    market_data: Option<MarketData>,
+}
+
+#[derive(Deserialize)]
+struct MarketData {
+    current_price: HashMap<String, f64>,
+}
+
+#[derive(Deserialize)]
+struct MarketChart {
+    /// Milliseconds and rate.
+    prices: Vec<(f64, f64)>,
+}
+
+/// Coin, currency, first and last day.
+type RangeKey = (&'static str, String, i64, i64);
+
+const DAY: i64 = 86_400;
+
+/// Rates of today may still change, they're fetched again after this long.
+const REFRESH_AFTER: i64 = 3_600;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct DayRates {
+    /// Unix time.
+    fetched_at: i64,
+    /// Only of [CURRENCIES].
+    rates: HashMap<String, f64>,
+}
+
+/// Persisted across sessions, so that prices are available offline.
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Cache {
+    base_url: String,
+    /// Bitcoin rates by start of the day (UTC).
+    days: BTreeMap<i64, DayRates>,
+    /// Rates of other coins, by their id in the API.
+    #[serde(default)]
+    coins: BTreeMap<String, BTreeMap<i64, DayRates>>,
+}
+
+impl Cache {
+    fn days(&mut self, coin: &str) -> &mut BTreeMap<i64, DayRates> {
+        if coin == "bitcoin" {
+            &mut self.days
+        } else {
+            self.coins.entry(coin.to_string()).or_default()
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    cache: Cache,
+    /// By coin and day.
+    pending: HashSet<(&'static str, i64)>,
+    failed: HashSet<(&'static str, i64)>,
+    /// Rates fetched before are fetched again.
+    refresh_before: i64,
+    /// Daily rates over a range of days, `None` while fetching. Only kept for the session.
+    ranges: HashMap<RangeKey, Option<Vec<(i64, f64)>>>,
+}
+
+#[derive(Clone)]
+struct Handle(Arc<Mutex<State>>);
+
+/// An amount of fiat money, e.g. what was paid for some bitcoin.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Fiat {
+    pub cents: i64,
+    /// Uppercase, e.g. `USD`.
+    pub currency: String,
+}
+
+impl std::fmt::Display for Fiat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            format_fiat(self.cents as f64 / 100.0, &self.currency)
+        )
+    }
+}
+
+/// Rate of a day.
+pub struct Quote {
+    pub rate: f64,
+    /// Unix time.
+    pub fetched_at: i64,
+    /// Fetched before the day was over, so it's not the final rate.
+    pub provisional: bool,
+}
+
+pub struct Prices;
+
+impl Prices {
+    fn load(ctx: &Context) -> Handle {
+        ctx.data_mut(|d| {
+            if let Some(handle) = d.get_temp::<Handle>(Id::NULL) {
+                return handle;
+            }
+            let cache = d
+                .get_persisted::<Cache>(Id::NULL)
+                .filter(|cache| !cache.base_url.is_empty())
+                .unwrap_or_else(|| Cache {
+                    base_url: DEFAULT_API.to_string(),
+                    ..Default::default()
+                });
+            let handle = Handle(Arc::new(Mutex::new(State {
+                cache,
+                ..Default::default()
+            })));
+            d.insert_temp(Id::NULL, handle.clone());
+            handle
+        })
+    }
+
+    fn store(ctx: &Context, cache: Cache) {
+        ctx.data_mut(|d| d.insert_persisted(Id::NULL, cache));
+    }
+
+    /// Cached rates from another API are dropped.
+    pub fn set_base_url(ctx: &Context, base_url: &str) {
+        let handle = Self::load(ctx);
+        let mut state = handle.0.lock();
+        if state.cache.base_url != base_url {
+            *state = State {
+                cache: Cache {
+                    base_url: base_url.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            Self::store(ctx, state.cache.clone());
+        }
+    }
+
+    /// Fetch all cached rates again, and retry those that failed. Cached rates are still used
+    /// until the new ones arrive, or if fetching fails.
+    pub fn refresh(ctx: &Context) {
+        let handle = Self::load(ctx);
+        let mut state = handle.0.lock();
+        state.failed.clear();
+        state.ranges.clear();
+        state.refresh_before = Utc::now().timestamp();
+    }
+
+    /// Number of days with cached rates, of the current chain.
+    pub fn cached_days(ctx: &Context) -> usize {
+        let coin = Client::network(ctx).chain().coingecko_id();
+        Self::load(ctx).0.lock().cache.days(coin).len()
+    }
+
+    /// Price of one coin of the current chain on the day of [timestamp]. Missing and outdated
+    /// rates are fetched in the background.
+    pub fn quote(ctx: &Context, currency: &str, timestamp: i64) -> Option<Quote> {
+        let now = Utc::now().timestamp();
+        let day = day_start(timestamp, now);
+        let coin = Client::network(ctx).chain().coingecko_id();
+        let handle = Self::load(ctx);
+        let mut state = handle.0.lock();
+
+        let cached = state.cache.days(coin).get(&day).cloned();
+        let outdated = cached.as_ref().map_or(true, |cached| {
+            cached.fetched_at < state.refresh_before
+                || (cached.fetched_at < day + DAY && now - cached.fetched_at > REFRESH_AFTER)
+        });
+        let key = (coin, day);
+        if outdated && !state.pending.contains(&key) && !state.failed.contains(&key) {
+            state.pending.insert(key);
+            let url = format!(
+                "{}/coins/{}/history?date={}&localization=false",
+                state.cache.base_url,
+                coin,
+                format_day(day)
+            );
+            drop(state);
+            Self::fetch(ctx, handle, key, url);
+        }
+
+        let cached = cached?;
+        Some(Quote {
+            rate: *cached.rates.get(currency)?,
+            fetched_at: cached.fetched_at,
+            provisional: cached.fetched_at < day + DAY,
+        })
+    }
+
+    /// Rate in [currency] of each day from [from] to [to], oldest first, fetched in one request.
+    /// Days the API has no rate for are left out. `None` while loading.
+    pub fn daily(ctx: &Context, currency: &str, from: i64, to: i64) -> Option<Vec<(i64, f64)>> {
+        let now = Utc::now().timestamp();
+        let (from, to) = (day_start(from, now), day_start(to, now));
+        let coin = Client::network(ctx).chain().coingecko_id();
+        let key = (coin, currency.to_string(), from, to);
+        let handle = Self::load(ctx);
+        let mut state = handle.0.lock();
+        if let Some(rates) = state.ranges.get(&key) {
+            return rates.clone();
+        }
+        state.ranges.insert(key.clone(), None);
+        let url = format!(
+            "{}/coins/{}/market_chart/range?vs_currency={}&from={}&to={}",
+            state.cache.base_url,
+            coin,
+            currency,
+            from,
+            to + DAY
+        );
+        drop(state);
+
+        let ctx2 = ctx.clone();
+        let handle2 = handle.clone();
+        Client::fetch_external_json(url, ctx, move |result: Result<MarketChart, _>| {
+            // Shorter ranges come hourly, keep the first rate of each day.
+            let mut rates: Vec<(i64, f64)> = vec![];
+            match result {
+                Ok(chart) => {
+                    for (millis, rate) in chart.prices {
+                        let day = day_start(millis as i64 / 1000, i64::MAX);
+                        if rates.last().map_or(true, |(last, _)| *last < day) {
+                            rates.push((day, rate));
+                        }
+                    }
+                }
+                Err(_) => log::warn!("No {} prices from {}", coin, format_day(from)),
+            }
+            handle2.0.lock().ranges.insert(key, Some(rates));
+            ctx2.request_repaint();
+        });
+        None
+    }
+
+    fn fetch(ctx: &Context, handle: Handle, key: (&'static str, i64), url: String) {
+        let (coin, day) = key;
+        let ctx2 = ctx.clone();
+        Client::fetch_external_json(url, ctx, move |result: Result<History, _>| {
+            let mut state = handle.0.lock();
+            state.pending.remove(&key);
+            match result.map(|history| history.market_data) {
+                Ok(Some(data)) => {
+                    let rates = data
+                        .current_price
+                        .into_iter()
+                        .filter(|(currency, _)| CURRENCIES.contains(&currency.as_str()))
+                        .collect();
+                    state.cache.days(coin).insert(
+                        day,
+                        DayRates {
+                            fetched_at: Utc::now().timestamp(),
+                            rates,
+                        },
+                    );
+                    Self::store(&ctx2, state.cache.clone());
+                }
+                _ => {
+                    log::warn!("No {} price for {}", coin, format_day(day));
+                    state.failed.insert(key);
+                }
+            }
+            ctx2.request_repaint();
+        });
+    }
+
+    fn failed(ctx: &Context, timestamp: i64) -> bool {
+        let day = day_start(timestamp, Utc::now().timestamp());
+        let coin = Client::network(ctx).chain().coingecko_id();
+        Self::load(ctx).0.lock().failed.contains(&(coin, day))
+    }
+
+    /// E.g. "≈ 1,234.56 USD", to show next to the BTC value.
+    pub fn fiat_text(ctx: &Context, currency: &str, sats: u64, timestamp: i64) -> String {
+        match Self::quote(ctx, currency, timestamp) {
+            Some(quote) => {
+                let text = format!(
+                    "≈ {}",
+                    format_fiat(sats as f64 / 100_000_000.0 * quote.rate, currency)
+                );
+                if quote.provisional {
+                    let fetched = NaiveDateTime::from_timestamp_opt(quote.fetched_at, 0)
+                        .map_or("never".to_string(), |t| t.format("%H:%M UTC").to_string());
+                    format!("{} (provisional, {})", text, fetched)
+                } else {
+                    text
+                }
+            }
+            None if Self::failed(ctx, timestamp) => {
+                format!("no {} price", currency.to_uppercase())
+            }
+            None => format!("… {}", currency.to_uppercase()),
+        }
+    }
+}
+
+/// Midnight (UTC) of the day of [timestamp]. Unconfirmed transactions belong to today.
+fn day_start(timestamp: i64, now: i64) -> i64 {
+    let timestamp = if timestamp > 0 && timestamp < now {
+        timestamp
+    } else {
+        now
+    };
+    timestamp - timestamp.rem_euclid(DAY)
+}
+
+/// E.g. `2024-01-31`.
+pub fn format_date(timestamp: i64) -> String {
+    NaiveDateTime::from_timestamp_opt(timestamp, 0)
+        .map(|t| t.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+fn format_day(day: i64) -> String {
+    NaiveDateTime::from_timestamp_opt(day, 0)
+        .unwrap_or_default()
+        .format("%d-%m-%Y")
+        .to_string()
+}
+
+/// Two decimals and thousands separators.
+pub fn format_fiat(value: f64, currency: &str) -> String {
+    let cents = (value.abs() * 100.0).round() as u64;
+    let whole = (cents / 100).to_string();
+    let mut grouped = String::new();
+    for (i, c) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    let sign = if value < 0.0 && cents > 0 { "-" } else { "" };
+    format!(
+        "{}{}.{:02} {}",
+        sign,
+        grouped,
+        cents % 100,
+        currency.to_uppercase()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{day_start, format_day, format_fiat};
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_fiat(1234567.891, "usd"), "1,234,567.89 USD");
+        assert_eq!(format_fiat(0.5, "eur"), "0.50 EUR");
+        assert_eq!(format_fiat(-999.999, "gbp"), "-1,000.00 GBP");
+        let genesis = 1231006505;
+        assert_eq!(format_day(day_start(genesis, genesis + 10)), "03-01-2009");
+        // Unconfirmed
+        assert_eq!(day_start(0, genesis), day_start(genesis, genesis + 10));
+    }
+}
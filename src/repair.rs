@@ -0,0 +1,141 @@
+//! Summary of what changed when re-validating a workspace against the backend.
+
+use std::fmt::Display;
+
+use crate::{bitcoin::Transaction, tx_cache::Refresh};
+
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct RepairReport {
+    pub checked: usize,
+    pub changed: usize,
+    pub newly_confirmed: usize,
+    /// Confirmed transactions that are now in a different block, or unconfirmed again.
+    pub reorged: usize,
+    pub newly_spent: usize,
+    /// Outputs that were spent before but aren't anymore.
+    pub spends_reverted: usize,
+    /// Transactions where script data was missing and is now present.
+    pub filled_in: usize,
+    pub failed: usize,
+}
+
+impl RepairReport {
+    pub fn new(refreshes: &[Refresh]) -> Self {
+        let mut report = Self::default();
+        for refresh in refreshes {
+            report.checked += 1;
+            match (&refresh.old, &refresh.new) {
+                (_, None) => report.failed += 1,
+                (None, Some(_)) => report.changed += 1,
+                (Some(old), Some(new)) => report.add(old, new),
+            }
+        }
+        report
+    }
+
+    fn add(&mut self, old: &Transaction, new: &Transaction) {
+        if old == new {
+            return;
+        }
+        self.changed += 1;
+
+        if old.block_height == 0 && new.block_height > 0 {
+            self.newly_confirmed += 1;
+        } else if old.block_height > 0 && new.block_height != old.block_height {
+            self.reorged += 1;
+        }
+
+        for (o, n) in old.outputs.iter().zip(&new.outputs) {
+            match (o.spending_txid, n.spending_txid) {
+                (None, Some(_)) => self.newly_spent += 1,
+                (Some(_), None) => self.spends_reverted += 1,
+                _ => {}
+            }
+        }
+
+        let missing_scripts = |tx: &Transaction| {
+            tx.outputs.iter().any(|o| o.script_pubkey.is_none())
+                || tx.inputs.iter().any(|i| i.script_sig.is_none())
+        };
+        if missing_scripts(old) && !missing_scripts(new) {
+            self.filled_in += 1;
+        }
+    }
+
+    /// Whether something happened that deserves a closer look.
+    pub fn is_suspicious(&self) -> bool {
+        self.reorged > 0 || self.spends_reverted > 0 || self.failed > 0
+    }
+}
+
+impl Display for RepairReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} of {} transactions changed.",
+            self.changed, self.checked
+        )?;
+        writeln!(f, "Newly confirmed: {}", self.newly_confirmed)?;
+        writeln!(f, "Reorged: {}", self.reorged)?;
+        writeln!(f, "Outputs newly spent: {}", self.newly_spent)?;
+        writeln!(f, "Spends reverted: {}", self.spends_reverted)?;
+        writeln!(f, "Missing data filled in: {}", self.filled_in)?;
+        write!(f, "Could not be fetched: {}", self.failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RepairReport;
+    use crate::{
+        bitcoin::{AddressType, Output, Transaction, Txid},
+        tx_cache::Refresh,
+    };
+
+    fn tx(block_height: u32, spent: bool) -> Transaction {
+        let txid = Txid::new(&"11".repeat(32)).unwrap();
+        Transaction {
+            timestamp: 0,
+            txid,
+            block_height,
+            inputs: vec![],
+            outputs: vec![Output {
+                spending_txid: spent.then_some(txid),
+                value: 1000,
+                address: "addr".to_string(),
+                address_type: AddressType::P2WPKH,
+                script_pubkey: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn classifies_changes() {
+        let refresh = |old: Option<Transaction>, new: Option<Transaction>| Refresh {
+            txid: Txid::new(&"11".repeat(32)).unwrap(),
+            old,
+            new,
+            not_found: false,
+        };
+        let report = RepairReport::new(&[
+            refresh(Some(tx(0, false)), Some(tx(100, true))),
+            refresh(Some(tx(100, true)), Some(tx(101, false))),
+            refresh(Some(tx(100, true)), Some(tx(100, true))),
+            refresh(Some(tx(100, true)), None),
+        ]);
+        assert_eq!(
+            report,
+            RepairReport {
+                checked: 4,
+                changed: 2,
+                newly_confirmed: 1,
+                reorged: 1,
+                newly_spent: 1,
+                spends_reverted: 1,
+                filled_in: 0,
+                failed: 1,
+            }
+        );
+        assert!(report.is_suspicious());
+    }
+}
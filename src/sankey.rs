@@ -0,0 +1,305 @@
+//! Sankey diagram of the flows between entities, as an overview next to the transaction graph.
+
+use egui::{ecolor::Hsva, Align2, Color32, FontId, Mesh, Pos2, Rect, Sense, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bezier::Cubic,
+    bitcoin::{Sats, SatsDisplay},
+    entity::FlowMatrix,
+    style,
+};
+
+/// An entity in one of the columns.
+#[derive(Debug, PartialEq)]
+pub struct Node {
+    /// Index into [FlowMatrix::entities].
+    pub entity: usize,
+    pub value: u64,
+    pub y: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Link {
+    /// Indices into the columns.
+    pub source: usize,
+    pub target: usize,
+    pub value: u64,
+    pub source_y: f32,
+    pub target_y: f32,
+    pub width: f32,
+}
+
+/// Senders on the left, receivers on the right, each sorted by value with the largest on top.
+#[derive(Debug, PartialEq)]
+pub struct Sankey {
+    pub sources: Vec<Node>,
+    pub targets: Vec<Node>,
+    pub links: Vec<Link>,
+}
+
+impl Sankey {
+    /// Fits the diagram into [height], with [gap] between the nodes of a column.
+    pub fn new(matrix: &FlowMatrix, hide_self_transfers: bool, height: f32, gap: f32) -> Self {
+        let n = matrix.entities.len();
+        let value = |from: usize, to: usize| {
+            if hide_self_transfers && from == to {
+                0
+            } else {
+                matrix.values[from][to]
+            }
+        };
+        let column = |total: &dyn Fn(usize) -> u64| {
+            let mut nodes: Vec<(usize, u64)> = (0..n)
+                .map(|i| (i, total(i)))
+                .filter(|(_, value)| *value > 0)
+                .collect();
+            nodes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            nodes
+        };
+        let sources = column(&|from| (0..n).map(|to| value(from, to)).sum());
+        let targets = column(&|to| (0..n).map(|from| value(from, to)).sum());
+
+        let total: u64 = sources.iter().map(|(_, value)| value).sum();
+        let gaps = sources.len().max(targets.len()).saturating_sub(1) as f32 * gap;
+        let scale = (height - gaps).max(0.0) / total.max(1) as f32;
+        let place = |nodes: Vec<(usize, u64)>| {
+            let mut y = 0.0;
+            nodes
+                .into_iter()
+                .map(|(entity, value)| {
+                    let height = value as f32 * scale;
+                    let node = Node {
+                        entity,
+                        value,
+                        y,
+                        height,
+                    };
+                    y += height + gap;
+                    node
+                })
+                .collect::<Vec<_>>()
+        };
+        let sources = place(sources);
+        let targets = place(targets);
+
+        // Links leave and enter their nodes in the order of the other end, so they don't cross
+        // within a node.
+        let mut links = vec![];
+        let mut target_offsets = vec![0.0; targets.len()];
+        for (s, source) in sources.iter().enumerate() {
+            let mut source_offset = 0.0;
+            for (t, target) in targets.iter().enumerate() {
+                let value = value(source.entity, target.entity);
+                if value == 0 {
+                    continue;
+                }
+                let width = value as f32 * scale;
+                links.push(Link {
+                    source: s,
+                    target: t,
+                    value,
+                    source_y: source.y + source_offset,
+                    target_y: target.y + target_offsets[t],
+                    width,
+                });
+                source_offset += width;
+                target_offsets[t] += width;
+            }
+        }
+
+        Self {
+            sources,
+            targets,
+            links,
+        }
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui, entities: &[String]) {
+        let style = style::get(ui);
+        let node_width = 12.0;
+        let label_width = 120.0;
+        let height = self
+            .sources
+            .iter()
+            .chain(&self.targets)
+            .map(|node| node.y + node.height)
+            .fold(0.0, f32::max);
+        let size = Vec2::new(ui.available_width().max(500.0), height);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+        let painter = ui.painter_at(rect);
+
+        let left = rect.left() + label_width;
+        let right = rect.right() - label_width - node_width;
+        let color = |entity: usize| {
+            Color32::from(Hsva::new(
+                (entity as f32 * 0.618_034).fract(),
+                0.6,
+                0.8,
+                1.0,
+            ))
+        };
+        let font = FontId::proportional(11.0);
+        let text_color = ui.visuals().text_color();
+
+        let mut hovered = None;
+        let pointer = response.hover_pos();
+        let steps = 20;
+        for (i, link) in self.links.iter().enumerate() {
+            let from = Pos2::new(left + node_width, rect.top() + link.source_y);
+            let to = Pos2::new(right, rect.top() + link.target_y);
+            let width = link.width.max(1.0);
+            let ribbon = |t: f32| {
+                let x = from.x + (to.x - from.x) * t;
+                let y = from.y + (to.y - from.y) * Cubic::move_to().eval(t).y;
+                (Pos2::new(x, y), Pos2::new(x, y + width))
+            };
+
+            let highlighted = pointer.is_some_and(|p| {
+                let t = (p.x - from.x) / (to.x - from.x);
+                let (top, bottom) = ribbon(t);
+                (0.0..=1.0).contains(&t) && p.y >= top.y && p.y <= bottom.y
+            });
+            if highlighted {
+                hovered = Some(i);
+            }
+            let alpha = if highlighted { 0.6 } else { 0.35 };
+            let fill = color(self.sources[link.source].entity).gamma_multiply(alpha);
+
+            let mut mesh = Mesh::default();
+            for n in 0..=steps {
+                let (top, bottom) = ribbon(n as f32 / steps as f32);
+                mesh.colored_vertex(top, fill);
+                mesh.colored_vertex(bottom, fill);
+                if n > 0 {
+                    let i = 2 * n as u32;
+                    mesh.add_triangle(i - 2, i - 1, i);
+                    mesh.add_triangle(i - 1, i, i + 1);
+                }
+            }
+            painter.add(mesh);
+        }
+
+        for (nodes, x, align, label_x) in [
+            (&self.sources, left, Align2::RIGHT_CENTER, left - 4.0),
+            (
+                &self.targets,
+                right,
+                Align2::LEFT_CENTER,
+                right + node_width + 4.0,
+            ),
+        ] {
+            for node in nodes {
+                let node_rect = Rect::from_min_size(
+                    Pos2::new(x, rect.top() + node.y),
+                    Vec2::new(node_width, node.height.max(1.0)),
+                );
+                painter.rect_filled(node_rect, 0.0, color(node.entity));
+                painter.text(
+                    Pos2::new(label_x, node_rect.center().y),
+                    align,
+                    &entities[node.entity],
+                    font.clone(),
+                    text_color,
+                );
+            }
+        }
+
+        if let Some(link) = hovered.map(|i| &self.links[i]) {
+            response.on_hover_ui_at_pointer(|ui| {
+                ui.label(format!(
+                    "{} → {}",
+                    entities[self.sources[link.source].entity],
+                    entities[self.targets[link.target].entity]
+                ));
+                ui.add(SatsDisplay::new(Sats(link.value), &style));
+            });
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct SankeyView {
+    hide_self_transfers: bool,
+    #[serde(skip)]
+    open: bool,
+}
+
+impl Default for SankeyView {
+    fn default() -> Self {
+        Self {
+            hide_self_transfers: true,
+            open: false,
+        }
+    }
+}
+
+impl SankeyView {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn show_window(&mut self, ctx: &egui::Context, matrix: impl FnOnce() -> FlowMatrix) {
+        if !self.open {
+            return;
+        }
+        let matrix = matrix();
+        let mut open = self.open;
+        egui::Window::new("Entity Flows")
+            .open(&mut open)
+            .default_width(600.0)
+            .show(ctx, |ui| {
+                ui.checkbox(
+                    &mut self.hide_self_transfers,
+                    "Hide transfers within an entity",
+                );
+                let sankey = Sankey::new(&matrix, self.hide_self_transfers, 300.0, 8.0);
+                if sankey.links.is_empty() {
+                    ui.label("Label some coins first. Coins with the same label form an entity.");
+                    return;
+                }
+                ui.weak("Senders on the left, receivers on the right.");
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| sankey.ui(ui, &matrix.entities));
+            });
+        self.open = open;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sankey;
+    use crate::entity::FlowMatrix;
+
+    #[test]
+    fn layout() {
+        let matrix = FlowMatrix {
+            entities: vec!["A".to_string(), "B".to_string(), "C".to_string()],
+            values: vec![vec![10, 0, 30], vec![20, 0, 0], vec![0, 0, 0]],
+        };
+        let sankey = Sankey::new(&matrix, true, 35.0, 10.0);
+
+        // Senders A (30) and B (20), receivers C (30) and A (20). Scale is (35 - 10) / 50.
+        let columns = |nodes: &[super::Node]| -> Vec<_> {
+            nodes.iter().map(|n| (n.entity, n.y, n.height)).collect()
+        };
+        assert_eq!(
+            columns(&sankey.sources),
+            vec![(0, 0.0, 15.0), (1, 25.0, 10.0)]
+        );
+        assert_eq!(
+            columns(&sankey.targets),
+            vec![(2, 0.0, 15.0), (0, 25.0, 10.0)]
+        );
+
+        let links: Vec<_> = sankey
+            .links
+            .iter()
+            .map(|l| (l.source, l.target, l.source_y, l.target_y))
+            .collect();
+        assert_eq!(links, vec![(0, 0, 0.0, 0.0), (1, 1, 25.0, 25.0)]);
+    }
+}
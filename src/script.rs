@@ -0,0 +1,326 @@
+//! Disassembly and classification of Bitcoin scripts.
+
+use egui::{Grid, RichText};
+
+use crate::widgets::UiExt;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Template {
+    P2PK,
+    P2PKH,
+    P2SH,
+    P2WPKH,
+    P2WSH,
+    P2TR,
+    Multisig { m: u8, n: u8 },
+    OpReturn,
+    NonStandard,
+}
+
+impl Template {
+    pub fn classify(script: &[u8]) -> Self {
+        match script {
+            [0x76, 0xa9, 0x14, hash @ .., 0x88, 0xac] if hash.len() == 20 => Self::P2PKH,
+            [0xa9, 0x14, hash @ .., 0x87] if hash.len() == 20 => Self::P2SH,
+            [0x00, 0x14, hash @ ..] if hash.len() == 20 => Self::P2WPKH,
+            [0x00, 0x20, hash @ ..] if hash.len() == 32 => Self::P2WSH,
+            [0x51, 0x20, key @ ..] if key.len() == 32 => Self::P2TR,
+            [0x21, key @ .., 0xac] if key.len() == 33 => Self::P2PK,
+            [0x41, key @ .., 0xac] if key.len() == 65 => Self::P2PK,
+            [0x6a, ..] => Self::OpReturn,
+            [m @ 0x51..=0x60, .., n @ 0x51..=0x60, 0xae] => Self::Multisig {
+                m: m - 0x50,
+                n: n - 0x50,
+            },
+            _ => Self::NonStandard,
+        }
+    }
+
+    /// For badges, `None` if there's no common name.
+    pub fn short_name(&self) -> Option<&'static str> {
+        match self {
+            Self::P2PK => Some("P2PK"),
+            Self::P2PKH => Some("P2PKH"),
+            Self::P2SH => Some("P2SH"),
+            Self::P2WPKH => Some("P2WPKH"),
+            Self::P2WSH => Some("P2WSH"),
+            Self::P2TR => Some("P2TR"),
+            Self::Multisig { .. } => Some("P2MS"),
+            Self::OpReturn => Some("OP_RETURN"),
+            Self::NonStandard => None,
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Self::P2PK => "Pay to public key".to_string(),
+            Self::P2PKH => "Pay to public key hash".to_string(),
+            Self::P2SH => "Pay to script hash".to_string(),
+            Self::P2WPKH => "Pay to witness public key hash (SegWit v0)".to_string(),
+            Self::P2WSH => "Pay to witness script hash (SegWit v0)".to_string(),
+            Self::P2TR => "Pay to Taproot (SegWit v1)".to_string(),
+            Self::Multisig { m, n } => format!("Bare {}-of-{} multisig", m, n),
+            Self::OpReturn => "Data carrier (OP_RETURN)".to_string(),
+            Self::NonStandard => "Non-standard".to_string(),
+        }
+    }
+}
+
+/// The data pushed after `OP_RETURN`, if [script] is a data carrier.
+pub fn op_return_data(script: &[u8]) -> Option<Vec<u8>> {
+    let (&op, rest) = script.split_first()?;
+    if op != 0x6a {
+        return None;
+    }
+    let (&push, rest) = rest.split_first()?;
+    let (len, rest) = match push {
+        0x01..=0x4b => (push as usize, rest),
+        // OP_PUSHDATA1
+        0x4c => {
+            let (&len, rest) = rest.split_first()?;
+            (len as usize, rest)
+        }
+        // OP_PUSHDATA2
+        0x4d => {
+            if rest.len() < 2 {
+                return None;
+            }
+            (u16::from_le_bytes([rest[0], rest[1]]) as usize, &rest[2..])
+        }
+        _ => return None,
+    };
+    rest.get(..len).map(|data| data.to_vec())
+}
+
+/// `None` unless [data] is valid UTF-8 without control characters.
+pub fn printable_text(data: &[u8]) -> Option<&str> {
+    let text = std::str::from_utf8(data).ok()?;
+    if text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        return None;
+    }
+    Some(text)
+}
+
+/// Render the script in the usual `OP_DUP OP_HASH160 <hex> ...` notation.
+pub fn disassemble(script: &[u8]) -> String {
+    let mut parts = vec![];
+    let mut rest = script;
+
+    while let Some((&op, tail)) = rest.split_first() {
+        rest = tail;
+        let push_len = match op {
+            0x01..=0x4b => Some(op as usize),
+            0x4c => Some(take_len(&mut rest, 1)),
+            0x4d => Some(take_len(&mut rest, 2)),
+            0x4e => Some(take_len(&mut rest, 4)),
+            _ => None,
+        };
+        match push_len {
+            Some(len) if len <= rest.len() => {
+                parts.push(hex::encode(&rest[..len]));
+                rest = &rest[len..];
+            }
+            Some(_) => {
+                parts.push("[error: push past end of script]".to_string());
+                break;
+            }
+            None => parts.push(opcode_name(op)),
+        }
+    }
+
+    parts.join(" ")
+}
+
+/// Read a little-endian length prefix of `bytes` bytes. A truncated prefix yields a length
+/// that can't be satisfied.
+fn take_len(rest: &mut &[u8], bytes: usize) -> usize {
+    if rest.len() < bytes {
+        *rest = &[];
+        return usize::MAX;
+    }
+    let len = rest[..bytes]
+        .iter()
+        .rev()
+        .fold(0, |acc, &b| (acc << 8) | b as usize);
+    *rest = &rest[bytes..];
+    len
+}
+
+fn opcode_name(op: u8) -> String {
+    let name = match op {
+        0x00 => "OP_0",
+        0x4f => "OP_1NEGATE",
+        0x50 => "OP_RESERVED",
+        0x51..=0x60 => return format!("OP_{}", op - 0x50),
+        0x61 => "OP_NOP",
+        0x62 => "OP_VER",
+        0x63 => "OP_IF",
+        0x64 => "OP_NOTIF",
+        0x65 => "OP_VERIF",
+        0x66 => "OP_VERNOTIF",
+        0x67 => "OP_ELSE",
+        0x68 => "OP_ENDIF",
+        0x69 => "OP_VERIFY",
+        0x6a => "OP_RETURN",
+        0x6b => "OP_TOALTSTACK",
+        0x6c => "OP_FROMALTSTACK",
+        0x6d => "OP_2DROP",
+        0x6e => "OP_2DUP",
+        0x6f => "OP_3DUP",
+        0x70 => "OP_2OVER",
+        0x71 => "OP_2ROT",
+        0x72 => "OP_2SWAP",
+        0x73 => "OP_IFDUP",
+        0x74 => "OP_DEPTH",
+        0x75 => "OP_DROP",
+        0x76 => "OP_DUP",
+        0x77 => "OP_NIP",
+        0x78 => "OP_OVER",
+        0x79 => "OP_PICK",
+        0x7a => "OP_ROLL",
+        0x7b => "OP_ROT",
+        0x7c => "OP_SWAP",
+        0x7d => "OP_TUCK",
+        0x7e => "OP_CAT",
+        0x7f => "OP_SUBSTR",
+        0x80 => "OP_LEFT",
+        0x81 => "OP_RIGHT",
+        0x82 => "OP_SIZE",
+        0x83 => "OP_INVERT",
+        0x84 => "OP_AND",
+        0x85 => "OP_OR",
+        0x86 => "OP_XOR",
+        0x87 => "OP_EQUAL",
+        0x88 => "OP_EQUALVERIFY",
+        0x89 => "OP_RESERVED1",
+        0x8a => "OP_RESERVED2",
+        0x8b => "OP_1ADD",
+        0x8c => "OP_1SUB",
+        0x8d => "OP_2MUL",
+        0x8e => "OP_2DIV",
+        0x8f => "OP_NEGATE",
+        0x90 => "OP_ABS",
+        0x91 => "OP_NOT",
+        0x92 => "OP_0NOTEQUAL",
+        0x93 => "OP_ADD",
+        0x94 => "OP_SUB",
+        0x95 => "OP_MUL",
+        0x96 => "OP_DIV",
+        0x97 => "OP_MOD",
+        0x98 => "OP_LSHIFT",
+        0x99 => "OP_RSHIFT",
+        0x9a => "OP_BOOLAND",
+        0x9b => "OP_BOOLOR",
+        0x9c => "OP_NUMEQUAL",
+        0x9d => "OP_NUMEQUALVERIFY",
+        0x9e => "OP_NUMNOTEQUAL",
+        0x9f => "OP_LESSTHAN",
+        0xa0 => "OP_GREATERTHAN",
+        0xa1 => "OP_LESSTHANOREQUAL",
+        0xa2 => "OP_GREATERTHANOREQUAL",
+        0xa3 => "OP_MIN",
+        0xa4 => "OP_MAX",
+        0xa5 => "OP_WITHIN",
+        0xa6 => "OP_RIPEMD160",
+        0xa7 => "OP_SHA1",
+        0xa8 => "OP_SHA256",
+        0xa9 => "OP_HASH160",
+        0xaa => "OP_HASH256",
+        0xab => "OP_CODESEPARATOR",
+        0xac => "OP_CHECKSIG",
+        0xad => "OP_CHECKSIGVERIFY",
+        0xae => "OP_CHECKMULTISIG",
+        0xaf => "OP_CHECKMULTISIGVERIFY",
+        0xb0 => "OP_NOP1",
+        0xb1 => "OP_CHECKLOCKTIMEVERIFY",
+        0xb2 => "OP_CHECKSEQUENCEVERIFY",
+        0xb3..=0xb9 => return format!("OP_NOP{}", op - 0xb3 + 4),
+        0xba => "OP_CHECKSIGADD",
+        _ => return format!("OP_UNKNOWN_{:#04x}", op),
+    };
+    name.to_string()
+}
+
+/// Show hex, disassembly and classification of the given named scripts.
+pub fn inspector_ui(ui: &mut egui::Ui, scripts: &[(&str, Vec<u8>)]) {
+    ui.set_max_width(500.0);
+
+    for (i, (name, script)) in scripts.iter().enumerate() {
+        if i > 0 {
+            ui.separator();
+        }
+        ui.bold(*name);
+        Grid::new(("Script", i)).num_columns(2).show(ui, |ui| {
+            ui.label("Type:");
+            ui.label(Template::classify(script).description());
+            ui.end_row();
+
+            ui.label("Hex:");
+            ui.add(egui::Label::new(RichText::new(hex::encode(script)).monospace()).wrap());
+            ui.end_row();
+
+            ui.label("Asm:");
+            ui.add(egui::Label::new(RichText::new(disassemble(script)).monospace()).wrap());
+            ui.end_row();
+        });
+    }
+}
+
+/// Show the payload of an `OP_RETURN` output as hex, and as text if it is.
+pub fn payload_ui(ui: &mut egui::Ui, data: &[u8]) {
+    ui.set_max_width(400.0);
+    Grid::new("Payload").num_columns(2).show(ui, |ui| {
+        ui.label("Data:");
+        ui.add(egui::Label::new(RichText::new(hex::encode(data)).monospace()).wrap());
+        ui.end_row();
+
+        if let Some(text) = printable_text(data) {
+            ui.label("UTF-8:");
+            ui.add(egui::Label::new(RichText::new(text).monospace()).wrap());
+            ui.end_row();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{disassemble, op_return_data, printable_text, Template};
+
+    #[test]
+    fn p2pkh() {
+        let script = hex::decode("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap();
+        assert_eq!(Template::classify(&script), Template::P2PKH);
+        assert_eq!(
+            disassemble(&script),
+            "OP_DUP OP_HASH160 62e907b15cbf27d5425399ebf6f0fb50ebb88f18 OP_EQUALVERIFY OP_CHECKSIG"
+        );
+    }
+
+    #[test]
+    fn multisig() {
+        let key = "21".to_string() + &"02".repeat(33);
+        let script = hex::decode(format!("52{}{}52ae", key, key)).unwrap();
+        assert_eq!(
+            Template::classify(&script),
+            Template::Multisig { m: 2, n: 2 }
+        );
+    }
+
+    #[test]
+    fn truncated_push() {
+        assert_eq!(
+            disassemble(&[0x6a, 0x05, 0x01]),
+            "OP_RETURN [error: push past end of script]"
+        );
+    }
+
+    #[test]
+    fn op_return() {
+        let script = [&[0x6a, 0x05][..], b"hello"].concat();
+        assert_eq!(Template::classify(&script).short_name(), Some("OP_RETURN"));
+        let data = op_return_data(&script).unwrap();
+        assert_eq!(printable_text(&data), Some("hello"));
+        assert_eq!(printable_text(&[0x00, 0xff]), None);
+        assert_eq!(op_return_data(&[0x6a, 0x05, 0x01]), None);
+    }
+}
@@ -0,0 +1,373 @@
+use egui::{Button, Context, DragValue, Grid, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotations::Stamps,
+    bitcoin::format_btc,
+    chain::{Network, Source},
+    client::Client,
+    explorer::Explorer,
+    features::Features,
+    layout::Layout,
+    prices,
+    prices::Prices,
+    style::Theme,
+    widgets::UiExt,
+};
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// `None` means the compile-time default, see [Client::DEFAULT_BASE_URL].
+    api_base: Option<String>,
+    /// How often to try a request before giving up.
+    max_attempts: u32,
+    /// Where transactions are fetched from.
+    source: Source,
+    /// CoinGecko-compatible API for fiat values.
+    price_api: String,
+    /// Where transactions and addresses are opened.
+    explorer: Explorer,
+    /// Amount columns in CSV exports.
+    pub export_unit: ExportUnit,
+    pub features: Features,
+    #[serde(skip)]
+    input_api_base: Option<String>,
+    #[serde(skip)]
+    pub export_server: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            api_base: None,
+            max_attempts: Client::DEFAULT_MAX_ATTEMPTS,
+            source: Source::default(),
+            price_api: prices::DEFAULT_API.to_string(),
+            explorer: Explorer::default(),
+            export_unit: ExportUnit::default(),
+            features: Features::default(),
+            input_api_base: None,
+            export_server: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ExportUnit {
+    #[default]
+    Btc,
+    Sats,
+    Both,
+}
+
+impl ExportUnit {
+    pub fn headers(&self) -> Vec<&'static str> {
+        match self {
+            Self::Btc => vec!["amount_btc"],
+            Self::Sats => vec!["amount_sats"],
+            Self::Both => vec!["amount_btc", "amount_sats"],
+        }
+    }
+
+    /// One value per header. BTC amounts are formatted exactly, without going through floats.
+    pub fn values(&self, sats: i64) -> Vec<String> {
+        let btc = || format_btc(sats);
+        match self {
+            Self::Btc => vec![btc()],
+            Self::Sats => vec![sats.to_string()],
+            Self::Both => vec![btc(), sats.to_string()],
+        }
+    }
+}
+
+/// Everything that's worth moving to a new machine, as a JSON file.
+#[derive(Serialize, Deserialize)]
+pub struct Preferences {
+    pub theme: Theme,
+    pub layout: Layout,
+    pub stamps: Stamps,
+    /// Left out unless asked for, as it may point to a private server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_base: Option<String>,
+    // The settings below are missing in files from older versions, which keeps the current ones.
+    /// Left out unless asked for if it's an Esplora server, like [Self::api_base].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Source>,
+    #[serde(default)]
+    pub price_api: Option<String>,
+    #[serde(default)]
+    pub explorer: Option<Explorer>,
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+    #[serde(default)]
+    pub features: Option<Features>,
+    #[serde(default)]
+    pub export_unit: Option<ExportUnit>,
+}
+
+impl Settings {
+    /// The settings to move to another machine, with the rest of [Preferences].
+    pub fn preferences(&self, theme: Theme, layout: Layout, stamps: Stamps) -> Preferences {
+        let private = |server: Option<String>| server.filter(|_| self.export_server);
+        Preferences {
+            theme,
+            layout,
+            stamps,
+            api_base: private(self.api_base.clone()),
+            source: match &self.source {
+                Source::Backend => Some(Source::Backend),
+                Source::Esplora { base_url, network } => {
+                    private(Some(base_url.clone())).map(|base_url| Source::Esplora {
+                        base_url,
+                        network: network.clone(),
+                    })
+                }
+            },
+            price_api: Some(self.price_api.clone()),
+            explorer: Some(self.explorer.clone()),
+            max_attempts: Some(self.max_attempts),
+            features: Some(self.features.clone()),
+            export_unit: Some(self.export_unit),
+        }
+    }
+
+    /// Takes over the settings that [preferences] has.
+    pub fn import_preferences(&mut self, ctx: &Context, preferences: &Preferences) {
+        if let Some(api_base) = &preferences.api_base {
+            self.api_base = Some(api_base.clone());
+            self.input_api_base = None;
+        }
+        if let Some(source) = &preferences.source {
+            self.source = source.clone();
+        }
+        if let Some(price_api) = &preferences.price_api {
+            self.price_api = price_api.clone();
+        }
+        if let Some(explorer) = &preferences.explorer {
+            self.explorer = explorer.clone();
+        }
+        if let Some(max_attempts) = preferences.max_attempts {
+            self.max_attempts = max_attempts;
+        }
+        if let Some(features) = &preferences.features {
+            self.features = features.clone();
+        }
+        if let Some(export_unit) = preferences.export_unit {
+            self.export_unit = export_unit;
+        }
+        self.apply(ctx);
+    }
+
+    pub fn set_api_base(&mut self, ctx: &Context, api_base: Option<String>) {
+        self.api_base = api_base;
+        self.input_api_base = None;
+        self.apply(ctx);
+    }
+
+    /// Make the client use the configured settings.
+    pub fn apply(&self, ctx: &Context) {
+        Client::set_base_url(
+            ctx,
+            self.api_base.as_deref().unwrap_or(Client::DEFAULT_BASE_URL),
+        );
+        Client::set_max_attempts(ctx, self.max_attempts);
+        Client::set_source(ctx, self.source.clone());
+        Prices::set_base_url(ctx, self.price_api.trim().trim_end_matches('/'));
+        Explorer::set(ctx, self.explorer.clone());
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.bold("Server:");
+
+        let current = self
+            .api_base
+            .clone()
+            .unwrap_or(Client::DEFAULT_BASE_URL.to_string());
+        let input = self.input_api_base.get_or_insert(current.clone());
+
+        Grid::new("Settings").num_columns(2).show(ui, |ui| {
+            ui.label("API base URL:");
+            ui.add(
+                TextEdit::singleline(input)
+                    .hint_text(Client::DEFAULT_BASE_URL)
+                    .desired_width(300.0),
+            );
+            ui.end_row();
+        });
+
+        let url = input.trim().trim_end_matches('/').to_string();
+        let valid = url.starts_with("http://") || url.starts_with("https://");
+        if !valid {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "URL must start with http:// or https://",
+            );
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(valid && url != current, Button::new("Apply"))
+                .clicked()
+            {
+                self.set_api_base(ui.ctx(), Some(url));
+            }
+            if ui
+                .add_enabled(self.api_base.is_some(), Button::new("Reset to default"))
+                .clicked()
+            {
+                self.set_api_base(ui.ctx(), None);
+            }
+        });
+
+        ui.add_space(3.0);
+        ui.bold("Transactions from:");
+        let before = self.source.clone();
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.source, Source::Backend, Source::Backend.name());
+            let esplora = match &self.source {
+                Source::Esplora { .. } => self.source.clone(),
+                Source::Backend => Source::Esplora {
+                    base_url: String::new(),
+                    network: Some(Client::network(ui.ctx()).name().to_string()),
+                },
+            };
+            let name = esplora.name();
+            ui.radio_value(&mut self.source, esplora, name);
+        });
+        if let Source::Esplora { base_url, network } = &mut self.source {
+            let current = Client::network(ui.ctx());
+            ui.horizontal(|ui| {
+                ui.label("Esplora URL:");
+                if ui
+                    .add(
+                        TextEdit::singleline(base_url)
+                            .hint_text("Default for the workspace's network")
+                            .desired_width(300.0),
+                    )
+                    .changed()
+                {
+                    *network = Some(current.name().to_string());
+                }
+            })
+            .response
+            .on_hover_text(format!(
+                "Any Esplora-compatible API, e.g. mempool.space or blockstream.info. \
+                 Workspaces on other networks than {} use their default.",
+                network.as_deref().unwrap_or(Network::default().name())
+            ));
+        }
+        if self.source != before {
+            self.apply(ui.ctx());
+        }
+
+        ui.add_space(3.0);
+        Grid::new("Requests").num_columns(2).show(ui, |ui| {
+            ui.label("Attempts per request:");
+            if ui
+                .add(DragValue::new(&mut self.max_attempts).range(1..=10))
+                .on_hover_text("Failed requests are retried with increasing delays.")
+                .changed()
+            {
+                self.apply(ui.ctx());
+            }
+            ui.end_row();
+        });
+
+        ui.add_space(3.0);
+        ui.horizontal(|ui| {
+            ui.label("Price API:");
+            let response = ui.add(
+                TextEdit::singleline(&mut self.price_api)
+                    .hint_text(prices::DEFAULT_API)
+                    .desired_width(300.0),
+            );
+            if response.lost_focus() {
+                if self.price_api.trim().is_empty() {
+                    self.price_api = prices::DEFAULT_API.to_string();
+                }
+                self.apply(ui.ctx());
+            }
+        })
+        .response
+        .on_hover_text("CoinGecko-compatible API for the fiat values of a workspace.");
+
+        ui.add_space(3.0);
+        if self.explorer.ui(ui) {
+            self.apply(ui.ctx());
+        }
+
+        ui.add_space(3.0);
+        ui.bold("Exports:");
+        ui.horizontal(|ui| {
+            ui.label("Amounts in CSV:");
+            ui.selectable_value(&mut self.export_unit, ExportUnit::Btc, "BTC");
+            ui.selectable_value(&mut self.export_unit, ExportUnit::Sats, "sats");
+            ui.selectable_value(&mut self.export_unit, ExportUnit::Both, "Both");
+        });
+
+        ui.add_space(3.0);
+        ui.bold("Experimental:");
+        self.features.ui(ui);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::Context;
+
+    use super::{ExportUnit, Preferences, Settings};
+    use crate::{annotations::Stamps, chain::Source, layout::Layout, style::Theme};
+
+    #[test]
+    fn exact_btc_values() {
+        assert_eq!(ExportUnit::Btc.values(-150_000_001), vec!["-1.50000001"]);
+        assert_eq!(
+            ExportUnit::Both.values(2_100_000_000_000_000),
+            vec!["21000000.00000000", "2100000000000000"]
+        );
+    }
+
+    #[test]
+    fn preferences_keep_settings() {
+        let ctx = Context::default();
+        let settings = Settings {
+            max_attempts: 5,
+            source: Source::Esplora {
+                base_url: "http://localhost:3000".to_string(),
+                network: Some("signet".to_string()),
+            },
+            export_unit: ExportUnit::Sats,
+            ..Default::default()
+        };
+        let export = |settings: &Settings| -> Preferences {
+            let preferences =
+                settings.preferences(Theme::default(), Layout::default(), Stamps::default());
+            serde_json::from_str(&serde_json::to_string(&preferences).unwrap()).unwrap()
+        };
+
+        let mut imported = Settings::default();
+        imported.import_preferences(&ctx, &export(&settings));
+        assert_eq!(imported.max_attempts, 5);
+        assert!(imported.export_unit == ExportUnit::Sats);
+        // The Esplora server is private unless asked for.
+        assert_eq!(imported.source, Source::Backend);
+
+        let settings = Settings {
+            export_server: true,
+            ..settings
+        };
+        imported.import_preferences(&ctx, &export(&settings));
+        assert_eq!(imported.source, settings.source);
+
+        // Files from older versions have none of the settings.
+        let old = serde_json::json!({
+            "theme": Theme::default(),
+            "layout": Layout::default(),
+            "stamps": Stamps::default(),
+        });
+        imported.import_preferences(&ctx, &serde_json::from_value(old).unwrap());
+        assert_eq!(imported.max_attempts, 5);
+        assert_eq!(imported.source, settings.source);
+    }
+}
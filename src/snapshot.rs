@@ -0,0 +1,77 @@
+//! PNG images of the canvas, taken from screenshots.
+
+use egui::{ColorImage, Context, Event, Rect, ViewportCommand};
+
+#[derive(Default)]
+pub struct Snapshot {
+    state: State,
+}
+
+#[derive(Default, PartialEq)]
+enum State {
+    #[default]
+    Idle,
+    /// Wait a frame so that the menu the request came from is closed.
+    Requested,
+    Taken,
+}
+
+impl Snapshot {
+    pub fn request(&mut self) {
+        self.state = State::Requested;
+    }
+
+    /// Call once per frame. Returns the PNG of [rect] once the screenshot arrived.
+    pub fn tick(&mut self, ctx: &Context, rect: Rect) -> Option<Result<Vec<u8>, String>> {
+        match self.state {
+            State::Idle => None,
+            State::Requested => {
+                ctx.send_viewport_cmd(ViewportCommand::Screenshot);
+                ctx.request_repaint();
+                self.state = State::Taken;
+                None
+            }
+            State::Taken => {
+                let image = ctx.input(|i| {
+                    i.raw.events.iter().find_map(|event| match event {
+                        Event::Screenshot { image, .. } => Some(image.clone()),
+                        _ => None,
+                    })
+                })?;
+                self.state = State::Idle;
+                Some(encode(&image.region(&rect, Some(ctx.pixels_per_point()))))
+            }
+        }
+    }
+}
+
+fn encode(image: &ColorImage) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    let mut encoder = png::Encoder::new(&mut bytes, image.width() as u32, image.height() as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer
+        .write_image_data(image.as_raw())
+        .map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{Color32, ColorImage};
+
+    use super::encode;
+
+    #[test]
+    fn png() {
+        let image = ColorImage::new([3, 2], Color32::RED);
+        let bytes = encode(&image).unwrap();
+        assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!((reader.info().width, reader.info().height), (3, 2));
+    }
+}
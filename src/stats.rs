@@ -0,0 +1,178 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use egui::Grid;
+
+use crate::widgets::UiExt;
+
+/// Metrics over the entities in the graph, i.e. the coin labels. An entity has an edge to
+/// every other entity it sent coins to.
+#[derive(Default, Debug)]
+pub struct GraphStats {
+    pub num_nodes: usize,
+    pub num_edges: usize,
+    /// Maps (undirected) degree to number of entities with that degree.
+    pub degree_distribution: BTreeMap<usize, usize>,
+    /// Sizes of the weakly connected components, largest first.
+    pub component_sizes: Vec<usize>,
+    /// Betweenness centrality, highest first.
+    pub betweenness: Vec<(String, f64)>,
+}
+
+impl GraphStats {
+    /// [edges] are indices into [nodes].
+    pub fn compute(nodes: &[String], edges: &[(usize, usize)]) -> Self {
+        let mut successors: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut neighbors: HashMap<usize, HashSet<usize>> = HashMap::new();
+        for &(source, target) in edges {
+            successors.entry(source).or_default().push(target);
+            neighbors.entry(source).or_default().insert(target);
+            neighbors.entry(target).or_default().insert(source);
+        }
+
+        let mut degree_distribution = BTreeMap::new();
+        for node in 0..nodes.len() {
+            let degree = neighbors.get(&node).map_or(0, |n| n.len());
+            *degree_distribution.entry(degree).or_insert(0) += 1;
+        }
+
+        let mut component_sizes = vec![];
+        let mut seen = HashSet::new();
+        for start in 0..nodes.len() {
+            if !seen.insert(start) {
+                continue;
+            }
+            let mut size = 0;
+            let mut queue = VecDeque::from([start]);
+            while let Some(node) = queue.pop_front() {
+                size += 1;
+                for &n in neighbors.get(&node).into_iter().flatten() {
+                    if seen.insert(n) {
+                        queue.push_back(n);
+                    }
+                }
+            }
+            component_sizes.push(size);
+        }
+        component_sizes.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut betweenness: Vec<_> = brandes(nodes.len(), &successors)
+            .into_iter()
+            .enumerate()
+            .map(|(node, centrality)| (nodes[node].clone(), centrality))
+            .collect();
+        betweenness.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        Self {
+            num_nodes: nodes.len(),
+            num_edges: edges.len(),
+            degree_distribution,
+            component_sizes,
+            betweenness,
+        }
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        ui.bold("Entities:");
+        if self.num_nodes == 0 {
+            ui.label("Label some coins first. Coins with the same label form an entity.");
+            return;
+        }
+        Grid::new("Stats").num_columns(2).show(ui, |ui| {
+            ui.label("Entities:");
+            ui.label(self.num_nodes.to_string());
+            ui.end_row();
+
+            ui.label("Flows:");
+            ui.label(self.num_edges.to_string());
+            ui.end_row();
+
+            ui.label("Components:");
+            ui.label(self.component_sizes.len().to_string());
+            ui.end_row();
+
+            ui.label("Largest component:");
+            ui.label(self.component_sizes.first().unwrap_or(&0).to_string());
+            ui.end_row();
+        });
+
+        ui.separator();
+        ui.bold("Degree distribution:");
+        Grid::new("Degrees").num_columns(2).show(ui, |ui| {
+            for (degree, count) in &self.degree_distribution {
+                ui.label(format!("{} counterparties:", degree));
+                ui.label(count.to_string());
+                ui.end_row();
+            }
+        });
+
+        ui.separator();
+        ui.bold("Most central entities:");
+        Grid::new("Centrality").num_columns(2).show(ui, |ui| {
+            for (entity, centrality) in self.betweenness.iter().take(5) {
+                ui.label(format!("{:.1}", centrality));
+                ui.label(entity);
+                ui.end_row();
+            }
+        });
+    }
+}
+
+/// Brandes' algorithm for betweenness centrality on an unweighted directed graph with nodes
+/// `0..num_nodes`.
+fn brandes(num_nodes: usize, successors: &HashMap<usize, Vec<usize>>) -> Vec<f64> {
+    let mut centrality = vec![0.0; num_nodes];
+
+    for s in 0..num_nodes {
+        let mut stack = vec![];
+        let mut predecessors: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut sigma: HashMap<usize, f64> = HashMap::from([(s, 1.0)]);
+        let mut dist: HashMap<usize, usize> = HashMap::from([(s, 0)]);
+        let mut queue = VecDeque::from([s]);
+
+        while let Some(v) = queue.pop_front() {
+            stack.push(v);
+            for &w in successors.get(&v).into_iter().flatten() {
+                if !dist.contains_key(&w) {
+                    dist.insert(w, dist[&v] + 1);
+                    queue.push_back(w);
+                }
+                if dist[&w] == dist[&v] + 1 {
+                    *sigma.entry(w).or_insert(0.0) += sigma[&v];
+                    predecessors.entry(w).or_default().push(v);
+                }
+            }
+        }
+
+        let mut delta: HashMap<usize, f64> = HashMap::new();
+        while let Some(w) = stack.pop() {
+            for &v in predecessors.get(&w).into_iter().flatten() {
+                let d = sigma[&v] / sigma[&w] * (1.0 + delta.get(&w).unwrap_or(&0.0));
+                *delta.entry(v).or_insert(0.0) += d;
+            }
+            if w != s {
+                centrality[w] += delta.get(&w).unwrap_or(&0.0);
+            }
+        }
+    }
+
+    centrality
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GraphStats;
+
+    #[test]
+    fn chain_with_isolated_node() {
+        let nodes = ["a", "b", "c", "d"].map(String::from);
+        let stats = GraphStats::compute(&nodes, &[(0, 1), (1, 2)]);
+
+        assert_eq!(stats.component_sizes, vec![3, 1]);
+        assert_eq!(
+            stats.degree_distribution.into_iter().collect::<Vec<_>>(),
+            vec![(0, 1), (1, 2), (2, 1)]
+        );
+        assert_eq!(stats.betweenness[0], ("b".to_string(), 1.0));
+        assert_eq!(stats.betweenness[1].1, 0.0);
+    }
+}
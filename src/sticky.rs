@@ -0,0 +1,307 @@
+//! Notes, frames and arrows placed anywhere on the canvas, for context that isn't tied to a
+//! single transaction.
+
+use egui::{
+    Align2, Button, Color32, CursorIcon, FontId, Id, Pos2, Rect, Rounding, Sense, Stroke, TextEdit,
+    Vec2,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    export::{Point0, Sticky0, StickyKind0},
+    svg::Svg,
+    transform::Transform,
+};
+
+const COLORS: [[u8; 3]; 5] = [
+    [255, 236, 153],
+    [255, 196, 206],
+    [196, 240, 196],
+    [190, 220, 255],
+    [220, 200, 255],
+];
+
+/// Size of new notes and frames, in graph coordinates.
+const DEFAULT_SIZE: Vec2 = Vec2::new(160.0, 90.0);
+const HANDLE_SIZE: f32 = 8.0;
+const FONT_SIZE: f32 = 12.0;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Note,
+    Frame,
+    Arrow,
+}
+
+impl Kind {
+    pub const ALL: [Self; 3] = [Self::Note, Self::Frame, Self::Arrow];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Note => "Note",
+            Self::Frame => "Frame",
+            Self::Arrow => "Arrow",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Sticky {
+    pub kind: Kind,
+    /// Graph coordinates. Corners of notes and frames, tail and head of arrows.
+    pub start: Pos2,
+    pub end: Pos2,
+    /// Shown inside notes and above frames.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+    pub color: [u8; 3],
+}
+
+impl Sticky {
+    pub fn export(&self) -> Sticky0 {
+        let point = |pos: Pos2| Point0 { x: pos.x, y: pos.y };
+        Sticky0 {
+            kind: match self.kind {
+                Kind::Note => StickyKind0::Note,
+                Kind::Frame => StickyKind0::Frame,
+                Kind::Arrow => StickyKind0::Arrow,
+            },
+            start: point(self.start),
+            end: point(self.end),
+            text: self.text.clone(),
+            color: self.color,
+        }
+    }
+
+    pub fn import(sticky0: Sticky0) -> Self {
+        let pos = |point: Point0| Pos2::new(point.x, point.y);
+        Self {
+            kind: match sticky0.kind {
+                StickyKind0::Note => Kind::Note,
+                StickyKind0::Frame => Kind::Frame,
+                StickyKind0::Arrow => Kind::Arrow,
+            },
+            start: pos(sticky0.start),
+            end: pos(sticky0.end),
+            text: sticky0.text,
+            color: sticky0.color,
+        }
+    }
+
+    pub fn new(kind: Kind, pos: Pos2) -> Self {
+        let (start, end) = match kind {
+            Kind::Note | Kind::Frame => (pos, pos + DEFAULT_SIZE),
+            Kind::Arrow => (pos, pos + Vec2::new(DEFAULT_SIZE.x, 0.0)),
+        };
+        Self {
+            kind,
+            start,
+            end,
+            text: String::new(),
+            color: COLORS[0],
+        }
+    }
+
+    fn color(&self) -> Color32 {
+        let [r, g, b] = self.color;
+        Color32::from_rgb(r, g, b)
+    }
+
+    /// What it covers in graph coordinates, with the title of a frame.
+    pub fn rect(&self) -> Rect {
+        let rect = Rect::from_two_pos(self.start, self.end);
+        match self.kind {
+            Kind::Frame => rect.with_min_y(rect.min.y - FONT_SIZE * 1.5),
+            Kind::Note | Kind::Arrow => rect,
+        }
+    }
+
+    /// Drawn like [ui] does at zoom 1.
+    pub fn to_svg(&self, svg: &mut Svg) {
+        let color = self.color();
+        let rect = Rect::from_two_pos(self.start, self.end);
+        match self.kind {
+            Kind::Note => {
+                svg.rect(rect, color, Stroke::NONE);
+                let lines = wrap(&self.text, rect.width() - 8.0);
+                let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+                svg.text(
+                    rect.min + Vec2::splat(4.0),
+                    &lines,
+                    FONT_SIZE,
+                    Color32::from_gray(30),
+                    Some(rect),
+                );
+            }
+            Kind::Frame => {
+                svg.rect(rect, color.gamma_multiply(0.08), Stroke::new(2.0, color));
+                svg.text(
+                    rect.left_top() - Vec2::new(-2.0, FONT_SIZE * 1.25),
+                    &[&self.text],
+                    FONT_SIZE,
+                    color,
+                    None,
+                );
+            }
+            Kind::Arrow => {
+                // The head of egui's arrow.
+                let tip = self.end;
+                let back = (self.start - self.end) / 4.0;
+                let rot = egui::emath::Rot2::from_angle(std::f32::consts::TAU / 12.0);
+                svg.polyline(&[self.start, tip], Stroke::new(3.0, color));
+                svg.polyline(
+                    &[tip + rot * back, tip, tip + rot.inverse() * back],
+                    Stroke::new(3.0, color),
+                );
+            }
+        }
+    }
+
+    fn menu(&mut self, ui: &mut egui::Ui) -> bool {
+        if self.kind != Kind::Arrow {
+            ui.add(
+                TextEdit::multiline(&mut self.text)
+                    .hint_text("Text")
+                    .desired_rows(3),
+            );
+        }
+        ui.horizontal(|ui| {
+            for color in COLORS {
+                let [r, g, b] = color;
+                if ui
+                    .add(Button::new("  ").fill(Color32::from_rgb(r, g, b)))
+                    .clicked()
+                {
+                    self.color = color;
+                }
+            }
+        });
+        let delete = ui.button("Delete").clicked();
+        if delete {
+            ui.close_menu();
+        }
+        delete
+    }
+}
+
+/// Breaks [text] into lines no wider than [width], judging by the width of monospace characters.
+fn wrap(text: &str, width: f32) -> Vec<String> {
+    let max_chars = ((width / (FONT_SIZE * 0.6)) as usize).max(1);
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let len = line.chars().count();
+            if len > 0 && len + 1 + word.chars().count() > max_chars {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Entries for the context menu of the empty canvas. [pos] is where it was opened, in graph
+/// coordinates.
+pub fn canvas_menu(ui: &mut egui::Ui, pos: Pos2, stickies: &mut Vec<Sticky>) {
+    for kind in Kind::ALL {
+        if ui.button(format!("Add {}", kind.name())).clicked() {
+            stickies.push(Sticky::new(kind, pos));
+            ui.close_menu();
+        }
+    }
+}
+
+/// Draws the stickies below the graph. They can be dragged around, resized by their handle and
+/// edited through their context menu. Frames are only grabbed by their title and arrows by their
+/// middle, so that the canvas under them can still be panned.
+pub fn ui(ui: &egui::Ui, transform: &Transform, stickies: &mut Vec<Sticky>) {
+    let zoom = transform.vec_to_screen(Vec2::X).x;
+    let font = FontId::proportional(FONT_SIZE * zoom);
+    let painter = ui.painter();
+    let mut delete = None;
+
+    for (i, sticky) in stickies.iter_mut().enumerate() {
+        let id = Id::new("sticky").with(i);
+        let start = transform.pos_to_screen(sticky.start);
+        let end = transform.pos_to_screen(sticky.end);
+        let color = sticky.color();
+
+        let (body, handle) = match sticky.kind {
+            Kind::Note => {
+                let rect = Rect::from_two_pos(start, end);
+                painter.rect(rect, Rounding::same(2.0), color, Stroke::NONE);
+                let galley = painter.layout(
+                    sticky.text.clone(),
+                    font.clone(),
+                    Color32::from_gray(30),
+                    (rect.width() - 8.0).max(0.0),
+                );
+                painter
+                    .with_clip_rect(rect.intersect(ui.clip_rect()))
+                    .galley(rect.min + Vec2::splat(4.0), galley, Color32::TRANSPARENT);
+                (rect, end)
+            }
+            Kind::Frame => {
+                let rect = Rect::from_two_pos(start, end);
+                painter.rect(
+                    rect,
+                    Rounding::same(4.0),
+                    color.gamma_multiply(0.08),
+                    Stroke::new(2.0, color),
+                );
+                let title = Rect::from_min_max(
+                    rect.left_top() - Vec2::new(0.0, font.size * 1.5),
+                    rect.right_top(),
+                );
+                painter.text(
+                    title.left_center() + Vec2::new(2.0, 0.0),
+                    Align2::LEFT_CENTER,
+                    &sticky.text,
+                    font.clone(),
+                    color,
+                );
+                (title, end)
+            }
+            Kind::Arrow => {
+                painter.arrow(start, end - start, Stroke::new(3.0, color));
+                // Grabbed by the middle, a bounding box would cover too much of the canvas.
+                let body = Rect::from_center_size(start.lerp(end, 0.5), Vec2::splat(12.0));
+                (body, end)
+            }
+        };
+
+        let response = ui
+            .interact(body, id, Sense::click_and_drag())
+            .on_hover_cursor(CursorIcon::Grab);
+        if response.dragged() {
+            let delta = transform.vec_from_screen(response.drag_delta());
+            sticky.start += delta;
+            sticky.end += delta;
+        }
+        response.context_menu(|ui| {
+            if sticky.menu(ui) {
+                delete = Some(i);
+            }
+        });
+
+        let handle = Rect::from_center_size(handle, Vec2::splat(HANDLE_SIZE));
+        let response = ui.interact(handle, id.with("handle"), Sense::drag());
+        if response.hovered() || response.dragged() {
+            ui.ctx().set_cursor_icon(CursorIcon::ResizeNwSe);
+            painter.rect_filled(handle.shrink(1.0), Rounding::ZERO, color);
+        }
+        if response.dragged() {
+            sticky.end += transform.vec_from_screen(response.drag_delta());
+        }
+    }
+
+    if let Some(i) = delete {
+        stickies.remove(i);
+    }
+}
@@ -76,6 +76,11 @@ impl Style {
         FontId::monospace(10.0)
     }
 
+    /// Outline of change outputs.
+    pub fn change_stroke(&self) -> Stroke {
+        Stroke::new(self.tx_stroke_width, self.btc)
+    }
+
     pub fn io_stroke(&self, response: &Response) -> Stroke {
         if response.is_pointer_button_down_on() || response.has_focus() {
             Stroke::new(self.tx_stroke_width * 2.0, self.io_highlight_color)
@@ -96,7 +101,7 @@ pub fn get(ui: &egui::Ui) -> Style {
     }
 }
 
-#[derive(Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[derive(Clone, Copy, Default, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum Theme {
     Light,
     Dark,
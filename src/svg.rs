@@ -0,0 +1,205 @@
+//! Writing SVG images, e.g. of the graph for reports.
+
+use std::fmt::Write;
+
+use egui::{Color32, Pos2, Rect, Stroke};
+
+pub struct Svg {
+    out: String,
+    clip_paths: usize,
+}
+
+impl Svg {
+    /// Everything outside of [view] is cut off.
+    pub fn new(view: Rect, background: Color32) -> Self {
+        let mut out = String::new();
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="{:.1} {:.1} {:.1} {:.1}" font-family="monospace">"#,
+            view.width(),
+            view.height(),
+            view.min.x,
+            view.min.y,
+            view.width(),
+            view.height()
+        )
+        .unwrap();
+        let mut svg = Self { out, clip_paths: 0 };
+        svg.rect(view, background, Stroke::NONE);
+        svg
+    }
+
+    pub fn rect(&mut self, rect: Rect, fill: Color32, stroke: Stroke) {
+        writeln!(
+            self.out,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}"{}{}/>"#,
+            rect.min.x,
+            rect.min.y,
+            rect.width(),
+            rect.height(),
+            paint("fill", fill),
+            stroke_attrs(stroke)
+        )
+        .unwrap();
+    }
+
+    /// Closed shape of straight segments and cubic Bézier curves, see [Segment].
+    pub fn path(&mut self, start: Pos2, segments: &[Segment], fill: Color32) {
+        let mut d = format!("M{:.1},{:.1}", start.x, start.y);
+        for segment in segments {
+            match segment {
+                Segment::Line(p) => write!(d, " L{:.1},{:.1}", p.x, p.y).unwrap(),
+                Segment::Cubic(p1, p2, p3) => write!(
+                    d,
+                    " C{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}",
+                    p1.x, p1.y, p2.x, p2.y, p3.x, p3.y
+                )
+                .unwrap(),
+            }
+        }
+        writeln!(self.out, r#"<path d="{} Z"{}/>"#, d, paint("fill", fill)).unwrap();
+    }
+
+    /// Open line through [points].
+    pub fn polyline(&mut self, points: &[Pos2], stroke: Stroke) {
+        let points: Vec<String> = points
+            .iter()
+            .map(|p| format!("{:.1},{:.1}", p.x, p.y))
+            .collect();
+        writeln!(
+            self.out,
+            r#"<polyline points="{}" fill="none"{} stroke-linecap="round"/>"#,
+            points.join(" "),
+            stroke_attrs(stroke)
+        )
+        .unwrap();
+    }
+
+    /// Lines of text starting at [pos] (top left), cut off at [clip] if given.
+    pub fn text(
+        &mut self,
+        pos: Pos2,
+        lines: &[&str],
+        size: f32,
+        color: Color32,
+        clip: Option<Rect>,
+    ) {
+        let clip_attr = match clip {
+            Some(clip) => {
+                self.clip_paths += 1;
+                writeln!(
+                    self.out,
+                    r#"<clipPath id="clip{}"><rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}"/></clipPath>"#,
+                    self.clip_paths,
+                    clip.min.x,
+                    clip.min.y,
+                    clip.width(),
+                    clip.height()
+                )
+                .unwrap();
+                format!(r#" clip-path="url(#clip{})""#, self.clip_paths)
+            }
+            None => String::new(),
+        };
+        write!(
+            self.out,
+            r#"<text x="{:.1}" y="{:.1}" font-size="{:.1}"{}{}>"#,
+            pos.x,
+            pos.y,
+            size,
+            paint("fill", color),
+            clip_attr
+        )
+        .unwrap();
+        for (i, line) in lines.iter().enumerate() {
+            let dy = if i == 0 { size } else { size * 1.2 };
+            write!(
+                self.out,
+                r#"<tspan x="{:.1}" dy="{:.1}">{}</tspan>"#,
+                pos.x,
+                dy,
+                escape(line)
+            )
+            .unwrap();
+        }
+        self.out.push_str("</text>\n");
+    }
+
+    pub fn finish(mut self) -> String {
+        self.out.push_str("</svg>\n");
+        self.out
+    }
+}
+
+pub enum Segment {
+    Line(Pos2),
+    /// Two control points and the end point.
+    Cubic(Pos2, Pos2, Pos2),
+}
+
+/// Color plus opacity, or `none`.
+fn paint(attr: &str, color: Color32) -> String {
+    if color.a() == 0 {
+        return format!(r#" {}="none""#, attr);
+    }
+    // Colors are premultiplied.
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let mut s = format!(r##" {}="#{:02x}{:02x}{:02x}""##, attr, r, g, b);
+    if a < 255 {
+        write!(s, r#" {}-opacity="{:.2}""#, attr, a as f32 / 255.0).unwrap();
+    }
+    s
+}
+
+fn stroke_attrs(stroke: Stroke) -> String {
+    if stroke.is_empty() {
+        return String::new();
+    }
+    format!(
+        r#"{} stroke-width="{:.1}""#,
+        paint("stroke", stroke.color),
+        stroke.width
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{Color32, Pos2, Rect, Stroke};
+
+    use super::Svg;
+
+    #[test]
+    fn elements() {
+        let view = Rect::from_min_max(Pos2::new(-10.0, 0.0), Pos2::new(90.0, 50.0));
+        let mut svg = Svg::new(view, Color32::WHITE);
+        svg.rect(
+            Rect::from_min_max(Pos2::ZERO, Pos2::new(10.0, 5.0)),
+            Color32::RED,
+            Stroke::new(1.0, Color32::BLACK),
+        );
+        svg.rect(view, Color32::BLACK.gamma_multiply(0.5), Stroke::NONE);
+        svg.text(Pos2::ZERO, &["a < b", "c"], 10.0, Color32::BLACK, None);
+        svg.polyline(
+            &[Pos2::ZERO, Pos2::new(4.0, 2.0)],
+            Stroke::new(2.0, Color32::BLACK),
+        );
+        let svg = svg.finish();
+        assert!(svg.contains(r#"viewBox="-10.0 0.0 100.0 50.0""#));
+        assert!(svg.contains(
+            r##"<rect x="0.0" y="0.0" width="10.0" height="5.0" fill="#ff0000" stroke="#000000" stroke-width="1.0"/>"##
+        ));
+        assert!(svg.contains(r##"fill="#000000" fill-opacity="0.50"/>"##));
+        assert!(svg.contains(
+            r#"<tspan x="0.0" dy="10.0">a &lt; b</tspan><tspan x="0.0" dy="12.0">c</tspan>"#
+        ));
+        assert!(svg.contains(
+            r##"<polyline points="0.0,0.0 4.0,2.0" fill="none" stroke="#000000" stroke-width="2.0" stroke-linecap="round"/>"##
+        ));
+    }
+}
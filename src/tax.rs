@@ -0,0 +1,690 @@
+//! Realized capital gains of an entity, for tax reports.
+
+use std::fmt::Write;
+
+use chrono::{Datelike, NaiveDateTime};
+use egui::{ComboBox, Context, Grid, ScrollArea};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotations::Annotations,
+    bitcoin::{format_btc, Sats, SatsDisplay, Txid},
+    csv,
+    entity::Flow,
+    notifications::NotifyExt,
+    platform::inner as platform,
+    prices::{format_date, format_fiat, Prices},
+    style,
+    widgets::UiExt,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Acquisition,
+    Disposal,
+}
+
+/// Bitcoin the entity gained or gave away in a transaction.
+#[derive(Debug, PartialEq)]
+pub struct Event {
+    pub txid: Txid,
+    pub timestamp: i64,
+    pub kind: Kind,
+    pub sats: u64,
+    /// What was paid or received, in the currency of the report.
+    pub fiat: f64,
+}
+
+/// How disposals are matched with earlier acquisitions. Chosen per workspace.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    #[default]
+    Fifo,
+    Lifo,
+    Hifo,
+    Average,
+}
+
+impl Method {
+    pub const ALL: [Method; 4] = [Method::Fifo, Method::Lifo, Method::Hifo, Method::Average];
+
+    pub fn is_default(&self) -> bool {
+        *self == Method::default()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Method::Fifo => "FIFO",
+            Method::Lifo => "LIFO",
+            Method::Hifo => "HIFO",
+            Method::Average => "Average",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Method::Fifo => "First in, first out: the oldest coins are sold first.",
+            Method::Lifo => "Last in, first out: the newest coins are sold first.",
+            Method::Hifo => "Highest in, first out: the most expensive coins are sold first.",
+            Method::Average => "Every sale takes from all holdings alike, at their average cost.",
+        }
+    }
+
+    /// Index of the lot to sell from next.
+    fn next(&self, lots: &[Lot]) -> usize {
+        match self {
+            Method::Fifo | Method::Average => 0,
+            Method::Lifo => lots.len() - 1,
+            Method::Hifo => {
+                let mut best = 0;
+                for (i, lot) in lots.iter().enumerate() {
+                    // Compare cost per sat without dividing.
+                    if lot.cost * lots[best].sats as f64 > lots[best].cost * lot.sats as f64 {
+                        best = i;
+                    }
+                }
+                best
+            }
+        }
+    }
+}
+
+/// Bitcoin from one acquisition that wasn't disposed of yet.
+struct Lot {
+    txid: Txid,
+    timestamp: i64,
+    sats: u64,
+    cost: f64,
+}
+
+impl Lot {
+    /// Take [sats] out of the lot, at its cost per sat.
+    fn take(&mut self, sats: u64) -> Match {
+        let cost = self.cost * sats as f64 / self.sats as f64;
+        self.cost -= cost;
+        self.sats -= sats;
+        Match {
+            txid: self.txid,
+            timestamp: self.timestamp,
+            sats,
+            cost,
+        }
+    }
+}
+
+/// Part of an acquisition that was sold in a disposal.
+#[derive(Debug, PartialEq)]
+pub struct Match {
+    /// Of the acquisition.
+    pub txid: Txid,
+    pub timestamp: i64,
+    pub sats: u64,
+    pub cost: f64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Disposal {
+    pub txid: Txid,
+    pub timestamp: i64,
+    pub sats: u64,
+    pub proceeds: f64,
+    pub cost: f64,
+    /// Where the sats came from.
+    pub lots: Vec<Match>,
+    /// Sats that weren't acquired before, e.g. because the funding transactions aren't in the
+    /// graph. They are counted at zero cost.
+    pub unmatched: u64,
+}
+
+impl Disposal {
+    pub fn gain(&self) -> f64 {
+        self.proceeds - self.cost
+    }
+}
+
+/// Totals of the disposals in a calendar year (UTC).
+#[derive(Debug, PartialEq)]
+pub struct Year {
+    pub year: i32,
+    pub proceeds: f64,
+    pub cost: f64,
+}
+
+impl Year {
+    pub fn gain(&self) -> f64 {
+        self.proceeds - self.cost
+    }
+}
+
+pub struct TaxReport {
+    pub entity: String,
+    /// Lowercase, e.g. `usd`.
+    pub currency: String,
+    pub method: Method,
+    /// Oldest first.
+    pub disposals: Vec<Disposal>,
+    /// Oldest first.
+    pub years: Vec<Year>,
+}
+
+/// Sell [sats] from [lots], leaving out empty lots.
+fn dispose(lots: &mut Vec<Lot>, method: Method, sats: u64) -> Vec<Match> {
+    let mut matches = vec![];
+    if method == Method::Average {
+        let total: u64 = lots.iter().map(|lot| lot.sats).sum();
+        let sats = sats.min(total);
+        let mut shares: Vec<u64> = lots
+            .iter()
+            .map(|lot| (lot.sats as u128 * sats as u128 / total.max(1) as u128) as u64)
+            .collect();
+        // Rounding leaves fewer sats than lots, spread them over the oldest lots.
+        let mut rest = sats - shares.iter().sum::<u64>();
+        for (share, lot) in shares.iter_mut().zip(lots.iter()) {
+            if rest > 0 && *share < lot.sats {
+                *share += 1;
+                rest -= 1;
+            }
+        }
+        for (lot, share) in lots.iter_mut().zip(shares) {
+            if share > 0 {
+                matches.push(lot.take(share));
+            }
+        }
+    } else {
+        let mut remaining = sats;
+        while remaining > 0 && !lots.is_empty() {
+            let i = method.next(lots);
+            let sats = remaining.min(lots[i].sats);
+            matches.push(lots[i].take(sats));
+            remaining -= sats;
+            if lots[i].sats == 0 {
+                lots.remove(i);
+            }
+        }
+    }
+    lots.retain(|lot| lot.sats > 0);
+    matches
+}
+
+fn year(timestamp: i64) -> i32 {
+    NaiveDateTime::from_timestamp_opt(timestamp, 0).map_or(1970, |t| t.year())
+}
+
+impl TaxReport {
+    pub fn new(entity: String, currency: String, method: Method, mut events: Vec<Event>) -> Self {
+        events.sort_by_key(|event| (event.timestamp, event.txid));
+
+        let mut lots = vec![];
+        let mut disposals = vec![];
+        for event in events {
+            match event.kind {
+                Kind::Acquisition => lots.push(Lot {
+                    txid: event.txid,
+                    timestamp: event.timestamp,
+                    sats: event.sats,
+                    cost: event.fiat,
+                }),
+                Kind::Disposal => {
+                    let matches = dispose(&mut lots, method, event.sats);
+                    let matched: u64 = matches.iter().map(|m| m.sats).sum();
+                    disposals.push(Disposal {
+                        txid: event.txid,
+                        timestamp: event.timestamp,
+                        sats: event.sats,
+                        proceeds: event.fiat,
+                        cost: matches.iter().map(|m| m.cost).sum(),
+                        lots: matches,
+                        unmatched: event.sats - matched,
+                    });
+                }
+            }
+        }
+
+        let mut years: Vec<Year> = vec![];
+        for disposal in &disposals {
+            let y = year(disposal.timestamp);
+            match years.last_mut() {
+                Some(last) if last.year == y => {
+                    last.proceeds += disposal.proceeds;
+                    last.cost += disposal.cost;
+                }
+                _ => years.push(Year {
+                    year: y,
+                    proceeds: disposal.proceeds,
+                    cost: disposal.cost,
+                }),
+            }
+        }
+
+        Self {
+            entity,
+            currency,
+            method,
+            disposals,
+            years,
+        }
+    }
+
+    /// One row per lot a disposal was matched with, for spreadsheets and tax software. The
+    /// proceeds are split by amount.
+    pub fn to_csv(&self) -> String {
+        let mut s = csv::write_record(&[
+            "date_sold".to_string(),
+            "txid_sold".to_string(),
+            "date_acquired".to_string(),
+            "txid_acquired".to_string(),
+            "btc".to_string(),
+            format!("proceeds_{}", self.currency),
+            format!("cost_{}", self.currency),
+            format!("gain_{}", self.currency),
+        ]);
+        for d in &self.disposals {
+            let mut row = |acquired: Option<&Match>, sats: u64, cost: f64| {
+                let proceeds = d.proceeds * sats as f64 / d.sats as f64;
+                s.push_str(&csv::write_record(&[
+                    format_date(d.timestamp),
+                    d.txid.hex_string(),
+                    acquired.map_or(String::new(), |m| format_date(m.timestamp)),
+                    acquired.map_or(String::new(), |m| m.txid.hex_string()),
+                    format_btc(sats as i64),
+                    format!("{:.2}", proceeds),
+                    format!("{:.2}", cost),
+                    format!("{:.2}", proceeds - cost),
+                ]));
+            };
+            for m in &d.lots {
+                row(Some(m), m.sats, m.cost);
+            }
+            if d.unmatched > 0 {
+                row(None, d.unmatched, 0.0);
+            }
+        }
+        s
+    }
+
+    /// Summary tables, for the workspace notes or printing.
+    pub fn to_markdown(&self) -> String {
+        let fiat = |value: f64| format_fiat(value, &self.currency);
+        let mut s = String::new();
+
+        writeln!(s, "## Capital gains: {}\n", self.entity).unwrap();
+        writeln!(s, "Cost basis method: {}\n", self.method.name()).unwrap();
+        writeln!(s, "| Year | Proceeds | Cost | Gain |").unwrap();
+        writeln!(s, "|---|---:|---:|---:|").unwrap();
+        for y in &self.years {
+            writeln!(
+                s,
+                "| {} | {} | {} | {} |",
+                y.year,
+                fiat(y.proceeds),
+                fiat(y.cost),
+                fiat(y.gain())
+            )
+            .unwrap();
+        }
+
+        writeln!(s, "\n### Disposals\n").unwrap();
+        writeln!(
+            s,
+            "| Date | Transaction | BTC | Proceeds | Cost | Gain | Acquired |"
+        )
+        .unwrap();
+        writeln!(s, "|---|---|---:|---:|---:|---:|---|").unwrap();
+        for d in &self.disposals {
+            let mut acquired: Vec<_> = d
+                .lots
+                .iter()
+                .map(|m| {
+                    format!(
+                        "{} BTC on {} ({})",
+                        format_btc(m.sats as i64),
+                        format_date(m.timestamp),
+                        &m.txid.hex_string()[..16]
+                    )
+                })
+                .collect();
+            if d.unmatched > 0 {
+                acquired.push(format!("{} BTC unknown", format_btc(d.unmatched as i64)));
+            }
+            writeln!(
+                s,
+                "| {} | {} | {} | {} | {} | {} | {} |",
+                format_date(d.timestamp),
+                d.txid.hex_string(),
+                format_btc(d.sats as i64),
+                fiat(d.proceeds),
+                fiat(d.cost),
+                fiat(d.gain()),
+                acquired.join("<br>")
+            )
+            .unwrap();
+        }
+        s
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui, annotations: &Annotations) {
+        let style = style::get(ui);
+        let fiat = |value: f64| format_fiat(value, &self.currency);
+        let name = |txid: Txid| {
+            annotations
+                .tx_label(txid)
+                .unwrap_or_else(|| txid.hex_string()[..16].to_string())
+        };
+
+        if self.disposals.is_empty() {
+            ui.label("No disposals yet.");
+            return;
+        }
+
+        Grid::new("TaxYears")
+            .striped(true)
+            .num_columns(4)
+            .show(ui, |ui| {
+                ui.bold("Year");
+                ui.bold("Proceeds");
+                ui.bold("Cost");
+                ui.bold("Gain");
+                ui.end_row();
+                for y in &self.years {
+                    ui.label(y.year.to_string());
+                    ui.label(fiat(y.proceeds));
+                    ui.label(fiat(y.cost));
+                    ui.label(fiat(y.gain()));
+                    ui.end_row();
+                }
+            });
+
+        ui.separator();
+        ui.bold("Disposals and the lots they were matched with:");
+        ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            Grid::new("TaxDisposals")
+                .striped(true)
+                .num_columns(6)
+                .show(ui, |ui| {
+                    for d in &self.disposals {
+                        ui.label(format_date(d.timestamp));
+                        ui.label(name(d.txid)).on_hover_text(d.txid.hex_string());
+                        ui.add(SatsDisplay::new(Sats(d.sats), &style));
+                        ui.label(fiat(d.proceeds));
+                        ui.label(fiat(d.cost));
+                        ui.label(fiat(d.gain()));
+                        ui.end_row();
+
+                        for m in &d.lots {
+                            ui.weak(format!("  {}", format_date(m.timestamp)));
+                            ui.weak(name(m.txid)).on_hover_text(m.txid.hex_string());
+                            ui.add(SatsDisplay::new(Sats(m.sats), &style));
+                            ui.label("");
+                            ui.weak(fiat(m.cost));
+                            ui.end_row();
+                        }
+                        if d.unmatched > 0 {
+                            ui.weak("  ?");
+                            ui.weak("Not in the graph")
+                                .on_hover_text("These sats count at zero cost.");
+                            ui.add(SatsDisplay::new(Sats(d.unmatched), &style));
+                            ui.end_row();
+                        }
+                    }
+                });
+        });
+    }
+}
+
+/// The entity's net acquisitions and disposals, valued in [currency]. Acquisitions use the
+/// recorded cost basis where there is one, otherwise the market value of the day, as do
+/// disposals. Fails with the number of prices that are still missing.
+pub fn events(
+    ctx: &Context,
+    annotations: &Annotations,
+    flows: Vec<Flow>,
+    currency: &str,
+) -> Result<Vec<Event>, usize> {
+    let mut events = vec![];
+    let mut missing = 0;
+    for flow in flows {
+        let (kind, sats) = if flow.received > flow.sent {
+            (Kind::Acquisition, flow.received - flow.sent)
+        } else if flow.sent > flow.received {
+            (Kind::Disposal, flow.sent - flow.received)
+        } else {
+            continue;
+        };
+        let cost_basis = annotations
+            .cost_basis(flow.txid)
+            .filter(|c| kind == Kind::Acquisition && c.currency.eq_ignore_ascii_case(currency));
+        let fiat = match cost_basis {
+            Some(cost_basis) => cost_basis.cents as f64 / 100.0,
+            None => match Prices::quote(ctx, currency, flow.timestamp) {
+                Some(quote) => sats as f64 / 100_000_000.0 * quote.rate,
+                None => {
+                    missing += 1;
+                    continue;
+                }
+            },
+        };
+        events.push(Event {
+            txid: flow.txid,
+            timestamp: flow.timestamp,
+            kind,
+            sats,
+            fiat,
+        });
+    }
+    if missing > 0 {
+        Err(missing)
+    } else {
+        Ok(events)
+    }
+}
+
+/// What a [TaxReport] was computed from: the graph revision, fingerprints of the coin labels and
+/// cost basis, the entity, currency and method.
+type ReportKey = (u64, u64, u64, String, String, Method);
+
+/// Picks the entity whose gains to report.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaxReportSettings {
+    entity: Option<String>,
+    /// The last report, so that it isn't computed every frame.
+    #[serde(skip)]
+    report: Option<(ReportKey, TaxReport)>,
+}
+
+impl TaxReportSettings {
+    #[allow(clippy::too_many_arguments)]
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        revision: u64,
+        entities: &[String],
+        currency: Option<&str>,
+        method: &mut Method,
+        annotations: &Annotations,
+        flows: impl FnOnce(&str) -> Vec<Flow>,
+    ) {
+        let Some(currency) = currency else {
+            ui.label("Choose a fiat currency for the workspace first.");
+            return;
+        };
+        if entities.is_empty() {
+            ui.label("Label your coins first. Coins with the same label form an entity.");
+            return;
+        }
+
+        ComboBox::from_label("Entity")
+            .selected_text(self.entity.as_deref().unwrap_or("Choose…"))
+            .show_ui(ui, |ui| {
+                for entity in entities {
+                    ui.selectable_value(&mut self.entity, Some(entity.clone()), entity);
+                }
+            });
+        ComboBox::from_label("Cost basis method")
+            .selected_text(method.name())
+            .show_ui(ui, |ui| {
+                for m in Method::ALL {
+                    ui.selectable_value(method, m, m.name())
+                        .on_hover_text(m.description());
+                }
+            })
+            .response
+            .on_hover_text("Saved with the workspace.");
+
+        let Some(entity) = self.entity.clone().filter(|e| entities.contains(e)) else {
+            return;
+        };
+        let key = (
+            revision,
+            annotations.coin_labels_fingerprint(),
+            annotations.cost_basis_fingerprint(),
+            entity.clone(),
+            currency.to_string(),
+            *method,
+        );
+        if self.report.as_ref().map(|(k, _)| k) != Some(&key) {
+            match events(ui.ctx(), annotations, flows(&entity), currency) {
+                Ok(events) => {
+                    let report = TaxReport::new(entity, currency.to_string(), *method, events);
+                    self.report = Some((key, report));
+                }
+                Err(missing) => {
+                    ui.weak(format!("Waiting for {} prices…", missing));
+                    return;
+                }
+            }
+        }
+        let Some((_, report)) = &self.report else {
+            return;
+        };
+
+        ui.separator();
+        report.ui(ui, annotations);
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Export CSV…").clicked() {
+                if platform::save_file(ui.ctx(), "capital-gains.csv", &report.to_csv()) {
+                    ui.ctx().notify_success("Exported capital gains.");
+                }
+                ui.close_menu();
+            }
+            if ui
+                .button("Copy as Markdown")
+                .on_hover_text("Tables for the notes, a document or printing.")
+                .clicked()
+            {
+                ui.output_mut(|o| o.copied_text = report.to_markdown());
+                ui.close_menu();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Kind, Method, TaxReport, Year};
+    use crate::bitcoin::Txid;
+
+    fn event(n: u8, timestamp: i64, kind: Kind, sats: u64, fiat: f64) -> Event {
+        Event {
+            txid: Txid::new(&format!("{:02x}", n).repeat(32)).unwrap(),
+            timestamp,
+            kind,
+            sats,
+            fiat,
+        }
+    }
+
+    #[test]
+    fn first_in_first_out() {
+        let day = 86_400;
+        // 2023-12-31 and 2024-01-01
+        let (y2023, y2024) = (1_704_067_200 - day, 1_704_067_200);
+        let report = TaxReport::new(
+            "Me".to_string(),
+            "usd".to_string(),
+            Method::Fifo,
+            vec![
+                event(3, y2024, Kind::Disposal, 150, 600.0),
+                event(1, y2023 - day, Kind::Acquisition, 100, 100.0),
+                event(2, y2023 - day / 2, Kind::Acquisition, 100, 200.0),
+                event(4, y2023, Kind::Disposal, 40, 80.0),
+                event(5, y2024 + day, Kind::Disposal, 20, 50.0),
+            ],
+        );
+        let summary: Vec<_> = report
+            .disposals
+            .iter()
+            .map(|d| (d.sats, d.cost, d.unmatched))
+            .collect();
+        // 40 of the first lot, then its other 60 and 90 of the second, then its last 10.
+        assert_eq!(
+            summary,
+            vec![(40, 40.0, 0), (150, 240.0, 0), (20, 20.0, 10)]
+        );
+        assert_eq!(
+            report.years,
+            vec![
+                Year {
+                    year: 2023,
+                    proceeds: 80.0,
+                    cost: 40.0
+                },
+                Year {
+                    year: 2024,
+                    proceeds: 650.0,
+                    cost: 260.0
+                },
+            ]
+        );
+        assert_eq!(report.years[1].gain(), 390.0);
+
+        let csv = report.to_csv();
+        let rows: Vec<_> = csv.lines().skip(1).collect();
+        assert_eq!(rows.len(), 5);
+        assert!(rows[1].ends_with(",0.00000060,240.00,60.00,180.00"));
+        assert!(rows[4].ends_with(",,,0.00000010,25.00,0.00,25.00"));
+    }
+
+    #[test]
+    fn methods() {
+        let events = || {
+            vec![
+                event(1, 10, Kind::Acquisition, 100, 100.0),
+                event(2, 20, Kind::Acquisition, 100, 300.0),
+                event(3, 30, Kind::Acquisition, 100, 200.0),
+                event(4, 40, Kind::Disposal, 150, 450.0),
+            ]
+        };
+        let matched = |method| {
+            let report = TaxReport::new("Me".to_string(), "usd".to_string(), method, events());
+            let d = &report.disposals[0];
+            let lots: Vec<_> = d
+                .lots
+                .iter()
+                .map(|m| (m.txid.hex_string()[..2].to_string(), m.sats))
+                .collect();
+            (lots, d.cost)
+        };
+        let lot = |n: &str, sats| (n.to_string(), sats);
+
+        assert_eq!(
+            matched(Method::Fifo),
+            (vec![lot("01", 100), lot("02", 50)], 250.0)
+        );
+        assert_eq!(
+            matched(Method::Lifo),
+            (vec![lot("03", 100), lot("02", 50)], 350.0)
+        );
+        assert_eq!(
+            matched(Method::Hifo),
+            (vec![lot("02", 100), lot("03", 50)], 400.0)
+        );
+        assert_eq!(
+            matched(Method::Average),
+            (vec![lot("01", 50), lot("02", 50), lot("03", 50)], 300.0)
+        );
+    }
+}
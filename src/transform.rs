@@ -28,7 +28,6 @@ impl Transform {
         Pos2::new((pos.x - self.t_x) / self.z, (pos.y - self.t_y) / self.z)
     }
 
-    #[allow(dead_code)]
     pub fn vec_to_screen(&self, vec: Vec2) -> Vec2 {
         Vec2::new(vec.x * self.z, vec.y * self.z)
     }
@@ -56,9 +55,24 @@ impl Transform {
         self.zoom(1.0 / self.z, pos);
     }
 
+    pub fn zoom_level(&self) -> f32 {
+        self.z
+    }
+
+    /// Shows [pos] at [screen_center], zoomed in by [z].
+    pub fn centered(pos: Pos2, z: f32, screen_center: Pos2) -> Self {
+        let mut transform = Self {
+            z,
+            ..Default::default()
+        };
+        transform.pan_to(pos, screen_center);
+        transform
+    }
+
     /// Make `pos` map to the center of the screen. `screen_center` is in real coordinates.
     pub fn pan_to(&mut self, pos: Pos2, screen_center: Pos2) {
-        self.translate(self.z * (screen_center - pos));
+        self.t_x = screen_center.x - self.z * pos.x;
+        self.t_y = screen_center.y - self.z * pos.y;
     }
 
     pub fn export(&self) -> Transform0 {
@@ -77,3 +91,27 @@ impl Transform {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use egui::{Pos2, Vec2};
+
+    use super::Transform;
+
+    #[test]
+    fn pan_when_zoomed() {
+        let mut transform = Transform::default();
+        transform.translate(Vec2::new(-40.0, 25.0));
+        transform.zoom(2.5, Pos2::new(10.0, 20.0));
+
+        let center = Pos2::new(400.0, 300.0);
+        let pos = Pos2::new(-120.0, 75.0);
+        transform.pan_to(pos, center);
+        assert_eq!(transform.pos_to_screen(pos), center);
+        assert_eq!(transform.z, 2.5);
+
+        let restored = Transform::centered(pos, 2.5, center);
+        assert_eq!(restored.pos_to_screen(pos), center);
+        assert_eq!(restored.pos_from_screen(center), pos);
+    }
+}
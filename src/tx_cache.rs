@@ -1,12 +1,12 @@
 use std::{num::NonZeroUsize, sync::Arc};
 
 use egui::{ahash::HashMap, mutex::Mutex, Context, Id};
-use ehttp::Request;
 use lru::LruCache;
 
 use crate::{
     bitcoin::{Transaction, Txid},
-    client::Client,
+    chain,
+    client::{ApiError, RequestHandle},
     loading::Loading,
 };
 
@@ -55,38 +55,34 @@ impl State {
         self.cache.lock().put(txid, tx);
     }
 
+    /// With [force], cached transactions are fetched again. [on_done] receives `None` for
+    /// transactions that couldn't be fetched. Returns handles for the requests that were sent.
     fn get_or_fetch(
         &self,
         ctx: &Context,
         txids: &[Txid],
-        on_success: impl 'static + FnOnce(HashMap<Txid, Transaction>),
-    ) {
+        force: bool,
+        on_done: impl 'static + FnOnce(HashMap<Txid, Result<Transaction, ApiError>>),
+    ) -> Vec<RequestHandle> {
         let (sender, receiver) = flume::unbounded();
+        let mut handles = vec![];
 
         for &txid in txids {
             let slf = self.clone();
             let ctx2 = ctx.clone();
             let sender = sender.clone();
-            if let Some(tx) = self.get(&txid) {
-                sender.send(Ok(tx)).unwrap();
+            if let Some(tx) = self.get(&txid).filter(|_| !force) {
+                sender.send((txid, Ok(tx))).unwrap();
             } else {
                 // Fetch tx from server
                 Loading::start_loading_txid(ctx, txid);
-                Client::fetch_json::<Transaction>(
-                    move |base_url| {
-                        let mut req = ehttp::Request::get(&format!("{}/tx/{}", base_url, txid));
-                        authenticate(&mut req, &txid);
-                        req
-                    },
-                    ctx,
-                    move |result| {
-                        Loading::loading_txid_done(&ctx2, txid);
-                        if let Ok(ref tx) = result {
-                            slf.insert(txid, tx.clone());
-                        }
-                        sender.send(result).unwrap();
-                    },
-                );
+                handles.extend(chain::fetch_tx(ctx, txid, move |result| {
+                    Loading::loading_txid_done(&ctx2, txid);
+                    if let Ok(ref tx) = result {
+                        slf.insert(txid, tx.clone());
+                    }
+                    sender.send((txid, result)).unwrap();
+                }));
             }
         }
 
@@ -94,37 +90,111 @@ impl State {
 
         let len_expected = txids.len();
         wasm_bindgen_futures::spawn_local(async move {
-            let mut results = vec![];
-            while let Ok(result) = receiver.recv_async().await {
-                results.push(result);
+            let mut results = HashMap::default();
+            while let Ok((txid, result)) = receiver.recv_async().await {
+                results.insert(txid, result);
                 if results.len() == len_expected {
                     break;
                 }
             }
-            if let Ok(txs) = results.into_iter().collect::<Result<Vec<_>, _>>() {
-                let map: HashMap<_, _> = txs.into_iter().map(|tx| (tx.txid, tx)).collect();
-                ctx2.request_repaint();
-                on_success(map)
-            }
+            ctx2.request_repaint();
+            on_done(results);
         });
+
+        handles
     }
 }
 
 pub struct TxCache;
 
+/// Result of fetching a transaction again.
+pub struct Refresh {
+    pub txid: Txid,
+    /// What was in the cache before, if anything.
+    pub old: Option<Transaction>,
+    /// `None` if the fetch failed.
+    pub new: Option<Transaction>,
+    /// The source answered that it doesn't know the transaction, rather than failing.
+    pub not_found: bool,
+}
+
+impl Refresh {
+    pub fn changed(&self) -> Option<&Transaction> {
+        self.new
+            .as_ref()
+            .filter(|new| self.old.as_ref() != Some(*new))
+    }
+}
+
 impl TxCache {
     /// [on_success] is only called when all transactions have been fetched successfully.
     pub fn get_batch(
         ctx: &Context,
         txids: &[Txid],
         on_success: impl 'static + FnOnce(HashMap<Txid, Transaction>),
+    ) -> Vec<RequestHandle> {
+        let state = State::load(ctx);
+        let ctx2 = ctx.clone();
+        let state2 = state.clone();
+        state.get_or_fetch(ctx, txids, false, move |results| {
+            state2.store(&ctx2);
+            if let Some(txs) = results
+                .into_iter()
+                .map(|(txid, tx)| Some((txid, tx.ok()?)))
+                .collect::<Option<HashMap<_, _>>>()
+            {
+                on_success(txs);
+            }
+        })
+    }
+
+    /// Like [Self::get_batch], but [on_done] also gets called when some transactions couldn't be
+    /// fetched, with the ones that could.
+    pub fn get_available(
+        ctx: &Context,
+        txids: &[Txid],
+        on_done: impl 'static + FnOnce(HashMap<Txid, Transaction>),
+    ) -> Vec<RequestHandle> {
+        let state = State::load(ctx);
+        let ctx2 = ctx.clone();
+        let state2 = state.clone();
+        state.get_or_fetch(ctx, txids, false, move |results| {
+            state2.store(&ctx2);
+            on_done(
+                results
+                    .into_iter()
+                    .filter_map(|(txid, tx)| Some((txid, tx.ok()?)))
+                    .collect(),
+            );
+        })
+    }
+
+    /// Fetch the transactions from the server again, e.g. to pick up new confirmations or
+    /// spends.
+    pub fn refresh_batch(
+        ctx: &Context,
+        txids: &[Txid],
+        on_done: impl 'static + FnOnce(Vec<Refresh>),
     ) {
         let state = State::load(ctx);
+        let old: HashMap<Txid, Transaction> = txids
+            .iter()
+            .filter_map(|txid| state.get(txid).map(|tx| (*txid, tx)))
+            .collect();
         let ctx2 = ctx.clone();
         let state2 = state.clone();
-        state.get_or_fetch(ctx, txids, move |txs| {
+        state.get_or_fetch(ctx, txids, true, move |results| {
             state2.store(&ctx2);
-            on_success(txs);
+            let refreshes = results
+                .into_iter()
+                .map(|(txid, new)| Refresh {
+                    txid,
+                    old: old.get(&txid).cloned(),
+                    not_found: new == Err(ApiError::NotFound),
+                    new: new.ok(),
+                })
+                .collect();
+            on_done(refreshes);
         });
     }
 
@@ -136,11 +206,3 @@ impl TxCache {
         });
     }
 }
-
-const API_TOKEN: &str = env!("API_TOKEN");
-
-fn authenticate(request: &mut Request, txid: &Txid) {
-    request
-        .headers
-        .insert("Authorization", format!("Bearer {API_TOKEN}"));
-}
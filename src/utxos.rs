@@ -0,0 +1,140 @@
+//! Overview of the unspent outputs in the graph.
+
+use egui::{Grid, Pos2, ScrollArea};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitcoin::{AddressType, Sats, Txid},
+    widgets::UiExt,
+};
+
+pub struct Utxo {
+    pub txid: Txid,
+    pub vout: usize,
+    pub value: u64,
+    pub address: String,
+    pub address_type: AddressType,
+    /// Of the funding transaction.
+    pub timestamp: i64,
+    /// Address label if there is one, otherwise the coin label.
+    pub label: Option<String>,
+    /// Whether the address has an address label.
+    pub tracked: bool,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SortBy {
+    #[default]
+    Value,
+    Age,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UtxoDashboard {
+    /// Only outputs to addresses with a label.
+    only_tracked: bool,
+    sort_by: SortBy,
+}
+
+impl UtxoDashboard {
+    fn filter_and_sort(&self, mut utxos: Vec<Utxo>) -> Vec<Utxo> {
+        if self.only_tracked {
+            utxos.retain(|utxo| utxo.tracked);
+        }
+        match self.sort_by {
+            SortBy::Value => utxos.sort_by_key(|utxo| std::cmp::Reverse(utxo.value)),
+            SortBy::Age => utxos.sort_by_key(|utxo| utxo.timestamp),
+        }
+        utxos
+    }
+
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        utxos: Vec<Utxo>,
+        load_tx: impl Fn(Txid, Option<Pos2>),
+    ) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.only_tracked, "Only labelled addresses");
+            ui.separator();
+            ui.label("Sort by:");
+            ui.selectable_value(&mut self.sort_by, SortBy::Value, "Value");
+            ui.selectable_value(&mut self.sort_by, SortBy::Age, "Age");
+        });
+
+        let utxos = self.filter_and_sort(utxos);
+        let total: u64 = utxos.iter().map(|utxo| utxo.value).sum();
+        ui.label(format!(
+            "{} UTXOs, {} sats in total.",
+            utxos.len(),
+            Sats(total)
+        ));
+        if utxos.is_empty() {
+            return;
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        ui.add_space(3.0);
+        ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            Grid::new("Utxos")
+                .num_columns(6)
+                .striped(true)
+                .show(ui, |ui| {
+                    for title in ["Value", "Age", "Type", "Address", "Label", ""] {
+                        ui.bold(title);
+                    }
+                    ui.end_row();
+
+                    for utxo in &utxos {
+                        ui.label(Sats(utxo.value).to_string());
+                        ui.label(format!("{} d", (now - utxo.timestamp).max(0) / 86_400));
+                        ui.label(format!("{:?}", utxo.address_type).to_lowercase());
+                        ui.monospace(&utxo.address);
+                        ui.label(utxo.label.as_deref().unwrap_or(""));
+                        if ui
+                            .small_button("Go")
+                            .on_hover_text(format!("{}:{}", utxo.txid, utxo.vout))
+                            .clicked()
+                        {
+                            load_tx(utxo.txid, None);
+                            ui.close_menu();
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SortBy, Utxo, UtxoDashboard};
+    use crate::bitcoin::{AddressType, Txid};
+
+    fn utxo(value: u64, timestamp: i64, tracked: bool) -> Utxo {
+        Utxo {
+            txid: Txid::new(&"11".repeat(32)).unwrap(),
+            vout: 0,
+            value,
+            address: String::new(),
+            address_type: AddressType::P2WPKH,
+            timestamp,
+            label: None,
+            tracked,
+        }
+    }
+
+    #[test]
+    fn filter_and_sort() {
+        let utxos = || vec![utxo(5, 300, false), utxo(10, 200, true), utxo(1, 100, true)];
+        let mut dashboard = UtxoDashboard::default();
+
+        let values = |utxos: Vec<Utxo>| utxos.iter().map(|u| u.value).collect::<Vec<_>>();
+        assert_eq!(values(dashboard.filter_and_sort(utxos())), vec![10, 5, 1]);
+
+        dashboard.only_tracked = true;
+        dashboard.sort_by = SortBy::Age;
+        assert_eq!(values(dashboard.filter_and_sort(utxos())), vec![1, 10]);
+    }
+}
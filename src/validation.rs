@@ -0,0 +1,368 @@
+//! Pointing at everything that's wrong in a JSON document that doesn't have the expected shape.
+
+use serde_json::Value;
+
+/// Looking for further problems stops after this many.
+const MAX_PROBLEMS: usize = 50;
+
+/// Offending values are cut off after this many characters.
+const MAX_VALUE_LEN: usize = 80;
+
+#[derive(Debug, PartialEq, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Problem {
+    /// E.g. `$.transactions[3].position`.
+    pub path: String,
+    /// E.g. `an array of length 2`, if the error says.
+    pub expected: Option<String>,
+    /// The value at [path] as JSON, shortened.
+    pub value: Option<String>,
+    pub message: String,
+}
+
+impl Problem {
+    /// A problem with the document as a whole.
+    pub fn document(message: String) -> Self {
+        Self {
+            path: render(&[]),
+            expected: None,
+            value: None,
+            message,
+        }
+    }
+
+    fn new(path: &[Segment], error: &serde_json::Error, document: Option<&Value>) -> Self {
+        let message = error.to_string();
+        let message = match message.rsplit_once(" at line ") {
+            Some((message, _)) if error.line() > 0 => message.to_string(),
+            _ => message,
+        };
+        let value = document
+            .and_then(|document| document.pointer(&pointer(path)))
+            .map(|value| {
+                let json = value.to_string();
+                match json.char_indices().nth(MAX_VALUE_LEN) {
+                    Some((end, _)) => format!("{}…", &json[..end]),
+                    None => json,
+                }
+            });
+        Self {
+            path: render(path),
+            expected: message
+                .rsplit_once(", expected ")
+                .map(|(_, expected)| expected.to_string()),
+            value,
+            message,
+        }
+    }
+}
+
+/// Everything that [parse] rejects in [json], in document order. After each problem, the part of
+/// the document that has it is left out so that the next one can be found.
+pub fn problems(json: &str, parse: impl Fn(&str) -> Result<(), serde_json::Error>) -> Vec<Problem> {
+    let mut document: Value = match serde_json::from_str(json) {
+        Ok(document) => document,
+        Err(e) => return vec![Problem::new(&path_at(json, &e), &e, None)],
+    };
+
+    let mut problems = vec![];
+    let mut removals: Vec<Vec<Segment>> = vec![];
+    while problems.len() < MAX_PROBLEMS {
+        let text = serde_json::to_string_pretty(&document).unwrap();
+        let Err(e) = parse(&text) else {
+            break;
+        };
+        let path = path_at(&text, &e);
+        // Leaving out a required field or an array element can make the parent fail, which
+        // isn't a problem of its own.
+        let follows_removal = match removals.last().and_then(|r| r.split_last()) {
+            Some((Segment::Key(key), parent)) => {
+                path == parent && e.to_string().starts_with(&format!("missing field `{key}`"))
+            }
+            Some((Segment::Index(_), parent)) => {
+                path == parent && e.to_string().starts_with("invalid length")
+            }
+            None => false,
+        };
+        if !follows_removal {
+            let mut problem = Problem::new(&path, &e, Some(&document));
+            problem.path = render(&original(path.clone(), &removals));
+            problems.push(problem);
+        }
+        if !remove(&mut document, &path) {
+            break;
+        }
+        removals.push(path);
+    }
+    problems
+}
+
+/// [path] in the document before [removals], as removing array elements shifts the later ones.
+fn original(mut path: Vec<Segment>, removals: &[Vec<Segment>]) -> Vec<Segment> {
+    for removal in removals.iter().rev() {
+        if let Some((Segment::Index(removed), parent)) = removal.split_last() {
+            if path.len() > parent.len() && path.starts_with(parent) {
+                if let Segment::Index(index) = &mut path[parent.len()] {
+                    if *index >= *removed {
+                        *index += 1;
+                    }
+                }
+            }
+        }
+    }
+    path
+}
+
+enum Frame {
+    Object {
+        key: Option<String>,
+        /// After the colon.
+        in_value: bool,
+    },
+    Array {
+        index: usize,
+        in_value: bool,
+    },
+}
+
+/// Where in [text] the error happened, found by walking the JSON up to the error position.
+fn path_at(text: &str, error: &serde_json::Error) -> Vec<Segment> {
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(error.line().saturating_sub(1))
+        .map(str::len)
+        .sum();
+    // The last character read before the error. A value around it is where the error is.
+    let last = line_start + error.column().saturating_sub(1);
+    let bytes = text.as_bytes();
+
+    let mut stack: Vec<Frame> = vec![];
+    let mut i = 0;
+    while i <= last && i < bytes.len() {
+        let structural = i < last;
+        match bytes[i] {
+            b'{' if structural => {
+                start_value(&mut stack);
+                stack.push(Frame::Object {
+                    key: None,
+                    in_value: false,
+                });
+            }
+            b'[' if structural => {
+                start_value(&mut stack);
+                stack.push(Frame::Array {
+                    index: 0,
+                    in_value: false,
+                });
+            }
+            b'}' | b']' if structural => {
+                stack.pop();
+                complete_value(&mut stack);
+            }
+            b',' if structural => {
+                if let Some(Frame::Array { index, .. }) = stack.last_mut() {
+                    *index += 1;
+                }
+            }
+            b':' if structural => {
+                if let Some(Frame::Object { in_value, .. }) = stack.last_mut() {
+                    *in_value = true;
+                }
+            }
+            b'"' => {
+                let mut end = i + 1;
+                while end < bytes.len() && bytes[end] != b'"' {
+                    end += if bytes[end] == b'\\' { 2 } else { 1 };
+                }
+                let key = match stack.last_mut() {
+                    Some(Frame::Object {
+                        key,
+                        in_value: false,
+                    }) => Some(key),
+                    _ => None,
+                };
+                match key {
+                    Some(key) => {
+                        *key = serde_json::from_str(&text[i..=end.min(text.len() - 1)]).ok()
+                    }
+                    None if end >= last => {
+                        start_value(&mut stack);
+                        break;
+                    }
+                    None => {
+                        start_value(&mut stack);
+                        complete_value(&mut stack);
+                    }
+                }
+                if end >= last {
+                    break;
+                }
+                i = end;
+            }
+            c if c.is_ascii_alphanumeric() || c == b'-' => {
+                start_value(&mut stack);
+                while i + 1 < bytes.len()
+                    && (bytes[i + 1].is_ascii_alphanumeric() || b"+-.".contains(&bytes[i + 1]))
+                {
+                    i += 1;
+                }
+                if i >= last {
+                    break;
+                }
+                complete_value(&mut stack);
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    stack
+        .into_iter()
+        .filter_map(|frame| match frame {
+            Frame::Object { key, .. } => key.map(Segment::Key),
+            Frame::Array {
+                index,
+                in_value: true,
+            } => Some(Segment::Index(index)),
+            Frame::Array { .. } => None,
+        })
+        .collect()
+}
+
+fn start_value(stack: &mut [Frame]) {
+    if let Some(Frame::Array { in_value, .. }) = stack.last_mut() {
+        *in_value = true;
+    }
+}
+
+fn complete_value(stack: &mut [Frame]) {
+    match stack.last_mut() {
+        Some(Frame::Object { key, in_value }) => {
+            *key = None;
+            *in_value = false;
+        }
+        Some(Frame::Array { in_value, .. }) => *in_value = false,
+        None => {}
+    }
+}
+
+/// Whether there was something to remove. The whole document can't be.
+fn remove(document: &mut Value, path: &[Segment]) -> bool {
+    let Some((last, parent)) = path.split_last() else {
+        return false;
+    };
+    match (document.pointer_mut(&pointer(parent)), last) {
+        (Some(Value::Object(map)), Segment::Key(key)) => map.remove(key).is_some(),
+        (Some(Value::Array(array)), Segment::Index(index)) if *index < array.len() => {
+            array.remove(*index);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// As in [Value::pointer].
+fn pointer(path: &[Segment]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            Segment::Key(key) => format!("/{}", key.replace('~', "~0").replace('/', "~1")),
+            Segment::Index(index) => format!("/{index}"),
+        })
+        .collect()
+}
+
+fn render(path: &[Segment]) -> String {
+    let mut rendered = "$".to_string();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if key.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                rendered.push('.');
+                rendered.push_str(key);
+            }
+            Segment::Key(key) => rendered.push_str(&format!("[{}]", Value::from(key.as_str()))),
+            Segment::Index(index) => rendered.push_str(&format!("[{index}]")),
+        }
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::problems;
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct Doc {
+        name: String,
+        points: Vec<Point>,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct Point {
+        id: u8,
+        position: [i32; 2],
+    }
+
+    fn check(json: &str) -> Vec<(String, String)> {
+        problems(json, |json| serde_json::from_str::<Doc>(json).map(drop))
+            .into_iter()
+            .map(|p| (p.path, p.value.unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn collects_problems() {
+        let json = r#"{
+            "name": "test",
+            "points": [
+                {"id": 1, "position": [1, 2]},
+                {"id": 300, "position": [1, 2]},
+                {"id": 3, "position": [1, "two"]},
+                {"id": 4},
+                {"id": 5, "position": [1, 2, 3]}
+            ],
+            "tags": ["a", 1, "a/b"]
+        }"#;
+        assert_eq!(
+            check(json),
+            vec![
+                ("$.points[1].id".to_string(), "300".to_string()),
+                (
+                    "$.points[2].position[1]".to_string(),
+                    r#""two""#.to_string()
+                ),
+                ("$.points[3]".to_string(), r#"{"id":4}"#.to_string()),
+                ("$.points[4].position[2]".to_string(), "3".to_string()),
+                ("$.tags[1]".to_string(), "1".to_string()),
+            ]
+        );
+
+        let problems = problems(r#"{"name": 1, "points": []}"#, |json| {
+            serde_json::from_str::<Doc>(json).map(drop)
+        });
+        assert_eq!(problems[0].expected.as_deref(), Some("a string"));
+        assert_eq!(
+            problems[0].message,
+            "invalid type: integer `1`, expected a string"
+        );
+
+        assert_eq!(
+            check(r#"{"name": "x"}"#),
+            vec![("$".to_string(), r#"{"name":"x"}"#.to_string())]
+        );
+        assert_eq!(
+            check(r#"{"name": "x", "points": [}"#),
+            vec![("$.points".to_string(), "".to_string())]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use egui::{Pos2, Sense, TextStyle, Vec2, Widget, WidgetText};
+use egui::{Color32, Pos2, Sense, TextStyle, Vec2, Widget, WidgetText};
 
 pub struct BulletPoint {
     text: WidgetText,
@@ -33,6 +33,31 @@ impl Widget for BulletPoint {
     }
 }
 
+/// A small colored circle with a tooltip, for status indicators.
+pub struct StatusDot {
+    color: Color32,
+    tooltip: String,
+}
+
+impl StatusDot {
+    pub fn new(color: Color32, tooltip: impl Into<String>) -> Self {
+        StatusDot {
+            color,
+            tooltip: tooltip.into(),
+        }
+    }
+}
+
+impl Widget for StatusDot {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let size = ui.spacing().icon_width;
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(size), Sense::hover());
+        ui.painter()
+            .circle_filled(rect.center(), 0.3 * size, self.color);
+        response.on_hover_text(self.tooltip)
+    }
+}
+
 pub trait UiExt {
     fn bold(&mut self, text: impl Into<String>);
 }
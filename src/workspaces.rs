@@ -4,12 +4,25 @@ use std::sync::{
 };
 
 use chrono::{DateTime, Local, Utc};
-use egui::{mutex::Mutex, Button, Context, Id, Label, TextEdit, Ui};
+use egui::{mutex::Mutex, Button, ComboBox, Context, Grid, Id, Label, RichText, TextEdit, Ui};
 use egui_extras::{Column, TableBuilder};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{app::Update, export, modal, notifications::NotifyExt, style, widgets::UiExt};
+use crate::{
+    annotations::Category,
+    app::Update,
+    backup::{self, Backups},
+    chain::Network,
+    expand::ExpandSettings,
+    export, modal,
+    notifications::NotifyExt,
+    palette::Palette,
+    platform::inner as platform,
+    prices::{self, Prices},
+    style, validation,
+    widgets::UiExt,
+};
 
 pub struct Workspaces {
     sender: Sender<Msg>,
@@ -20,9 +33,51 @@ pub struct Workspaces {
     window_open: bool,
     input_new_name: Option<String>,
     input_import_json: Option<String>,
+    /// Why the pasted JSON couldn't be imported.
+    import_problems: Vec<validation::Problem>,
+    /// Whether the pasted JSON is an encrypted export, which needs [input_import_passphrase].
+    import_encrypted: bool,
+    input_import_passphrase: String,
+    /// Of the file being imported, `None` for pasted JSON.
+    import_name: Option<String>,
+    /// The passphrase for an encrypted export, and again to make sure there's no typo.
+    input_export_passphrase: Option<(String, String)>,
+    /// Whether a key is being derived from a passphrase, see [Msg::Decrypted].
+    decrypting: bool,
+    encrypting: bool,
     input_rename: Option<String>,
+    /// An imported workspace whose name is already taken, waiting for the user to decide.
+    pending_import: Option<(String, export::Workspace)>,
+    /// Edits on the canvas of a workspace that isn't owned, which a fork takes along.
+    view_only_edits: Option<export::Workspace>,
     input_confirm_delete: bool,
     request_focus: bool,
+    show_archived: bool,
+    tag_filter: Option<String>,
+    input_new_tag: String,
+    /// Time of the last auto-refresh in seconds, as in [egui::InputState::time].
+    last_refresh: f64,
+    palette: Palette,
+    defaults: Defaults,
+    backups: Backups,
+    /// Whether the current workspace changed since the journal was last written.
+    journal_dirty: bool,
+    /// Time of the last journal write, as in [egui::InputState::time].
+    last_journal: f64,
+    /// Set when unsaved changes from a previous session were restored.
+    restored_from_journal: Option<DateTime<Utc>>,
+}
+
+/// How often changes to the current workspace are written to the journal, in seconds.
+const JOURNAL_INTERVAL: f64 = 2.0;
+
+/// Changes to the current workspace since the last regular save. Written frequently and cheaply
+/// so that little is lost if the app is closed without saving, e.g. after a crash.
+#[derive(Serialize, Deserialize)]
+struct JournalEntry {
+    workspace: Uuid,
+    written_at: DateTime<Utc>,
+    data: export::Workspace,
 }
 
 /// This is a bit of a hack. Ideally, we'd like this to be part of [AppStore].
@@ -30,6 +85,78 @@ pub struct Workspaces {
 struct WorkspacesStore {
     current_workspace: Uuid,
     window_open: bool,
+    #[serde(default)]
+    show_archived: bool,
+    #[serde(default)]
+    defaults: Defaults,
+    #[serde(default)]
+    backups: Backups,
+}
+
+/// Applied to newly created workspaces.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Defaults {
+    /// In minutes.
+    refresh_interval: Option<u32>,
+    /// Comma separated.
+    tags: String,
+    layout: Option<export::Layout0>,
+    /// See [Network::name]. `None` is [Network::default].
+    network: Option<String>,
+    /// Fiat currency, `None` to hide fiat values.
+    currency: Option<String>,
+    /// `None` keeps the expansion settings of the workspace before.
+    expand: Option<ExpandSettings>,
+    categories: Vec<Category>,
+}
+
+impl Defaults {
+    fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = vec![];
+        for tag in self.tags.split(',').map(str::trim) {
+            if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+        tags
+    }
+}
+
+/// Deriving a key from a passphrase takes a moment, so [f] runs after this frame. In the browser
+/// it still blocks the page, see [platform::OFF_MAIN_THREAD]. It waits until the busy state has
+/// been drawn then.
+fn derive_key(ctx: &Context, f: impl 'static + Send + FnOnce()) {
+    ctx.request_repaint();
+    platform::set_timeout(if platform::OFF_MAIN_THREAD { 0 } else { 100 }, f);
+}
+
+/// A spinner, or in the browser, where it wouldn't move, a note that the page is busy.
+fn deriving_key_ui(ui: &mut Ui, text: &str) {
+    if platform::OFF_MAIN_THREAD {
+        ui.spinner();
+        ui.label(text);
+    } else {
+        ui.label(text);
+        ui.weak("The page doesn't respond until it's done.");
+    }
+}
+
+fn refresh_interval_ui(ui: &mut Ui, id: &str, interval: &mut Option<u32>) {
+    fn interval_text(minutes: Option<u32>) -> String {
+        match minutes {
+            None => "Off".to_string(),
+            Some(m) => format!("Every {} min", m),
+        }
+    }
+
+    ComboBox::from_id_source(id)
+        .selected_text(interval_text(*interval))
+        .show_ui(ui, |ui| {
+            for option in [None, Some(1), Some(5), Some(15), Some(60)] {
+                ui.selectable_value(interval, option, interval_text(option));
+            }
+        });
 }
 
 impl Workspaces {
@@ -49,9 +176,28 @@ impl Workspaces {
             window_open: false,
             input_new_name: None,
             input_import_json: None,
+            import_problems: vec![],
+            import_encrypted: false,
+            input_import_passphrase: String::new(),
+            import_name: None,
+            input_export_passphrase: None,
+            decrypting: false,
+            encrypting: false,
             input_rename: None,
+            pending_import: None,
+            view_only_edits: None,
             input_confirm_delete: false,
             request_focus: false,
+            show_archived: false,
+            tag_filter: None,
+            input_new_tag: String::new(),
+            last_refresh: 0.0,
+            palette: Palette::new(egui::Key::P),
+            defaults: Defaults::default(),
+            backups: Backups::default(),
+            journal_dirty: false,
+            last_journal: 0.0,
+            restored_from_journal: None,
         }
     }
 
@@ -65,8 +211,14 @@ impl Workspaces {
             &WorkspacesStore {
                 current_workspace: self.current_workspace,
                 window_open: self.window_open,
+                show_archived: self.show_archived,
+                defaults: self.defaults.clone(),
+                backups: self.backups.clone(),
             },
         );
+
+        // Everything in the journal is part of the regular storage now.
+        platform::clear_journal();
     }
 
     pub fn load(
@@ -85,6 +237,9 @@ impl Workspaces {
         {
             result.window_open = workspaces_store.window_open;
             result.current_workspace = workspaces_store.current_workspace;
+            result.show_archived = workspaces_store.show_archived;
+            result.defaults = workspaces_store.defaults;
+            result.backups = workspaces_store.backups;
         }
 
         if result.workspaces.is_empty() {
@@ -101,9 +256,67 @@ impl Workspaces {
             result.current_workspace = result.workspaces.first().unwrap().id;
         }
 
+        result.replay_journal();
+
         result
     }
 
+    /// Restore changes that were journaled but not saved before the app was last closed.
+    fn replay_journal(&mut self) {
+        let Some(json) = platform::read_journal() else {
+            return;
+        };
+        let entry = match serde_json::from_str::<JournalEntry>(&json) {
+            Ok(entry) => entry,
+            Err(err) => {
+                log::warn!("Ignoring unreadable journal: {}", err);
+                return;
+            }
+        };
+        let Some(workspace) = self
+            .workspaces
+            .iter_mut()
+            .find(|p| p.id == entry.workspace && p.is_owned)
+        else {
+            return;
+        };
+        if workspace.data != entry.data {
+            workspace.data = entry.data;
+            self.current_workspace = entry.workspace;
+            self.restored_from_journal = Some(entry.written_at);
+        }
+    }
+
+    fn write_journal(&mut self, ctx: &Context) {
+        if let Some(written_at) = self.restored_from_journal.take() {
+            ctx.notify_info(format!(
+                "Restored unsaved changes from {}.",
+                written_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")
+            ));
+        }
+        if !self.journal_dirty {
+            return;
+        }
+        let now = ctx.input(|i| i.time);
+        if now - self.last_journal < JOURNAL_INTERVAL {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(
+                JOURNAL_INTERVAL - (now - self.last_journal),
+            ));
+            return;
+        }
+        self.last_journal = now;
+        self.journal_dirty = false;
+        let entry = JournalEntry {
+            workspace: self.current_workspace,
+            written_at: Utc::now(),
+            data: self.current().data.clone(),
+        };
+        match serde_json::to_string(&entry) {
+            Ok(json) => platform::write_journal(&json),
+            Err(err) => log::warn!("Could not serialize journal: {}", err),
+        }
+    }
+
     fn with_current(&mut self, f: impl FnOnce(&mut Workspace)) {
         let i = self
             .workspaces
@@ -121,47 +334,366 @@ impl Workspaces {
             .unwrap()
     }
 
+    pub fn current_name(&self) -> &str {
+        &self.current().name
+    }
+
+    /// E.g. `my-workspace-selection-20240131-142501.json`.
+    pub fn selection_file_name(&self) -> String {
+        export_file_name(&format!("{} selection", self.current_name()))
+    }
+
     pub fn current_data(&self) -> export::Workspace {
         self.current().data.clone()
     }
 
-    fn apply_update(&mut self, msg: Msg) {
+    /// The update that makes the app show the current workspace.
+    pub fn load_current(&self) -> Update {
+        Update::LoadWorkspace {
+            data: Box::new(self.current_data()),
+            pinned: self.current().pinned,
+            view_only: !self.current().is_owned,
+        }
+    }
+
+    /// Trigger the current workspace's auto-refresh when it's due.
+    pub fn tick(&mut self, ctx: &Context) {
+        self.write_journal(ctx);
+        if self.backups.is_due(Utc::now()) {
+            self.backup(ctx, false);
+        }
+
+        let Some(minutes) = self.current().refresh_interval else {
+            return;
+        };
+        let interval = 60.0 * minutes as f64;
+        let now = ctx.input(|i| i.time);
+        if now - self.last_refresh >= interval {
+            self.last_refresh = now;
+            self.update_sender
+                .send(Update::RefreshWorkspace { report: false })
+                .unwrap();
+        }
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64(
+            interval - (now - self.last_refresh),
+        ));
+    }
+
+    /// Whether another workspace than [except] is called [name], ignoring case.
+    fn name_taken(&self, name: &str, except: Option<Uuid>) -> bool {
+        self.workspaces
+            .iter()
+            .any(|w| Some(w.id) != except && w.name.trim().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// [name], or `name (2)`, `name (3)`, ... if that's taken.
+    fn unique_name(&self, name: &str, except: Option<Uuid>) -> String {
+        let name = name.trim();
+        (1..)
+            .map(|n| match n {
+                1 => name.to_string(),
+                n => format!("{} ({})", name, n),
+            })
+            .find(|candidate| !self.name_taken(candidate, except))
+            .unwrap()
+    }
+
+    /// Imports the JSON of the import dialog, or shows what's wrong with it.
+    fn import_from_dialog(&mut self, ctx: &Context, json: &str) {
+        match import_json(ctx, json) {
+            Ok(data) => {
+                let name = self
+                    .import_name
+                    .take()
+                    .unwrap_or_else(|| "JSON import".to_string());
+                self.sender.send(Msg::Import { name, data }).unwrap();
+                self.input_import_json = None;
+                self.input_import_passphrase.clear();
+                self.import_problems.clear();
+            }
+            Err(e) => {
+                self.import_problems = export::validate(json);
+                if self.import_problems.is_empty() {
+                    ctx.notify_error("Could not import JSON", Some(e));
+                }
+            }
+        }
+    }
+
+    /// Shows whether [name] is free, with a button to pick a free variant instead.
+    fn name_check_ui(&self, ui: &mut Ui, name: &mut String, except: Option<Uuid>) {
+        if name.trim().is_empty() {
+            return;
+        }
+        if self.name_taken(name, except) {
+            ui.horizontal(|ui| {
+                ui.colored_label(ui.visuals().warn_fg_color, "⚠ Name already in use.");
+                let unique = self.unique_name(name, except);
+                if ui.small_button(format!("Use \"{}\"", unique)).clicked() {
+                    *name = unique;
+                }
+            });
+        } else {
+            ui.weak("✔ Name is available.");
+        }
+    }
+
+    fn backup(&mut self, ctx: &Context, manual: bool) {
+        let bundle = Bundle {
+            workspaces: self.workspaces.clone(),
+        };
+        let contents = serde_json::to_string_pretty(&bundle).unwrap();
+        match self.backups.write(&contents, Utc::now()) {
+            Ok(()) if manual => {
+                ctx.notify_success(format!("Backed up {} workspaces.", bundle.workspaces.len()))
+            }
+            Ok(()) => log::info!("Backed up {} workspaces", bundle.workspaces.len()),
+            Err(e) => ctx.notify_error("Backup failed", Some(e)),
+        }
+    }
+
+    fn apply_update(&mut self, ctx: &Context, msg: Msg) {
         match msg {
             Msg::New { name, data } => {
-                let mut p = Workspace::new(name);
-                if let Some(data) = data {
-                    p.data = data;
+                let mut p = Workspace::new(self.unique_name(&name, None));
+                p.refresh_interval = self.defaults.refresh_interval;
+                p.tags = self.defaults.tags();
+                let defaults = data.is_none();
+                match data {
+                    Some(data) => p.data = data,
+                    None => {
+                        if let Some(layout) = &self.defaults.layout {
+                            p.data.layout = layout.clone();
+                        }
+                        p.data.currency = self.defaults.currency.clone();
+                        p.data
+                            .annotations
+                            .categories_mut()
+                            .clone_from(&self.defaults.categories);
+                    }
                 }
                 let id = p.id;
                 self.workspaces.push(p);
-                self.apply_update(Msg::Select { id });
+                self.apply_update(ctx, Msg::Select { id });
+                if defaults {
+                    // After loading, which switches to the network the workspace was saved with.
+                    let network = self
+                        .defaults
+                        .network
+                        .as_deref()
+                        .and_then(Network::from_name);
+                    if let Some(network) = network.filter(|n| *n != Network::default()) {
+                        self.update_sender
+                            .send(Update::SetNetwork { network })
+                            .unwrap();
+                    }
+                    if let Some(settings) = &self.defaults.expand {
+                        self.update_sender
+                            .send(Update::SetExpandSettings {
+                                settings: settings.clone(),
+                            })
+                            .unwrap();
+                    }
+                }
+            }
+            Msg::Import { name, data } => {
+                if self.name_taken(&name, None) {
+                    self.pending_import = Some((name, data));
+                } else {
+                    self.apply_update(
+                        ctx,
+                        Msg::New {
+                            name,
+                            data: Some(data),
+                        },
+                    );
+                }
+            }
+            Msg::ImportEncrypted { name, text } => {
+                self.import_encrypted = true;
+                self.import_name = Some(name);
+                self.input_import_json = Some(text);
+                self.input_import_passphrase.clear();
+                self.import_problems.clear();
+            }
+            Msg::ImportInvalid {
+                name,
+                text,
+                problems,
+            } => {
+                self.import_encrypted = false;
+                self.import_name = Some(name);
+                self.input_import_json = Some(text);
+                self.input_import_passphrase.clear();
+                self.import_problems = problems;
+            }
+            Msg::Decrypted { result } => {
+                self.decrypting = false;
+                // Unless the dialog was closed in the meantime.
+                if self.input_import_json.is_some() {
+                    match result {
+                        Ok(json) => self.import_from_dialog(ctx, &json),
+                        Err(e) => ctx.notify_error("Could not decrypt", Some(e)),
+                    }
+                }
+            }
+            Msg::SaveEncrypted {
+                name,
+                file_name,
+                contents,
+            } => {
+                self.encrypting = false;
+                self.input_export_passphrase = None;
+                if platform::save_file(ctx, &file_name, &contents) {
+                    ctx.notify_success(format!("Exported workspace `{}` encrypted.", name));
+                }
+            }
+            Msg::MergeInto { id, data } => {
+                if let Some(workspace) = self.workspaces.iter_mut().find(|w| w.id == id) {
+                    workspace.data.merge(data);
+                }
+                self.apply_update(ctx, Msg::Select { id });
             }
             Msg::UpdateData { data } => {
-                self.with_current(|p| p.data = data);
+                // The notes are edited here, not in the app, so they're not part of `data`.
+                if !self.current().is_owned {
+                    self.view_only_edits = Some(export::Workspace {
+                        notes: self.current().data.notes.clone(),
+                        ..data
+                    });
+                    return;
+                }
+                let mut changed = false;
+                self.with_current(|p| {
+                    let data = export::Workspace {
+                        notes: std::mem::take(&mut p.data.notes),
+                        ..data
+                    };
+                    changed = p.data != data;
+                    p.data = data;
+                });
+                self.journal_dirty |= changed;
+            }
+            Msg::AppendNotes { text } => {
+                if !self.current().is_owned {
+                    self.apply_update(ctx, Msg::Fork);
+                }
+                self.with_current(|p| {
+                    let notes = &mut p.data.notes;
+                    notes.truncate(notes.trim_end().len());
+                    if !notes.is_empty() {
+                        notes.push_str("\n\n");
+                    }
+                    notes.push_str(&text);
+                });
+                self.journal_dirty = true;
+            }
+            Msg::SetNotes { notes } => {
+                self.with_current(|p| p.data.notes = notes);
+                self.journal_dirty = true;
             }
             Msg::Select { id } => {
                 self.current_workspace = id;
-                self.update_sender
-                    .send(Update::LoadWorkspace {
-                        data: self.current_data(),
-                    })
-                    .unwrap();
+                self.view_only_edits = None;
+                self.update_sender.send(self.load_current()).unwrap();
             }
             Msg::Rename { name } => {
                 self.with_current(|p| p.name = name);
             }
+            Msg::ImportBundle {
+                workspaces,
+                restore,
+            } => {
+                let mut added = 0;
+                let mut unchanged = 0;
+                for mut workspace in workspaces {
+                    if let Some(existing) = self.workspaces.iter().find(|w| w.id == workspace.id) {
+                        if *existing == workspace {
+                            unchanged += 1;
+                            continue;
+                        }
+                        // Keep both versions around.
+                        workspace.id = Uuid::now_v7();
+                        workspace.name = format!("{} (imported)", workspace.name);
+                    }
+                    workspace.name = self.unique_name(&workspace.name, None);
+                    self.workspaces.push(workspace);
+                    added += 1;
+                }
+                let verb = if restore { "Restored" } else { "Imported" };
+                let mut message = format!("{} {} workspaces.", verb, added);
+                if unchanged > 0 {
+                    message.push_str(&format!(" {} were already up to date.", unchanged));
+                }
+                ctx.notify_success(message);
+            }
+            Msg::AddTag { tag } => {
+                self.with_current(|p| {
+                    if !p.tags.contains(&tag) {
+                        p.tags.push(tag);
+                    }
+                });
+            }
+            Msg::RemoveTag { tag } => {
+                self.with_current(|p| p.tags.retain(|t| *t != tag));
+            }
+            Msg::TakeSnapshot => {
+                self.with_current(|p| {
+                    p.snapshots.push(Snapshot {
+                        created_at: Utc::now(),
+                        data: p.data.clone(),
+                    })
+                });
+            }
+            Msg::RestoreSnapshot { index } => {
+                self.with_current(|p| p.data = p.snapshots[index].data.clone());
+                self.update_sender.send(self.load_current()).unwrap();
+            }
+            Msg::DeleteSnapshot { index } => {
+                self.with_current(|p| {
+                    p.snapshots.remove(index);
+                });
+            }
+            Msg::Fork => {
+                let mut fork = self.current().clone();
+                if let Some(data) = self.view_only_edits.take() {
+                    fork.data = data;
+                }
+                fork.id = Uuid::now_v7();
+                fork.name = format!("{} (fork)", fork.name);
+                fork.is_owned = true;
+                fork.is_public = false;
+                fork.created_at = Utc::now();
+                let id = fork.id;
+                self.workspaces.push(fork);
+                self.apply_update(ctx, Msg::Select { id });
+            }
+            Msg::TogglePinned => {
+                self.with_current(|p| p.pinned = !p.pinned);
+                // Reloading pins the client to the recorded source, or unpins it.
+                self.update_sender.send(self.load_current()).unwrap();
+            }
+            Msg::ToggleArchived => {
+                self.with_current(|p| p.archived = !p.archived);
+            }
+            Msg::SetRefreshInterval { minutes } => {
+                self.with_current(|p| p.refresh_interval = minutes);
+            }
             // Msg::TogglePublic => {
             //     self.with_current(|p| p.is_public = !p.is_public);
             // }
             Msg::Delete => {
                 self.workspaces.retain(|p| p.id != self.current_workspace);
                 if let Some(p) = self.workspaces.first() {
-                    self.apply_update(Msg::Select { id: p.id });
+                    self.apply_update(ctx, Msg::Select { id: p.id });
                 } else {
-                    self.apply_update(Msg::New {
-                        name: "Unnamed".to_string(),
-                        data: None,
-                    });
+                    self.apply_update(
+                        ctx,
+                        Msg::New {
+                            name: "Unnamed".to_string(),
+                            data: None,
+                        },
+                    );
                 }
             }
         }
@@ -177,6 +709,20 @@ impl Workspaces {
     }
 
     pub fn show_window(&mut self, ctx: &Context) {
+        let receiver = self.receiver.clone();
+        for msg in receiver.lock().try_iter() {
+            self.apply_update(ctx, msg);
+        }
+
+        let items = self
+            .workspaces
+            .iter()
+            .filter(|w| !w.archived)
+            .map(|w| (w.id, w.name.as_str()));
+        if let Some(id) = self.palette.show(ctx, "Switch to workspace...", items) {
+            self.sender.send(Msg::Select { id }).unwrap();
+        }
+
         let mut open = self.window_open;
         egui::Window::new("Workspaces")
             .open(&mut open)
@@ -185,10 +731,20 @@ impl Workspaces {
     }
 
     fn show_ui(&mut self, ui: &mut Ui) {
-        let receiver = self.receiver.clone();
-        for msg in receiver.lock().try_iter() {
-            self.apply_update(msg);
-        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_archived, "Show archived");
+            if let Some(tag) = &self.tag_filter {
+                ui.separator();
+                ui.label(format!("Tag: {}", tag));
+                if ui.small_button("✖").on_hover_text("Clear filter").clicked() {
+                    self.tag_filter = None;
+                }
+            }
+        });
+
+        ui.add_space(3.0);
+
+        let mut clicked_tag = None;
 
         TableBuilder::new(ui)
             .striped(true)
@@ -201,12 +757,16 @@ impl Workspaces {
                     .resizable(false),
             )
             .column(Column::auto())
+            .column(Column::auto())
             // .column(Column::auto().at_least(10.0))
             .sense(egui::Sense::click())
             .header(20.0, |mut header| {
                 header.col(|ui| {
                     ui.bold("Name");
                 });
+                header.col(|ui| {
+                    ui.bold("Tags");
+                });
                 header.col(|ui| {
                     ui.bold("Created");
                 });
@@ -215,12 +775,35 @@ impl Workspaces {
                 // });
             })
             .body(|mut body| {
-                for workspace in &self.workspaces {
+                let visible = self.workspaces.iter().filter(|w| {
+                    (self.show_archived || !w.archived || w.id == self.current_workspace)
+                        && self
+                            .tag_filter
+                            .as_ref()
+                            .map_or(true, |t| w.tags.contains(t))
+                });
+                for workspace in visible {
                     body.row(20.0, |mut row| {
                         row.set_selected(workspace.id == self.current_workspace);
 
                         row.col(|ui| {
-                            ui.add(Label::new(workspace.name.clone()).selectable(false));
+                            let name = if workspace.archived {
+                                RichText::new(format!("{} (archived)", workspace.name)).weak()
+                            } else {
+                                RichText::new(workspace.name.clone())
+                            };
+                            ui.add(Label::new(name).selectable(false));
+                        });
+                        row.col(|ui| {
+                            for tag in &workspace.tags {
+                                if ui
+                                    .small_button(tag)
+                                    .on_hover_text("Filter by tag")
+                                    .clicked()
+                                {
+                                    clicked_tag = Some(tag.clone());
+                                }
+                            }
                         });
                         row.col(|ui| {
                             ui.add(
@@ -250,6 +833,10 @@ impl Workspaces {
                 }
             });
 
+        if clicked_tag.is_some() {
+            self.tag_filter = clicked_tag;
+        }
+
         ui.add_space(3.0);
 
         ui.horizontal(|ui| {
@@ -267,6 +854,7 @@ impl Workspaces {
                         resp.request_focus();
                         self.request_focus = false;
                     }
+                    self.name_check_ui(ui, &mut new_name, None);
 
                     ui.add_space(3.0);
 
@@ -274,10 +862,8 @@ impl Workspaces {
                         if ui.button("Cancel").clicked() {
                             self.input_new_name = None;
                         }
-                        if ui
-                            .add_enabled(!new_name.is_empty(), Button::new("Create"))
-                            .clicked()
-                        {
+                        let valid = !new_name.is_empty() && !self.name_taken(&new_name, None);
+                        if ui.add_enabled(valid, Button::new("Create")).clicked() {
                             self.sender
                                 .send(Msg::New {
                                     name: new_name.clone(),
@@ -293,8 +879,57 @@ impl Workspaces {
                 }
             }
 
-            if ui.button("Import JSON").clicked() {
+            if let Some((name, _)) = &self.pending_import {
+                let name = name.clone();
+                let unique = self.unique_name(&name, None);
+                let existing = self
+                    .workspaces
+                    .iter()
+                    .find(|w| w.name.trim().eq_ignore_ascii_case(name.trim()))
+                    .map(|w| (w.id, w.name.clone()));
+                modal::show(ui.ctx(), "Workspace Exists", |ui| {
+                    ui.label(format!("A workspace called \"{}\" already exists.", name));
+
+                    ui.add_space(3.0);
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Cancel").clicked() {
+                            self.pending_import = None;
+                        }
+                        if ui.button(format!("Import as \"{}\"", unique)).clicked() {
+                            if let Some((_, data)) = self.pending_import.take() {
+                                self.sender
+                                    .send(Msg::New {
+                                        name: unique.clone(),
+                                        data: Some(data),
+                                    })
+                                    .unwrap();
+                            }
+                        }
+                        if let Some((id, existing_name)) = &existing {
+                            if ui
+                                .button(format!("Merge into \"{}\"", existing_name))
+                                .on_hover_text(
+                                    "Adds transactions and annotations that are missing.",
+                                )
+                                .clicked()
+                            {
+                                if let Some((_, data)) = self.pending_import.take() {
+                                    self.sender.send(Msg::MergeInto { id: *id, data }).unwrap();
+                                }
+                            }
+                        }
+                    });
+                });
+            }
+
+            if ui
+                .button("Import JSON")
+                .on_hover_text("Also takes compact exports, on their own or as part of a link.")
+                .clicked()
+            {
                 self.input_import_json = Some("".to_string());
+                self.import_name = None;
                 self.request_focus = true;
             }
             if let Some(json) = &self.input_import_json {
@@ -329,44 +964,171 @@ impl Workspaces {
                         }
                     });
 
+                    if self.import_encrypted {
+                        ui.add_space(3.0);
+                        ui.horizontal(|ui| {
+                            ui.label("🔒 Encrypted. Passphrase:");
+                            ui.add(
+                                TextEdit::singleline(&mut self.input_import_passphrase)
+                                    .password(true),
+                            );
+                        });
+                    }
+
+                    if !self.import_problems.is_empty() {
+                        ui.add_space(3.0);
+                        show_problems(ui, &self.import_problems);
+                    }
+
                     ui.add_space(3.0);
 
                     ui.horizontal(|ui| {
                         if ui.button("Cancel").clicked() {
                             self.input_import_json = None;
+                            self.import_name = None;
+                            self.input_import_passphrase.clear();
+                            self.import_problems.clear();
                         }
                         if ui
-                            .add_enabled(!new_json.is_empty(), Button::new("Import"))
+                            .add_enabled(
+                                !new_json.is_empty() && !self.decrypting,
+                                Button::new("Import"),
+                            )
                             .clicked()
                         {
-                            match serde_json::from_str(&new_json) {
-                                Ok(data) => {
-                                    self.sender
-                                        .send(Msg::New {
-                                            name: "JSON import".to_string(),
-                                            data: Some(data),
-                                        })
-                                        .unwrap();
-                                    self.input_import_json = None;
-                                }
-                                Err(e) => {
-                                    ui.ctx().notify_error("Could not import JSON", Some(e));
-                                }
+                            if self.import_encrypted {
+                                self.decrypting = true;
+                                let sender = self.sender.clone();
+                                let ctx = ui.ctx().clone();
+                                let text = new_json.clone();
+                                let passphrase = self.input_import_passphrase.clone();
+                                derive_key(ui.ctx(), move || {
+                                    let result = export::decrypt(&text, &passphrase);
+                                    sender.send(Msg::Decrypted { result }).unwrap();
+                                    ctx.request_repaint();
+                                });
+                            } else {
+                                self.import_from_dialog(ui.ctx(), &new_json);
                             }
                         }
+                        if self.decrypting {
+                            deriving_key_ui(ui, "Decrypting…");
+                        }
                     });
                 });
                 if new_json != old_json {
+                    self.import_encrypted = export::is_encrypted(&new_json);
                     self.input_import_json = Some(new_json);
+                    self.import_problems.clear();
                 }
             }
+
+            if ui.button("Import from file…").clicked() {
+                let sender = self.sender.clone();
+                let ctx = ui.ctx().clone();
+                platform::open_file(ui.ctx(), "json", move |file_name, contents| {
+                    let name = file_name
+                        .strip_suffix(".json")
+                        .unwrap_or(&file_name)
+                        .to_string();
+                    match import_json(&ctx, &contents) {
+                        Ok(data) => {
+                            sender.send(Msg::Import { name, data }).unwrap();
+                            ctx.request_repaint();
+                        }
+                        Err(_) if export::is_encrypted(&contents) => {
+                            sender
+                                .send(Msg::ImportEncrypted {
+                                    name,
+                                    text: contents,
+                                })
+                                .unwrap();
+                            ctx.request_repaint();
+                        }
+                        Err(e) => {
+                            let problems = export::validate(&contents);
+                            if problems.is_empty() {
+                                ctx.notify_error(
+                                    format!("Could not import `{}`", file_name),
+                                    Some(e),
+                                );
+                            } else {
+                                sender
+                                    .send(Msg::ImportInvalid {
+                                        name,
+                                        text: contents,
+                                        problems,
+                                    })
+                                    .unwrap();
+                                ctx.request_repaint();
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Export all…").clicked() {
+                let bundle = Bundle {
+                    workspaces: self.workspaces.clone(),
+                };
+                let file_name = format!("workspaces-{}.json", Local::now().format("%Y%m%d-%H%M%S"));
+                let contents = serde_json::to_string_pretty(&bundle).unwrap();
+                if platform::save_file(ui.ctx(), &file_name, &contents) {
+                    ui.ctx().notify_success(format!(
+                        "Exported {} workspaces.",
+                        bundle.workspaces.len()
+                    ));
+                }
+            }
+
+            if ui.button("Import bundle…").clicked() {
+                let sender = self.sender.clone();
+                let ctx = ui.ctx().clone();
+                platform::open_file(ui.ctx(), "json", move |file_name, contents| {
+                    match serde_json::from_str::<Bundle>(&contents) {
+                        Ok(bundle) => {
+                            sender
+                                .send(Msg::ImportBundle {
+                                    workspaces: bundle.workspaces,
+                                    restore: false,
+                                })
+                                .unwrap();
+                            ctx.request_repaint();
+                        }
+                        Err(e) => {
+                            ctx.notify_error(format!("Could not import `{}`", file_name), Some(e));
+                        }
+                    }
+                });
+            }
         });
 
         ui.separator();
         ui.bold("Current Workspace:");
 
+        // Someone else's workspace can only be looked at, or forked.
+        let owned = self.current().is_owned;
+        if !owned {
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new("View only")
+                        .strong()
+                        .color(ui.visuals().warn_fg_color),
+                );
+                if ui
+                    .button("Fork to my workspaces")
+                    .on_hover_text("Create an editable copy.")
+                    .clicked()
+                {
+                    self.sender.send(Msg::Fork).unwrap();
+                }
+            });
+        }
+
         ui.horizontal(|ui| {
-            if ui.button("Rename").clicked() {
+            if ui.add_enabled(owned, Button::new("Rename")).clicked() {
                 self.input_rename = Some(self.current().name.to_string());
                 self.request_focus = true;
             }
@@ -380,6 +1142,8 @@ impl Workspaces {
                         resp.request_focus();
                         self.request_focus = false;
                     }
+                    let id = self.current_workspace;
+                    self.name_check_ui(ui, &mut new_name, Some(id));
 
                     ui.add_space(3.0);
 
@@ -387,10 +1151,8 @@ impl Workspaces {
                         if ui.button("Cancel").clicked() {
                             self.input_rename = None;
                         }
-                        if ui
-                            .add_enabled(!new_name.is_empty(), Button::new("Rename"))
-                            .clicked()
-                        {
+                        let valid = !new_name.is_empty() && !self.name_taken(&new_name, Some(id));
+                        if ui.add_enabled(valid, Button::new("Rename")).clicked() {
                             self.sender
                                 .send(Msg::Rename {
                                     name: new_name.clone(),
@@ -405,7 +1167,7 @@ impl Workspaces {
                 }
             }
 
-            if ui.button("Delete").clicked() {
+            if ui.add_enabled(owned, Button::new("Delete")).clicked() {
                 self.input_confirm_delete = true;
             }
             if self.input_confirm_delete {
@@ -426,6 +1188,15 @@ impl Workspaces {
                 });
             }
 
+            let archive_label = if self.current().archived {
+                "Unarchive"
+            } else {
+                "Archive"
+            };
+            if ui.add_enabled(owned, Button::new(archive_label)).clicked() {
+                self.sender.send(Msg::ToggleArchived).unwrap();
+            }
+
             // let mut is_public = self.current().is_public;
             // if ui.checkbox(&mut is_public, "Public").clicked() {
             //     self.sender.send(Msg::TogglePublic).unwrap();
@@ -439,8 +1210,422 @@ impl Workspaces {
                     current.name
                 ));
             }
+
+            if ui
+                .button("Export Compact")
+                .on_hover_text("A short string to paste in a chat or to put in a link.")
+                .clicked()
+            {
+                let current = self.current();
+                let compact = current.data.to_compact();
+                ui.ctx().notify_success(format!(
+                    "Exported workspace `{}` to clipboard ({} characters).",
+                    current.name,
+                    compact.len()
+                ));
+                ui.output_mut(|o| o.copied_text = compact);
+            }
+
+            if ui.button("Export to file…").clicked() {
+                let current = self.current();
+                let contents = serde_json::to_string_pretty(&current.data).unwrap();
+                if platform::save_file(ui.ctx(), &current.export_file_name(), &contents) {
+                    ui.ctx()
+                        .notify_success(format!("Exported workspace `{}`.", current.name));
+                }
+            }
+
+            if ui.button("Export encrypted…").clicked() {
+                self.input_export_passphrase = Some(Default::default());
+            }
+            let encrypting = self.encrypting;
+            if let Some((passphrase, repeated)) = &mut self.input_export_passphrase {
+                let mut close = false;
+                let mut export = None;
+                modal::show(ui.ctx(), "Export Encrypted", |ui| {
+                    Grid::new("ExportPassphrase").num_columns(2).show(ui, |ui| {
+                        ui.label("Passphrase:");
+                        ui.add(TextEdit::singleline(passphrase).password(true));
+                        ui.end_row();
+                        ui.label("Repeat:");
+                        ui.add(TextEdit::singleline(repeated).password(true));
+                        ui.end_row();
+                    });
+                    ui.weak("Without the passphrase, there's no way to read the export.");
+
+                    ui.add_space(3.0);
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Cancel").clicked() {
+                            close = true;
+                        }
+                        let valid = !passphrase.is_empty() && passphrase == repeated;
+                        if ui
+                            .add_enabled(valid && !encrypting, Button::new("Export"))
+                            .clicked()
+                        {
+                            export = Some(passphrase.clone());
+                        }
+                        if encrypting {
+                            deriving_key_ui(ui, "Encrypting…");
+                        }
+                    });
+                });
+                if let Some(passphrase) = export {
+                    // Saved once done, see [Msg::SaveEncrypted].
+                    let current = self.current();
+                    let name = current.name.clone();
+                    let file_name = current.export_file_name();
+                    let data = current.data.clone();
+                    let sender = self.sender.clone();
+                    let ctx = ui.ctx().clone();
+                    derive_key(ui.ctx(), move || {
+                        let contents = data.to_encrypted(&passphrase);
+                        sender
+                            .send(Msg::SaveEncrypted {
+                                name,
+                                file_name,
+                                contents,
+                            })
+                            .unwrap();
+                        ctx.request_repaint();
+                    });
+                    self.encrypting = true;
+                }
+                if close {
+                    self.input_export_passphrase = None;
+                }
+            }
         });
 
+        ui.horizontal_wrapped(|ui| {
+            if !owned {
+                ui.disable();
+            }
+            ui.label("Tags:");
+            for tag in &self.current().tags {
+                if ui
+                    .small_button(format!("{} ✖", tag))
+                    .on_hover_text("Remove tag")
+                    .clicked()
+                {
+                    self.sender
+                        .send(Msg::RemoveTag { tag: tag.clone() })
+                        .unwrap();
+                }
+            }
+            let resp = ui.add(
+                TextEdit::singleline(&mut self.input_new_tag)
+                    .hint_text("New tag...")
+                    .desired_width(80.0),
+            );
+            let tag = self.input_new_tag.trim().to_string();
+            let submitted = resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (submitted
+                || ui
+                    .add_enabled(!tag.is_empty(), Button::new("Add"))
+                    .clicked())
+                && !tag.is_empty()
+            {
+                self.sender.send(Msg::AddTag { tag }).unwrap();
+                self.input_new_tag.clear();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if !owned {
+                ui.disable();
+            }
+            ui.label("Auto-refresh:");
+            let mut interval = self.current().refresh_interval;
+            refresh_interval_ui(ui, "Auto-refresh", &mut interval);
+            if interval != self.current().refresh_interval {
+                self.sender
+                    .send(Msg::SetRefreshInterval { minutes: interval })
+                    .unwrap();
+            }
+
+            let network = self
+                .current()
+                .data
+                .source
+                .as_ref()
+                .and_then(|source| Network::from_name(&source.network))
+                .unwrap_or_default();
+            let mut selected = network;
+            ui.add_enabled_ui(self.current().data.transactions.is_empty(), |ui| {
+                ComboBox::from_id_source("Network")
+                    .selected_text(network.name())
+                    .show_ui(ui, |ui| {
+                        for n in Network::ALL {
+                            ui.selectable_value(&mut selected, n, n.name());
+                        }
+                    })
+                    .response
+                    .on_disabled_hover_text("Only empty workspaces can change their network.");
+            });
+            if selected != network {
+                self.update_sender
+                    .send(Update::SetNetwork { network: selected })
+                    .unwrap();
+            }
+
+            let currency = self.current().data.currency.clone();
+            let mut selected = currency.clone();
+            ComboBox::from_id_source("Currency")
+                .selected_text(
+                    currency
+                        .as_deref()
+                        .map_or("No fiat".to_string(), str::to_uppercase),
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected, None, "No fiat");
+                    for c in prices::CURRENCIES {
+                        ui.selectable_value(&mut selected, Some(c.to_string()), c.to_uppercase());
+                    }
+                })
+                .response
+                .on_hover_text("Show values in this currency, at the rate of the day.");
+            if selected != currency {
+                self.update_sender
+                    .send(Update::SetCurrency { currency: selected })
+                    .unwrap();
+            }
+            if currency.is_some()
+                && ui
+                    .button("Refresh prices")
+                    .on_hover_text(format!(
+                        "Fetch the rates again. {} day(s) of rates are cached for offline use.",
+                        Prices::cached_days(ui.ctx())
+                    ))
+                    .clicked()
+            {
+                Prices::refresh(ui.ctx());
+            }
+
+            let mut pinned = self.current().pinned;
+            let source = match &self.current().data.source {
+                Some(source) => format!("Data from {} ({}).", source.api_base, source.network),
+                None => "Data source unknown.".to_string(),
+            };
+            if ui
+                .checkbox(&mut pinned, "Pin source")
+                .on_hover_text(source)
+                .clicked()
+            {
+                self.sender.send(Msg::TogglePinned).unwrap();
+            }
+
+            if ui
+                .button("Refresh now")
+                .on_hover_text("Check for new confirmations and spends.")
+                .clicked()
+            {
+                self.update_sender
+                    .send(Update::RefreshWorkspace { report: false })
+                    .unwrap();
+            }
+
+            if ui
+                .button("Re-validate")
+                .on_hover_text(
+                    "Fetch all transactions again and report confirmations, spends, reorgs and \
+                     repaired data.",
+                )
+                .clicked()
+            {
+                self.update_sender
+                    .send(Update::RefreshWorkspace { report: true })
+                    .unwrap();
+            }
+        });
+
+        egui::CollapsingHeader::new(format!("Snapshots ({})", self.current().snapshots.len()))
+            .id_source("Workspace snapshots")
+            .show(ui, |ui| {
+                if !owned {
+                    ui.disable();
+                }
+                if ui
+                    .button("Take Snapshot")
+                    .on_hover_text("Store a copy of the current state in this workspace.")
+                    .clicked()
+                {
+                    self.sender.send(Msg::TakeSnapshot).unwrap();
+                }
+                Grid::new("Snapshots").num_columns(3).show(ui, |ui| {
+                    for (index, snapshot) in self.current().snapshots.iter().enumerate().rev() {
+                        ui.label(
+                            snapshot
+                                .created_at
+                                .with_timezone(&Local)
+                                .format("%Y-%m-%d %H:%M:%S")
+                                .to_string(),
+                        )
+                        .on_hover_ui(|ui| {
+                            ui.label(format!("{} transactions", snapshot.data.transactions.len()));
+                            if !snapshot.data.notes.is_empty() {
+                                ui.separator();
+                                ui.label(&snapshot.data.notes);
+                            }
+                        });
+                        if ui
+                            .button("Restore")
+                            .on_hover_text("Replace the current state with this snapshot.")
+                            .clicked()
+                        {
+                            self.sender.send(Msg::RestoreSnapshot { index }).unwrap();
+                        }
+                        if ui.button("✖").on_hover_text("Delete snapshot").clicked() {
+                            self.sender.send(Msg::DeleteSnapshot { index }).unwrap();
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+
+        egui::CollapsingHeader::new("Notes")
+            .id_source("Workspace notes")
+            .show(ui, |ui| {
+                let mut notes = self.current().data.notes.clone();
+                ui.add(
+                    TextEdit::multiline(&mut notes)
+                        .interactive(owned)
+                        .hint_text("Context for this investigation (Markdown)...")
+                        .desired_rows(6)
+                        .desired_width(f32::INFINITY),
+                );
+                if notes != self.current().data.notes {
+                    self.sender.send(Msg::SetNotes { notes }).unwrap();
+                }
+            });
+
+        egui::CollapsingHeader::new("Defaults for New Workspaces")
+            .id_source("Workspace defaults")
+            .show(ui, |ui| {
+                Grid::new("Defaults").num_columns(2).show(ui, |ui| {
+                    ui.label("Auto-refresh:");
+                    refresh_interval_ui(
+                        ui,
+                        "Default auto-refresh",
+                        &mut self.defaults.refresh_interval,
+                    );
+                    ui.end_row();
+
+                    ui.label("Tags:");
+                    ui.add(
+                        TextEdit::singleline(&mut self.defaults.tags)
+                            .hint_text("Comma separated...")
+                            .desired_width(150.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Layout:");
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button("Use current")
+                            .on_hover_text("Scale and layout parameters of the current workspace.")
+                            .clicked()
+                        {
+                            self.defaults.layout = Some(self.current().data.layout.clone());
+                        }
+                        if ui
+                            .add_enabled(self.defaults.layout.is_some(), Button::new("Reset"))
+                            .clicked()
+                        {
+                            self.defaults.layout = None;
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Network:");
+                    let network = &mut self.defaults.network;
+                    ComboBox::from_id_source("Default network")
+                        .selected_text(network.as_deref().unwrap_or(Network::default().name()))
+                        .show_ui(ui, |ui| {
+                            for n in Network::ALL {
+                                ui.selectable_value(network, Some(n.name().to_string()), n.name());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Currency:");
+                    ComboBox::from_id_source("Default currency")
+                        .selected_text(
+                            self.defaults
+                                .currency
+                                .as_deref()
+                                .map_or("No fiat".to_string(), str::to_uppercase),
+                        )
+                        .show_ui(ui, |ui| {
+                            let currency = &mut self.defaults.currency;
+                            ui.selectable_value(currency, None, "No fiat");
+                            for c in prices::CURRENCIES {
+                                ui.selectable_value(
+                                    currency,
+                                    Some(c.to_string()),
+                                    c.to_uppercase(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Categories:");
+                    ui.horizontal(|ui| {
+                        ui.label(self.defaults.categories.len().to_string());
+                        if ui
+                            .button("Use current")
+                            .on_hover_text(
+                                "Names and colors of the current workspace's categories.",
+                            )
+                            .clicked()
+                        {
+                            self.defaults.categories =
+                                self.current().data.annotations.categories().to_vec();
+                        }
+                        if ui
+                            .add_enabled(!self.defaults.categories.is_empty(), Button::new("Reset"))
+                            .clicked()
+                        {
+                            self.defaults.categories.clear();
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Expansion:");
+                    let mut custom = self.defaults.expand.is_some();
+                    ui.checkbox(&mut custom, "Own settings").on_hover_text(
+                        "Otherwise new workspaces keep the expansion settings in use.",
+                    );
+                    if custom != self.defaults.expand.is_some() {
+                        self.defaults.expand = custom.then(ExpandSettings::default);
+                    }
+                    ui.end_row();
+                });
+                if let Some(expand) = &mut self.defaults.expand {
+                    ui.indent("Default expansion", |ui| expand.settings_ui(ui));
+                }
+            });
+
+        egui::CollapsingHeader::new("Automatic Backups")
+            .id_source("Workspace backups")
+            .show(ui, |ui| match self.backups.ui(ui) {
+                Some(backup::Action::BackupNow) => self.backup(ui.ctx(), true),
+                Some(backup::Action::Restore(contents)) => {
+                    match serde_json::from_str::<Bundle>(&contents) {
+                        Ok(bundle) => self.apply_update(
+                            ui.ctx(),
+                            Msg::ImportBundle {
+                                workspaces: bundle.workspaces,
+                                restore: true,
+                            },
+                        ),
+                        Err(e) => ui.ctx().notify_error("Could not restore backup", Some(e)),
+                    }
+                }
+                None => {}
+            });
+
         ui.add_space(3.0);
 
         ui.horizontal_wrapped(|ui| {
@@ -451,6 +1636,7 @@ impl Workspaces {
 }
 
 enum Msg {
+    /// The name is made unique.
     New {
         name: String,
         data: Option<export::Workspace>,
@@ -458,17 +1644,78 @@ enum Msg {
     UpdateData {
         data: export::Workspace,
     },
+    /// Like [Msg::New], but asks what to do if the name is taken.
+    Import {
+        name: String,
+        data: export::Workspace,
+    },
+    /// An encrypted export opened from a file, to be imported once the passphrase is entered.
+    ImportEncrypted {
+        /// Of the file.
+        name: String,
+        text: String,
+    },
+    /// A file that couldn't be imported, shown with its problems so that it can be fixed.
+    ImportInvalid {
+        name: String,
+        text: String,
+        problems: Vec<validation::Problem>,
+    },
+    /// The JSON in the encrypted export of the import dialog.
+    Decrypted {
+        result: Result<String, String>,
+    },
+    /// An encrypted export of the workspace called [name], ready to be saved.
+    SaveEncrypted {
+        name: String,
+        file_name: String,
+        contents: String,
+    },
+    MergeInto {
+        id: Uuid,
+        data: export::Workspace,
+    },
     Select {
         id: Uuid,
     },
     Rename {
         name: String,
     },
+    SetNotes {
+        notes: String,
+    },
+    AppendNotes {
+        text: String,
+    },
+    AddTag {
+        tag: String,
+    },
+    RemoveTag {
+        tag: String,
+    },
+    ImportBundle {
+        workspaces: Vec<Workspace>,
+        /// From a backup, rather than a file the user picked.
+        restore: bool,
+    },
+    Fork,
+    TakeSnapshot,
+    RestoreSnapshot {
+        index: usize,
+    },
+    DeleteSnapshot {
+        index: usize,
+    },
+    TogglePinned,
+    ToggleArchived,
+    SetRefreshInterval {
+        minutes: Option<u32>,
+    },
     // TogglePublic,
     Delete,
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
 struct Workspace {
     is_owned: bool,
     is_public: bool,
@@ -476,6 +1723,25 @@ struct Workspace {
     id: Uuid,
     name: String,
     created_at: DateTime<Utc>,
+    #[serde(default)]
+    archived: bool,
+    /// Keep fetching from the backend recorded in the data, see [export::Workspace::source].
+    #[serde(default)]
+    pinned: bool,
+    /// In minutes.
+    #[serde(default)]
+    refresh_interval: Option<u32>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Oldest first.
+    #[serde(default)]
+    snapshots: Vec<Snapshot>,
+}
+
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+struct Snapshot {
+    created_at: DateTime<Utc>,
+    data: export::Workspace,
 }
 
 impl Workspace {
@@ -487,8 +1753,75 @@ impl Workspace {
             id: Uuid::now_v7(),
             name,
             created_at: Utc::now(),
+            archived: false,
+            pinned: false,
+            refresh_interval: None,
+            tags: vec![],
+            snapshots: vec![],
         }
     }
+
+    /// E.g. `my-workspace-20240131-142501.json`.
+    fn export_file_name(&self) -> String {
+        export_file_name(&self.name)
+    }
+}
+
+fn export_file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("{}-{}.json", name, Local::now().format("%Y%m%d-%H%M%S"))
+}
+
+/// Why an import failed, one row per problem.
+fn show_problems(ui: &mut Ui, problems: &[validation::Problem]) {
+    ui.colored_label(
+        ui.visuals().error_fg_color,
+        format!("{} problem(s) found:", problems.len()),
+    );
+    egui::ScrollArea::vertical()
+        .id_source("ImportProblems")
+        .max_height(150.0)
+        .show(ui, |ui| {
+            Grid::new("ImportProblems")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.bold("Path");
+                    ui.bold("Expected");
+                    ui.bold("Value");
+                    ui.end_row();
+                    for problem in problems {
+                        let expected = problem.expected.as_deref().unwrap_or(&problem.message);
+                        ui.monospace(&problem.path).on_hover_text(&problem.message);
+                        ui.label(expected);
+                        ui.monospace(problem.value.as_deref().unwrap_or(""));
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+/// Reads an exported workspace, mentioning it if it was written in an older format.
+fn import_json(ctx: &Context, json: &str) -> Result<export::Workspace, String> {
+    let imported = export::import(json)?;
+    if !imported.migrations.is_empty() {
+        ctx.notify_info(format!(
+            "Upgraded from format version {} to {}. {}",
+            imported.version,
+            export::VERSION,
+            imported.migrations.join(" ")
+        ));
+    }
+    Ok(imported.workspace)
+}
+
+/// All workspaces in one file, for moving them between machines.
+#[derive(Deserialize, Serialize)]
+struct Bundle {
+    workspaces: Vec<Workspace>,
 }
 
 #[derive(Clone)]
@@ -502,4 +1835,29 @@ impl WorkspacesHandle {
             sender.send(Msg::UpdateData { data }).unwrap();
         }
     }
+
+    /// Creates a workspace with [data] and switches to it. A taken [name] gets a number.
+    pub fn new_workspace(ctx: &Context, name: String, data: export::Workspace) {
+        if let Some(WorkspacesSender(sender)) = ctx.data(|d| d.get_temp(Id::NULL)) {
+            sender
+                .send(Msg::New {
+                    name,
+                    data: Some(data),
+                })
+                .unwrap();
+        }
+    }
+
+    /// Creates an editable copy of the current workspace, with the edits made to it so far.
+    pub fn fork(ctx: &Context) {
+        if let Some(WorkspacesSender(sender)) = ctx.data(|d| d.get_temp(Id::NULL)) {
+            sender.send(Msg::Fork).unwrap();
+        }
+    }
+
+    pub fn append_notes(ctx: &Context, text: String) {
+        if let Some(WorkspacesSender(sender)) = ctx.data(|d| d.get_temp(Id::NULL)) {
+            sender.send(Msg::AppendNotes { text }).unwrap();
+        }
+    }
 }
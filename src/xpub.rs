@@ -0,0 +1,328 @@
+//! Addresses of a wallet, derived from an extended public key (BIP32) or an output descriptor
+//! (BIP380) without any private keys.
+
+use hmac::{Hmac, Mac};
+use k256::{
+    elliptic_curve::{sec1::ToEncodedPoint, PrimeField},
+    ProjectivePoint, PublicKey, Scalar,
+};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::chain::Network;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScriptType {
+    P2pkh,
+    P2shP2wpkh,
+    P2wpkh,
+    P2tr,
+}
+
+impl ScriptType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScriptType::P2pkh => "Legacy (P2PKH)",
+            ScriptType::P2shP2wpkh => "Nested SegWit (P2SH-P2WPKH)",
+            ScriptType::P2wpkh => "Native SegWit (P2WPKH)",
+            ScriptType::P2tr => "Taproot (P2TR)",
+        }
+    }
+}
+
+/// A BIP32 extended public key.
+#[derive(Clone, Debug, PartialEq)]
+struct ExtendedKey {
+    /// Compressed.
+    key: [u8; 33],
+    chain_code: [u8; 32],
+}
+
+impl ExtendedKey {
+    /// Non-hardened child [index].
+    fn child(&self, index: u32) -> Result<Self, String> {
+        if index >= HARDENED {
+            return Err("Hardened keys can't be derived from a public key.".to_string());
+        }
+        let mut mac = Hmac::<Sha512>::new_from_slice(&self.chain_code).unwrap();
+        mac.update(&self.key);
+        mac.update(&index.to_be_bytes());
+        let i = mac.finalize().into_bytes();
+        let (left, right) = i.split_at(32);
+
+        let tweak: Option<Scalar> = Scalar::from_repr(*k256::FieldBytes::from_slice(left)).into();
+        let parent = PublicKey::from_sec1_bytes(&self.key).map_err(|e| e.to_string())?;
+        let child = tweak
+            .map(|tweak| ProjectivePoint::GENERATOR * tweak + parent.to_projective())
+            .filter(|child| *child != ProjectivePoint::IDENTITY)
+            .ok_or_else(|| format!("Child key {} is invalid.", index))?;
+        Ok(Self {
+            key: compress(&child),
+            chain_code: right.try_into().unwrap(),
+        })
+    }
+}
+
+const HARDENED: u32 = 1 << 31;
+
+/// Version bytes of serialized extended public keys, and the script type they imply.
+const VERSIONS: [([u8; 4], ScriptType, bool); 8] = [
+    ([0x04, 0x88, 0xb2, 0x1e], ScriptType::P2pkh, true), // xpub
+    ([0x04, 0x9d, 0x7c, 0xb2], ScriptType::P2shP2wpkh, true), // ypub
+    ([0x04, 0xb2, 0x47, 0x46], ScriptType::P2wpkh, true), // zpub
+    ([0x01, 0x9d, 0xa4, 0x62], ScriptType::P2pkh, true), // Ltub
+    ([0x01, 0xb2, 0x6e, 0xf6], ScriptType::P2shP2wpkh, true), // Mtub
+    ([0x04, 0x35, 0x87, 0xcf], ScriptType::P2pkh, false), // tpub
+    ([0x04, 0x4a, 0x52, 0x62], ScriptType::P2shP2wpkh, false), // upub
+    ([0x04, 0x5f, 0x1c, 0xf6], ScriptType::P2wpkh, false), // vpub
+];
+
+/// Where a wallet's addresses come from.
+#[derive(Debug, PartialEq)]
+pub struct Wallet {
+    key: ExtendedKey,
+    pub script_type: ScriptType,
+    /// For a mainnet chain rather than a test network.
+    mainnet: bool,
+    /// Paths from [Self::key] to the parents of the addresses, usually receive and change.
+    chains: Vec<Vec<u32>>,
+}
+
+impl Wallet {
+    /// Takes an xpub, ypub or zpub (or their testnet versions), with receive addresses at `/0/i`
+    /// and change at `/1/i`. Or a `pkh`, `sh(wpkh)`, `wpkh` or `tr` descriptor with one key
+    /// ending in `/*`, e.g. `wpkh([d34db33f/84h/0h/0h]xpub…/<0;1>/*)`. A descriptor for the receive
+    /// addresses (`/0/*`) also brings the change addresses (`/1/*`).
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let text = text.split_once('#').map_or(text, |(text, _checksum)| text);
+
+        let descriptors = [
+            ("pkh(", ")", ScriptType::P2pkh),
+            ("sh(wpkh(", "))", ScriptType::P2shP2wpkh),
+            ("wpkh(", ")", ScriptType::P2wpkh),
+            ("tr(", ")", ScriptType::P2tr),
+        ];
+        let descriptor = descriptors.iter().find_map(|(start, end, script_type)| {
+            let key = text.strip_prefix(start)?.strip_suffix(end)?;
+            Some((key, *script_type))
+        });
+        if descriptor.is_none() && text.contains('(') {
+            return Err("Only pkh, sh(wpkh), wpkh and tr descriptors are supported.".to_string());
+        }
+
+        let expression = descriptor.map_or(text, |(key, _)| key);
+        // Where the key came from doesn't matter for deriving from it.
+        let expression = match expression.strip_prefix('[') {
+            Some(rest) => rest.split_once(']').ok_or("Unclosed key origin.")?.1,
+            None => expression,
+        };
+        let (key, path) = expression.split_once('/').unwrap_or((expression, ""));
+
+        let data = bs58::decode(key)
+            .with_check(None)
+            .into_vec()
+            .map_err(|e| format!("Not an extended public key: {}", e))?;
+        if data.len() != 78 {
+            return Err("Not an extended public key: wrong length.".to_string());
+        }
+        let Some((_, implied_type, mainnet)) = VERSIONS.iter().find(|(v, _, _)| data[..4] == *v)
+        else {
+            return Err("Unknown key version. Private keys are not accepted.".to_string());
+        };
+        let key = ExtendedKey {
+            chain_code: data[13..45].try_into().unwrap(),
+            key: data[45..78].try_into().unwrap(),
+        };
+        PublicKey::from_sec1_bytes(&key.key).map_err(|_| "Invalid public key.".to_string())?;
+
+        let chains = match descriptor {
+            None if !path.is_empty() => {
+                return Err("Use a descriptor to derive from a path below the key.".to_string())
+            }
+            None => vec![vec![0], vec![1]],
+            Some(_) => parse_path(path)?,
+        };
+
+        Ok(Self {
+            key,
+            script_type: descriptor.map_or(*implied_type, |(_, script_type)| script_type),
+            mainnet: *mainnet,
+            chains,
+        })
+    }
+
+    /// Usually two: receive and change.
+    pub fn num_chains(&self) -> usize {
+        self.chains.len()
+    }
+
+    /// Whether the key is for [network], judging by its version.
+    pub fn matches_network(&self, network: Network) -> bool {
+        self.mainnet == matches!(network, Network::Mainnet | Network::Litecoin)
+    }
+
+    /// Address [index] of [chain], see [Self::num_chains].
+    pub fn address(&self, network: Network, chain: usize, index: u32) -> Result<String, String> {
+        let mut key = self.key.clone();
+        for step in self.chains[chain].iter().chain([&index]) {
+            key = key.child(*step)?;
+        }
+        let (p2pkh, p2sh) = network.base58_versions();
+        let segwit = |version, program: &[u8]| {
+            let hrp = bech32::Hrp::parse(network.bech32_hrp()).unwrap();
+            bech32::segwit::encode(hrp, version, program).map_err(|e| e.to_string())
+        };
+        match self.script_type {
+            ScriptType::P2pkh => Ok(base58check(p2pkh, &hash160(&key.key))),
+            ScriptType::P2shP2wpkh => {
+                let redeem_script = [&[0x00, 0x14], &hash160(&key.key)[..]].concat();
+                Ok(base58check(p2sh, &hash160(&redeem_script)))
+            }
+            ScriptType::P2wpkh => segwit(bech32::segwit::VERSION_0, &hash160(&key.key)),
+            ScriptType::P2tr => segwit(bech32::segwit::VERSION_1, &taproot_output_key(&key.key)?),
+        }
+    }
+}
+
+/// E.g. `/<0;1>/*` or `/0/*`, after the key.
+fn parse_path(path: &str) -> Result<Vec<Vec<u32>>, String> {
+    let mut steps: Vec<&str> = path.split('/').collect();
+    if steps.pop() != Some("*") {
+        return Err(
+            "The descriptor has to end in `/*`, hardened steps aren't possible.".to_string(),
+        );
+    }
+    let step = |step: &str| {
+        step.parse::<u32>()
+            .ok()
+            .filter(|step| *step < HARDENED)
+            .ok_or_else(|| format!("Can't derive `{}` from a public key.", step))
+    };
+    let last = steps.pop();
+    let prefix = steps
+        .into_iter()
+        .map(step)
+        .collect::<Result<Vec<u32>, String>>()?;
+    let last: Vec<u32> = match last {
+        None => return Ok(vec![prefix]),
+        Some(multi) if multi.starts_with('<') && multi.ends_with('>') => multi[1..multi.len() - 1]
+            .split(';')
+            .map(step)
+            .collect::<Result<_, _>>()?,
+        Some("0") => vec![0, 1],
+        Some(last) => vec![step(last)?],
+    };
+    Ok(last
+        .into_iter()
+        .map(|last| [prefix.as_slice(), &[last]].concat())
+        .collect())
+}
+
+fn compress(point: &ProjectivePoint) -> [u8; 33] {
+    point
+        .to_affine()
+        .to_encoded_point(true)
+        .as_bytes()
+        .try_into()
+        .unwrap()
+}
+
+fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+fn base58check(version: u8, payload: &[u8]) -> String {
+    bs58::encode([&[version], payload].concat())
+        .with_check()
+        .into_string()
+}
+
+/// The key in a BIP86 output: the internal key tweaked with its own hash, x-only.
+fn taproot_output_key(key: &[u8; 33]) -> Result<[u8; 32], String> {
+    let x = &key[1..];
+    // The internal key is the one with the even y coordinate.
+    let internal = PublicKey::from_sec1_bytes(&[&[0x02], x].concat()).map_err(|e| e.to_string())?;
+    let tag = Sha256::digest(b"TapTweak");
+    let tweak = Sha256::new()
+        .chain_update(tag)
+        .chain_update(tag)
+        .chain_update(x)
+        .finalize();
+    let tweak: Option<Scalar> = Scalar::from_repr(tweak).into();
+    let tweak = tweak.ok_or("Invalid taproot tweak.")?;
+    let output = ProjectivePoint::GENERATOR * tweak + internal.to_projective();
+    Ok(compress(&output)[1..].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScriptType, Wallet};
+    use crate::chain::Network;
+
+    // Account keys of the "abandon abandon … about" test mnemonic, from BIP44, BIP84 and BIP86.
+    const XPUB: &str = "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj";
+    const ZPUB: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+    const TAPROOT: &str = "xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ";
+
+    #[test]
+    fn derives_addresses() {
+        let mainnet = Network::Mainnet;
+
+        let wallet = Wallet::parse(XPUB).unwrap();
+        assert_eq!(wallet.script_type, ScriptType::P2pkh);
+        assert_eq!(
+            wallet.address(mainnet, 0, 0).unwrap(),
+            "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"
+        );
+
+        let wallet = Wallet::parse(ZPUB).unwrap();
+        assert_eq!(wallet.script_type, ScriptType::P2wpkh);
+        assert_eq!(
+            wallet.address(mainnet, 0, 0).unwrap(),
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+        assert_eq!(
+            wallet.address(mainnet, 1, 0).unwrap(),
+            "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el"
+        );
+        assert!(wallet.matches_network(mainnet));
+        assert!(!wallet.matches_network(Network::Testnet));
+
+        let wallet = Wallet::parse(&format!(
+            "tr([73c5da0a/86h/0h/0h]{}/<0;1>/*)#abcdefgh",
+            TAPROOT
+        ))
+        .unwrap();
+        assert_eq!(wallet.num_chains(), 2);
+        assert_eq!(
+            wallet.address(mainnet, 0, 0).unwrap(),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+        assert_eq!(
+            wallet.address(mainnet, 0, 1).unwrap(),
+            "bc1p4qhjn9zdvkux4e44uhx8tc55attvtyu358kutcqkudyccelu0was9fqzwh"
+        );
+        assert_eq!(
+            wallet.address(mainnet, 1, 0).unwrap(),
+            "bc1p3qkhfews2uk44qtvauqyr2ttdsw7svhkl9nkm9s9c3x4ax5h60wqwruhk7"
+        );
+    }
+
+    #[test]
+    fn parses_descriptors() {
+        let wallet = Wallet::parse(&format!("wpkh({}/0/*)", XPUB)).unwrap();
+        assert_eq!(wallet.script_type, ScriptType::P2wpkh);
+        assert_eq!(wallet.chains, vec![vec![0], vec![1]]);
+
+        let wallet = Wallet::parse(&format!("sh(wpkh({}/2/1/*))", XPUB)).unwrap();
+        assert_eq!(wallet.script_type, ScriptType::P2shP2wpkh);
+        assert_eq!(wallet.chains, vec![vec![2, 1]]);
+
+        assert!(Wallet::parse(&format!("wpkh({}/0h/*)", XPUB)).is_err());
+        assert!(Wallet::parse(&format!("wpkh({}/0/*h)", XPUB)).is_err());
+        assert!(Wallet::parse(&format!("wsh(multi(1,{}/0/*))", XPUB)).is_err());
+        assert!(Wallet::parse(&format!("{}/0", XPUB)).is_err());
+        assert!(Wallet::parse("xpub123").is_err());
+    }
+}